@@ -7,6 +7,9 @@ use utils::bump::bump_instance;
 pub enum AccessControlError {
     AdminNotFound = 101,
     UserNotAdmin = 102,
+    FutureAdminNotFound = 103,
+    UserNotFutureAdmin = 104,
+    UserNotRewardsOperator = 105,
 }
 
 #[derive(Clone)]
@@ -14,6 +17,7 @@ pub enum AccessControlError {
 enum DataKey {
     Admin,
     FutureAdmin,
+    RewardsOperator,
 }
 
 #[derive(Clone)]
@@ -33,6 +37,11 @@ pub trait AccessControlTrait {
     fn require_admin(&self);
     fn get_future_admin(&self) -> Option<Address>;
     fn set_future_admin(&self, admin: &Address);
+    fn remove_future_admin(&self);
+    fn check_future_admin(&self, user: &Address);
+    fn get_rewards_operator(&self) -> Option<Address>;
+    fn set_rewards_operator(&self, operator: &Address);
+    fn check_rewards_operator(&self, user: &Address);
     fn perform_admin_check(&self) -> Result<Address, AccessControlError>;
 }
 
@@ -65,20 +74,54 @@ impl AccessControlTrait for AccessControl {
     }
 
     fn get_future_admin(&self) -> Option<Address> {
+        bump_instance(&self.0);
+        self.0.storage().instance().get(&DataKey::FutureAdmin)
+    }
+
+    fn set_future_admin(&self, admin: &Address) {
         bump_instance(&self.0);
         self.0
             .storage()
             .instance()
-            .get(&DataKey::FutureAdmin)
-            .expect("Cant find future admin")
+            .set(&DataKey::FutureAdmin, admin)
     }
 
-    fn set_future_admin(&self, admin: &Address) {
+    fn remove_future_admin(&self) {
+        bump_instance(&self.0);
+        self.0.storage().instance().remove(&DataKey::FutureAdmin)
+    }
+
+    fn check_future_admin(&self, user: &Address) {
+        let future_admin = match self.get_future_admin() {
+            Some(future_admin) => future_admin,
+            None => panic_with_error!(&self.0, AccessControlError::FutureAdminNotFound),
+        };
+        if future_admin != user.clone() {
+            panic_with_error!(&self.0, AccessControlError::UserNotFutureAdmin);
+        }
+    }
+
+    fn get_rewards_operator(&self) -> Option<Address> {
+        bump_instance(&self.0);
+        self.0.storage().instance().get(&DataKey::RewardsOperator)
+    }
+
+    fn set_rewards_operator(&self, operator: &Address) {
         bump_instance(&self.0);
         self.0
             .storage()
             .instance()
-            .set(&DataKey::FutureAdmin, admin)
+            .set(&DataKey::RewardsOperator, operator)
+    }
+
+    // Rewards are managed either by the admin or by the rewards operator
+    fn check_rewards_operator(&self, user: &Address) {
+        if self.get_admin() == Some(user.clone()) {
+            return;
+        }
+        if self.get_rewards_operator() != Some(user.clone()) {
+            panic_with_error!(&self.0, AccessControlError::UserNotRewardsOperator);
+        }
     }
 
     fn perform_admin_check(&self) -> Result<Address, AccessControlError> {
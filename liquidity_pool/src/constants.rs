@@ -1 +1,35 @@
 pub(crate) const FEE_MULTIPLIER: u128 = 10_000;
+pub(crate) const MIGRATION_PRECISION: u128 = 1_0000000;
+pub(crate) const REWARD_RATE_PRECISION: u128 = 1_0000000;
+// lock duration granting the max (2x) rewards boost
+pub(crate) const MAX_LOCK_DURATION: u64 = 365 * 24 * 60 * 60;
+// users per batch claim, keeps the invocation within resource limits
+pub(crate) const MAX_BATCH_CLAIM_SIZE: u32 = 20;
+// reward checkpoints per page, keeps the read within resource limits
+pub(crate) const MAX_REWARD_CHECKPOINTS_PAGE: u32 = 100;
+// decimals amounts are scaled to for the price and initial shares calculation
+pub(crate) const NORMALIZED_DECIMALS: u32 = 7;
+pub(crate) const PRICE_PRECISION: u128 = 1_0000000;
+// absolute maximum value for the stableswap amplification
+pub(crate) const MAX_AMP: u128 = 1_000_000;
+// weighted pool weights sum up to it
+pub(crate) const WEIGHT_DENOMINATOR: u32 = 100;
+// max share of the reserve bought by the percentage swap. 0.01% = 1
+pub(crate) const MAX_SWAP_PCT: u32 = 3000;
+// reserve floor swaps can't go below until admin sets another one
+pub(crate) const DEFAULT_MIN_RESERVE: u128 = 1000;
+// max referral cut of the swap fee. 0.01% = 1
+pub(crate) const MAX_REFERRAL_FEE: u32 = 10;
+// layout of the pool storage expected by the code. Pools deployed before versioning are at 0
+pub(crate) const STORAGE_VERSION: u32 = 2;
+// blocks per reward invariant page of pools created before the cumulative reward per share
+pub(crate) const LEGACY_REWARD_PAGE_SIZE: u64 = 1000;
+// characters taken from each pool token symbol to name the share token, "XLM-USDC LP"
+pub(crate) const SHARE_SYMBOL_PART_LENGTH: usize = 5;
+// longest token symbol read when naming the share token
+pub(crate) const MAX_TOKEN_SYMBOL_LENGTH: usize = 32;
+// share token metadata limits enforced by the token contract
+pub(crate) const MAX_SHARE_NAME_LENGTH: u32 = 32;
+pub(crate) const MAX_SHARE_SYMBOL_LENGTH: u32 = 12;
+// precision of the sqrt(k) per share used to track fee earnings
+pub(crate) const ROOT_K_PRECISION: u128 = 1_000_000_000_000;
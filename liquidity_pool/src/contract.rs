@@ -1,27 +1,69 @@
-use crate::constants::FEE_MULTIPLIER;
-use crate::plane::update_plane;
+use crate::constants::{
+    FEE_MULTIPLIER, LEGACY_REWARD_PAGE_SIZE, MAX_AMP, MAX_BATCH_CLAIM_SIZE, MAX_REFERRAL_FEE,
+    MAX_REWARD_CHECKPOINTS_PAGE, MAX_SHARE_NAME_LENGTH, MAX_SHARE_SYMBOL_LENGTH, MAX_SWAP_PCT,
+    MIGRATION_PRECISION, REWARD_RATE_PRECISION, STORAGE_VERSION, WEIGHT_DENOMINATOR,
+};
+use crate::events::{Events, LiquidityPoolEvents};
+use crate::guards::{
+    check_swap_allowed, get_rejection_stats, get_swap_price_impact, get_swap_violations,
+    is_user_allowed, record_rejection, require_swap_allowed, require_user_allowed, SwapRequest,
+};
+use crate::holders::{backfill_holder, check_backfill_complete, sync_holder};
+use crate::math::{mul_div_ceil, mul_div_floor};
+use crate::plane::{update_plane, update_plane_state};
 use crate::plane_interface::Plane;
 use crate::pool;
 use crate::pool_interface::{
-    LiquidityPoolCrunch, LiquidityPoolTrait, RewardsTrait, UpgradeableContractTrait,
+    AdminInterfaceTrait, LiquidityMigrationTrait, LiquidityPoolCrunch, LiquidityPoolTrait,
+    RewardsTrait, UpgradeableContractTrait,
 };
-use crate::rewards::get_rewards_manager;
+use crate::rewards::{
+    fund_rewards, get_lock_boost_amount, get_reward_budget, get_rewards_manager,
+    get_spare_reward_balance, get_total_reward_shares, get_user_boosted_shares,
+    get_user_reward_shares, get_vested_amount, is_rewards_configured, pay_referral_bonus,
+    release_vested, sync_reward_shares, transfer_reward, vest_reward,
+};
+use crate::router::PoolRouterClient;
+use crate::stable_math;
 use crate::storage::{
-    get_fee_fraction, get_plane, get_reserve_a, get_reserve_b, get_token_a, get_token_b, has_plane,
-    put_fee_fraction, put_reserve_a, put_reserve_b, put_token_a, put_token_b, set_plane,
+    get_backfill_complete, get_bootstrapper, get_curve, get_decimals_a, get_decimals_b,
+    get_fee_earnings, get_fee_fraction, get_holders_count, get_initial_price_range,
+    get_is_emergency_mode, get_is_fee_exempt, get_is_killed, get_is_paused, get_is_permissioned,
+    get_lock_boost, get_max_price_impact, get_migrated_shares, get_migration_pool,
+    get_migration_router, get_migration_shares, get_min_reserve, get_opted_out_shares, get_plane,
+    get_referrer_stats, get_reserve_a, get_reserve_b, get_reserves_updated_at, get_reward_claimed,
+    get_reward_fee, get_reward_fee_bucket, get_reward_funded, get_reward_opt_out,
+    get_reward_vesting, get_share_lock, get_storage_version, get_swap_stats, get_token_a,
+    get_token_b, get_vesting_duration, has_migration_pool, has_plane, put_allowlisted,
+    put_backfill_complete, put_bootstrapper, put_curve, put_decimals_a, put_decimals_b,
+    put_fee_earnings, put_fee_exempt, put_fee_fraction, put_initial_price_range,
+    put_is_emergency_mode, put_is_killed, put_is_paused, put_is_permissioned, put_lock_boost,
+    put_max_price_impact, put_migrated_shares, put_migration_pool, put_migration_router,
+    put_migration_shares, put_min_reserve, put_opted_out_shares, put_referral_bonus, put_reserve_a,
+    put_reserve_b, put_reward_claimed, put_reward_fee, put_reward_fee_bucket, put_reward_funded,
+    put_reward_opt_out, put_share_lock, put_storage_version, put_swap_stats, put_token_a,
+    put_token_b, put_vesting_duration, remove_allowlisted, remove_bootstrapper, remove_fee_exempt,
+    remove_reward_opt_out, remove_share_lock, set_plane, Curve, FeeEarnings, PoolContracts,
+    PoolState, QuoteContext, ReferralBonus, RewardsInfo, ShareLock, SwapOutcome, UserPosition,
+};
+use crate::token::{
+    create_contract, get_balance_a, get_balance_b, get_pool_balances, get_share_address,
+    get_share_metadata, get_user_funds, transfer_a, transfer_b, transfer_token,
 };
-use crate::token::{create_contract, get_balance_a, get_balance_b, transfer_a, transfer_b};
-use access_control::access::{AccessControl, AccessControlTrait};
-use num_integer::Roots;
-use rewards::storage::{PoolRewardConfig, RewardsStorageTrait};
+use crate::weighted_math;
+use access_control::access::{AccessControl, AccessControlError, AccessControlTrait};
+use liquidity_pool_interface::{LiquidityPoolInterface, SwapResult};
+use rewards::storage::{PoolRewardConfig, PoolRewardData, RewardsStorageTrait, UserRewardData};
+use soroban_sdk::auth::{ContractContext, InvokerContractAuthEntry, SubContractInvocation};
 use soroban_sdk::token::TokenClient as SorobanTokenClient;
 use soroban_sdk::{
     contract, contracterror, contractimpl, contractmeta, panic_with_error, symbol_short, Address,
-    BytesN, Env, IntoVal, Map, Symbol, Val, Vec,
+    BytesN, Env, IntoVal, Map, String, Symbol, Val, Vec, U256,
 };
 use token_share::{
     burn_shares, get_balance_shares, get_token_share, get_total_shares, get_user_balance_shares,
-    mint_shares, put_token_share, Client as LPTokenClient,
+    lock_user_shares, mint_shares, put_token_share, receive_shares, sync_balance_shares,
+    take_shares, Client as LPTokenClient,
 };
 use utils::bump::bump_instance;
 
@@ -37,6 +79,62 @@ contractmeta!(
 pub enum LiquidityPoolError {
     AlreadyInitialized = 201,
     PlaneAlreadyInitialized = 202,
+    PoolKilled = 203,
+    PoolNotKilled = 204,
+    LiquidityMigrated = 205,
+    LiquidityNotMigrated = 206,
+    MigrationTokensMismatch = 207,
+    PoolPaused = 208,
+    EmergencyModeDisabled = 209,
+    ProtectedToken = 210,
+    InsufficientBalanceA = 211,
+    InsufficientBalanceB = 212,
+    InsufficientAllowanceA = 213,
+    InsufficientAllowanceB = 214,
+    RewardsNotActive = 215,
+    InvalidRewardsExpiration = 216,
+    RewardsBudgetExceeded = 217,
+    ZeroSharesDeposit = 218,
+    RewardsNotExpired = 219,
+    SharesLocked = 220,
+    InvalidShareLock = 221,
+    InvalidRewardsTps = 222,
+    SelfReferral = 223,
+    InvalidReferralBonus = 224,
+    HolderIndexIncomplete = 225,
+    BatchTooLarge = 226,
+    CurveNotSupported = 227,
+    ReferralFeeTooHigh = 228,
+    UserNotAllowed = 229,
+    PriceImpactTooHigh = 230,
+    StorageMigrationRequired = 231,
+    NotInitialized = 232,
+    IdenticalTokens = 233,
+    TokensNotSorted = 234,
+    InvalidRewardsConfig = 235,
+    ShareTokenInitFailed = 236,
+    InvalidShareMetadata = 237,
+    ZeroSharesWithdraw = 238,
+    InvalidClaimAmount = 239,
+    ReinvestNotSupported = 240,
+    RewardFeeNotConvertible = 241,
+    InitialPriceOutOfRange = 242,
+    InvalidInitialPriceRange = 243,
+    PoolNotEmpty = 244,
+    InvalidSlippage = 245,
+    InvalidShareToken = 246,
+    DepositAmountBelowMin = 247,
+    EmptyReserve = 248,
+    InsufficientReserves = 249,
+    InvalidSwapPercentage = 250,
+    MigrationRouterNotSet = 251,
+    UnknownMigrationPool = 252,
+    NothingToMigrate = 253,
+    MigrationReservesNotAccepted = 254,
+    MigrationSharesBelowMin = 255,
+    BootstrapRequired = 256,
+    RewardStorageUnderfunded = 257,
+    DepositSharesBelowMin = 258,
 }
 
 #[contract]
@@ -75,402 +173,2979 @@ impl LiquidityPoolTrait for LiquidityPool {
         tokens: Vec<Address>,
         fee_fraction: u32,
     ) {
-        let access_control = AccessControl::new(&e);
-        if access_control.has_admin() {
-            panic_with_error!(&e, LiquidityPoolError::AlreadyInitialized);
-        }
-        access_control.set_admin(&admin);
-
-        let token_a = tokens.get(0).unwrap();
-        let token_b = tokens.get(1).unwrap();
+        Self::initialize_with_share_metadata(
+            e,
+            admin,
+            lp_token_wasm_hash,
+            tokens,
+            fee_fraction,
+            None,
+            None,
+        );
+    }
 
-        if token_a >= token_b {
-            panic!("token_a must be less than token_b");
+    fn initialize_with_share_metadata(
+        e: Env,
+        admin: Address,
+        lp_token_wasm_hash: BytesN<32>,
+        tokens: Vec<Address>,
+        fee_fraction: u32,
+        share_name: Option<String>,
+        share_symbol: Option<String>,
+    ) {
+        let (token_a, token_b) = check_init_config(&e, &tokens, fee_fraction);
+
+        // deploy share token first and record it before calling any external contract.
+        //  any failure reverts the whole call, so pool is never left partially initialized
+        let (default_name, default_symbol) = get_share_metadata(&e, &token_a, &token_b);
+        let share_name = share_name.unwrap_or(default_name);
+        let share_symbol = share_symbol.unwrap_or(default_symbol);
+        if share_name.len() == 0
+            || share_name.len() > MAX_SHARE_NAME_LENGTH
+            || share_symbol.len() == 0
+            || share_symbol.len() > MAX_SHARE_SYMBOL_LENGTH
+        {
+            panic_with_error!(&e, LiquidityPoolError::InvalidShareMetadata);
         }
 
         let share_contract = create_contract(&e, lp_token_wasm_hash, &token_a, &token_b);
-        LPTokenClient::new(&e, &share_contract).initialize(
+        put_token_share(&e, share_contract.clone());
+        let share_initialized = LPTokenClient::new(&e, &share_contract).try_initialize(
             &e.current_contract_address(),
             &7u32,
-            &"Pool Share Token".into_val(&e),
-            &"POOL".into_val(&e),
+            &share_name,
+            &share_symbol,
+            &false,
         );
+        if !matches!(share_initialized, Ok(Ok(()))) {
+            panic_with_error!(&e, LiquidityPoolError::ShareTokenInitFailed);
+        }
 
-        // 0.01% = 1; 1% = 100; 0.3% = 30
-        if fee_fraction > 9999 {
-            panic!("fee cannot be equal or greater than 100%");
+        init_pool_state(&e, &admin, token_a, token_b, fee_fraction);
+    }
+
+    fn initialize_with_share_token(
+        e: Env,
+        admin: Address,
+        share_token: Address,
+        tokens: Vec<Address>,
+        fee_fraction: u32,
+    ) {
+        let (token_a, token_b) = check_init_config(&e, &tokens, fee_fraction);
+
+        // pool keeps track of the shares itself, so the token should have no holders yet.
+        //  zero mint proves pool is the token admin without changing any balance
+        let share_client = LPTokenClient::new(&e, &share_token);
+        let share_supply = share_client.try_total_supply();
+        let share_minted = share_client.try_mint(&e.current_contract_address(), &0);
+        if !matches!(share_supply, Ok(Ok(0))) || !matches!(share_minted, Ok(Ok(()))) {
+            panic_with_error!(&e, LiquidityPoolError::InvalidShareToken);
         }
-        put_fee_fraction(&e, fee_fraction);
+        put_token_share(&e, share_token);
 
-        put_token_a(&e, token_a);
-        put_token_b(&e, token_b);
-        put_token_share(&e, share_contract);
-        put_reserve_a(&e, 0);
-        put_reserve_b(&e, 0);
+        init_pool_state(&e, &admin, token_a, token_b, fee_fraction);
+    }
 
-        let rewards = get_rewards_manager(&e);
-        rewards.manager().initialize();
+    fn initialize_stableswap(
+        e: Env,
+        admin: Address,
+        lp_token_wasm_hash: BytesN<32>,
+        tokens: Vec<Address>,
+        fee_fraction: u32,
+        amp: u128,
+    ) {
+        if amp == 0 || amp > MAX_AMP {
+            panic!("amp out of bounds");
+        }
 
-        // update plane data for every pool update
-        update_plane(&e);
+        put_curve(&e, Curve::StableSwap(amp));
+        Self::initialize(e, admin, lp_token_wasm_hash, tokens, fee_fraction);
     }
 
-    fn share_id(e: Env) -> Address {
-        get_token_share(&e)
+    fn initialize_weighted(
+        e: Env,
+        admin: Address,
+        lp_token_wasm_hash: BytesN<32>,
+        tokens: Vec<Address>,
+        fee_fraction: u32,
+        weights: Vec<u32>,
+    ) {
+        let weight_a = weights.get(0).unwrap();
+        let weight_b = weights.get(1).unwrap();
+        if weight_a == 0 || weight_b == 0 || weight_a + weight_b != WEIGHT_DENOMINATOR {
+            panic!("weights must be positive and sum up to 100");
+        }
+
+        put_curve(&e, Curve::Weighted(weight_a, weight_b));
+        Self::initialize(e, admin, lp_token_wasm_hash, tokens, fee_fraction);
+    }
+
+    fn get_total_shares(e: Env) -> i128 {
+        require_pool_initialized(&e);
+        get_total_shares(&e) as i128
     }
 
     fn get_tokens(e: Env) -> Vec<Address> {
+        require_pool_initialized(&e);
         Vec::from_array(&e, [get_token_a(&e), get_token_b(&e)])
     }
 
-    fn deposit(
+    fn get_contracts(e: Env) -> PoolContracts {
+        require_initialized(&e);
+        PoolContracts {
+            token_a: get_token_a(&e),
+            token_b: get_token_b(&e),
+            share_token: get_token_share(&e),
+            reward_token: get_rewards_manager(&e).storage().get_reward_token(),
+        }
+    }
+
+    fn derive_share_address(e: Env, token_a: Address, token_b: Address) -> Address {
+        get_share_address(&e, &token_a, &token_b)
+    }
+
+    fn verify_share_token(e: Env, share: Address) -> bool {
+        require_pool_initialized(&e);
+        share == get_token_share(&e)
+            && share == get_share_address(&e, &get_token_a(&e), &get_token_b(&e))
+    }
+
+    fn deposit_with_referrer(
         e: Env,
         user: Address,
         desired_amounts: Vec<u128>,
-        // min_amounts: Vec<u128>,
+        referrer: Option<Address>,
     ) -> (Vec<u128>, u128) {
+        require_pool_initialized(&e);
         // Depositor needs to authorize the deposit
         user.require_auth();
 
-        let (reserve_a, reserve_b) = (get_reserve_a(&e), get_reserve_b(&e));
-
-        // Before actual changes were made to the pool, update total rewards data and refresh/initialize user reward
-        let rewards = get_rewards_manager(&e);
-        let total_shares = get_total_shares(&e);
-        let user_shares = get_user_balance_shares(&e, &user);
-        let pool_data = rewards.manager().update_rewards_data(total_shares);
-        rewards
-            .manager()
-            .update_user_reward(&pool_data, &user, user_shares);
-        rewards.storage().bump_user_reward_data(&user);
-
-        let desired_a = desired_amounts.get(0).unwrap();
-        let desired_b = desired_amounts.get(1).unwrap();
+        deposit_amounts(e, user, referrer, |e, reserve_a, reserve_b| {
+            let desired_a = desired_amounts.get(0).unwrap();
+            let desired_b = desired_amounts.get(1).unwrap();
 
-        // let min_a = min_amounts.get(0).unwrap();
-        // let min_b = min_amounts.get(1).unwrap();
-        let (min_a, min_b) = (0, 0);
+            // let min_a = min_amounts.get(0).unwrap();
+            // let min_b = min_amounts.get(1).unwrap();
+            let (min_a, min_b) = (0, 0);
 
-        // Calculate deposit amounts
-        let amounts =
-            pool::get_deposit_amounts(desired_a, min_a, desired_b, min_b, reserve_a, reserve_b);
+            pool::get_deposit_amounts(desired_a, min_a, desired_b, min_b, reserve_a, reserve_b)
+                .unwrap_or_else(|error| panic_with_error!(e, error))
+        })
+    }
 
-        let token_a_client = SorobanTokenClient::new(&e, &get_token_a(&e));
-        let token_b_client = SorobanTokenClient::new(&e, &get_token_b(&e));
+    fn deposit_locked(
+        e: Env,
+        user: Address,
+        desired_amounts: Vec<u128>,
+        lock_until: u64,
+    ) -> (Vec<u128>, u128) {
+        require_pool_initialized(&e);
+        let (amounts, share_amount) = Self::deposit(e.clone(), user.clone(), desired_amounts);
+        add_liquidity_lock(&e, &user, share_amount, lock_until);
+        (amounts, share_amount)
+    }
 
-        token_a_client.transfer_from(
-            &e.current_contract_address(),
-            &user,
-            &e.current_contract_address(),
-            &(amounts.0 as i128),
-        );
-        token_b_client.transfer_from(
-            &e.current_contract_address(),
-            &user,
-            &e.current_contract_address(),
-            &(amounts.1 as i128),
-        );
+    fn lock_liquidity(e: Env, user: Address, amount: u128, lock_until: u64) {
+        require_pool_initialized(&e);
+        user.require_auth();
+        add_liquidity_lock(&e, &user, amount, lock_until);
+    }
 
-        // Now calculate how many new pool shares to mint
-        let (balance_a, balance_b) = (get_balance_a(&e), get_balance_b(&e));
-        let total_shares = get_total_shares(&e);
+    fn get_locked(e: Env, user: Address) -> (u128, u64) {
+        require_pool_initialized(&e);
+        match get_active_liquidity_lock(&e, &user) {
+            Some(lock) => (lock.amount, lock.unlock_at),
+            None => (0, 0),
+        }
+    }
 
-        let zero = 0;
-        let new_total_shares = if reserve_a > zero && reserve_b > zero {
-            let shares_a = (balance_a * total_shares) / reserve_a;
-            let shares_b = (balance_b * total_shares) / reserve_b;
-            shares_a.min(shares_b)
-        } else {
-            (balance_a * balance_b).sqrt()
-        };
+    fn deposit_single(e: Env, user: Address, in_idx: u32, amount: u128, min_shares: u128) -> u128 {
+        require_pool_initialized(&e);
+        user.require_auth();
+        deposit_single_token(e, user, in_idx, amount, min_shares)
+    }
 
-        let shares_to_mint = new_total_shares - total_shares;
-        mint_shares(&e, user, shares_to_mint as i128);
-        put_reserve_a(&e, balance_a);
-        put_reserve_b(&e, balance_b);
+    fn get_deposit_amounts(
+        e: Env,
+        desired_a: u128,
+        min_a: u128,
+        desired_b: u128,
+        min_b: u128,
+    ) -> (u128, u128) {
+        require_pool_initialized(&e);
+        pool::get_deposit_amounts(
+            desired_a,
+            min_a,
+            desired_b,
+            min_b,
+            get_reserve_a(&e),
+            get_reserve_b(&e),
+        )
+        .unwrap_or_else(|error| panic_with_error!(&e, error))
+    }
 
-        // update plane data for every pool update
-        update_plane(&e);
+    fn deposit_proportional(
+        e: Env,
+        user: Address,
+        max_a: u128,
+        max_b: u128,
+        min_shares: u128,
+    ) -> (u128, u128, u128) {
+        require_pool_initialized(&e);
+        user.require_auth();
 
-        (Vec::from_array(&e, [amounts.0, amounts.1]), shares_to_mint)
+        let (amounts, shares) =
+            deposit_amounts(e.clone(), user, None, |e, reserve_a, reserve_b| {
+                pool::get_proportional_amounts(max_a, max_b, reserve_a, reserve_b)
+                    .unwrap_or_else(|error| panic_with_error!(e, error))
+            });
+        if shares < min_shares {
+            panic_with_error!(&e, LiquidityPoolError::DepositSharesBelowMin);
+        }
+        (amounts.get(0).unwrap(), amounts.get(1).unwrap(), shares)
     }
 
-    fn swap(
+    // same arguments as swap followed by the referral pair, see the interface
+    #[allow(clippy::too_many_arguments)]
+    fn swap_with_referral(
         e: Env,
         user: Address,
         in_idx: u32,
         out_idx: u32,
         in_amount: u128,
         out_min: u128,
+        referrer: Address,
+        referral_bps: u32,
     ) -> u128 {
+        require_pool_initialized(&e);
         user.require_auth();
 
-        if in_idx == out_idx {
-            panic!("cannot swap token to same one")
-        }
-
-        if in_idx > 1 {
-            panic!("in_idx out of bounds");
-        }
-
-        if out_idx > 1 {
-            panic!("in_idx out of bounds");
+        let request = get_swap_request(&e, user, in_idx, out_idx, in_amount, out_min);
+        if referral_bps > MAX_REFERRAL_FEE || referral_bps > request.fee_fraction {
+            panic_with_error!(&e, LiquidityPoolError::ReferralFeeTooHigh);
         }
 
-        let reserve_a = get_reserve_a(&e);
-        let reserve_b = get_reserve_b(&e);
-        let reserves = Vec::from_array(&e, [reserve_a, reserve_b]);
-        let tokens = Self::get_tokens(e.clone());
-        let reserve_sell = reserves.get(in_idx).unwrap();
-        let reserve_buy = reserves.get(out_idx).unwrap();
+        require_swap_allowed(&e, &request);
+        execute_swap(&e, request, Some((referrer, referral_bps))).amount_out
+    }
 
-        let fee_fraction = get_fee_fraction(&e);
+    fn swap_strict(
+        e: Env,
+        user: Address,
+        in_idx: u32,
+        out_idx: u32,
+        out: u128,
+        quoted_in: u128,
+        max_slippage_bps: u32,
+    ) -> u128 {
+        require_pool_initialized(&e);
+        user.require_auth();
 
-        // First calculate how much we can get with in_amount from the pool
-        let multiplier_with_fee = FEE_MULTIPLIER - fee_fraction as u128;
-        let n = in_amount * reserve_buy * multiplier_with_fee;
-        let d = reserve_sell * FEE_MULTIPLIER + in_amount * multiplier_with_fee;
-        let out = n / d;
-        if out < out_min {
-            panic!("out amount is less than min")
+        if max_slippage_bps as u128 > FEE_MULTIPLIER {
+            panic_with_error!(&e, LiquidityPoolError::InvalidSlippage);
         }
-
-        // Transfer the amount being sold to the contract
-        let sell_token = tokens.get(in_idx).unwrap();
-        let sell_token_client = SorobanTokenClient::new(&e, &sell_token);
-        sell_token_client.transfer_from(
-            &e.current_contract_address(),
-            &user,
-            &e.current_contract_address(),
-            &(in_amount as i128),
+        // amount sold is recomputed on the reserves at the execution time
+        let state = PoolState::load(&e);
+        let fee_fraction = get_user_fee_fraction(&e, &state, &user);
+        let in_amount = get_swap_in_amount(&e, &state, fee_fraction, in_idx, out_idx, out);
+        let in_max = mul_div_floor(
+            quoted_in,
+            FEE_MULTIPLIER + max_slippage_bps as u128,
+            FEE_MULTIPLIER,
         );
+        if in_amount > in_max {
+            panic!("in amount is over max");
+        }
 
-        let (balance_a, balance_b) = (get_balance_a(&e), get_balance_b(&e));
-
-        // residue_numerator and residue_denominator are the amount that the invariant considers after
-        // deducting the fee, scaled up by FEE_MULTIPLIER to avoid fractions
-        let residue_numerator = FEE_MULTIPLIER - fee_fraction as u128;
-        let residue_denominator = FEE_MULTIPLIER;
+        let request = get_swap_request(&e, user.clone(), in_idx, out_idx, in_amount, out);
+        require_swap_allowed(&e, &request);
+        execute_swap(&e, request, None);
 
-        let new_invariant_factor = |balance: u128, reserve: u128, out: u128| {
-            if balance - reserve > out {
-                residue_denominator * reserve + residue_numerator * (balance - reserve - out)
-            } else {
-                residue_denominator * reserve + residue_denominator * balance
-                    - residue_denominator * (reserve + out)
-            }
+        // price may only move against the quote by the slippage, improvement counts as zero
+        let slippage_bps = match in_amount > quoted_in {
+            true => mul_div_ceil(in_amount - quoted_in, FEE_MULTIPLIER, quoted_in) as u32,
+            false => 0,
         };
+        Events::new(&e).swap_slippage(user, quoted_in, in_amount, slippage_bps);
+        in_amount
+    }
 
-        let (out_a, out_b) = if out_idx == 0 { (out, 0) } else { (0, out) };
+    fn estimate_swap_in(e: Env, in_idx: u32, out_idx: u32, out: u128) -> u128 {
+        require_pool_initialized(&e);
+        let state = PoolState::load(&e);
+        get_swap_in_amount(&e, &state, state.fee_fraction, in_idx, out_idx, out)
+    }
 
-        let new_inv_a = new_invariant_factor(balance_a, reserve_a, out_a);
-        let new_inv_b = new_invariant_factor(balance_b, reserve_b, out_b);
-        let old_inv_a = residue_denominator * reserve_a;
-        let old_inv_b = residue_denominator * reserve_b;
+    fn swap_pct(
+        e: Env,
+        user: Address,
+        in_idx: u32,
+        out_idx: u32,
+        bps_of_reserve: u32,
+        in_max: u128,
+    ) -> SwapResult {
+        require_pool_initialized(&e);
+        user.require_auth();
 
-        if new_inv_a * new_inv_b < old_inv_a * old_inv_b {
-            panic!("constant product invariant does not hold");
+        // amounts are priced on the reserves at the execution time
+        let state = PoolState::load(&e);
+        let fee_fraction = get_user_fee_fraction(&e, &state, &user);
+        let (in_amount, out) =
+            get_swap_pct_amounts(&e, &state, fee_fraction, in_idx, out_idx, bps_of_reserve);
+        if in_amount > in_max {
+            panic!("in amount is over max");
         }
 
-        if out_idx == 0 {
-            transfer_a(&e, user, out_a);
-        } else {
-            transfer_b(&e, user, out_b);
-        }
+        let request = get_swap_request(&e, user, in_idx, out_idx, in_amount, out);
+        require_swap_allowed(&e, &request);
+        execute_swap(&e, request, None)
+    }
 
-        put_reserve_a(&e, balance_a - out_a);
-        put_reserve_b(&e, balance_b - out_b);
+    fn estimate_swap_pct(e: Env, in_idx: u32, out_idx: u32, bps_of_reserve: u32) -> (u128, u128) {
+        require_pool_initialized(&e);
+        let state = PoolState::load(&e);
+        get_swap_pct_amounts(
+            &e,
+            &state,
+            state.fee_fraction,
+            in_idx,
+            out_idx,
+            bps_of_reserve,
+        )
+    }
 
-        // update plane data for every pool update
-        update_plane(&e);
+    fn swap_or_reject(
+        e: Env,
+        user: Address,
+        in_idx: u32,
+        out_idx: u32,
+        in_amount: u128,
+        out_min: u128,
+    ) -> SwapOutcome {
+        require_pool_initialized(&e);
+        user.require_auth();
 
-        out
+        let request = get_swap_request(&e, user, in_idx, out_idx, in_amount, out_min);
+        match check_swap_allowed(&e, &request) {
+            Ok(()) => SwapOutcome::Swapped(execute_swap(&e, request, None).amount_out),
+            Err(guard) => {
+                let guard = record_rejection(&e, guard);
+                Events::new(&e).swap_rejected(request.user, guard.clone());
+                SwapOutcome::Rejected(guard)
+            }
+        }
     }
 
-    fn estimate_swap(e: Env, in_idx: u32, out_idx: u32, in_amount: u128) -> u128 {
-        if in_idx == out_idx {
-            panic!("cannot swap token to same one")
-        }
+    fn estimate_swap_for(
+        e: Env,
+        user: Address,
+        in_idx: u32,
+        out_idx: u32,
+        in_amount: u128,
+    ) -> u128 {
+        require_pool_initialized(&e);
+        get_swap_request(&e, user, in_idx, out_idx, in_amount, 0).out
+    }
 
-        if in_idx > 1 {
-            panic!("in_idx out of bounds");
-        }
+    fn estimate_price_impact(e: Env, in_idx: u32, out_idx: u32, in_amount: u128) -> u128 {
+        require_pool_initialized(&e);
+        let out = Self::estimate_swap(e.clone(), in_idx, out_idx, in_amount);
+        get_swap_price_impact(&e, &PoolState::load(&e), in_idx, out_idx, in_amount, out)
+    }
 
-        if out_idx > 1 {
-            panic!("in_idx out of bounds");
-        }
+    fn check_swap(
+        e: Env,
+        user: Address,
+        in_idx: u32,
+        out_idx: u32,
+        in_amount: u128,
+        out_min: u128,
+    ) -> Vec<Symbol> {
+        require_pool_initialized(&e);
+        let request = get_swap_request(&e, user, in_idx, out_idx, in_amount, out_min);
+        get_swap_violations(&e, &request)
+    }
+
+    fn withdraw_all(e: Env, user: Address, min_amounts: Vec<u128>) -> Vec<u128> {
+        require_pool_initialized(&e);
+        user.require_auth();
+        require_storage_migrated(&e);
 
-        let reserve_a = get_reserve_a(&e);
-        let reserve_b = get_reserve_b(&e);
-        let reserves = Vec::from_array(&e, [reserve_a, reserve_b]);
-        let reserve_sell = reserves.get(in_idx).unwrap();
-        let reserve_buy = reserves.get(out_idx).unwrap();
+        let share_amount = get_user_balance_shares(&e, &user);
+        if share_amount == 0 {
+            panic_with_error!(&e, LiquidityPoolError::ZeroSharesWithdraw);
+        }
+        checkpoint_withdraw(&e, &user, share_amount);
 
-        let fee_fraction = get_fee_fraction(&e);
+        take_shares(&e, &user, share_amount);
+        pay_withdraw(&e, &user, min_amounts)
+    }
 
-        // First calculate how much needs to be sold to buy amount out from the pool
-        let multiplier_with_fee = FEE_MULTIPLIER - fee_fraction as u128;
-        let n = in_amount * reserve_buy * multiplier_with_fee;
-        let d = reserve_sell * FEE_MULTIPLIER + in_amount * multiplier_with_fee;
+    fn withdraw_to(
+        e: Env,
+        user: Address,
+        share_amount: u128,
+        min_amounts: Vec<u128>,
+        recipient_a: Address,
+        recipient_b: Address,
+    ) -> Vec<u128> {
+        require_pool_initialized(&e);
+        user.require_auth();
+        require_storage_migrated(&e);
+        checkpoint_withdraw(&e, &user, share_amount);
 
-        n / d
+        receive_shares(&e, &user, share_amount);
+        pay_withdraw_to(&e, &user, &recipient_a, &recipient_b, min_amounts)
     }
 
-    fn withdraw(e: Env, user: Address, share_amount: u128, min_amounts: Vec<u128>) -> Vec<u128> {
+    fn emergency_withdraw(e: Env, user: Address, share_amount: u128) -> Vec<u128> {
+        require_pool_initialized(&e);
         user.require_auth();
 
-        // Before actual changes were made to the pool, update total rewards data and refresh user reward
-        let rewards = get_rewards_manager(&e);
-        let total_shares = get_total_shares(&e);
-        let user_shares = get_user_balance_shares(&e, &user);
-        let pool_data = rewards.manager().update_rewards_data(total_shares);
-        rewards
-            .manager()
-            .update_user_reward(&pool_data, &user, user_shares);
-        rewards.storage().bump_user_reward_data(&user);
+        if !get_is_emergency_mode(&e) {
+            panic_with_error!(&e, LiquidityPoolError::EmergencyModeDisabled);
+        }
+        if has_migration_pool(&e) {
+            panic_with_error!(&e, LiquidityPoolError::LiquidityMigrated);
+        }
 
-        // First transfer the pool shares that need to be redeemed
-        let share_token_client = SorobanTokenClient::new(&e, &get_token_share(&e));
-        share_token_client.transfer_from(
-            &e.current_contract_address(),
-            &user,
-            &e.current_contract_address(),
-            &(share_amount as i128),
-        );
+        require_liquidity_unlocked(&e, &user, share_amount);
+
+        // rewards are skipped intentionally: rewards subsystem may be broken
+        receive_shares(&e, &user, share_amount);
 
         let (balance_a, balance_b) = (get_balance_a(&e), get_balance_b(&e));
         let balance_shares = get_balance_shares(&e);
         let total_shares = get_total_shares(&e);
 
-        // Now calculate the withdraw amounts
-        let out_a = (balance_a * balance_shares) / total_shares;
-        let out_b = (balance_b * balance_shares) / total_shares;
-
-        let min_a = min_amounts.get(0).unwrap();
-        let min_b = min_amounts.get(1).unwrap();
-
-        if out_a < min_a || out_b < min_b {
-            panic!("min not satisfied");
-        }
+        let out_a = mul_div_floor(balance_a, balance_shares, total_shares);
+        let out_b = mul_div_floor(balance_b, balance_shares, total_shares);
 
         burn_shares(&e, balance_shares as i128);
-        transfer_a(&e, user.clone(), out_a);
-        transfer_b(&e, user, out_b);
+        sync_reward_shares(&e, &user);
+        sync_holder(&e, &user);
+        // effects before interactions: reserves are final before tokens leave the pool
         put_reserve_a(&e, balance_a - out_a);
         put_reserve_b(&e, balance_b - out_b);
+        transfer_a(&e, user.clone(), out_a);
+        transfer_b(&e, user.clone(), out_b);
 
         // update plane data for every pool update
         update_plane(&e);
 
-        Vec::from_array(&e, [out_a, out_b])
+        let amounts = Vec::from_array(&e, [out_a, out_b]);
+        Events::new(&e).withdraw(user, balance_shares, amounts.clone());
+        amounts
     }
 
-    fn get_reserves(e: Env) -> Vec<u128> {
-        Vec::from_array(&e, [get_reserve_a(&e), get_reserve_b(&e)])
+    fn get_price(e: Env) -> u128 {
+        require_pool_initialized(&e);
+        get_pool_price(&e, get_curve(&e), get_reserve_a(&e), get_reserve_b(&e))
     }
 
-    fn get_fee_fraction(e: Env) -> u32 {
-        // returns fee fraction. 0.01% = 1; 1% = 100; 0.3% = 30
-        get_fee_fraction(&e)
+    fn get_reserves_with_timestamp(e: Env) -> (u128, u128, u64) {
+        require_pool_initialized(&e);
+        (
+            get_reserve_a(&e),
+            get_reserve_b(&e),
+            get_reserves_updated_at(&e),
+        )
     }
 
-    fn get_info(e: Env) -> Map<Symbol, Val> {
-        let fee = get_fee_fraction(&e);
-        let pool_type = Self::pool_type(e.clone());
-        let mut result = Map::new(&e);
-        result.set(symbol_short!("pool_type"), pool_type.into_val(&e));
-        result.set(symbol_short!("fee"), fee.into_val(&e));
-        result
-    }
-}
+    fn donate(e: Env, from: Address, amount_a: u128, amount_b: u128) -> Vec<u128> {
+        require_pool_initialized(&e);
+        from.require_auth();
+        require_storage_migrated(&e);
 
-impl UpgradeableContractTrait for LiquidityPool {
-    fn version() -> u32 {
-        100
-    }
+        if get_is_killed(&e) {
+            panic_with_error!(&e, LiquidityPoolError::PoolKilled);
+        }
+        if get_is_paused(&e) {
+            panic_with_error!(&e, LiquidityPoolError::PoolPaused);
+        }
+        // nobody would own the donation
+        if get_total_shares(&e) == 0 {
+            panic!("pool is empty");
+        }
 
-    fn upgrade(e: Env, new_wasm_hash: BytesN<32>) {
-        let access_control = AccessControl::new(&e);
-        access_control.require_admin();
-        e.deployer().update_current_contract_wasm(new_wasm_hash);
+        let (balance_a, balance_b) = (get_balance_a(&e), get_balance_b(&e));
+        if amount_a > 0 {
+            SorobanTokenClient::new(&e, &get_token_a(&e)).transfer(
+                &from,
+                &e.current_contract_address(),
+                &(amount_a as i128),
+            );
+        }
+        if amount_b > 0 {
+            SorobanTokenClient::new(&e, &get_token_b(&e)).transfer(
+                &from,
+                &e.current_contract_address(),
+                &(amount_b as i128),
+            );
+        }
+
+        // tokens charging transfer fee credit less than transferred, only received amount counts
+        let received_a = get_balance_a(&e) - balance_a;
+        let received_b = get_balance_b(&e) - balance_b;
+        put_reserve_a(&e, get_reserve_a(&e) + received_a);
+        put_reserve_b(&e, get_reserve_b(&e) + received_b);
+
+        // update plane data for every pool update
+        update_plane(&e);
+
+        let amounts = Vec::from_array(&e, [received_a, received_b]);
+        Events::new(&e).donate(from, amounts.clone());
+        amounts
     }
-}
 
-#[contractimpl]
-impl RewardsTrait for LiquidityPool {
-    fn initialize_rewards_config(e: Env, reward_token: Address, reward_storage: Address) {
-        // admin.require_auth();
-        // check_admin(&e, &admin);
+    fn skim(e: Env, to: Address) -> Vec<u128> {
+        require_pool_initialized(&e);
+        let (reserve_a, reserve_b) = (get_reserve_a(&e), get_reserve_b(&e));
+        let (balance_a, balance_b) = (get_balance_a(&e), get_balance_b(&e));
 
-        let rewards = get_rewards_manager(&e);
-        if rewards.storage().has_reward_token() {
-            panic!("rewards config already initialized")
+        // reserves are never touched, only the excess is transferred
+        let excess_a = balance_a.saturating_sub(reserve_a);
+        let excess_b = balance_b.saturating_sub(reserve_b);
+        if excess_a == 0 && excess_b == 0 {
+            return Vec::from_array(&e, [0, 0]);
         }
 
-        rewards.storage().put_reward_token(reward_token);
-        rewards.storage().put_reward_storage(reward_storage);
+        if excess_a > 0 {
+            transfer_a(&e, to.clone(), excess_a);
+        }
+        if excess_b > 0 {
+            transfer_b(&e, to.clone(), excess_b);
+        }
+
+        let amounts = Vec::from_array(&e, [excess_a, excess_b]);
+        Events::new(&e).skim(to, amounts.clone());
+        amounts
     }
 
-    fn set_rewards_config(
-        e: Env,
-        admin: Address,
+    fn sync(e: Env) {
+        require_pool_initialized(&e);
+        let old_reserves = Vec::from_array(&e, [get_reserve_a(&e), get_reserve_b(&e)]);
+        let (balance_a, balance_b) = (get_balance_a(&e), get_balance_b(&e));
+        put_reserve_a(&e, balance_a);
+        put_reserve_b(&e, balance_b);
+        // shares transferred to the pool directly are redeemed by the next withdrawal
+        sync_balance_shares(&e);
+
+        // update plane data for every pool update
+        update_plane(&e);
+
+        Events::new(&e).sync(old_reserves, Vec::from_array(&e, [balance_a, balance_b]));
+    }
+
+    fn get_fee_fraction(e: Env) -> u32 {
+        require_pool_initialized(&e);
+        // returns fee fraction. 0.01% = 1; 1% = 100; 0.3% = 30
+        get_fee_fraction(&e)
+    }
+
+    fn get_quote_context(e: Env) -> QuoteContext {
+        require_pool_initialized(&e);
+        QuoteContext {
+            token_a: get_token_a(&e),
+            token_b: get_token_b(&e),
+            reserve_a: get_reserve_a(&e),
+            reserve_b: get_reserve_b(&e),
+            fee_bps: get_fee_fraction(&e),
+            paused: get_is_paused(&e),
+        }
+    }
+
+    fn get_position(e: Env, user: Address) -> UserPosition {
+        require_pool_initialized(&e);
+        let shares = get_user_balance_shares(&e, &user);
+        let total_shares = get_total_shares(&e);
+        // position doesn't depend on rewards, pool without rewards config has nothing pending
+        let pending_reward = match get_rewards_manager(&e).storage().has_reward_token() {
+            true => Self::get_user_reward(e.clone(), user),
+            false => 0,
+        };
+        if shares == 0 {
+            return UserPosition {
+                shares,
+                ownership_bps: 0,
+                amount_a: 0,
+                amount_b: 0,
+                pending_reward,
+            };
+        }
+
+        UserPosition {
+            shares,
+            ownership_bps: mul_div_floor(shares, FEE_MULTIPLIER, total_shares) as u32,
+            amount_a: mul_div_floor(get_reserve_a(&e), shares, total_shares),
+            amount_b: mul_div_floor(get_reserve_b(&e), shares, total_shares),
+            pending_reward,
+        }
+    }
+
+    fn get_fee_earnings(e: Env, user: Address) -> Vec<u128> {
+        require_pool_initialized(&e);
+        let earnings = calc_fee_earnings(&e, &user);
+        Vec::from_array(&e, [earnings.earned_a, earnings.earned_b])
+    }
+
+    fn get_stats(e: Env) -> Map<Symbol, i128> {
+        require_pool_initialized(&e);
+        let stats = get_swap_stats(&e);
+        let saturate = |value: u128| value.min(i128::MAX as u128) as i128;
+        let mut result = Map::new(&e);
+        result.set(Symbol::new(&e, "volume_a"), saturate(stats.volume_a));
+        result.set(Symbol::new(&e, "volume_b"), saturate(stats.volume_b));
+        result.set(symbol_short!("fee_a"), saturate(stats.fee_a));
+        result.set(symbol_short!("fee_b"), saturate(stats.fee_b));
+        result.set(Symbol::new(&e, "last_trade"), stats.last_trade_at as i128);
+        result
+    }
+
+    fn get_info(e: Env) -> Map<Symbol, Val> {
+        require_pool_initialized(&e);
+        let fee = get_fee_fraction(&e);
+        let pool_type = Self::pool_type(e.clone());
+        let mut result = Map::new(&e);
+        result.set(symbol_short!("pool_type"), pool_type.into_val(&e));
+        result.set(symbol_short!("fee"), fee.into_val(&e));
+        result.set(
+            Symbol::new(&e, "min_reserve"),
+            get_min_reserve(&e).into_val(&e),
+        );
+        match get_curve(&e) {
+            Curve::ConstantProduct => {
+                result.set(
+                    symbol_short!("curve"),
+                    Symbol::new(&e, "constant_product").into_val(&e),
+                );
+            }
+            Curve::StableSwap(amp) => {
+                result.set(symbol_short!("curve"), symbol_short!("stable").into_val(&e));
+                result.set(symbol_short!("amp"), amp.into_val(&e));
+            }
+            Curve::Weighted(weight_a, weight_b) => {
+                result.set(
+                    symbol_short!("curve"),
+                    symbol_short!("weighted").into_val(&e),
+                );
+                result.set(
+                    symbol_short!("weights"),
+                    Vec::from_array(&e, [weight_a, weight_b]).into_val(&e),
+                );
+            }
+        }
+        result.set(
+            Symbol::new(&e, "risk_params"),
+            Self::get_risk_params(e.clone()).into_val(&e),
+        );
+        result
+    }
+}
+
+// The first deposit sets the pool price, so once the bootstrapper is set nobody else can seed the pool
+fn require_bootstrapper(e: &Env, user: &Address) {
+    if let Some(bootstrapper) = get_bootstrapper(e) {
+        if *user != bootstrapper {
+            panic_with_error!(e, LiquidityPoolError::BootstrapRequired);
+        }
+    }
+}
+
+#[contractimpl]
+impl LiquidityPoolInterface for LiquidityPool {
+    fn share_id(e: Env) -> Address {
+        require_pool_initialized(&e);
+        get_token_share(&e)
+    }
+
+    fn get_reserves(e: Env) -> Vec<u128> {
+        require_pool_initialized(&e);
+        Vec::from_array(&e, [get_reserve_a(&e), get_reserve_b(&e)])
+    }
+
+    fn deposit(
+        e: Env,
+        user: Address,
+        desired_amounts: Vec<u128>,
+        // min_amounts: Vec<u128>,
+    ) -> (Vec<u128>, u128) {
+        require_pool_initialized(&e);
+        Self::deposit_with_referrer(e, user, desired_amounts, None)
+    }
+
+    fn swap(
+        e: Env,
+        user: Address,
+        in_idx: u32,
+        out_idx: u32,
+        in_amount: u128,
+        out_min: u128,
+    ) -> u128 {
+        require_pool_initialized(&e);
+        user.require_auth();
+
+        let request = get_swap_request(&e, user, in_idx, out_idx, in_amount, out_min);
+        require_swap_allowed(&e, &request);
+        execute_swap(&e, request, None).amount_out
+    }
+
+    fn swap_v2(
+        e: Env,
+        user: Address,
+        in_idx: u32,
+        out_idx: u32,
+        in_amount: u128,
+        out_min: u128,
+    ) -> SwapResult {
+        require_pool_initialized(&e);
+        user.require_auth();
+
+        let request = get_swap_request(&e, user, in_idx, out_idx, in_amount, out_min);
+        require_swap_allowed(&e, &request);
+        execute_swap(&e, request, None)
+    }
+
+    fn estimate_swap(e: Env, in_idx: u32, out_idx: u32, in_amount: u128) -> u128 {
+        require_pool_initialized(&e);
+        if in_idx == out_idx {
+            panic!("cannot swap token to same one")
+        }
+
+        if in_idx > 1 {
+            panic!("in_idx out of bounds");
+        }
+
+        if out_idx > 1 {
+            panic!("in_idx out of bounds");
+        }
+
+        let state = PoolState::load(&e);
+        get_swap_out(&e, &state, state.fee_fraction, in_idx, out_idx, in_amount)
+    }
+
+    fn withdraw(e: Env, user: Address, share_amount: u128, min_amounts: Vec<u128>) -> Vec<u128> {
+        require_pool_initialized(&e);
+        user.require_auth();
+        withdraw_shares(&e, &user, share_amount, min_amounts)
+    }
+}
+
+// Validate swap indexes and estimate the swap
+fn get_swap_request(
+    e: &Env,
+    user: Address,
+    in_idx: u32,
+    out_idx: u32,
+    in_amount: u128,
+    out_min: u128,
+) -> SwapRequest {
+    require_storage_migrated(e);
+    if in_idx == out_idx {
+        panic!("cannot swap token to same one")
+    }
+
+    if in_idx > 1 {
+        panic!("in_idx out of bounds");
+    }
+
+    if out_idx > 1 {
+        panic!("in_idx out of bounds");
+    }
+
+    // First calculate how much we can get with in_amount from the pool
+    let state = PoolState::load(e);
+    let fee_fraction = get_user_fee_fraction(e, &state, &user);
+    let out = get_swap_out(e, &state, fee_fraction, in_idx, out_idx, in_amount);
+    SwapRequest {
+        state,
+        user,
+        fee_fraction,
+        in_idx,
+        out_idx,
+        in_amount,
+        out,
+        out_min,
+    }
+}
+
+// Amount to sell to buy out of the token out_idx.
+//  Only the constant product curve is solved for the amount sold
+fn get_swap_in_amount(
+    e: &Env,
+    state: &PoolState,
+    fee_fraction: u32,
+    in_idx: u32,
+    out_idx: u32,
+    out: u128,
+) -> u128 {
+    if in_idx == out_idx {
+        panic!("cannot swap token to same one")
+    }
+    if in_idx > 1 || out_idx > 1 {
+        panic!("in_idx out of bounds");
+    }
+    if state.curve != Curve::ConstantProduct {
+        panic_with_error!(e, LiquidityPoolError::CurveNotSupported);
+    }
+
+    let reserves = state.reserves(e);
+    let reserve_sell = reserves.get(in_idx).unwrap();
+    let reserve_buy = reserves.get(out_idx).unwrap();
+    if out >= reserve_buy {
+        panic_with_error!(e, LiquidityPoolError::InsufficientReserves);
+    }
+    pool::get_swap_in(fee_fraction, reserve_sell, reserve_buy, out)
+}
+
+// Amount to sell and amount bought for the bps_of_reserve share of the reserve being bought.
+//  Only the constant product curve is solved for the amount sold
+fn get_swap_pct_amounts(
+    e: &Env,
+    state: &PoolState,
+    fee_fraction: u32,
+    in_idx: u32,
+    out_idx: u32,
+    bps_of_reserve: u32,
+) -> (u128, u128) {
+    if in_idx == out_idx {
+        panic!("cannot swap token to same one")
+    }
+    if in_idx > 1 || out_idx > 1 {
+        panic!("in_idx out of bounds");
+    }
+    if bps_of_reserve == 0 || bps_of_reserve > MAX_SWAP_PCT {
+        panic_with_error!(e, LiquidityPoolError::InvalidSwapPercentage);
+    }
+    if state.curve != Curve::ConstantProduct {
+        panic_with_error!(e, LiquidityPoolError::CurveNotSupported);
+    }
+
+    let reserves = state.reserves(e);
+    let reserve_sell = reserves.get(in_idx).unwrap();
+    let reserve_buy = reserves.get(out_idx).unwrap();
+    let out = reserve_buy * bps_of_reserve as u128 / FEE_MULTIPLIER;
+    // share is too small to buy anything
+    if out == 0 {
+        panic_with_error!(e, LiquidityPoolError::InvalidSwapPercentage);
+    }
+    let in_amount = pool::get_swap_in(fee_fraction, reserve_sell, reserve_buy, out);
+    (in_amount, out)
+}
+
+// Calculate how much can be bought with in_amount on the pool curve.
+//  StableSwap math operates on the normalized amounts, so tokens decimals may differ
+fn get_swap_out(
+    e: &Env,
+    state: &PoolState,
+    fee_fraction: u32,
+    in_idx: u32,
+    out_idx: u32,
+    in_amount: u128,
+) -> u128 {
+    let reserves = state.reserves(e);
+    let reserve_sell = reserves.get(in_idx).unwrap();
+    let reserve_buy = reserves.get(out_idx).unwrap();
+
+    match state.curve {
+        Curve::ConstantProduct => {
+            pool::get_swap_out(fee_fraction, reserve_sell, reserve_buy, in_amount)
+        }
+        Curve::StableSwap(amp) => {
+            let decimals = Vec::from_array(e, [get_decimals_a(e), get_decimals_b(e)]);
+            let decimals_sell = decimals.get(in_idx).unwrap();
+            let decimals_buy = decimals.get(out_idx).unwrap();
+            let out = stable_math::get_swap_out(
+                fee_fraction,
+                amp,
+                pool::normalize_amount(reserve_sell, decimals_sell),
+                pool::normalize_amount(reserve_buy, decimals_buy),
+                pool::normalize_amount(in_amount, decimals_sell),
+            );
+            pool::denormalize_amount(out, decimals_buy)
+        }
+        Curve::Weighted(weight_a, weight_b) => {
+            let weights = Vec::from_array(e, [weight_a, weight_b]);
+            weighted_math::get_swap_out(
+                fee_fraction,
+                weights.get(in_idx).unwrap(),
+                weights.get(out_idx).unwrap(),
+                reserve_sell,
+                reserve_buy,
+                in_amount,
+            )
+        }
+    }
+}
+
+// Pool initialize completed. Admin is written by initialize along with the rest of the pool config,
+//  so it marks the pool initialized
+fn require_pool_initialized(e: &Env) {
+    if !AccessControl::new(e).has_admin() {
+        panic_with_error!(e, LiquidityPoolError::NotInitialized);
+    }
+}
+
+// Rewards entrypoints and contract addresses need both pool and rewards config initialized
+fn require_initialized(e: &Env) {
+    require_pool_initialized(e);
+    if !get_rewards_manager(e).storage().has_reward_token() {
+        panic_with_error!(e, LiquidityPoolError::NotInitialized);
+    }
+}
+
+// Deposit amount of the token in_idx alone and mint the shares it's worth on the pool curve.
+//  User authorization is left to the entrypoint, so it's required once per call
+fn deposit_single_token(
+    e: Env,
+    user: Address,
+    in_idx: u32,
+    amount: u128,
+    min_shares: u128,
+) -> u128 {
+    require_storage_migrated(&e);
+
+    if in_idx > 1 {
+        panic!("in_idx out of bounds");
+    }
+    if get_is_killed(&e) {
+        panic_with_error!(&e, LiquidityPoolError::PoolKilled);
+    }
+    if get_is_paused(&e) {
+        panic_with_error!(&e, LiquidityPoolError::PoolPaused);
+    }
+    require_user_allowed(&e, &user);
+    let weights = match get_curve(&e) {
+        Curve::ConstantProduct => Vec::from_array(&e, [1, 1]),
+        Curve::Weighted(weight_a, weight_b) => Vec::from_array(&e, [weight_a, weight_b]),
+        Curve::StableSwap(_) => panic_with_error!(&e, LiquidityPoolError::CurveNotSupported),
+    };
+    let weight_in = weights.get(in_idx).unwrap() * WEIGHT_DENOMINATOR
+        / (weights.get(0).unwrap() + weights.get(1).unwrap());
+
+    let total_shares = get_total_shares(&e);
+    if total_shares == 0 {
+        panic!("pool is empty");
+    }
+
+    // Before actual changes were made to the pool, update total rewards data and refresh/initialize user reward
+    let rewards = get_rewards_manager(&e);
+    let pool_data = rewards
+        .manager()
+        .update_rewards_data(get_total_reward_shares(&e));
+    rewards
+        .manager()
+        .update_user_reward(&pool_data, &user, get_user_reward_shares(&e, &user));
+    rewards.storage().bump_user_reward_data(&user);
+    checkpoint_fee_earnings(&e, &user);
+
+    let tokens = Vec::from_array(&e, [get_token_a(&e), get_token_b(&e)]);
+    let reserves = Vec::from_array(&e, [get_reserve_a(&e), get_reserve_b(&e)]);
+    let balances_before = Vec::from_array(&e, [get_balance_a(&e), get_balance_b(&e)]);
+    SorobanTokenClient::new(&e, &tokens.get(in_idx).unwrap()).transfer(
+        &user,
+        &e.current_contract_address(),
+        &(amount as i128),
+    );
+    let (balance_a, balance_b) = (get_balance_a(&e), get_balance_b(&e));
+
+    // tokens charging transfer fee credit less than transferred, only received amount counts
+    let balances = Vec::from_array(&e, [balance_a, balance_b]);
+    let received = balances.get(in_idx).unwrap() - balances_before.get(in_idx).unwrap();
+    let shares_to_mint = weighted_math::get_single_deposit_shares(
+        get_fee_fraction(&e),
+        weight_in,
+        reserves.get(in_idx).unwrap(),
+        total_shares,
+        received,
+    );
+    if shares_to_mint == 0 {
+        panic_with_error!(&e, LiquidityPoolError::ZeroSharesDeposit);
+    }
+    if shares_to_mint < min_shares {
+        panic!("min not satisfied");
+    }
+
+    mint_shares(&e, user.clone(), shares_to_mint as i128);
+    sync_reward_shares(&e, &user);
+    sync_holder(&e, &user);
+    put_reserve_a(&e, balance_a);
+    put_reserve_b(&e, balance_b);
+
+    // update plane data for every pool update
+    update_plane(&e);
+
+    let mut amounts = Vec::from_array(&e, [0, 0]);
+    amounts.set(in_idx, received);
+    Events::new(&e).deposit(user, amounts, shares_to_mint);
+    shares_to_mint
+}
+
+// Validate initialize arguments. Returns the pool tokens
+fn check_init_config(e: &Env, tokens: &Vec<Address>, fee_fraction: u32) -> (Address, Address) {
+    if AccessControl::new(e).has_admin() {
+        panic_with_error!(e, LiquidityPoolError::AlreadyInitialized);
+    }
+
+    let token_a = tokens.get(0).unwrap();
+    let token_b = tokens.get(1).unwrap();
+
+    if token_a == token_b {
+        panic_with_error!(e, LiquidityPoolError::IdenticalTokens);
+    }
+    if token_a > token_b {
+        panic_with_error!(e, LiquidityPoolError::TokensNotSorted);
+    }
+
+    // 0.01% = 1; 1% = 100; 0.3% = 30
+    if fee_fraction > 9999 {
+        panic!("fee cannot be equal or greater than 100%");
+    }
+    (token_a, token_b)
+}
+
+// Write the pool state once the share token is recorded
+fn init_pool_state(
+    e: &Env,
+    admin: &Address,
+    token_a: Address,
+    token_b: Address,
+    fee_fraction: u32,
+) {
+    AccessControl::new(e).set_admin(admin);
+    put_fee_fraction(e, fee_fraction);
+    put_decimals_a(e, SorobanTokenClient::new(e, &token_a).decimals());
+    put_decimals_b(e, SorobanTokenClient::new(e, &token_b).decimals());
+    put_token_a(e, token_a);
+    put_token_b(e, token_b);
+    put_reserve_a(e, 0);
+    put_reserve_b(e, 0);
+    // new pool has no holders to backfill
+    put_backfill_complete(e, true);
+    put_storage_version(e, STORAGE_VERSION);
+
+    let rewards = get_rewards_manager(e);
+    rewards.manager().initialize();
+
+    // update plane data for every pool update
+    update_plane(e);
+}
+
+// Deposit amounts chosen by get_amounts on the pool reserves and mint the shares.
+//  User authorization is left to the entrypoint, so it's required once per call
+fn deposit_amounts(
+    e: Env,
+    user: Address,
+    referrer: Option<Address>,
+    get_amounts: impl FnOnce(&Env, u128, u128) -> (u128, u128),
+) -> (Vec<u128>, u128) {
+    require_storage_migrated(&e);
+
+    if referrer == Some(user.clone()) {
+        panic_with_error!(&e, LiquidityPoolError::SelfReferral);
+    }
+
+    if get_is_killed(&e) {
+        panic_with_error!(&e, LiquidityPoolError::PoolKilled);
+    }
+    if get_is_paused(&e) {
+        panic_with_error!(&e, LiquidityPoolError::PoolPaused);
+    }
+    require_user_allowed(&e, &user);
+
+    let mut state = PoolState::load(&e);
+    let (reserve_a, reserve_b) = (state.reserve_a, state.reserve_b);
+
+    // Before actual changes were made to the pool, update total rewards data and refresh/initialize user reward
+    let rewards = get_rewards_manager(&e);
+    let total_shares = get_total_reward_shares(&e);
+    let user_shares = get_user_reward_shares(&e, &user);
+    let pool_data = rewards.manager().update_rewards_data(total_shares);
+    let user_data = rewards
+        .manager()
+        .update_user_reward(&pool_data, &user, user_shares);
+    rewards.storage().bump_user_reward_data(&user);
+    checkpoint_fee_earnings(&e, &user);
+
+    if let Some(referrer) = referrer {
+        let (referrer_bonus, user_bonus) =
+            pay_referral_bonus(&e, &referrer, &user, user_data.to_claim);
+        Events::new(&e).referral_bonus(referrer, user.clone(), referrer_bonus, user_bonus);
+    }
+
+    // Calculate deposit amounts
+    let amounts = get_amounts(&e, reserve_a, reserve_b);
+
+    // Check user funds for both tokens before moving anything
+    let (user_balance_a, _) = get_user_funds(&e, state.token_a.clone(), &user);
+    let (user_balance_b, _) = get_user_funds(&e, state.token_b.clone(), &user);
+    if user_balance_a < amounts.0 {
+        panic_with_error!(&e, LiquidityPoolError::InsufficientBalanceA);
+    }
+    if user_balance_b < amounts.1 {
+        panic_with_error!(&e, LiquidityPoolError::InsufficientBalanceB);
+    }
+
+    // Transfer exactly the computed amounts, authorized by the user within the deposit.
+    //  no allowance is required, so nothing is left approved to the pool
+    let token_a_client = SorobanTokenClient::new(&e, &state.token_a);
+    let token_b_client = SorobanTokenClient::new(&e, &state.token_b);
+    let (balance_a_before, balance_b_before) = get_pool_balances(&e, &state);
+    token_a_client.transfer(&user, &e.current_contract_address(), &(amounts.0 as i128));
+    token_b_client.transfer(&user, &e.current_contract_address(), &(amounts.1 as i128));
+
+    // Now calculate how many new pool shares to mint
+    let (balance_a, balance_b) = get_pool_balances(&e, &state);
+    // tokens charging transfer fee credit less than transferred, only received amounts count
+    let amounts = (balance_a - balance_a_before, balance_b - balance_b_before);
+    let total_shares = get_total_shares(&e);
+    if total_shares == 0 {
+        require_bootstrapper(&e, &user);
+        require_initial_price_in_range(&e, state.curve, balance_a, balance_b);
+    }
+
+    let zero = 0;
+    let new_total_shares = match state.curve {
+        Curve::StableSwap(amp) => {
+            // shares are valued by the invariant growth
+            let d0 = get_stable_d(&e, amp, reserve_a, reserve_b);
+            let d1 = get_stable_d(&e, amp, balance_a, balance_b);
+            if total_shares > zero && d0 > zero {
+                total_shares + mul_div_floor(total_shares, d1.saturating_sub(d0), d0)
+            } else {
+                d1
+            }
+        }
+        curve => {
+            if reserve_a > zero && reserve_b > zero {
+                let shares_a = mul_div_floor(balance_a, total_shares, reserve_a);
+                let shares_b = mul_div_floor(balance_b, total_shares, reserve_b);
+                shares_a.min(shares_b)
+            } else {
+                // tokens with different decimals would skew the share count, so it's normalized
+                let amount_a = pool::normalize_amount(balance_a, get_decimals_a(&e));
+                let amount_b = pool::normalize_amount(balance_b, get_decimals_b(&e));
+                match curve {
+                    Curve::Weighted(weight_a, weight_b) => {
+                        weighted_math::get_initial_shares(weight_a, weight_b, amount_a, amount_b)
+                    }
+                    _ => pool::get_initial_shares(amount_a, amount_b),
+                }
+            }
+        }
+    };
+
+    let shares_to_mint = new_total_shares - total_shares;
+    // zero shares deposit would leave pool with reserves nobody owns
+    if shares_to_mint == 0 {
+        panic_with_error!(&e, LiquidityPoolError::ZeroSharesDeposit);
+    }
+    mint_shares(&e, user.clone(), shares_to_mint as i128);
+    sync_reward_shares(&e, &user);
+    sync_holder(&e, &user);
+    state.reserve_a = balance_a;
+    state.reserve_b = balance_b;
+    state.save(&e);
+
+    // update plane data for every pool update
+    update_plane_state(&e, &state);
+
+    let amounts = Vec::from_array(&e, [amounts.0, amounts.1]);
+    Events::new(&e).deposit(user, amounts.clone(), shares_to_mint);
+    (amounts, shares_to_mint)
+}
+
+// Withdraw preconditions and user rewards checkpoint before the pool shares change
+fn checkpoint_withdraw(e: &Env, user: &Address, share_amount: u128) {
+    // liquidity belongs to the successor pool now, shares can only be exchanged
+    if has_migration_pool(e) {
+        panic_with_error!(e, LiquidityPoolError::LiquidityMigrated);
+    }
+
+    // Before actual changes were made to the pool, update total rewards data and refresh user reward
+    let rewards = get_rewards_manager(e);
+    let total_shares = get_total_reward_shares(e);
+    let user_shares = get_user_reward_shares(e, user);
+    let pool_data = rewards.manager().update_rewards_data(total_shares);
+    rewards
+        .manager()
+        .update_user_reward(&pool_data, user, user_shares);
+    rewards.storage().bump_user_reward_data(user);
+    checkpoint_fee_earnings(e, user);
+
+    // locked shares can't be withdrawn until the lock expires
+    if let Some(lock) = get_share_lock(e, user) {
+        let user_balance = get_user_balance_shares(e, user);
+        if lock.unlock_at > e.ledger().timestamp()
+            && user_balance.saturating_sub(share_amount) < lock.amount
+        {
+            panic_with_error!(e, LiquidityPoolError::SharesLocked);
+        }
+    }
+}
+
+// Redeem share_amount of the user shares for the pool tokens.
+//  User authorization is left to the entrypoint, so it's required once per call
+fn withdraw_shares(
+    e: &Env,
+    user: &Address,
+    share_amount: u128,
+    min_amounts: Vec<u128>,
+) -> Vec<u128> {
+    require_storage_migrated(e);
+    checkpoint_withdraw(e, user, share_amount);
+
+    // First transfer the pool shares that need to be redeemed
+    receive_shares(e, user, share_amount);
+    pay_withdraw(e, user, min_amounts)
+}
+
+// Burn shares received by the pool and send the corresponding amount of tokens to the user
+fn pay_withdraw(e: &Env, user: &Address, min_amounts: Vec<u128>) -> Vec<u128> {
+    pay_withdraw_to(e, user, user, user, min_amounts)
+}
+
+// Same as pay_withdraw, sending every token to its own recipient
+fn pay_withdraw_to(
+    e: &Env,
+    user: &Address,
+    recipient_a: &Address,
+    recipient_b: &Address,
+    min_amounts: Vec<u128>,
+) -> Vec<u128> {
+    let mut state = PoolState::load(e);
+    let (balance_a, balance_b) = get_pool_balances(e, &state);
+    let balance_shares = get_balance_shares(e);
+    let total_shares = get_total_shares(e);
+
+    // Now calculate the withdraw amounts
+    let out_a = mul_div_floor(balance_a, balance_shares, total_shares);
+    let out_b = mul_div_floor(balance_b, balance_shares, total_shares);
+
+    let min_a = min_amounts.get(0).unwrap();
+    let min_b = min_amounts.get(1).unwrap();
+
+    if out_a < min_a || out_b < min_b {
+        panic!("min not satisfied");
+    }
+
+    burn_shares(e, balance_shares as i128);
+    sync_reward_shares(e, user);
+    sync_holder(e, user);
+    // effects before interactions: reserves are final before tokens leave the pool
+    state.reserve_a = balance_a - out_a;
+    state.reserve_b = balance_b - out_b;
+    state.save(e);
+    transfer_token(e, state.token_a.clone(), recipient_a.clone(), out_a);
+    transfer_token(e, state.token_b.clone(), recipient_b.clone(), out_b);
+
+    // update plane data for every pool update
+    update_plane_state(e, &state);
+
+    let amounts = Vec::from_array(e, [out_a, out_b]);
+    Events::new(e).withdraw(user.clone(), balance_shares, amounts.clone());
+    amounts
+}
+
+// User fee earnings including the ones pending since the last checkpoint.
+//  Tracking starts at the first user deposit or withdrawal
+fn calc_fee_earnings(e: &Env, user: &Address) -> FeeEarnings {
+    let total_shares = get_total_shares(e);
+    let root_k_per_share =
+        pool::get_root_k_per_share(get_reserve_a(e), get_reserve_b(e), total_shares);
+    let mut earnings = match get_fee_earnings(e, user) {
+        Some(earnings) => earnings,
+        None => {
+            return FeeEarnings {
+                root_k_per_share,
+                earned_a: 0,
+                earned_b: 0,
+            }
+        }
+    };
+
+    let shares = get_user_balance_shares(e, user);
+    if shares > 0 {
+        let amount_a = mul_div_floor(get_reserve_a(e), shares, total_shares);
+        let amount_b = mul_div_floor(get_reserve_b(e), shares, total_shares);
+        earnings.earned_a +=
+            pool::get_fee_part(amount_a, earnings.root_k_per_share, root_k_per_share);
+        earnings.earned_b +=
+            pool::get_fee_part(amount_b, earnings.root_k_per_share, root_k_per_share);
+    }
+    earnings.root_k_per_share = root_k_per_share;
+    earnings
+}
+
+// Accrue user fee earnings before the user shares change
+fn checkpoint_fee_earnings(e: &Env, user: &Address) {
+    put_fee_earnings(e, user, &calc_fee_earnings(e, user));
+}
+
+// Liquidity lock holding the user shares at the moment, expired lock holds nothing
+fn get_active_liquidity_lock(e: &Env, user: &Address) -> Option<ShareLock> {
+    get_share_lock(e, user)
+        .filter(|lock| lock.is_liquidity_lock && lock.unlock_at > e.ledger().timestamp())
+}
+
+// Shares leaving the user through the pool can't dip into the liquidity locked by the user
+fn require_liquidity_unlocked(e: &Env, user: &Address, share_amount: u128) {
+    if let Some(lock) = get_active_liquidity_lock(e, user) {
+        if get_user_balance_shares(e, user).saturating_sub(share_amount) < lock.amount {
+            panic_with_error!(e, LiquidityPoolError::SharesLocked);
+        }
+    }
+}
+
+// Lock amount more of the user shares until unlock_at. Lock can be extended, never shortened.
+// Shares locked for the rewards boost can't be liquidity locked until released
+fn add_liquidity_lock(e: &Env, user: &Address, amount: u128, unlock_at: u64) {
+    if let Some(lock) = get_share_lock(e, user) {
+        if !lock.is_liquidity_lock {
+            panic_with_error!(e, LiquidityPoolError::InvalidShareLock);
+        }
+    }
+    let (locked, locked_until) = match get_active_liquidity_lock(e, user) {
+        Some(lock) => (lock.amount, lock.unlock_at),
+        None => (0, 0),
+    };
+    let amount = locked + amount;
+    if amount == 0
+        || unlock_at <= e.ledger().timestamp()
+        || unlock_at < locked_until
+        || amount > get_user_balance_shares(e, user)
+    {
+        panic_with_error!(e, LiquidityPoolError::InvalidShareLock);
+    }
+
+    put_share_lock(
+        e,
+        user,
+        &ShareLock {
+            amount,
+            unlock_at,
+            boost: 0,
+            is_liquidity_lock: true,
+        },
+    );
+    lock_user_shares(e, user, amount);
+    Events::new(e).lock_liquidity(user.clone(), amount, unlock_at);
+}
+
+// Pool storage must match the layout code expects before any funds are moved
+fn require_storage_migrated(e: &Env) {
+    if get_storage_version(e) < STORAGE_VERSION {
+        panic_with_error!(e, LiquidityPoolError::StorageMigrationRequired);
+    }
+}
+
+// Swap fee charged from the user. Fee exempt users swap for free
+fn get_user_fee_fraction(e: &Env, state: &PoolState, user: &Address) -> u32 {
+    if get_is_fee_exempt(e, user) {
+        0
+    } else {
+        state.fee_fraction
+    }
+}
+
+// Spot price of token a in token b for the raw token amounts, scaled by PRICE_PRECISION
+fn get_pool_price(e: &Env, curve: Curve, amount_a: u128, amount_b: u128) -> u128 {
+    let amount_a = pool::normalize_amount(amount_a, get_decimals_a(e));
+    let amount_b = pool::normalize_amount(amount_b, get_decimals_b(e));
+    match curve {
+        Curve::Weighted(weight_a, weight_b) => {
+            pool::get_price(amount_a * weight_b as u128, amount_b * weight_a as u128)
+        }
+        _ => pool::get_price(amount_a, amount_b),
+    }
+}
+
+// The first deposit sets the pool price, so it's checked against the range set by admin
+fn require_initial_price_in_range(e: &Env, curve: Curve, amount_a: u128, amount_b: u128) {
+    if let Some((min_price, max_price)) = get_initial_price_range(e) {
+        let price = get_pool_price(e, curve, amount_a, amount_b);
+        if price < min_price || price > max_price {
+            panic_with_error!(e, LiquidityPoolError::InitialPriceOutOfRange);
+        }
+    }
+}
+
+// StableSwap invariant of the raw token amounts
+fn get_stable_d(e: &Env, amp: u128, amount_a: u128, amount_b: u128) -> u128 {
+    stable_math::get_d(
+        pool::normalize_amount(amount_a, get_decimals_a(e)),
+        pool::normalize_amount(amount_b, get_decimals_b(e)),
+        amp,
+    )
+}
+
+// Move funds for the swap allowed by the guards. Referrer gets the cut of the fee in the sell token
+fn execute_swap(e: &Env, request: SwapRequest, referral: Option<(Address, u32)>) -> SwapResult {
+    let SwapRequest {
+        mut state,
+        user,
+        fee_fraction,
+        in_idx,
+        out_idx,
+        in_amount,
+        out_min,
+        ..
+    } = request;
+    let (reserve_a, reserve_b) = (state.reserve_a, state.reserve_b);
+    let tokens = state.tokens(e);
+
+    // Transfer the amount being sold to the contract
+    let (before_a, before_b) = get_pool_balances(e, &state);
+    let balances_before = Vec::from_array(e, [before_a, before_b]);
+    let sell_token = tokens.get(in_idx).unwrap();
+    let sell_token_client = SorobanTokenClient::new(e, &sell_token);
+    sell_token_client.transfer_from(
+        &e.current_contract_address(),
+        &user,
+        &e.current_contract_address(),
+        &(in_amount as i128),
+    );
+
+    let (balance_a, balance_b) = get_pool_balances(e, &state);
+
+    // tokens charging transfer fee credit less than transferred,
+    //  so the swap is priced by the amount actually received
+    let balances = Vec::from_array(e, [balance_a, balance_b]);
+    let received = balances.get(in_idx).unwrap() - balances_before.get(in_idx).unwrap();
+    let out = get_swap_out(e, &state, fee_fraction, in_idx, out_idx, received);
+    if out < out_min {
+        panic!("out amount is less than min");
+    }
+
+    // referral cut leaves the pool, so the invariant is checked against what's left
+    let (referral_fee, referral_amount) = match &referral {
+        Some((_, referral_fee)) => (
+            *referral_fee,
+            mul_div_floor(received, *referral_fee as u128, FEE_MULTIPLIER),
+        ),
+        None => (0, 0),
+    };
+    // reward fee is set aside from the fee left after the referral cut
+    let reward_fee = mul_div_floor(
+        (fee_fraction - referral_fee) as u128,
+        get_reward_fee(e) as u128,
+        FEE_MULTIPLIER,
+    ) as u32;
+    let reward_fee_amount = mul_div_floor(received, reward_fee as u128, FEE_MULTIPLIER);
+    let (balance_a, balance_b) = match in_idx {
+        0 => (balance_a - referral_amount - reward_fee_amount, balance_b),
+        _ => (balance_a, balance_b - referral_amount - reward_fee_amount),
+    };
+
+    let (out_a, out_b) = if out_idx == 0 { (out, 0) } else { (0, out) };
+
+    match state.curve {
+        Curve::Weighted(weight_a, weight_b) if weight_a != weight_b => {
+            // balance_a^weight_a * balance_b^weight_b can't decrease.
+            // sold amount counts net of the fee the pool keeps, same as for the constant product
+            let fee_fraction = fee_fraction - referral_fee - reward_fee;
+            let (weight_sell, weight_buy, reserve_sell, reserve_buy, balance_sell, balance_buy) =
+                match in_idx {
+                    0 => (
+                        weight_a, weight_b, reserve_a, reserve_b, balance_a, balance_b,
+                    ),
+                    _ => (
+                        weight_b, weight_a, reserve_b, reserve_a, balance_b, balance_a,
+                    ),
+                };
+            let new_reserve_sell = reserve_sell
+                + mul_div_floor(
+                    balance_sell - reserve_sell,
+                    FEE_MULTIPLIER - fee_fraction as u128,
+                    FEE_MULTIPLIER,
+                );
+            let min_reserve_buy = weighted_math::get_min_reserve_buy(
+                weight_sell,
+                weight_buy,
+                reserve_sell,
+                reserve_buy,
+                new_reserve_sell,
+            );
+            if balance_buy - out < min_reserve_buy {
+                panic!("weighted invariant does not hold");
+            }
+        }
+        Curve::ConstantProduct | Curve::Weighted(..) => {
+            // residue_numerator and residue_denominator are the amount that the invariant considers after
+            // deducting the fee, scaled up by FEE_MULTIPLIER to avoid fractions.
+            // pool keeps the fee less the referral cut and the reward fee.
+            // products exceed u128 for large reserves, so the comparison is made in U256
+            let fee_fraction = fee_fraction - referral_fee - reward_fee;
+            let residue_numerator = U256::from_u128(e, FEE_MULTIPLIER - fee_fraction as u128);
+            let residue_denominator = U256::from_u128(e, FEE_MULTIPLIER);
+
+            let new_invariant_factor = |balance: u128, reserve: u128, out: u128| {
+                if balance - reserve > out {
+                    residue_denominator
+                        .mul(&U256::from_u128(e, reserve))
+                        .add(&residue_numerator.mul(&U256::from_u128(e, balance - reserve - out)))
+                } else {
+                    residue_denominator.mul(&U256::from_u128(e, balance - out))
+                }
+            };
+
+            let new_inv_a = new_invariant_factor(balance_a, reserve_a, out_a);
+            let new_inv_b = new_invariant_factor(balance_b, reserve_b, out_b);
+            let old_inv_a = residue_denominator.mul(&U256::from_u128(e, reserve_a));
+            let old_inv_b = residue_denominator.mul(&U256::from_u128(e, reserve_b));
+
+            if new_inv_a.mul(&new_inv_b) < old_inv_a.mul(&old_inv_b) {
+                panic!("constant product invariant does not hold");
+            }
+        }
+        Curve::StableSwap(amp) => {
+            // fee stays in the pool, so the invariant can only grow
+            let new_d = get_stable_d(e, amp, balance_a - out_a, balance_b - out_b);
+            if new_d < get_stable_d(e, amp, reserve_a, reserve_b) {
+                panic!("stableswap invariant does not hold");
+            }
+        }
+    }
+
+    let fee = get_swap_fee(e, &state, fee_fraction, in_idx, out_idx, received, out);
+    update_swap_stats(e, in_idx, received, out, fee);
+
+    // effects before interactions: reserves are final before tokens leave the pool
+    state.reserve_a = balance_a - out_a;
+    state.reserve_b = balance_b - out_b;
+    state.save(e);
+    if reward_fee_amount > 0 {
+        let (bucket_a, bucket_b) = get_reward_fee_bucket(e);
+        put_reward_fee_bucket(
+            e,
+            match in_idx {
+                0 => (bucket_a + reward_fee_amount, bucket_b),
+                _ => (bucket_a, bucket_b + reward_fee_amount),
+            },
+        );
+    }
+
+    transfer_token(e, tokens.get(out_idx).unwrap(), user.clone(), out);
+
+    // update plane data for every pool update
+    update_plane_state(e, &state);
+
+    Events::new(e).swap(user.clone(), in_idx, out_idx, received, out);
+
+    if let Some((referrer, _)) = referral {
+        if referral_amount > 0 {
+            transfer_token(e, sell_token, referrer.clone(), referral_amount);
+        }
+        Events::new(e).swap_referral(referrer, user, referral_amount);
+    }
+    SwapResult {
+        amount_in: received,
+        amount_out: out,
+        new_reserve_a: state.reserve_a,
+        new_reserve_b: state.reserve_b,
+    }
+}
+
+// Swap fee charged from the trader as the token index and amount. Evaluated on the pre-swap reserves.
+//  StableSwap charges fee from the amount bought, other curves from the amount sold
+fn get_swap_fee(
+    e: &Env,
+    state: &PoolState,
+    fee_fraction: u32,
+    in_idx: u32,
+    out_idx: u32,
+    received: u128,
+    out: u128,
+) -> (u32, u128) {
+    match state.curve {
+        Curve::StableSwap(_) => (
+            out_idx,
+            get_swap_out(e, state, 0, in_idx, out_idx, received) - out,
+        ),
+        _ => (
+            in_idx,
+            mul_div_ceil(received, fee_fraction as u128, FEE_MULTIPLIER),
+        ),
+    }
+}
+
+fn update_swap_stats(e: &Env, in_idx: u32, received: u128, out: u128, fee: (u32, u128)) {
+    let mut stats = get_swap_stats(e);
+    let (amount_a, amount_b) = match in_idx {
+        0 => (received, out),
+        _ => (out, received),
+    };
+    stats.volume_a = stats.volume_a.saturating_add(amount_a);
+    stats.volume_b = stats.volume_b.saturating_add(amount_b);
+    match fee {
+        (0, fee) => stats.fee_a = stats.fee_a.saturating_add(fee),
+        (_, fee) => stats.fee_b = stats.fee_b.saturating_add(fee),
+    }
+    stats.last_trade_at = e.ledger().timestamp();
+    put_swap_stats(e, &stats);
+}
+
+#[contractimpl]
+impl UpgradeableContractTrait for LiquidityPool {
+    fn version() -> u32 {
+        101
+    }
+
+    fn upgrade(e: Env, new_wasm_hash: BytesN<32>) {
+        let access_control = AccessControl::new(&e);
+        access_control.require_admin();
+        bump_instance(&e);
+        e.deployer()
+            .update_current_contract_wasm(new_wasm_hash.clone());
+        Events::new(&e).upgrade(new_wasm_hash, Self::version());
+    }
+
+    fn storage_version(e: Env) -> u32 {
+        require_pool_initialized(&e);
+        get_storage_version(&e)
+    }
+
+    fn migrate(e: Env, admin: Address) -> u32 {
+        require_pool_initialized(&e);
+        admin.require_auth();
+        AccessControl::new(&e).check_admin(&admin);
+
+        let from_version = get_storage_version(&e);
+        if from_version < 1 {
+            // token decimals weren't cached, so reading them fell back to 7
+            put_decimals_a(&e, SorobanTokenClient::new(&e, &get_token_a(&e)).decimals());
+            put_decimals_b(&e, SorobanTokenClient::new(&e, &get_token_b(&e)).decimals());
+        }
+        if from_version < 2 {
+            // rewards were kept in reward invariant pages, users are converted on the first touch
+            get_rewards_manager(&e)
+                .manager()
+                .migrate_legacy_rewards(LEGACY_REWARD_PAGE_SIZE);
+        }
+
+        let to_version = from_version.max(STORAGE_VERSION);
+        put_storage_version(&e, to_version);
+        Events::new(&e).migrate(from_version, to_version);
+        to_version
+    }
+}
+
+#[contractimpl]
+impl RewardsTrait for LiquidityPool {
+    fn initialize_rewards_config(e: Env, reward_token: Address, reward_storage: Address) {
+        // admin.require_auth();
+        // check_admin(&e, &admin);
+
+        let rewards = get_rewards_manager(&e);
+        if rewards.storage().has_reward_token() {
+            panic!("rewards config already initialized")
+        }
+        // share token is neither rewarded nor holds the rewards
+        if AccessControl::new(&e).has_admin() {
+            let share_contract = get_token_share(&e);
+            if reward_token == share_contract || reward_storage == share_contract {
+                panic_with_error!(&e, LiquidityPoolError::InvalidRewardsConfig);
+            }
+        }
+
+        rewards.storage().put_reward_token(reward_token);
+        rewards.storage().put_reward_storage(reward_storage);
+    }
+
+    fn get_reward_storage(e: Env) -> Address {
+        require_initialized(&e);
+        get_rewards_manager(&e).storage().get_reward_storage()
+    }
+
+    fn set_reward_storage(e: Env, admin: Address, new_storage: Address) {
+        require_initialized(&e);
+        require_storage_migrated(&e);
+        admin.require_auth();
+        AccessControl::new(&e).check_admin(&admin);
+
+        if new_storage == get_token_share(&e) {
+            panic_with_error!(&e, LiquidityPoolError::InvalidRewardsConfig);
+        }
+
+        // preserve accrual made while rewards were paid from the old storage
+        let rewards = get_rewards_manager(&e);
+        rewards
+            .manager()
+            .update_rewards_data(get_total_reward_shares(&e));
+
+        // budget secured by the pool itself follows the rewards
+        let old_storage = rewards.storage().get_reward_storage();
+        let budget = get_reward_budget(&e);
+        if old_storage == e.current_contract_address() && new_storage != old_storage && budget > 0 {
+            transfer_reward(&e, &new_storage, budget);
+        }
+
+        rewards.storage().put_reward_storage(new_storage.clone());
+        Events::new(&e).reward_storage(old_storage, new_storage);
+    }
+
+    fn set_rewards_config(
+        e: Env,
+        admin: Address,
         expired_at: u64, // timestamp
         tps: u128,       // value with 7 decimal places. example: 600_0000000
     ) {
+        require_initialized(&e);
+        require_storage_migrated(&e);
+        admin.require_auth();
+        AccessControl::new(&e).check_rewards_operator(&admin);
+
+        if tps == 0 {
+            panic_with_error!(&e, LiquidityPoolError::InvalidRewardsTps);
+        }
+
+        let rewards = get_rewards_manager(&e);
+        let total_shares = get_total_reward_shares(&e);
+        rewards.manager().update_rewards_data(total_shares);
+
+        let config = PoolRewardConfig { tps, expired_at };
+        bump_instance(&e);
+        rewards.storage().set_pool_reward_config(&config);
+        rewards.storage().set_pool_reward_schedule(&Vec::new(&e));
+        fund_rewards(&e, &admin);
+    }
+
+    fn set_rewards_schedule(e: Env, admin: Address, phases: Vec<(u64, u128)>) {
+        require_initialized(&e);
+        require_storage_migrated(&e);
+        admin.require_auth();
+        AccessControl::new(&e).check_rewards_operator(&admin);
+
+        let rewards = get_rewards_manager(&e);
+        let total_shares = get_total_reward_shares(&e);
+        rewards.manager().update_rewards_data(total_shares);
+
+        if phases.is_empty() {
+            panic_with_error!(&e, LiquidityPoolError::InvalidRewardsExpiration);
+        }
+        let mut schedule = Vec::new(&e);
+        let mut phase_start = e.ledger().timestamp();
+        for (expired_at, amount) in phases.iter() {
+            if expired_at <= phase_start {
+                panic_with_error!(&e, LiquidityPoolError::InvalidRewardsExpiration);
+            }
+            let tps = amount / (expired_at - phase_start) as u128;
+            if tps == 0 {
+                panic_with_error!(&e, LiquidityPoolError::InvalidRewardsTps);
+            }
+            schedule.push_back(PoolRewardConfig { tps, expired_at });
+            phase_start = expired_at;
+        }
+
+        bump_instance(&e);
+        rewards.manager().set_schedule(&schedule);
+        fund_rewards(&e, &admin);
+    }
+
+    fn top_up_rewards(e: Env, admin: Address, amount: u128) {
+        require_initialized(&e);
+        require_storage_migrated(&e);
+        admin.require_auth();
+        AccessControl::new(&e).check_rewards_operator(&admin);
+
+        // preserve accrual made with the current config
+        let rewards = get_rewards_manager(&e);
+        rewards
+            .manager()
+            .update_rewards_data(get_total_reward_shares(&e));
+
+        let config = rewards.storage().get_pool_reward_config();
+        let now = e.ledger().timestamp();
+        if config.expired_at <= now {
+            panic_with_error!(&e, LiquidityPoolError::RewardsNotActive);
+        }
+
+        let tps = config.tps + amount / (config.expired_at - now) as u128;
+        rewards.storage().set_pool_reward_config(&PoolRewardConfig {
+            tps,
+            expired_at: config.expired_at,
+        });
+        fund_rewards(&e, &admin);
+    }
+
+    fn convert_fees_to_rewards(e: Env, min_out: u128) -> u128 {
+        require_initialized(&e);
+        if get_is_killed(&e) {
+            panic_with_error!(&e, LiquidityPoolError::PoolKilled);
+        }
+        if get_is_paused(&e) {
+            panic_with_error!(&e, LiquidityPoolError::PoolPaused);
+        }
+
+        let (bucket_a, bucket_b) = get_reward_fee_bucket(&e);
+        if bucket_a == 0 && bucket_b == 0 {
+            return 0;
+        }
+        let amounts = Vec::from_array(&e, [bucket_a, bucket_b]);
+
+        let rewards = get_rewards_manager(&e);
+        let reward_token = rewards.storage().get_reward_token();
+        let reward_storage = rewards.storage().get_reward_storage();
+        let mut state = PoolState::load(&e);
+        let tokens = state.tokens(&e);
+
+        // preserve accrual made with the current config
+        rewards
+            .manager()
+            .update_rewards_data(get_total_reward_shares(&e));
+        let config = rewards.storage().get_pool_reward_config();
+        let now = e.ledger().timestamp();
+        if config.expired_at <= now {
+            panic_with_error!(&e, LiquidityPoolError::RewardsNotActive);
+        }
+        let duration = (config.expired_at - now) as u128;
+
+        let reward_idx = tokens.first_index_of(&reward_token);
+        let reward = match reward_idx {
+            Some(reward_idx) => {
+                // buy the reward token with the other token fees. Fees join the pool reserves
+                let other_idx = 1 - reward_idx;
+                let mut reward = amounts.get(reward_idx).unwrap();
+                let other_amount = amounts.get(other_idx).unwrap();
+                let out = match other_amount {
+                    0 => 0,
+                    _ => get_swap_out(
+                        &e,
+                        &state,
+                        state.fee_fraction,
+                        other_idx,
+                        reward_idx,
+                        other_amount,
+                    ),
+                };
+                // the swap is permissionless, so the caller bounds the price it can be sandwiched at
+                if out < min_out {
+                    panic!("out amount is less than min");
+                }
+                if out > 0 {
+                    let (out_a, out_b) = if reward_idx == 0 { (out, 0) } else { (0, out) };
+                    let (in_a, in_b) = if other_idx == 0 {
+                        (other_amount, 0)
+                    } else {
+                        (0, other_amount)
+                    };
+                    state.reserve_a = state.reserve_a + in_a - out_a;
+                    state.reserve_b = state.reserve_b + in_b - out_b;
+                    state.save(&e);
+                    update_plane_state(&e, &state);
+                    reward += out;
+                }
+                reward
+            }
+            None => {
+                // fees can't be exchanged for the reward token in the pool, so they're handed over
+                //  to the reward storage, which pays min_out reward tokens into the campaign for them
+                if reward_storage == e.current_contract_address() {
+                    panic_with_error!(&e, LiquidityPoolError::RewardFeeNotConvertible);
+                }
+                reward_storage.require_auth();
+                for (token, amount) in tokens.iter().zip(amounts.iter()) {
+                    if amount > 0 {
+                        transfer_token(&e, token, reward_storage.clone(), amount);
+                    }
+                }
+                min_out
+            }
+        };
+
+        // budget is credited by what the emission pays out, remainder waits for the next conversion
+        let added_tps = reward / duration;
+        let funded = added_tps * duration;
+        let remainder = reward - funded;
+        // reward storage has to hold the tokens it pays for the fees on top of the existing budget
+        if reward_idx.is_none() && get_spare_reward_balance(&e) < funded {
+            panic_with_error!(&e, LiquidityPoolError::RewardStorageUnderfunded);
+        }
+        put_reward_fee_bucket(
+            &e,
+            match reward_idx {
+                Some(0) => (remainder, 0),
+                Some(_) => (0, remainder),
+                None => (0, 0),
+            },
+        );
+        if funded > 0 {
+            // reward budget held by the pool is excluded from the liquidity once funded
+            if reward_idx.is_some() && reward_storage != e.current_contract_address() {
+                transfer_token(&e, reward_token, reward_storage, funded);
+            }
+            rewards.storage().set_pool_reward_config(&PoolRewardConfig {
+                tps: config.tps + added_tps,
+                expired_at: config.expired_at,
+            });
+            put_reward_funded(&e, get_reward_funded(&e) + funded);
+        }
+
+        Events::new(&e).convert_fees(amounts, funded);
+        funded
+    }
+
+    fn extend_rewards(e: Env, admin: Address, expired_at: u64) {
+        require_initialized(&e);
+        require_storage_migrated(&e);
+        admin.require_auth();
+        AccessControl::new(&e).check_rewards_operator(&admin);
+
+        // preserve accrual made with the current config
+        let rewards = get_rewards_manager(&e);
+        rewards
+            .manager()
+            .update_rewards_data(get_total_reward_shares(&e));
+
+        let config = rewards.storage().get_pool_reward_config();
+        let now = e.ledger().timestamp();
+        if config.expired_at <= now {
+            panic_with_error!(&e, LiquidityPoolError::RewardsNotActive);
+        }
+        // moving the end of a phase would overlap the next one
+        let schedule_len = rewards.storage().get_pool_reward_schedule().len();
+        if expired_at <= config.expired_at
+            || rewards.storage().get_pool_reward_phase() + 1 < schedule_len
+        {
+            panic_with_error!(&e, LiquidityPoolError::InvalidRewardsExpiration);
+        }
+
+        // stretch remaining budget over the new window
+        let remaining_budget = config.tps * (config.expired_at - now) as u128;
+        rewards.storage().set_pool_reward_config(&PoolRewardConfig {
+            tps: remaining_budget / (expired_at - now) as u128,
+            expired_at,
+        });
+    }
+
+    fn stop_rewards(e: Env, admin: Address) -> u128 {
+        require_initialized(&e);
+        require_storage_migrated(&e);
+        admin.require_auth();
+        AccessControl::new(&e).check_rewards_operator(&admin);
+
+        // preserve accrual made with the current config
+        let rewards = get_rewards_manager(&e);
+        let pool_data = rewards
+            .manager()
+            .update_rewards_data(get_total_reward_shares(&e));
+
+        // config is the last phase once updated after the schedule end
+        let config = rewards.storage().get_pool_reward_config();
+        let now = e.ledger().timestamp();
+        if config.expired_at <= now {
+            panic_with_error!(&e, LiquidityPoolError::RewardsNotActive);
+        }
+
+        bump_instance(&e);
+        rewards.storage().set_pool_reward_config(&PoolRewardConfig {
+            tps: 0,
+            expired_at: now,
+        });
+        rewards.storage().set_pool_reward_schedule(&Vec::new(&e));
+
+        get_reward_funded(&e).saturating_sub(pool_data.accumulated)
+    }
+
+    fn withdraw_unused_rewards(e: Env, admin: Address, to: Address) -> u128 {
+        require_initialized(&e);
+        require_storage_migrated(&e);
+        admin.require_auth();
+        AccessControl::new(&e).check_rewards_operator(&admin);
+
+        // checkpoint accrual up to the campaign end
+        let rewards = get_rewards_manager(&e);
+        let pool_data = rewards
+            .manager()
+            .update_rewards_data(get_total_reward_shares(&e));
+
+        let config = rewards.storage().get_pool_reward_config();
+        if config.expired_at > e.ledger().timestamp() {
+            panic_with_error!(&e, LiquidityPoolError::RewardsNotExpired);
+        }
+
+        // everything accumulated belongs to users, whether claimed or not
+        let funded = get_reward_funded(&e);
+        if funded <= pool_data.accumulated {
+            return 0;
+        }
+        let amount = funded - pool_data.accumulated;
+        put_reward_funded(&e, pool_data.accumulated);
+        transfer_reward(&e, &to, amount);
+        amount
+    }
+
+    fn set_referral_bonus(e: Env, admin: Address, bonus_bps: u32, referrer_cap: u128) {
+        require_initialized(&e);
+        require_storage_migrated(&e);
+        admin.require_auth();
+        AccessControl::new(&e).check_rewards_operator(&admin);
+
+        if bonus_bps as u128 > FEE_MULTIPLIER {
+            panic_with_error!(&e, LiquidityPoolError::InvalidReferralBonus);
+        }
+        put_referral_bonus(
+            &e,
+            &ReferralBonus {
+                bonus_bps,
+                referrer_cap,
+            },
+        );
+    }
+
+    fn get_referral_stats(e: Env, referrer: Address) -> Map<Symbol, i128> {
+        require_initialized(&e);
+        let stats = get_referrer_stats(&e, &referrer);
+        let config = get_rewards_manager(&e).storage().get_pool_reward_config();
+        // bonus of the past periods doesn't count against the cap
+        let period_bonus = match stats.period == config.expired_at {
+            true => stats.period_bonus,
+            false => 0,
+        };
+        let mut result = Map::new(&e);
+        result.set(symbol_short!("period"), config.expired_at as i128);
+        result.set(symbol_short!("bonus"), period_bonus as i128);
+        result.set(symbol_short!("total"), stats.total_bonus as i128);
+        result.set(symbol_short!("referrals"), stats.referrals as i128);
+        result
+    }
+
+    fn get_rewards_info(e: Env, user: Address) -> Map<Symbol, i128> {
+        require_initialized(&e);
+        let info = get_user_rewards_info(&e, &user);
+        let mut result = Map::new(&e);
+        result.set(symbol_short!("tps"), info.tps as i128);
+        result.set(symbol_short!("exp_at"), info.expired_at as i128);
+        result.set(symbol_short!("acc"), info.accumulated as i128);
+        result.set(symbol_short!("last_time"), info.last_time as i128);
+        result.set(symbol_short!("pool_acc"), info.pool_accumulated as i128);
+        result.set(symbol_short!("block"), info.pool_block as i128);
+        result.set(symbol_short!("usr_block"), info.user_block as i128);
+        result.set(symbol_short!("to_claim"), info.to_claim as i128);
+        result.set(symbol_short!("claimed"), info.claimed as i128);
+        result.set(symbol_short!("vesting"), info.vesting as i128);
+        result.set(symbol_short!("unlocked"), info.unlocked as i128);
+        result.set(symbol_short!("budget"), info.budget as i128);
+        result.set(symbol_short!("opted_out"), info.opted_out as i128);
+        result.set(symbol_short!("phase"), info.phase as i128);
+        result.set(symbol_short!("next_at"), info.next_phase_at as i128);
+        result
+    }
+
+    fn get_rewards_info_v2(e: Env, user: Address) -> RewardsInfo {
+        require_initialized(&e);
+        get_user_rewards_info(&e, &user)
+    }
+
+    fn get_reward_rate(e: Env) -> Map<Symbol, i128> {
+        require_initialized(&e);
+        let (_, config) = get_rewards_manager(&e).manager().get_current_phase();
+        let now = e.ledger().timestamp();
+        // boosted and opted out shares change the weight of a share in distribution
+        let reward_shares = get_total_reward_shares(&e);
+
+        let mut result = Map::new(&e);
+        let (tps, tps_share, left) = match config.expired_at > now && reward_shares > 0 {
+            true => (
+                config.tps,
+                config.tps * REWARD_RATE_PRECISION / reward_shares,
+                config.expired_at - now,
+            ),
+            false => (0, 0, 0),
+        };
+        result.set(symbol_short!("tps"), tps as i128);
+        result.set(symbol_short!("tps_share"), tps_share as i128);
+        result.set(symbol_short!("shares"), get_total_shares(&e) as i128);
+        result.set(symbol_short!("left"), left as i128);
+        result
+    }
+
+    fn poke(e: Env) -> PoolRewardData {
+        require_initialized(&e);
+        require_storage_migrated(&e);
+
+        bump_instance(&e);
+        let pool_data = get_rewards_manager(&e)
+            .manager()
+            .update_rewards_data(get_total_reward_shares(&e));
+        Events::new(&e).poke(pool_data.block, pool_data.accumulated);
+        pool_data
+    }
+
+    fn get_pool_reward_data(e: Env) -> PoolRewardData {
+        require_initialized(&e);
+        get_rewards_manager(&e).storage().get_pool_reward_data()
+    }
+
+    fn set_reward_checkpoints(e: Env, admin: Address, enabled: bool) {
+        require_initialized(&e);
+        admin.require_auth();
+        AccessControl::new(&e).check_rewards_operator(&admin);
+
+        bump_instance(&e);
+        get_rewards_manager(&e)
+            .manager()
+            .set_reward_checkpoints_enabled(enabled);
+        Events::new(&e).reward_checkpoints(enabled);
+    }
+
+    fn get_reward_checkpoints(e: Env, start_block: u64, limit: u32) -> Map<u64, u128> {
+        require_initialized(&e);
+        get_rewards_manager(&e)
+            .manager()
+            .get_reward_checkpoints(start_block, limit.min(MAX_REWARD_CHECKPOINTS_PAGE))
+    }
+
+    fn get_user_reward(e: Env, user: Address) -> u128 {
+        require_initialized(&e);
+        if !is_rewards_configured(&e) {
+            return 0;
+        }
+
+        let rewards = get_rewards_manager(&e);
+        let total_shares = get_total_reward_shares(&e);
+        let user_shares = get_user_reward_shares(&e, &user);
+        rewards
+            .manager()
+            .calculate_user_reward(&user, total_shares, user_shares)
+            .to_claim
+    }
+
+    fn set_vesting_duration(e: Env, admin: Address, duration: u64) {
+        require_initialized(&e);
+        require_storage_migrated(&e);
+        admin.require_auth();
+        AccessControl::new(&e).check_rewards_operator(&admin);
+        put_vesting_duration(&e, duration);
+    }
+
+    fn release_vested(e: Env, user: Address) -> u128 {
+        require_initialized(&e);
+        user.require_auth();
+        release_vested(&e, &user)
+    }
+
+    fn get_user_claimed(e: Env, user: Address) -> u128 {
+        require_initialized(&e);
+        match get_rewards_manager(&e)
+            .storage()
+            .get_user_reward_data(&user)
+        {
+            Some(data) => data.claimed,
+            None => 0,
+        }
+    }
+
+    fn get_total_claimed(e: Env) -> u128 {
+        require_initialized(&e);
+        get_rewards_manager(&e).storage().get_total_claimed()
+    }
+
+    fn claim(e: Env, user: Address) -> u128 {
+        require_initialized(&e);
+        Self::claim_to(e, user.clone(), user)
+    }
+
+    fn claim_to(e: Env, user: Address, recipient: Address) -> u128 {
+        require_initialized(&e);
+        user.require_auth();
+        claim_reward(&e, user, &recipient, u128::MAX)
+    }
+
+    fn claim_amount(e: Env, user: Address, amount: u128) -> u128 {
+        require_initialized(&e);
+        user.require_auth();
+        if amount == 0 {
+            panic_with_error!(&e, LiquidityPoolError::InvalidClaimAmount);
+        }
+        claim_reward(&e, user.clone(), &user, amount)
+    }
+
+    fn claim_and_reinvest(e: Env, user: Address, min_shares: u128) -> u128 {
+        require_initialized(&e);
+        user.require_auth();
+
+        let reward_token = get_rewards_manager(&e).storage().get_reward_token();
+        let in_idx = if reward_token == get_token_a(&e) {
+            0
+        } else if reward_token == get_token_b(&e) {
+            1
+        } else {
+            panic_with_error!(&e, LiquidityPoolError::ReinvestNotSupported);
+        };
+        // vested reward is not paid out at claim, so there is nothing to deposit
+        if get_vesting_duration(&e) > 0 {
+            panic_with_error!(&e, LiquidityPoolError::ReinvestNotSupported);
+        }
+
+        let reward = claim_reward(&e, user.clone(), &user, u128::MAX);
+        if reward == 0 {
+            return 0;
+        }
+        deposit_single_token(e, user, in_idx, reward, min_shares)
+    }
+
+    fn claim_for(e: Env, operator: Address, user: Address) -> u128 {
+        require_initialized(&e);
+        operator.require_auth();
+        AccessControl::new(&e).check_rewards_operator(&operator);
+        claim_reward(&e, user.clone(), &user, u128::MAX)
+    }
+
+    fn batch_claim(e: Env, operator: Address, users: Vec<Address>) -> Vec<u128> {
+        require_initialized(&e);
+        operator.require_auth();
+        AccessControl::new(&e).check_rewards_operator(&operator);
+
+        if users.len() > MAX_BATCH_CLAIM_SIZE {
+            panic_with_error!(&e, LiquidityPoolError::BatchTooLarge);
+        }
+
+        let mut result = Vec::new(&e);
+        for user in users {
+            result.push_back(claim_reward(&e, user.clone(), &user, u128::MAX));
+        }
+        result
+    }
+
+    fn set_reward_opt_out(e: Env, user: Address, opted_out: bool) {
+        require_initialized(&e);
+        user.require_auth();
+
+        // checkpoint user reward before the toggle
+        let rewards = get_rewards_manager(&e);
+        let total_shares = get_total_reward_shares(&e);
+        let user_shares = get_user_reward_shares(&e, &user);
+        let pool_data = rewards.manager().update_rewards_data(total_shares);
+        rewards
+            .manager()
+            .update_user_reward(&pool_data, &user, user_shares);
+        rewards.storage().bump_user_reward_data(&user);
+
+        match (get_reward_opt_out(&e, &user), opted_out) {
+            (None, true) => {
+                let user_shares = get_user_boosted_shares(&e, &user);
+                put_opted_out_shares(&e, get_opted_out_shares(&e) + user_shares);
+                put_reward_opt_out(&e, &user, user_shares);
+            }
+            (Some(user_shares), false) => {
+                put_opted_out_shares(&e, get_opted_out_shares(&e) - user_shares);
+                remove_reward_opt_out(&e, &user);
+            }
+            _ => {}
+        }
+    }
+
+    fn lock_shares(e: Env, user: Address, amount: u128, duration: u64) {
+        require_initialized(&e);
+        user.require_auth();
+
+        if amount == 0
+            || duration == 0
+            || amount > get_user_balance_shares(&e, &user)
+            || get_share_lock(&e, &user).is_some()
+        {
+            panic_with_error!(&e, LiquidityPoolError::InvalidShareLock);
+        }
+
+        // checkpoint user reward before the boost
+        let rewards = get_rewards_manager(&e);
+        let total_shares = get_total_reward_shares(&e);
+        let user_shares = get_user_reward_shares(&e, &user);
+        let pool_data = rewards.manager().update_rewards_data(total_shares);
+        rewards
+            .manager()
+            .update_user_reward(&pool_data, &user, user_shares);
+        rewards.storage().bump_user_reward_data(&user);
+
+        let lock = ShareLock {
+            amount,
+            unlock_at: e.ledger().timestamp() + duration,
+            boost: get_lock_boost_amount(amount, duration),
+            is_liquidity_lock: false,
+        };
+        put_lock_boost(&e, get_lock_boost(&e) + lock.boost);
+        put_share_lock(&e, &user, &lock);
+        lock_user_shares(&e, &user, amount);
+        sync_reward_shares(&e, &user);
+    }
+
+    fn get_lock(e: Env, user: Address) -> Option<ShareLock> {
+        require_initialized(&e);
+        get_share_lock(&e, &user)
+    }
+
+    fn unlock(e: Env, user: Address) {
+        require_initialized(&e);
+        // no auth: expired lock can be released by anyone, so the boost doesn't outlive the lock
+        let lock = match get_share_lock(&e, &user) {
+            Some(lock) => lock,
+            None => panic_with_error!(&e, LiquidityPoolError::InvalidShareLock),
+        };
+        if lock.unlock_at > e.ledger().timestamp() {
+            panic_with_error!(&e, LiquidityPoolError::SharesLocked);
+        }
+
+        // checkpoint user reward before the boost is gone
+        let rewards = get_rewards_manager(&e);
+        let total_shares = get_total_reward_shares(&e);
+        let user_shares = get_user_reward_shares(&e, &user);
+        let pool_data = rewards.manager().update_rewards_data(total_shares);
+        rewards
+            .manager()
+            .update_user_reward(&pool_data, &user, user_shares);
+        rewards.storage().bump_user_reward_data(&user);
+
+        put_lock_boost(&e, get_lock_boost(&e) - lock.boost);
+        remove_share_lock(&e, &user);
+        lock_user_shares(&e, &user, 0);
+        sync_reward_shares(&e, &user);
+    }
+}
+
+// User and pool rewards state as seen by the views, nothing is written
+fn get_user_rewards_info(e: &Env, user: &Address) -> RewardsInfo {
+    let rewards = get_rewards_manager(e);
+    let (phase, config) = rewards.manager().get_current_phase();
+    let schedule_len = rewards.storage().get_pool_reward_schedule().len();
+    let total_shares = get_total_reward_shares(e);
+    let user_shares = get_user_reward_shares(e, user);
+    let pool_data = rewards.manager().calculate_rewards_data(total_shares);
+    let user_data = match is_rewards_configured(e) {
+        true => rewards
+            .manager()
+            .calculate_user_reward(user, total_shares, user_shares),
+        false => UserRewardData {
+            pool_accumulated: 0,
+            to_claim: 0,
+            last_block: 0,
+            reward_per_share: 0,
+            claimed: 0,
+        },
+    };
+    let (vesting, unlocked) = match get_reward_vesting(e, user) {
+        Some(vesting) => (
+            vesting.total - vesting.released,
+            get_vested_amount(e, &vesting) - vesting.released,
+        ),
+        None => (0, 0),
+    };
+    RewardsInfo {
+        tps: config.tps,
+        expired_at: config.expired_at,
+        accumulated: pool_data.accumulated,
+        last_time: pool_data.last_time,
+        pool_block: pool_data.block,
+        pool_accumulated: user_data.pool_accumulated,
+        user_block: user_data.last_block,
+        user_shares,
+        to_claim: user_data.to_claim,
+        claimed: user_data.claimed,
+        vesting,
+        unlocked,
+        budget: get_reward_budget(e),
+        opted_out: get_reward_opt_out(e, user).is_some(),
+        phase: match schedule_len > 0 {
+            true => phase + 1,
+            false => 0,
+        },
+        next_phase_at: match phase + 1 < schedule_len {
+            true => config.expired_at,
+            false => 0,
+        },
+    }
+}
+
+// Claim up to max_amount of user reward to the recipient, the rest stays available for the
+// next claim. Caller is responsible for the authorization.
+// With vesting enabled the reward is vested for the user instead
+fn claim_reward(e: &Env, user: Address, recipient: &Address, max_amount: u128) -> u128 {
+    require_storage_migrated(e);
+    // nothing was ever distributed, skip rewards bookkeeping
+    if !is_rewards_configured(e) {
+        return 0;
+    }
+
+    let rewards = get_rewards_manager(e);
+    let total_shares = get_total_reward_shares(e);
+    let user_shares = get_user_reward_shares(e, &user);
+    let to_claim = rewards
+        .manager()
+        .calculate_user_reward(&user, total_shares, user_shares)
+        .to_claim
+        .min(max_amount);
+    if to_claim > get_reward_budget(e) {
+        panic_with_error!(e, LiquidityPoolError::RewardsBudgetExceeded);
+    }
+
+    let reward = match get_vesting_duration(e) {
+        0 => {
+            let reward = rewards.manager().claim_reward_amount_to(
+                &user,
+                recipient,
+                total_shares,
+                user_shares,
+                max_amount,
+            );
+            put_reward_claimed(e, get_reward_claimed(e) + reward);
+            reward
+        }
+        _ => {
+            let reward =
+                rewards
+                    .manager()
+                    .take_reward_amount(&user, total_shares, user_shares, max_amount);
+            vest_reward(e, &user, reward);
+            reward
+        }
+    };
+    rewards.storage().bump_user_reward_data(&user);
+    Events::new(e).claim(user, reward);
+    reward
+}
+
+#[contractimpl]
+impl AdminInterfaceTrait for LiquidityPool {
+    fn kill_me(e: Env, admin: Address) {
+        require_pool_initialized(&e);
         admin.require_auth();
         AccessControl::new(&e).check_admin(&admin);
 
-        let rewards = get_rewards_manager(&e);
-        let total_shares = get_total_shares(&e);
-        rewards.manager().update_rewards_data(total_shares);
+        put_is_killed(&e, true);
+        Events::new(&e).risk_param(symbol_short!("killed"), 1);
+    }
 
-        let config = PoolRewardConfig { tps, expired_at };
-        bump_instance(&e);
-        rewards.storage().set_pool_reward_config(&config);
+    fn unkill_me(e: Env, admin: Address) {
+        require_pool_initialized(&e);
+        admin.require_auth();
+        AccessControl::new(&e).check_admin(&admin);
+
+        // pool without liquidity cannot be resumed
+        if has_migration_pool(&e) {
+            panic_with_error!(&e, LiquidityPoolError::LiquidityMigrated);
+        }
+
+        put_is_killed(&e, false);
+        Events::new(&e).risk_param(symbol_short!("killed"), 0);
     }
 
-    fn get_rewards_info(e: Env, user: Address) -> Map<Symbol, i128> {
-        let rewards = get_rewards_manager(&e);
-        let config = rewards.storage().get_pool_reward_config();
-        let total_shares = get_total_shares(&e);
-        let user_shares = get_user_balance_shares(&e, &user);
-        let pool_data = rewards.manager().update_rewards_data(total_shares);
-        let user_data = rewards
-            .manager()
-            .update_user_reward(&pool_data, &user, user_shares);
+    fn get_is_killed(e: Env) -> bool {
+        require_pool_initialized(&e);
+        get_is_killed(&e)
+    }
+
+    fn set_paused(e: Env, admin: Address, paused: bool) {
+        require_pool_initialized(&e);
+        admin.require_auth();
+        AccessControl::new(&e).check_admin(&admin);
+
+        put_is_paused(&e, paused);
+        if paused {
+            Events::new(&e).pause(admin);
+        } else {
+            Events::new(&e).unpause(admin);
+        }
+        Events::new(&e).risk_param(symbol_short!("paused"), paused as i128);
+    }
+
+    fn is_paused(e: Env) -> bool {
+        require_pool_initialized(&e);
+        get_is_paused(&e)
+    }
+
+    fn set_permissioned(e: Env, admin: Address, enabled: bool) {
+        require_pool_initialized(&e);
+        admin.require_auth();
+        AccessControl::new(&e).check_admin(&admin);
+
+        put_is_permissioned(&e, enabled);
+        Events::new(&e).risk_param(Symbol::new(&e, "permissioned"), enabled as i128);
+    }
+
+    fn set_share_transferable(e: Env, admin: Address, transferable: bool) {
+        require_pool_initialized(&e);
+        admin.require_auth();
+        AccessControl::new(&e).check_admin(&admin);
+
+        LPTokenClient::new(&e, &get_token_share(&e)).set_transferable(&transferable);
+        Events::new(&e).risk_param(Symbol::new(&e, "share_transferable"), transferable as i128);
+    }
+
+    fn add_to_allowlist(e: Env, admin: Address, user: Address) {
+        require_pool_initialized(&e);
+        admin.require_auth();
+        AccessControl::new(&e).check_admin(&admin);
+
+        put_allowlisted(&e, &user);
+        Events::new(&e).allowlist(user, true);
+    }
+
+    fn remove_from_allowlist(e: Env, admin: Address, user: Address) {
+        require_pool_initialized(&e);
+        admin.require_auth();
+        AccessControl::new(&e).check_admin(&admin);
+
+        remove_allowlisted(&e, &user);
+        Events::new(&e).allowlist(user, false);
+    }
+
+    fn is_allowed(e: Env, user: Address) -> bool {
+        require_pool_initialized(&e);
+        is_user_allowed(&e, &user)
+    }
+
+    fn set_max_price_impact(e: Env, admin: Address, max_price_impact: u32) {
+        require_pool_initialized(&e);
+        admin.require_auth();
+        AccessControl::new(&e).check_admin(&admin);
+
+        // 0.01% = 1; 0 disables the guard
+        if max_price_impact as u128 > FEE_MULTIPLIER {
+            panic!("max price impact cannot be greater than 100%");
+        }
+
+        put_max_price_impact(&e, max_price_impact);
+        Events::new(&e).risk_param(Symbol::new(&e, "price_impact"), max_price_impact as i128);
+    }
+
+    fn get_max_price_impact(e: Env) -> u32 {
+        require_pool_initialized(&e);
+        get_max_price_impact(&e)
+    }
+
+    fn set_min_reserve(e: Env, admin: Address, min_reserve: u128) {
+        require_pool_initialized(&e);
+        admin.require_auth();
+        AccessControl::new(&e).check_admin(&admin);
+
+        put_min_reserve(&e, min_reserve);
+        Events::new(&e).risk_param(Symbol::new(&e, "min_reserve"), min_reserve as i128);
+    }
+
+    fn get_min_reserve(e: Env) -> u128 {
+        require_pool_initialized(&e);
+        get_min_reserve(&e)
+    }
+
+    fn set_reward_fee(e: Env, admin: Address, reward_fee: u32) {
+        require_pool_initialized(&e);
+        admin.require_auth();
+        AccessControl::new(&e).check_admin(&admin);
+
+        // 100% = 10000; 0 disables the reward fee
+        if reward_fee as u128 > FEE_MULTIPLIER {
+            panic!("reward fee cannot be greater than 100%");
+        }
+
+        put_reward_fee(&e, reward_fee);
+        Events::new(&e).risk_param(Symbol::new(&e, "reward_fee"), reward_fee as i128);
+    }
+
+    fn get_reward_fee(e: Env) -> u32 {
+        require_pool_initialized(&e);
+        get_reward_fee(&e)
+    }
+
+    fn get_reward_fee_bucket(e: Env) -> Vec<u128> {
+        require_pool_initialized(&e);
+        let (bucket_a, bucket_b) = get_reward_fee_bucket(&e);
+        Vec::from_array(&e, [bucket_a, bucket_b])
+    }
+
+    fn set_initial_price_range(e: Env, admin: Address, min_price: u128, max_price: u128) {
+        require_pool_initialized(&e);
+        admin.require_auth();
+        AccessControl::new(&e).check_admin(&admin);
+
+        if get_total_shares(&e) > 0 {
+            panic_with_error!(&e, LiquidityPoolError::PoolNotEmpty);
+        }
+        if min_price > max_price {
+            panic_with_error!(&e, LiquidityPoolError::InvalidInitialPriceRange);
+        }
+
+        put_initial_price_range(&e, (min_price, max_price));
+        Events::new(&e).initial_price_range(min_price, max_price);
+    }
+
+    fn get_initial_price_range(e: Env) -> Option<(u128, u128)> {
+        require_pool_initialized(&e);
+        get_initial_price_range(&e)
+    }
+
+    fn set_fee_exempt(e: Env, admin: Address, user: Address, exempt: bool) {
+        require_pool_initialized(&e);
+        admin.require_auth();
+        AccessControl::new(&e).check_admin(&admin);
+
+        if exempt {
+            put_fee_exempt(&e, &user);
+        } else {
+            remove_fee_exempt(&e, &user);
+        }
+        Events::new(&e).fee_exempt(user, exempt);
+    }
+
+    fn is_fee_exempt(e: Env, user: Address) -> bool {
+        require_pool_initialized(&e);
+        get_is_fee_exempt(&e, &user)
+    }
+
+    fn set_emergency_mode(e: Env, admin: Address, value: bool) {
+        require_pool_initialized(&e);
+        admin.require_auth();
+        AccessControl::new(&e).check_admin(&admin);
+
+        put_is_emergency_mode(&e, value);
+        Events::new(&e).risk_param(symbol_short!("emergency"), value as i128);
+    }
+
+    fn get_emergency_mode(e: Env) -> bool {
+        require_pool_initialized(&e);
+        get_is_emergency_mode(&e)
+    }
+
+    fn backfill_holders(e: Env, admin: Address, holders: Vec<Address>) -> u32 {
+        require_pool_initialized(&e);
+        admin.require_auth();
+        AccessControl::new(&e).check_admin(&admin);
+
+        let mut indexed = 0;
+        for holder in holders {
+            if backfill_holder(&e, &holder) {
+                indexed += 1;
+            }
+        }
+        check_backfill_complete(&e);
+        indexed
+    }
+
+    fn backfill_complete(e: Env) -> bool {
+        require_pool_initialized(&e);
+        get_backfill_complete(&e)
+    }
+
+    fn get_holders_count(e: Env) -> u32 {
+        require_pool_initialized(&e);
+        if !get_backfill_complete(&e) {
+            panic_with_error!(&e, LiquidityPoolError::HolderIndexIncomplete);
+        }
+        get_holders_count(&e)
+    }
+
+    fn set_rewards_operator(e: Env, admin: Address, operator: Address) {
+        require_pool_initialized(&e);
+        admin.require_auth();
+        let access_control = AccessControl::new(&e);
+        access_control.check_admin(&admin);
+        access_control.set_rewards_operator(&operator);
+    }
+
+    fn get_rewards_operator(e: Env) -> Option<Address> {
+        require_pool_initialized(&e);
+        AccessControl::new(&e).get_rewards_operator()
+    }
+
+    fn get_rejection_stats(e: Env) -> Map<Symbol, u64> {
+        require_pool_initialized(&e);
+        get_rejection_stats(&e)
+    }
+
+    fn get_risk_params(e: Env) -> Map<Symbol, i128> {
+        require_pool_initialized(&e);
         let mut result = Map::new(&e);
-        result.set(symbol_short!("tps"), config.tps as i128);
-        result.set(symbol_short!("exp_at"), config.expired_at as i128);
-        result.set(symbol_short!("acc"), pool_data.accumulated as i128);
-        result.set(symbol_short!("last_time"), pool_data.last_time as i128);
+        result.set(symbol_short!("killed"), get_is_killed(&e) as i128);
+        result.set(symbol_short!("paused"), get_is_paused(&e) as i128);
+        result.set(
+            symbol_short!("emergency"),
+            get_is_emergency_mode(&e) as i128,
+        );
+        result.set(
+            Symbol::new(&e, "permissioned"),
+            get_is_permissioned(&e) as i128,
+        );
+        result.set(
+            Symbol::new(&e, "price_impact"),
+            get_max_price_impact(&e) as i128,
+        );
+        result.set(Symbol::new(&e, "min_reserve"), get_min_reserve(&e) as i128);
+        result.set(Symbol::new(&e, "reward_fee"), get_reward_fee(&e) as i128);
+        result.set(
+            Symbol::new(&e, "share_transferable"),
+            LPTokenClient::new(&e, &get_token_share(&e)).transferable() as i128,
+        );
         result.set(
-            symbol_short!("pool_acc"),
-            user_data.pool_accumulated as i128,
+            Symbol::new(&e, "bootstrapper"),
+            get_bootstrapper(&e).is_some() as i128,
         );
-        result.set(symbol_short!("block"), pool_data.block as i128);
-        result.set(symbol_short!("usr_block"), user_data.last_block as i128);
-        result.set(symbol_short!("to_claim"), user_data.to_claim as i128);
+        let (min_price, max_price) = get_initial_price_range(&e).unwrap_or((0, 0));
+        result.set(Symbol::new(&e, "init_price_min"), min_price as i128);
+        result.set(Symbol::new(&e, "init_price_max"), max_price as i128);
         result
     }
 
-    fn get_user_reward(e: Env, user: Address) -> u128 {
-        let rewards = get_rewards_manager(&e);
+    fn rescue_token(e: Env, admin: Address, token: Address, to: Address, amount: u128) {
+        require_pool_initialized(&e);
+        admin.require_auth();
+        AccessControl::new(&e).check_admin(&admin);
+
+        let reward_token = get_rewards_manager(&e).storage().get_reward_token();
+        if token == get_token_a(&e)
+            || token == get_token_b(&e)
+            || token == get_token_share(&e)
+            || token == reward_token
+        {
+            panic_with_error!(&e, LiquidityPoolError::ProtectedToken);
+        }
+
+        SorobanTokenClient::new(&e, &token).transfer(
+            &e.current_contract_address(),
+            &to,
+            &(amount as i128),
+        );
+        Events::new(&e).rescue_token(token, to, amount);
+    }
+
+    fn get_admin(e: Env) -> Address {
+        require_pool_initialized(&e);
+        AccessControl::new(&e).get_admin().unwrap()
+    }
+
+    fn propose_admin(e: Env, admin: Address, new_admin: Address) {
+        require_pool_initialized(&e);
+        admin.require_auth();
+        let access_control = AccessControl::new(&e);
+        access_control.check_admin(&admin);
+
+        access_control.set_future_admin(&new_admin);
+        Events::new(&e).admin_proposed(admin, new_admin);
+    }
+
+    fn accept_admin(e: Env, new_admin: Address) {
+        require_pool_initialized(&e);
+        new_admin.require_auth();
+        let access_control = AccessControl::new(&e);
+        access_control.check_future_admin(&new_admin);
+
+        let old_admin = access_control.get_admin().unwrap();
+        access_control.set_admin(&new_admin);
+        access_control.remove_future_admin();
+        Events::new(&e).admin_changed(old_admin, new_admin);
+    }
+
+    fn cancel_admin_proposal(e: Env, admin: Address) {
+        require_pool_initialized(&e);
+        admin.require_auth();
+        let access_control = AccessControl::new(&e);
+        access_control.check_admin(&admin);
+
+        if access_control.get_future_admin().is_none() {
+            panic_with_error!(&e, AccessControlError::FutureAdminNotFound);
+        }
+        access_control.remove_future_admin();
+    }
+
+    fn set_bootstrapper(e: Env, admin: Address, bootstrapper: Address) {
+        require_pool_initialized(&e);
+        admin.require_auth();
+        AccessControl::new(&e).check_admin(&admin);
+
+        if get_total_shares(&e) > 0 {
+            panic_with_error!(&e, LiquidityPoolError::PoolNotEmpty);
+        }
+
+        put_bootstrapper(&e, &bootstrapper);
+        Events::new(&e).bootstrapper(bootstrapper);
+    }
+
+    fn remove_bootstrapper(e: Env, admin: Address) {
+        require_pool_initialized(&e);
+        admin.require_auth();
+        AccessControl::new(&e).check_admin(&admin);
+
+        remove_bootstrapper(&e);
+        Events::new(&e).remove_bootstrapper();
+    }
+
+    fn get_bootstrapper(e: Env) -> Option<Address> {
+        require_pool_initialized(&e);
+        get_bootstrapper(&e)
+    }
+}
+
+#[contractimpl]
+impl LiquidityMigrationTrait for LiquidityPool {
+    fn migrate_liquidity(
+        e: Env,
+        admin: Address,
+        new_pool: Address,
+        min_shares_out_per_share: u128,
+    ) -> u128 {
+        require_pool_initialized(&e);
+        admin.require_auth();
+        AccessControl::new(&e).check_admin(&admin);
+
+        if !get_is_killed(&e) {
+            panic_with_error!(&e, LiquidityPoolError::PoolNotKilled);
+        }
+        if has_migration_pool(&e) {
+            panic_with_error!(&e, LiquidityPoolError::LiquidityMigrated);
+        }
+
+        // successor should operate on exactly the same tokens
+        let new_pool_client = LiquidityPoolClient::new(&e, &new_pool);
+        if new_pool == e.current_contract_address()
+            || new_pool_client.get_tokens() != Self::get_tokens(e.clone())
+        {
+            panic_with_error!(&e, LiquidityPoolError::MigrationTokensMismatch);
+        }
+        // successor should be deployed by the trusted router, not just any contract quoting the same tokens
+        let router = match get_migration_router(&e) {
+            Some(router) => router,
+            None => panic_with_error!(&e, LiquidityPoolError::MigrationRouterNotSet),
+        };
+        if !PoolRouterClient::new(&e, &router)
+            .get_pools(&Self::get_tokens(e.clone()))
+            .values()
+            .contains(&new_pool)
+        {
+            panic_with_error!(&e, LiquidityPoolError::UnknownMigrationPool);
+        }
+
         let total_shares = get_total_shares(&e);
-        let user_shares = get_user_balance_shares(&e, &user);
-        rewards
-            .manager()
-            .get_amount_to_claim(&user, total_shares, user_shares)
+        if total_shares == 0 {
+            panic_with_error!(&e, LiquidityPoolError::NothingToMigrate);
+        }
+
+        // successor pool deposit transfers the reserves from this pool, authorize exactly these transfers
+        let (reserve_a, reserve_b) = (get_reserve_a(&e), get_reserve_b(&e));
+        let mut auth_entries = Vec::new(&e);
+        for (token, amount) in [(get_token_a(&e), reserve_a), (get_token_b(&e), reserve_b)] {
+            auth_entries.push_back(InvokerContractAuthEntry::Contract(SubContractInvocation {
+                context: ContractContext {
+                    contract: token,
+                    fn_name: symbol_short!("transfer"),
+                    args: (
+                        e.current_contract_address(),
+                        new_pool.clone(),
+                        amount as i128,
+                    )
+                        .into_val(&e),
+                },
+                sub_invocations: Vec::new(&e),
+            }));
+        }
+        e.authorize_as_current_contract(auth_entries);
+
+        let (amounts, new_shares) = new_pool_client.deposit(
+            &e.current_contract_address(),
+            &Vec::from_array(&e, [reserve_a, reserve_b]),
+        );
+        if amounts.get(0).unwrap() != reserve_a || amounts.get(1).unwrap() != reserve_b {
+            panic_with_error!(&e, LiquidityPoolError::MigrationReservesNotAccepted);
+        }
+        if new_shares * MIGRATION_PRECISION < min_shares_out_per_share * total_shares {
+            panic_with_error!(&e, LiquidityPoolError::MigrationSharesBelowMin);
+        }
+
+        put_reserve_a(&e, 0);
+        put_reserve_b(&e, 0);
+        put_migration_pool(&e, &new_pool);
+        put_migrated_shares(&e, total_shares);
+        put_migration_shares(&e, new_shares);
+
+        // update plane data for every pool update
+        update_plane(&e);
+
+        Events::new(&e).migrate_liquidity(new_pool, amounts, new_shares);
+        new_shares
     }
 
-    fn claim(e: Env, user: Address) -> u128 {
+    fn set_migration_router(e: Env, admin: Address, router: Address) {
+        require_pool_initialized(&e);
+        admin.require_auth();
+        AccessControl::new(&e).check_admin(&admin);
+
+        put_migration_router(&e, &router);
+        Events::new(&e).migration_router(router);
+    }
+
+    fn get_migration_router(e: Env) -> Option<Address> {
+        require_pool_initialized(&e);
+        get_migration_router(&e)
+    }
+
+    fn claim_migrated_shares(e: Env, user: Address) -> u128 {
+        require_pool_initialized(&e);
+        user.require_auth();
+
+        if !has_migration_pool(&e) {
+            panic_with_error!(&e, LiquidityPoolError::LiquidityNotMigrated);
+        }
+
+        // Before shares are burnt, update total rewards data and refresh user reward
         let rewards = get_rewards_manager(&e);
-        let total_shares = get_total_shares(&e);
-        let user_shares = get_user_balance_shares(&e, &user);
-        let reward = rewards
+        let total_shares = get_total_reward_shares(&e);
+        let pool_data = rewards.manager().update_rewards_data(total_shares);
+        rewards
             .manager()
-            .claim_reward(&user, total_shares, user_shares);
+            .update_user_reward(&pool_data, &user, get_user_reward_shares(&e, &user));
         rewards.storage().bump_user_reward_data(&user);
-        reward
+
+        let user_shares = get_user_balance_shares(&e, &user);
+        if user_shares == 0 {
+            return 0;
+        }
+
+        receive_shares(&e, &user, user_shares);
+        burn_shares(&e, user_shares as i128);
+        sync_reward_shares(&e, &user);
+        sync_holder(&e, &user);
+
+        // exchange pro-rata to the shares left, so the last liquidity provider receives the remainder
+        let migrated_shares = get_migrated_shares(&e);
+        let migration_shares = get_migration_shares(&e);
+        let new_shares = mul_div_floor(migration_shares, user_shares, migrated_shares);
+        put_migrated_shares(&e, migrated_shares - user_shares);
+        put_migration_shares(&e, migration_shares - new_shares);
+
+        let new_pool = get_migration_pool(&e);
+        SorobanTokenClient::new(&e, &LiquidityPoolClient::new(&e, &new_pool).share_id()).transfer(
+            &e.current_contract_address(),
+            &user,
+            &(new_shares as i128),
+        );
+
+        Events::new(&e).claim_migrated_shares(user, user_shares, new_shares);
+        new_shares
+    }
+
+    fn migrate_position(
+        e: Env,
+        user: Address,
+        new_pool: Address,
+        share_amount: u128,
+        min_a: u128,
+        min_b: u128,
+        min_shares: u128,
+    ) -> u128 {
+        require_pool_initialized(&e);
+        user.require_auth();
+
+        let new_pool_client = LiquidityPoolClient::new(&e, &new_pool);
+        if new_pool == e.current_contract_address()
+            || new_pool_client.get_tokens() != Self::get_tokens(e.clone())
+        {
+            panic_with_error!(&e, LiquidityPoolError::MigrationTokensMismatch);
+        }
+
+        // rewards are checkpointed by the withdrawal, tokens are sent to the user
+        let amounts = withdraw_shares(&e, &user, share_amount, Vec::from_array(&e, [min_a, min_b]));
+        let (_, new_shares) = new_pool_client.deposit(&user, &amounts);
+        if new_shares < min_shares {
+            panic_with_error!(&e, LiquidityPoolError::MigrationSharesBelowMin);
+        }
+        new_shares
     }
 }
 
@@ -484,6 +3159,7 @@ impl Plane for LiquidityPool {
         set_plane(&e, &plane);
     }
     fn get_pools_plane(e: Env) -> Address {
+        require_pool_initialized(&e);
         get_plane(&e)
     }
 }
@@ -0,0 +1,310 @@
+use soroban_sdk::{symbol_short, Address, BytesN, Env, Symbol, Vec};
+
+#[derive(Clone)]
+pub(crate) struct Events(Env);
+
+impl Events {
+    #[inline(always)]
+    pub(crate) fn env(&self) -> &Env {
+        &self.0
+    }
+
+    #[inline(always)]
+    pub(crate) fn new(env: &Env) -> Events {
+        Events(env.clone())
+    }
+}
+
+pub(crate) trait LiquidityPoolEvents {
+    fn deposit(&self, user: Address, amounts: Vec<u128>, share_amount: u128);
+
+    fn swap(&self, user: Address, in_idx: u32, out_idx: u32, in_amount: u128, out_amount: u128);
+
+    fn withdraw(&self, user: Address, share_amount: u128, amounts: Vec<u128>);
+
+    fn claim(&self, user: Address, amount: u128);
+
+    fn migrate_liquidity(&self, new_pool: Address, amounts: Vec<u128>, new_share_amount: u128);
+
+    fn migration_router(&self, router: Address);
+
+    fn claim_migrated_shares(&self, user: Address, share_amount: u128, new_share_amount: u128);
+
+    fn skim(&self, to: Address, amounts: Vec<u128>);
+
+    fn donate(&self, from: Address, amounts: Vec<u128>);
+
+    fn sync(&self, old_reserves: Vec<u128>, new_reserves: Vec<u128>);
+
+    fn pause(&self, admin: Address);
+
+    fn unpause(&self, admin: Address);
+
+    fn rescue_token(&self, token: Address, to: Address, amount: u128);
+
+    fn risk_param(&self, name: Symbol, value: i128);
+
+    fn bootstrapper(&self, bootstrapper: Address);
+
+    fn remove_bootstrapper(&self);
+
+    fn referral_bonus(
+        &self,
+        referrer: Address,
+        user: Address,
+        referrer_amount: u128,
+        user_amount: u128,
+    );
+
+    fn swap_rejected(&self, user: Address, guard: Symbol);
+
+    fn swap_slippage(&self, user: Address, quoted_in: u128, in_amount: u128, slippage_bps: u32);
+
+    fn swap_referral(&self, referrer: Address, user: Address, amount: u128);
+
+    fn allowlist(&self, user: Address, allowed: bool);
+
+    fn fee_exempt(&self, user: Address, exempt: bool);
+
+    fn release_vested(&self, user: Address, amount: u128);
+
+    fn migrate(&self, from_version: u32, to_version: u32);
+
+    fn reward_storage(&self, old_storage: Address, new_storage: Address);
+
+    fn lock_liquidity(&self, user: Address, amount: u128, unlock_at: u64);
+
+    fn convert_fees(&self, amounts: Vec<u128>, reward: u128);
+
+    fn initial_price_range(&self, min_price: u128, max_price: u128);
+
+    fn admin_proposed(&self, admin: Address, future_admin: Address);
+
+    fn admin_changed(&self, old_admin: Address, new_admin: Address);
+
+    fn upgrade(&self, new_wasm_hash: BytesN<32>, version: u32);
+
+    fn poke(&self, block: u64, accumulated: u128);
+
+    fn reward_checkpoints(&self, enabled: bool);
+}
+
+impl LiquidityPoolEvents for Events {
+    fn deposit(&self, user: Address, amounts: Vec<u128>, share_amount: u128) {
+        self.env()
+            .events()
+            .publish((symbol_short!("deposit"), user), (amounts, share_amount));
+    }
+
+    fn swap(&self, user: Address, in_idx: u32, out_idx: u32, in_amount: u128, out_amount: u128) {
+        self.env().events().publish(
+            (symbol_short!("swap"), user),
+            (in_idx, out_idx, in_amount, out_amount),
+        );
+    }
+
+    fn withdraw(&self, user: Address, share_amount: u128, amounts: Vec<u128>) {
+        self.env()
+            .events()
+            .publish((symbol_short!("withdraw"), user), (share_amount, amounts));
+    }
+
+    fn claim(&self, user: Address, amount: u128) {
+        self.env()
+            .events()
+            .publish((symbol_short!("claim"), user), amount);
+    }
+
+    fn migrate_liquidity(&self, new_pool: Address, amounts: Vec<u128>, new_share_amount: u128) {
+        self.env().events().publish(
+            (Symbol::new(self.env(), "migrate_liquidity"), new_pool),
+            (amounts, new_share_amount),
+        );
+    }
+
+    fn migration_router(&self, router: Address) {
+        self.env()
+            .events()
+            .publish((Symbol::new(self.env(), "migration_router"),), router);
+    }
+
+    fn claim_migrated_shares(&self, user: Address, share_amount: u128, new_share_amount: u128) {
+        self.env().events().publish(
+            (Symbol::new(self.env(), "claim_migrated_shares"), user),
+            (share_amount, new_share_amount),
+        );
+    }
+
+    fn skim(&self, to: Address, amounts: Vec<u128>) {
+        self.env()
+            .events()
+            .publish((symbol_short!("skim"), to), amounts);
+    }
+
+    fn donate(&self, from: Address, amounts: Vec<u128>) {
+        self.env()
+            .events()
+            .publish((symbol_short!("donate"), from), amounts);
+    }
+
+    fn sync(&self, old_reserves: Vec<u128>, new_reserves: Vec<u128>) {
+        self.env()
+            .events()
+            .publish((symbol_short!("sync"),), (old_reserves, new_reserves));
+    }
+
+    fn pause(&self, admin: Address) {
+        self.env()
+            .events()
+            .publish((symbol_short!("pause"), admin), ());
+    }
+
+    fn unpause(&self, admin: Address) {
+        self.env()
+            .events()
+            .publish((symbol_short!("unpause"), admin), ());
+    }
+
+    fn rescue_token(&self, token: Address, to: Address, amount: u128) {
+        self.env().events().publish(
+            (Symbol::new(self.env(), "rescue_token"), token),
+            (to, amount),
+        );
+    }
+
+    fn risk_param(&self, name: Symbol, value: i128) {
+        self.env()
+            .events()
+            .publish((Symbol::new(self.env(), "risk_param"), name), value);
+    }
+
+    fn bootstrapper(&self, bootstrapper: Address) {
+        self.env()
+            .events()
+            .publish((Symbol::new(self.env(), "bootstrapper"),), bootstrapper);
+    }
+
+    fn remove_bootstrapper(&self) {
+        self.env()
+            .events()
+            .publish((Symbol::new(self.env(), "remove_bootstrapper"),), ());
+    }
+
+    fn referral_bonus(
+        &self,
+        referrer: Address,
+        user: Address,
+        referrer_amount: u128,
+        user_amount: u128,
+    ) {
+        self.env().events().publish(
+            (Symbol::new(self.env(), "referral_bonus"), referrer),
+            (user, referrer_amount, user_amount),
+        );
+    }
+
+    fn swap_rejected(&self, user: Address, guard: Symbol) {
+        self.env()
+            .events()
+            .publish((Symbol::new(self.env(), "swap_rejected"), user), guard);
+    }
+
+    fn swap_slippage(&self, user: Address, quoted_in: u128, in_amount: u128, slippage_bps: u32) {
+        self.env().events().publish(
+            (Symbol::new(self.env(), "swap_slippage"), user),
+            (quoted_in, in_amount, slippage_bps),
+        );
+    }
+
+    fn swap_referral(&self, referrer: Address, user: Address, amount: u128) {
+        self.env().events().publish(
+            (Symbol::new(self.env(), "swap_referral"), referrer),
+            (user, amount),
+        );
+    }
+
+    fn allowlist(&self, user: Address, allowed: bool) {
+        self.env()
+            .events()
+            .publish((symbol_short!("allowlist"), user), allowed);
+    }
+
+    fn fee_exempt(&self, user: Address, exempt: bool) {
+        self.env()
+            .events()
+            .publish((Symbol::new(self.env(), "fee_exempt"), user), exempt);
+    }
+
+    fn release_vested(&self, user: Address, amount: u128) {
+        self.env()
+            .events()
+            .publish((Symbol::new(self.env(), "release_vested"), user), amount);
+    }
+
+    fn migrate(&self, from_version: u32, to_version: u32) {
+        self.env()
+            .events()
+            .publish((symbol_short!("migrate"),), (from_version, to_version));
+    }
+
+    fn reward_storage(&self, old_storage: Address, new_storage: Address) {
+        self.env().events().publish(
+            (Symbol::new(self.env(), "reward_storage"),),
+            (old_storage, new_storage),
+        );
+    }
+
+    fn lock_liquidity(&self, user: Address, amount: u128, unlock_at: u64) {
+        self.env().events().publish(
+            (Symbol::new(self.env(), "lock_liquidity"), user),
+            (amount, unlock_at),
+        );
+    }
+
+    fn convert_fees(&self, amounts: Vec<u128>, reward: u128) {
+        self.env().events().publish(
+            (Symbol::new(self.env(), "convert_fees"),),
+            (amounts, reward),
+        );
+    }
+
+    fn initial_price_range(&self, min_price: u128, max_price: u128) {
+        self.env().events().publish(
+            (Symbol::new(self.env(), "initial_price_range"),),
+            (min_price, max_price),
+        );
+    }
+
+    fn admin_proposed(&self, admin: Address, future_admin: Address) {
+        self.env().events().publish(
+            (Symbol::new(self.env(), "admin_proposed"),),
+            (admin, future_admin),
+        );
+    }
+
+    fn admin_changed(&self, old_admin: Address, new_admin: Address) {
+        self.env().events().publish(
+            (Symbol::new(self.env(), "admin_changed"),),
+            (old_admin, new_admin),
+        );
+    }
+
+    // version of the contract being replaced. Current wasm hash isn't available to the contract
+    fn upgrade(&self, new_wasm_hash: BytesN<32>, version: u32) {
+        self.env()
+            .events()
+            .publish((symbol_short!("upgrade"),), (new_wasm_hash, version));
+    }
+
+    fn poke(&self, block: u64, accumulated: u128) {
+        self.env()
+            .events()
+            .publish((symbol_short!("poke"),), (block, accumulated));
+    }
+
+    fn reward_checkpoints(&self, enabled: bool) {
+        self.env()
+            .events()
+            .publish((Symbol::new(self.env(), "reward_checkpoints"),), enabled);
+    }
+}
@@ -0,0 +1,155 @@
+#![cfg(any(test, feature = "testutils"))]
+extern crate std;
+use crate::plane::{pool_plane, PoolPlaneClient};
+use crate::LiquidityPoolClient;
+use soroban_sdk::{
+    testutils::{Address as _, Ledger, LedgerInfo},
+    Address, BytesN, Env, Vec,
+};
+use std::vec;
+use token_share::token_contract::{Client, WASM};
+
+pub const FIXTURE_FEE_FRACTION: u32 = 30;
+pub const FIXTURE_USERS_COUNT: u32 = 2;
+pub const FIXTURE_USER_BALANCE: i128 = 1_000_000_0000000;
+
+/// Pool with sorted tokens, share token and plane deployed, ready for integration tests
+pub struct PoolTestFixture<'a> {
+    pub env: Env,
+    pub admin: Address,
+    pub users: vec::Vec<Address>,
+    pub token_a: Client<'a>,
+    pub token_b: Client<'a>,
+    pub token_reward: Client<'a>,
+    pub token_share: Client<'a>,
+    pub liq_pool: LiquidityPoolClient<'a>,
+    pub plane: PoolPlaneClient<'a>,
+}
+
+impl PoolTestFixture<'_> {
+    /// Constant product pool with 0.3% fee and two users funded with FIXTURE_USER_BALANCE
+    ///  of both tokens, the pool is approved to spend them. All auths are mocked
+    pub fn setup(e: &Env) -> Self {
+        let fixture = Self::new(e, FIXTURE_FEE_FRACTION, FIXTURE_USERS_COUNT);
+        for user in fixture.users.iter() {
+            fixture.fund_user(user, FIXTURE_USER_BALANCE);
+        }
+        fixture
+    }
+
+    /// Deploy the pool without funding anyone. All auths are mocked
+    pub fn new(e: &Env, fee_fraction: u32, users_count: u32) -> Self {
+        e.mock_all_auths();
+
+        let admin = Address::generate(e);
+        let mut users = vec![];
+        for _ in 0..users_count {
+            users.push(Address::generate(e));
+        }
+
+        let mut token_a = create_token_contract(e, &Address::generate(e));
+        let mut token_b = create_token_contract(e, &Address::generate(e));
+        let token_reward = create_token_contract(e, &Address::generate(e));
+        if token_b.address < token_a.address {
+            std::mem::swap(&mut token_a, &mut token_b);
+        }
+
+        let plane = create_plane_contract(e);
+        let liq_pool = create_liqpool_contract(
+            e,
+            &admin,
+            &install_token_wasm(e),
+            &Vec::from_array(e, [token_a.address.clone(), token_b.address.clone()]),
+            &token_reward.address,
+            fee_fraction,
+            &plane.address,
+        );
+        let token_share = Client::new(e, &liq_pool.share_id());
+
+        Self {
+            env: e.clone(),
+            admin,
+            users,
+            token_a,
+            token_b,
+            token_reward,
+            token_share,
+            liq_pool,
+            plane,
+        }
+    }
+
+    /// Mint amount of both pool tokens to the user and approve the pool to spend them
+    pub fn fund_user(&self, user: &Address, amount: i128) {
+        for token in [&self.token_a, &self.token_b] {
+            token.mint(user, &amount);
+            token.approve(user, &self.liq_pool.address, &amount, &99999);
+        }
+    }
+
+    /// Deposit both tokens on behalf of the user. Returns amount of shares minted
+    pub fn seed_liquidity(&self, user: &Address, amount_a: u128, amount_b: u128) -> u128 {
+        let (_, shares) = self
+            .liq_pool
+            .deposit(user, &Vec::from_array(&self.env, [amount_a, amount_b]));
+        shares
+    }
+
+    /// Sell in_amount of the other token to buy token a or token b. Returns amount bought
+    pub fn do_swap(&self, user: &Address, buy_a: bool, in_amount: u128) -> u128 {
+        let (in_idx, out_idx) = if buy_a { (1, 0) } else { (0, 1) };
+        self.liq_pool.swap(user, &in_idx, &out_idx, &in_amount, &0)
+    }
+
+    /// Move ledger time forward
+    pub fn jump(&self, time: u64) {
+        jump(&self.env, time);
+    }
+}
+
+pub fn create_token_contract<'a>(e: &Env, admin: &Address) -> Client<'a> {
+    Client::new(e, &e.register_stellar_asset_contract(admin.clone()))
+}
+
+pub fn create_plane_contract<'a>(e: &Env) -> PoolPlaneClient<'a> {
+    PoolPlaneClient::new(e, &e.register_contract_wasm(None, pool_plane::WASM))
+}
+
+pub fn install_token_wasm(e: &Env) -> BytesN<32> {
+    e.deployer().upload_contract_wasm(WASM)
+}
+
+pub fn create_liqpool_contract<'a>(
+    e: &Env,
+    admin: &Address,
+    token_wasm_hash: &BytesN<32>,
+    tokens: &Vec<Address>,
+    token_reward: &Address,
+    fee_fraction: u32,
+    plane: &Address,
+) -> LiquidityPoolClient<'a> {
+    let liqpool = LiquidityPoolClient::new(e, &e.register_contract(None, crate::LiquidityPool {}));
+    liqpool.initialize_all(
+        admin,
+        token_wasm_hash,
+        tokens,
+        &fee_fraction,
+        token_reward,
+        &liqpool.address,
+        plane,
+    );
+    liqpool
+}
+
+pub fn jump(e: &Env, time: u64) {
+    e.ledger().set(LedgerInfo {
+        timestamp: e.ledger().timestamp().saturating_add(time),
+        protocol_version: 20,
+        sequence_number: e.ledger().sequence(),
+        network_id: Default::default(),
+        base_reserve: 10,
+        min_temp_entry_ttl: 999999,
+        min_persistent_entry_ttl: 999999,
+        max_entry_ttl: u32::MAX,
+    });
+}
@@ -0,0 +1,193 @@
+use crate::contract::LiquidityPoolError;
+use crate::pool;
+use crate::storage::{
+    get_is_allowlisted, get_is_killed, get_is_paused, get_is_permissioned, get_max_price_impact,
+    get_min_reserve, get_rejections, put_rejections, PoolState,
+};
+use crate::token::get_user_funds;
+use soroban_sdk::{panic_with_error, Address, Env, Map, Symbol, Vec};
+
+// Swap being validated against the guards, priced on the pool state loaded for the call
+pub(crate) struct SwapRequest {
+    pub(crate) state: PoolState,
+    pub(crate) user: Address,
+    pub(crate) fee_fraction: u32,
+    pub(crate) in_idx: u32,
+    pub(crate) out_idx: u32,
+    pub(crate) in_amount: u128,
+    pub(crate) out: u128,
+    pub(crate) out_min: u128,
+}
+
+// Guards able to reject the swap. The same predicates are used both by swap itself
+// and by check_swap, so preview never drifts from the execution.
+#[derive(Clone, Copy)]
+pub(crate) enum SwapGuard {
+    Killed,
+    Paused,
+    Allowlist,
+    OutMin,
+    PriceImpact,
+    MinReserve,
+    Balance,
+    Allowance,
+}
+
+const SWAP_GUARDS: [SwapGuard; 8] = [
+    SwapGuard::Killed,
+    SwapGuard::Paused,
+    SwapGuard::Allowlist,
+    SwapGuard::OutMin,
+    SwapGuard::PriceImpact,
+    SwapGuard::MinReserve,
+    SwapGuard::Balance,
+    SwapGuard::Allowance,
+];
+
+impl SwapGuard {
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            SwapGuard::Killed => "killed",
+            SwapGuard::Paused => "paused",
+            SwapGuard::Allowlist => "allowlist",
+            SwapGuard::OutMin => "out_min",
+            SwapGuard::PriceImpact => "price_impact",
+            SwapGuard::MinReserve => "min_reserve",
+            SwapGuard::Balance => "balance",
+            SwapGuard::Allowance => "allowance",
+        }
+    }
+
+    fn rejects(&self, e: &Env, request: &SwapRequest) -> bool {
+        match self {
+            SwapGuard::Killed => get_is_killed(e),
+            SwapGuard::Paused => get_is_paused(e),
+            SwapGuard::Allowlist => !is_user_allowed(e, &request.user),
+            SwapGuard::OutMin => request.out < request.out_min,
+            SwapGuard::PriceImpact => {
+                let max_price_impact = get_max_price_impact(e);
+                max_price_impact > 0
+                    && get_swap_price_impact(
+                        e,
+                        &request.state,
+                        request.in_idx,
+                        request.out_idx,
+                        request.in_amount,
+                        request.out,
+                    ) > max_price_impact as u128
+            }
+            SwapGuard::MinReserve => {
+                let reserve_out = request.state.reserves(e).get(request.out_idx).unwrap();
+                reserve_out.saturating_sub(request.out) < get_min_reserve(e)
+            }
+            SwapGuard::Balance => get_user_sell_funds(e, request).0 < request.in_amount,
+            SwapGuard::Allowance => get_user_sell_funds(e, request).1 < request.in_amount,
+        }
+    }
+
+    fn reject(&self, e: &Env, request: &SwapRequest) -> ! {
+        match (self, request.in_idx) {
+            (SwapGuard::Killed, _) => panic_with_error!(e, LiquidityPoolError::PoolKilled),
+            (SwapGuard::Paused, _) => panic_with_error!(e, LiquidityPoolError::PoolPaused),
+            (SwapGuard::Allowlist, _) => panic_with_error!(e, LiquidityPoolError::UserNotAllowed),
+            (SwapGuard::OutMin, _) => panic!("out amount is less than min"),
+            (SwapGuard::PriceImpact, _) => {
+                panic_with_error!(e, LiquidityPoolError::PriceImpactTooHigh)
+            }
+            (SwapGuard::MinReserve, _) => {
+                panic_with_error!(e, LiquidityPoolError::InsufficientReserves)
+            }
+            (SwapGuard::Balance, 0) => {
+                panic_with_error!(e, LiquidityPoolError::InsufficientBalanceA)
+            }
+            (SwapGuard::Balance, _) => {
+                panic_with_error!(e, LiquidityPoolError::InsufficientBalanceB)
+            }
+            (SwapGuard::Allowance, 0) => {
+                panic_with_error!(e, LiquidityPoolError::InsufficientAllowanceA)
+            }
+            (SwapGuard::Allowance, _) => {
+                panic_with_error!(e, LiquidityPoolError::InsufficientAllowanceB)
+            }
+        }
+    }
+}
+
+// Permissioned pool only lets allowlisted users deposit and swap
+pub(crate) fn is_user_allowed(e: &Env, user: &Address) -> bool {
+    !get_is_permissioned(e) || get_is_allowlisted(e, user)
+}
+
+pub(crate) fn require_user_allowed(e: &Env, user: &Address) {
+    if !is_user_allowed(e, user) {
+        panic_with_error!(e, LiquidityPoolError::UserNotAllowed);
+    }
+}
+
+// Price impact of the swap on the current reserves. 0.01% = 1
+pub(crate) fn get_swap_price_impact(
+    e: &Env,
+    state: &PoolState,
+    in_idx: u32,
+    out_idx: u32,
+    in_amount: u128,
+    out: u128,
+) -> u128 {
+    let reserves = state.reserves(e);
+    pool::get_price_impact(
+        reserves.get(in_idx).unwrap(),
+        reserves.get(out_idx).unwrap(),
+        in_amount,
+        out,
+    )
+}
+
+fn get_user_sell_funds(e: &Env, request: &SwapRequest) -> (u128, u128) {
+    let sell_token = request.state.tokens(e).get(request.in_idx).unwrap();
+    get_user_funds(e, sell_token, &request.user)
+}
+
+// Symbols of all the guards rejecting the swap
+pub(crate) fn get_swap_violations(e: &Env, request: &SwapRequest) -> Vec<Symbol> {
+    let mut result = Vec::new(e);
+    for guard in SWAP_GUARDS {
+        if guard.rejects(e, request) {
+            result.push_back(Symbol::new(e, guard.name()));
+        }
+    }
+    result
+}
+
+// First guard rejecting the swap. Evaluated before any transfer
+pub(crate) fn check_swap_allowed(e: &Env, request: &SwapRequest) -> Result<(), SwapGuard> {
+    for guard in SWAP_GUARDS {
+        if guard.rejects(e, request) {
+            return Err(guard);
+        }
+    }
+    Ok(())
+}
+
+// Panic with the first guard rejecting the swap
+pub(crate) fn require_swap_allowed(e: &Env, request: &SwapRequest) {
+    if let Err(guard) = check_swap_allowed(e, request) {
+        guard.reject(e, request);
+    }
+}
+
+// Count soft rejection. Only survives if the invocation doesn't fail
+pub(crate) fn record_rejection(e: &Env, guard: SwapGuard) -> Symbol {
+    let name = Symbol::new(e, guard.name());
+    put_rejections(e, &name, get_rejections(e, &name) + 1);
+    name
+}
+
+// Soft rejections count for every guard
+pub(crate) fn get_rejection_stats(e: &Env) -> Map<Symbol, u64> {
+    let mut result = Map::new(e);
+    for guard in SWAP_GUARDS {
+        let name = Symbol::new(e, guard.name());
+        result.set(name.clone(), get_rejections(e, &name));
+    }
+    result
+}
@@ -0,0 +1,56 @@
+use crate::storage::{
+    get_backfill_complete, get_holder_shares, get_holders_count, get_indexed_shares,
+    put_backfill_complete, put_holder_shares, put_holders_count, put_indexed_shares,
+    remove_holder_shares,
+};
+use soroban_sdk::{Address, Env};
+use token_share::{get_total_shares, get_user_balance_shares};
+
+// Refresh holder index after user balance changed. New holders are indexed on the fly,
+// holders from before the index was introduced are backfilled by the admin
+pub(crate) fn sync_holder(e: &Env, user: &Address) {
+    let balance = get_user_balance_shares(e, user);
+    match get_holder_shares(e, user) {
+        Some(shares) => {
+            put_indexed_shares(e, get_indexed_shares(e) - shares + balance);
+            if balance == 0 {
+                put_holders_count(e, get_holders_count(e) - 1);
+                remove_holder_shares(e, user);
+            } else {
+                put_holder_shares(e, user, balance);
+            }
+        }
+        None => index_holder(e, user, balance),
+    }
+}
+
+// Index holder unknown to the index yet. Returns whether holder was indexed
+pub(crate) fn backfill_holder(e: &Env, user: &Address) -> bool {
+    if get_holder_shares(e, user).is_some() {
+        return false;
+    }
+    let balance = get_user_balance_shares(e, user);
+    index_holder(e, user, balance);
+    balance > 0
+}
+
+// Backfill is complete once indexed holders cover the whole shares supply
+pub(crate) fn check_backfill_complete(e: &Env) -> bool {
+    if get_backfill_complete(e) {
+        return true;
+    }
+    let complete = get_indexed_shares(e) >= get_total_shares(e);
+    if complete {
+        put_backfill_complete(e, true);
+    }
+    complete
+}
+
+fn index_holder(e: &Env, user: &Address, balance: u128) {
+    if balance == 0 {
+        return;
+    }
+    put_holders_count(e, get_holders_count(e) + 1);
+    put_indexed_shares(e, get_indexed_shares(e) + balance);
+    put_holder_shares(e, user, balance);
+}
@@ -2,14 +2,27 @@
 
 mod constants;
 mod contract;
+mod events;
+pub mod fixture;
+mod guards;
+mod holders;
+mod math;
 mod plane;
 mod plane_interface;
 mod pool;
 mod pool_interface;
 mod rewards;
+mod router;
+mod stable_math;
 mod storage;
 mod test;
+mod test_budget;
+mod test_invariants;
 mod testutils;
 pub mod token;
+mod weighted_math;
 
 pub use contract::{LiquidityPool, LiquidityPoolClient};
+pub use storage::{
+    Curve, PoolContracts, QuoteContext, RewardsInfo, ShareLock, SwapOutcome, UserPosition,
+};
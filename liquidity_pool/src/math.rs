@@ -0,0 +1,178 @@
+// Rounding policy: every division rounds in the pool's favor.
+//  Amounts users pay (deposit amounts) are rounded up,
+//  amounts users receive (shares minted, swap out, withdraw payouts) are rounded down.
+//  Dust-level operations can't leak value out of the pool this way.
+
+use num_integer::Roots;
+
+// a * b / c rounded down. For amounts users receive
+pub fn mul_div_floor(a: u128, b: u128, c: u128) -> u128 {
+    mul_div(a, b, c).0
+}
+
+// a * b / c rounded up. For amounts users pay
+pub fn mul_div_ceil(a: u128, b: u128, c: u128) -> u128 {
+    match mul_div(a, b, c) {
+        (quotient, 0) => quotient,
+        (quotient, _) => quotient + 1,
+    }
+}
+
+// Quotient and remainder of a * b / c. Products of 18 decimals amounts exceed u128,
+//  so those are divided in 256 bits. Only the quotient has to fit u128
+fn mul_div(a: u128, b: u128, c: u128) -> (u128, u128) {
+    match a.checked_mul(b) {
+        Some(n) => (n / c, n % c),
+        None => {
+            let (high, low) = mul_wide(a, b);
+            div_wide(high, low, c)
+        }
+    }
+}
+
+// floor(sqrt(a * b)). When the product exceeds u128 the root is found by Newton's method,
+//  starting above it, so the iterations decrease monotonically to the exact root
+pub fn sqrt_mul(a: u128, b: u128) -> u128 {
+    if let Some(n) = a.checked_mul(b) {
+        return n.sqrt();
+    }
+    let (high, _) = mul_wide(a, b);
+    let root_bits = (256 - high.leading_zeros() + 1) / 2;
+    let mut x = match root_bits {
+        128 => u128::MAX,
+        _ => 1 << root_bits,
+    };
+    loop {
+        let y = mul_div_floor(a, b, x);
+        if y >= x {
+            return x;
+        }
+        x = y + (x - y) / 2;
+    }
+}
+
+// Full 256 bits product as (high, low) halves
+fn mul_wide(a: u128, b: u128) -> (u128, u128) {
+    const MASK: u128 = u64::MAX as u128;
+    let (a_high, a_low) = (a >> 64, a & MASK);
+    let (b_high, b_low) = (b >> 64, b & MASK);
+
+    let low = a_low * b_low;
+    let mid_a = a_high * b_low;
+    let mid_b = a_low * b_high;
+    let mid = (low >> 64) + (mid_a & MASK) + (mid_b & MASK);
+    let high = a_high * b_high + (mid_a >> 64) + (mid_b >> 64) + (mid >> 64);
+    (high, (mid << 64) | (low & MASK))
+}
+
+// Long division of the 256 bits number by c, bit by bit
+fn div_wide(high: u128, low: u128, c: u128) -> (u128, u128) {
+    if high >= c {
+        panic!("mul_div overflow");
+    }
+    let mut remainder = high;
+    let mut quotient = 0;
+    for i in (0..128).rev() {
+        // remainder < c before the shift, so the carried out bit means it's over c after
+        let carry = remainder >> 127;
+        remainder = (remainder << 1) | ((low >> i) & 1);
+        quotient <<= 1;
+        if carry == 1 || remainder >= c {
+            remainder = remainder.wrapping_sub(c);
+            quotient |= 1;
+        }
+    }
+    (quotient, remainder)
+}
+
+// Fixed point arithmetic with 18 decimals for the weighted pool math
+pub const ONE: u128 = 1_000_000_000_000_000_000;
+const ONE_SIGNED: i128 = ONE as i128;
+// ln(2) scaled by ONE
+const LN_2: i128 = 693_147_180_559_945_309;
+// pow result is accurate way better than that, so padding by it keeps rounding in the pool's favor
+const MAX_POW_RELATIVE_ERROR: u128 = 10_000;
+
+// Natural logarithm of the fixed point x
+pub fn ln(x: u128) -> i128 {
+    if x == 0 {
+        panic!("ln of zero");
+    }
+
+    // x = m * 2^k, where 1 <= m < 2
+    let mut k = 0_i128;
+    let mut m = x;
+    if m >= 2 * ONE {
+        let shift = (m / ONE).ilog2();
+        m >>= shift;
+        k = shift as i128;
+    }
+    while m < ONE {
+        m *= 2;
+        k -= 1;
+    }
+
+    // ln(m) = 2 * atanh(z) = 2 * (z + z^3 / 3 + z^5 / 5 + ...), where z = (m - 1) / (m + 1) < 1 / 3
+    let z = ((m - ONE) * ONE / (m + ONE)) as i128;
+    let z2 = z * z / ONE_SIGNED;
+    let mut term = z;
+    let mut sum = 0;
+    let mut n = 1;
+    while term > 0 {
+        sum += term / n;
+        term = term * z2 / ONE_SIGNED;
+        n += 2;
+    }
+    k * LN_2 + 2 * sum
+}
+
+// Exponent of the fixed point x
+pub fn exp(x: i128) -> u128 {
+    // too small to be represented
+    if x < -41 * ONE_SIGNED {
+        return 0;
+    }
+    if x > 43 * ONE_SIGNED {
+        panic!("exp overflow");
+    }
+
+    // x = k * ln(2) + r, where |r| <= ln(2) / 2
+    let k = (x + x.signum() * (LN_2 / 2)) / LN_2;
+    let r = x - k * LN_2;
+
+    // Taylor series converges fast for small r
+    let mut term = ONE_SIGNED;
+    let mut sum = ONE_SIGNED;
+    let mut n = 1;
+    while term != 0 {
+        term = term * r / ONE_SIGNED / n;
+        sum += term;
+        n += 1;
+    }
+
+    if k >= 0 {
+        (sum as u128) << k
+    } else {
+        (sum as u128) >> -k
+    }
+}
+
+// x^y for fixed point x and y
+fn pow(x: u128, y: u128) -> u128 {
+    // split multiplication, so it doesn't overflow for the large exponents
+    let ln_x = ln(x);
+    let y = y as i128;
+    exp(ln_x / ONE_SIGNED * y + ln_x % ONE_SIGNED * y / ONE_SIGNED)
+}
+
+// x^y rounded up. For amounts users pay
+pub fn pow_up(x: u128, y: u128) -> u128 {
+    let result = pow(x, y);
+    result + mul_div_ceil(result, MAX_POW_RELATIVE_ERROR, ONE) + 1
+}
+
+// x^y rounded down. For amounts users receive
+pub fn pow_down(x: u128, y: u128) -> u128 {
+    let result = pow(x, y);
+    result.saturating_sub(mul_div_ceil(result, MAX_POW_RELATIVE_ERROR, ONE) + 1)
+}
@@ -7,21 +7,48 @@ pub mod pool_plane {
 
 pub use crate::plane::pool_plane::Client as PoolPlaneClient;
 
-use crate::storage::{get_fee_fraction, get_plane, get_reserve_a, get_reserve_b};
-use soroban_sdk::{symbol_short, Env, Vec};
+use crate::storage::{get_plane, Curve, PoolState};
+use soroban_sdk::{symbol_short, Env, Symbol, Vec};
 
-fn get_pool_data(e: &Env) -> (Vec<u128>, Vec<u128>) {
-    (
-        Vec::from_array(e, [get_fee_fraction(e) as u128]),
-        Vec::from_array(e, [get_reserve_a(e), get_reserve_b(e)]),
-    )
+fn get_pool_data(e: &Env, state: &PoolState) -> (Symbol, Vec<u128>, Vec<u128>) {
+    let fee_fraction = state.fee_fraction as u128;
+    let reserves = state.reserves(e);
+    match state.curve {
+        Curve::ConstantProduct => (
+            symbol_short!("standard"),
+            Vec::from_array(e, [fee_fraction]),
+            reserves,
+        ),
+        Curve::Weighted(weight_a, weight_b) if weight_a != weight_b => (
+            symbol_short!("weighted"),
+            Vec::from_array(e, [fee_fraction, weight_a as u128, weight_b as u128]),
+            reserves,
+        ),
+        // equal weights are exactly the constant product curve
+        Curve::Weighted(..) => (
+            symbol_short!("standard"),
+            Vec::from_array(e, [fee_fraction]),
+            reserves,
+        ),
+        // same layout as the stableswap pool reports, amplification is never ramped
+        Curve::StableSwap(amp) => (
+            symbol_short!("stable"),
+            Vec::from_array(e, [fee_fraction, amp, 0, amp, 0]),
+            reserves,
+        ),
+    }
 }
 
 pub fn update_plane(e: &Env) {
-    let (init_args, reserves) = get_pool_data(e);
+    update_plane_state(e, &PoolState::load(e));
+}
+
+// Same as update_plane, with the pool state already loaded by the caller
+pub fn update_plane_state(e: &Env, state: &PoolState) {
+    let (pool_type, init_args, reserves) = get_pool_data(e, state);
     PoolPlaneClient::new(e, &get_plane(e)).update(
         &e.current_contract_address(),
-        &symbol_short!("standard"),
+        &pool_type,
         &init_args,
         &reserves,
     );
@@ -1,3 +1,9 @@
+use crate::constants::{FEE_MULTIPLIER, NORMALIZED_DECIMALS, PRICE_PRECISION, ROOT_K_PRECISION};
+use crate::contract::LiquidityPoolError;
+use crate::math::{mul_div_ceil, mul_div_floor, sqrt_mul};
+
+// Amounts to take from the depositor. Rounded up, so deposit never dilutes the pool.
+//  The first deposit sets the ratio, so the empty pool takes the desired amounts as is
 pub fn get_deposit_amounts(
     desired_a: u128,
     min_a: u128,
@@ -5,22 +11,153 @@ pub fn get_deposit_amounts(
     min_b: u128,
     reserve_a: u128,
     reserve_b: u128,
-) -> (u128, u128) {
-    if reserve_a == 0 && reserve_b == 0 {
-        return (desired_a, desired_b);
+) -> Result<(u128, u128), LiquidityPoolError> {
+    if desired_a == 0 || desired_b == 0 {
+        return Err(LiquidityPoolError::ZeroSharesDeposit);
     }
 
-    let amount_b = desired_a * reserve_b / reserve_a;
-    if amount_b <= desired_b {
-        if amount_b < min_b {
-            panic!("amount_b less than min")
-        }
-        (desired_a, amount_b)
+    let amounts = if reserve_a == 0 && reserve_b == 0 {
+        (desired_a, desired_b)
+    } else if reserve_a == 0 || reserve_b == 0 {
+        return Err(LiquidityPoolError::EmptyReserve);
     } else {
-        let amount_a = desired_b * reserve_a / reserve_b;
-        if amount_a > desired_a || desired_a < min_a {
-            panic!("amount_a invalid")
+        let amount_b = mul_div_ceil(desired_a, reserve_b, reserve_a);
+        if amount_b <= desired_b {
+            (desired_a, amount_b)
+        } else {
+            // desired_b * reserve_a / reserve_b < desired_a here, so rounded up amount fits desired_a
+            (mul_div_ceil(desired_b, reserve_a, reserve_b), desired_b)
         }
-        (amount_a, desired_b)
+    };
+    if amounts.0 < min_a || amounts.1 < min_b {
+        return Err(LiquidityPoolError::DepositAmountBelowMin);
+    }
+    Ok(amounts)
+}
+
+// Largest amounts of the reserves ratio fitting into max_a and max_b. Rounded up like deposit amounts.
+//  The ratio is undefined for the empty pool, so it's rejected
+pub fn get_proportional_amounts(
+    max_a: u128,
+    max_b: u128,
+    reserve_a: u128,
+    reserve_b: u128,
+) -> Result<(u128, u128), LiquidityPoolError> {
+    if reserve_a == 0 || reserve_b == 0 {
+        return Err(LiquidityPoolError::EmptyReserve);
+    }
+    let amounts = match mul_div_ceil(max_a, reserve_b, reserve_a) {
+        // token a binds
+        amount_b if amount_b <= max_b => (max_a, amount_b),
+        // token b binds: max_b * reserve_a / reserve_b < max_a, so rounded up amount fits max_a
+        _ => (mul_div_ceil(max_b, reserve_a, reserve_b), max_b),
+    };
+    if amounts.0 == 0 || amounts.1 == 0 {
+        return Err(LiquidityPoolError::ZeroSharesDeposit);
+    }
+    Ok(amounts)
+}
+
+// Calculate how much can be bought with in_amount from the pool. Rounded down
+pub fn get_swap_out(
+    fee_fraction: u32,
+    reserve_sell: u128,
+    reserve_buy: u128,
+    in_amount: u128,
+) -> u128 {
+    let multiplier_with_fee = FEE_MULTIPLIER - fee_fraction as u128;
+    let d = reserve_sell * FEE_MULTIPLIER + in_amount * multiplier_with_fee;
+    mul_div_floor(in_amount * multiplier_with_fee, reserve_buy, d)
+}
+
+// Calculate how much should be sold to buy out from the pool. Rounded up,
+//  so get_swap_out of the result is never below out. out should be below reserve_buy
+pub fn get_swap_in(fee_fraction: u32, reserve_sell: u128, reserve_buy: u128, out: u128) -> u128 {
+    let multiplier_with_fee = FEE_MULTIPLIER - fee_fraction as u128;
+    mul_div_ceil(
+        reserve_sell * out,
+        FEE_MULTIPLIER,
+        (reserve_buy - out) * multiplier_with_fee,
+    )
+}
+
+// Relative drop of the sold token price after the swap. 0.01% = 1. Rounded up.
+//  Reserves ratio is compared, so weights and decimals cancel out
+pub fn get_price_impact(reserve_sell: u128, reserve_buy: u128, in_amount: u128, out: u128) -> u128 {
+    if reserve_sell == 0 || reserve_buy == 0 {
+        return 0;
+    }
+    let price_retained = mul_div_floor(
+        reserve_buy - out,
+        reserve_sell * FEE_MULTIPLIER,
+        reserve_buy,
+    ) / (reserve_sell + in_amount);
+    FEE_MULTIPLIER - price_retained
+}
+
+// Calculate shares minted for the first deposit.
+//  At the deposit implied price p = amount_b / amount_a its value is
+//  V = amount_a * p + amount_b = 2 * amount_b (in token b) = 2 * amount_a (in token a),
+//  so sqrt(amount_a * amount_b) = V / (2 * sqrt(p)) is the deposit value in sqrt(a * b) units.
+//  It doesn't depend on which side is more valuable nor on the token value is measured in.
+//  Depositor owns the whole pool, so the count only sets the unit for later proportional deposits.
+//  No share formula protects from the off-market ratio though: liquidity v of token a added at 1:skew
+//  loses v * (1 + skew - 2 * sqrt(skew)) to the arbitrage restoring the price, and the first
+//  depositor arbitraging it gets that back. The bootstrapper, if admin opts in to set one, closes
+//  the first deposit to anyone else, so the pool is seeded at the market price.
+pub fn get_initial_shares(amount_a: u128, amount_b: u128) -> u128 {
+    sqrt_mul(amount_a, amount_b)
+}
+
+// sqrt(reserve_a * reserve_b) per share scaled by ROOT_K_PRECISION.
+//  Price movement doesn't change sqrt(k) of the constant product pool, deposits and withdrawals
+//  keep it per share, so it only grows with the fees left in the pool
+pub fn get_root_k_per_share(reserve_a: u128, reserve_b: u128, total_shares: u128) -> u128 {
+    if total_shares == 0 {
+        return 0;
+    }
+    mul_div_floor(
+        sqrt_mul(reserve_a, reserve_b),
+        ROOT_K_PRECISION,
+        total_shares,
+    )
+}
+
+// Part of the amount explained by sqrt(k) per share growth since the checkpoint
+pub fn get_fee_part(amount: u128, root_k_checkpoint: u128, root_k_per_share: u128) -> u128 {
+    if root_k_per_share <= root_k_checkpoint {
+        return 0;
+    }
+    mul_div_floor(
+        amount,
+        root_k_per_share - root_k_checkpoint,
+        root_k_per_share,
+    )
+}
+
+// Scale amount of the token with given decimals to NORMALIZED_DECIMALS.
+//  Only used for prices and share counts, swap math stays in raw units
+pub fn normalize_amount(amount: u128, decimals: u32) -> u128 {
+    if decimals > NORMALIZED_DECIMALS {
+        amount / 10_u128.pow(decimals - NORMALIZED_DECIMALS)
+    } else {
+        amount * 10_u128.pow(NORMALIZED_DECIMALS - decimals)
+    }
+}
+
+// Scale normalized amount back to the token decimals. Rounded down
+pub fn denormalize_amount(amount: u128, decimals: u32) -> u128 {
+    if decimals > NORMALIZED_DECIMALS {
+        amount * 10_u128.pow(decimals - NORMALIZED_DECIMALS)
+    } else {
+        amount / 10_u128.pow(NORMALIZED_DECIMALS - decimals)
+    }
+}
+
+// Spot price of token a in token b scaled by PRICE_PRECISION. Reserves are expected normalized
+pub fn get_price(reserve_a: u128, reserve_b: u128) -> u128 {
+    if reserve_a == 0 {
+        return 0;
     }
+    reserve_b * PRICE_PRECISION / reserve_a
 }
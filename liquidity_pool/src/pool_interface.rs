@@ -1,4 +1,9 @@
-use soroban_sdk::{Address, BytesN, Env, Map, Symbol, Val, Vec};
+use crate::storage::{
+    PoolContracts, QuoteContext, RewardsInfo, ShareLock, SwapOutcome, UserPosition,
+};
+use liquidity_pool_interface::SwapResult;
+use rewards::storage::PoolRewardData;
+use soroban_sdk::{Address, BytesN, Env, Map, String, Symbol, Val, Vec};
 
 pub trait LiquidityPoolCrunch {
     // Initialize pool completely to reduce calculations cost
@@ -14,8 +19,14 @@ pub trait LiquidityPoolCrunch {
     );
 }
 
+// share_id, get_reserves, deposit, swap, estimate_swap and withdraw are part of
+//  liquidity_pool_interface::LiquidityPoolInterface, shared with the contracts calling the pool.
+// Every entrypoint except pool_type, derive_share_address, initializers, set_pools_plane and
+//  initialize_rewards_config fails with NotInitialized until the pool is initialized,
+//  rewards entrypoints also until the rewards config is initialized.
+//  initialize_all sets the plane before initialize runs, so set_pools_plane can't require it
 pub trait LiquidityPoolTrait {
-    // Get symbolic explanation of pool type.
+    // Get symbolic explanation of pool type. View, O(1)
     fn pool_type(e: Env) -> Symbol;
 
     // Sets the token contract addresses for this pool
@@ -27,78 +38,616 @@ pub trait LiquidityPoolTrait {
         fee_fraction: u32,
     );
 
-    // Returns the token contract address for the pool share token
-    fn share_id(e: Env) -> Address;
+    // Same as initialize, but share token name and symbol can be set explicitly.
+    // By default they are built from the pool token symbols: "XLM-USDC LP" / "XLM-USDC"
+    fn initialize_with_share_metadata(
+        e: Env,
+        admin: Address,
+        lp_token_wasm_hash: BytesN<32>,
+        tokens: Vec<Address>,
+        fee_fraction: u32,
+        share_name: Option<String>,
+        share_symbol: Option<String>,
+    );
+
+    // Same as initialize, but reuses already deployed share token instead of deploying a new one.
+    // Pool must be the share token admin and the token must have no supply yet
+    fn initialize_with_share_token(
+        e: Env,
+        admin: Address,
+        share_token: Address,
+        tokens: Vec<Address>,
+        fee_fraction: u32,
+    );
 
+    // Same as initialize, but tokens are priced with the StableSwap curve for like-kind assets.
+    // amp is the amplification coefficient A
+    fn initialize_stableswap(
+        e: Env,
+        admin: Address,
+        lp_token_wasm_hash: BytesN<32>,
+        tokens: Vec<Address>,
+        fee_fraction: u32,
+        amp: u128,
+    );
+
+    // Same as initialize, but tokens are priced with the weighted product curve.
+    // weights must sum up to 100, so 80/20 pool has weights [80, 20]
+    fn initialize_weighted(
+        e: Env,
+        admin: Address,
+        lp_token_wasm_hash: BytesN<32>,
+        tokens: Vec<Address>,
+        fee_fraction: u32,
+        weights: Vec<u32>,
+    );
+
+    // Total supply of the pool share token, tracked by the pool. View, O(1)
+    fn get_total_shares(e: Env) -> i128;
+
+    // Get pool tokens. View, O(1)
     fn get_tokens(e: Env) -> Vec<Address>;
 
-    // Deposits token_a and token_b. Also mints pool shares for the "to" Identifier. The amount minted
-    // is determined based on the difference between the reserves stored by this contract, and
-    // the actual balance of token_a and token_b for this contract.
-    fn deposit(e: Env, user: Address, desired_amounts: Vec<u128>) -> (Vec<u128>, u128);
+    // Get pool tokens, share token and reward token addresses.
+    // Fails with NotInitialized before the pool and rewards are initialized. View, O(1)
+    fn get_contracts(e: Env) -> PoolContracts;
+
+    // Get address of the share token the pool deploys for the sorted tokens.
+    // Available before the pool is initialized. View, O(1)
+    fn derive_share_address(e: Env, token_a: Address, token_b: Address) -> Address;
+
+    // Check the share token belongs to the pool: it's the token the pool deployed
+    // for its own tokens. View, O(1)
+    fn verify_share_token(e: Env, share: Address) -> bool;
+
+    // Same as deposit, locking the minted shares until lock_until. Locked shares can't be
+    // withdrawn before that, nor transferred until unlock. Returns amounts deposited and shares minted
+    fn deposit_locked(
+        e: Env,
+        user: Address,
+        desired_amounts: Vec<u128>,
+        lock_until: u64,
+    ) -> (Vec<u128>, u128);
+
+    // Lock amount more of the user shares until lock_until. Lock gives no rewards boost,
+    // can be extended, but never shortened. Fails if user shares are locked with lock_shares
+    fn lock_liquidity(e: Env, user: Address, amount: u128, lock_until: u64);
+
+    // Get amount of user shares locked and the unlock timestamp, zeros if nothing is locked.
+    // View, O(1)
+    fn get_locked(e: Env, user: Address) -> (u128, u64);
+
+    // Deposit single token into the non-empty pool. Part of the amount not matching the pool
+    // proportion is charged with the swap fee. Not available for the StableSwap curve.
+    // Returns minted shares
+    fn deposit_single(e: Env, user: Address, in_idx: u32, amount: u128, min_shares: u128) -> u128;
+
+    // Get amounts deposit takes for the desired ones at the current reserves, rounded up the same way.
+    // Empty pool takes the desired amounts as is. Fails with ZeroSharesDeposit if any desired
+    // amount is zero and with DepositAmountBelowMin if the ratio forces an amount below its min.
+    // View, O(1)
+    fn get_deposit_amounts(
+        e: Env,
+        desired_a: u128,
+        min_a: u128,
+        desired_b: u128,
+        min_b: u128,
+    ) -> (u128, u128);
+
+    // Deposit the largest amounts of the current reserves ratio fitting into max_a and max_b,
+    // so only the value to deposit has to be chosen. Fails with EmptyReserve for the empty pool
+    // and with DepositSharesBelowMin if less than min_shares minted.
+    // Returns amounts of token a and token b deposited and shares minted
+    fn deposit_proportional(
+        e: Env,
+        user: Address,
+        max_a: u128,
+        max_b: u128,
+        min_shares: u128,
+    ) -> (u128, u128, u128);
+
+    // Same as deposit crediting the referrer and the depositor with the referral bonus:
+    // share of the depositor accrued rewards paid to each while rewards campaign is active
+    fn deposit_with_referrer(
+        e: Env,
+        user: Address,
+        desired_amounts: Vec<u128>,
+        referrer: Option<Address>,
+    ) -> (Vec<u128>, u128);
 
-    // Perform an exchange between two coins.
-    // in_idx: index of token to send
-    // out_idx: index of token to receive
-    // in_amount: Amount of token in being exchanged
-    // out_min: Minimum amount of token out to receive
-    // Returns the actual amount of coin out received
-    fn swap(
+    // Same as swap, paying referrer the referral_bps cut of the amount sold (0.01% = 1).
+    // The cut is carved out of the pool fee, so the trader receives the same amount as with swap.
+    // Arguments are the swap ones followed by the referral pair, kept flat for the clients
+    #[allow(clippy::too_many_arguments)]
+    fn swap_with_referral(
         e: Env,
         user: Address,
         in_idx: u32,
         out_idx: u32,
         in_amount: u128,
         out_min: u128,
+        referrer: Address,
+        referral_bps: u32,
     ) -> u128;
 
-    // Estimate amount of coins to retrieve using swap function
-    fn estimate_swap(e: Env, in_idx: u32, out_idx: u32, in_amount: u128) -> u128;
+    // Buy out of the token out_idx guarded by the percentage instead of the absolute maximum:
+    // amount sold is recomputed on-chain and the swap fails if it's above quoted_in by more than
+    // max_slippage_bps (0.01% = 1). Realized slippage is published with the swap_slippage event.
+    // Returns amount sold. Constant product pools only
+    fn swap_strict(
+        e: Env,
+        user: Address,
+        in_idx: u32,
+        out_idx: u32,
+        out: u128,
+        quoted_in: u128,
+        max_slippage_bps: u32,
+    ) -> u128;
+
+    // Estimate amount to sell to buy out with swap_strict. View, O(1)
+    fn estimate_swap_in(e: Env, in_idx: u32, out_idx: u32, out: u128) -> u128;
+
+    // Buy bps_of_reserve share of the out token reserve (0.01% = 1, up to 30%), priced on
+    // the reserves at the execution time, so the order doesn't need requoting when they move.
+    // Fails if more than in_max has to be sold. Constant product pools only
+    fn swap_pct(
+        e: Env,
+        user: Address,
+        in_idx: u32,
+        out_idx: u32,
+        bps_of_reserve: u32,
+        in_max: u128,
+    ) -> SwapResult;
+
+    // Estimate amount sold and amount bought by swap_pct. View, O(1)
+    fn estimate_swap_pct(e: Env, in_idx: u32, out_idx: u32, bps_of_reserve: u32) -> (u128, u128);
+
+    // Same as swap, but swap rejected by the guard isn't failed: rejecting guard is returned
+    // and counted in rejection stats instead, so rejected flow is visible on-chain
+    fn swap_or_reject(
+        e: Env,
+        user: Address,
+        in_idx: u32,
+        out_idx: u32,
+        in_amount: u128,
+        out_min: u128,
+    ) -> SwapOutcome;
+
+    // Same as estimate_swap for the given user, so quotes match for the fee exempt users.
+    // View, O(1)
+    fn estimate_swap_for(e: Env, user: Address, in_idx: u32, out_idx: u32, in_amount: u128)
+        -> u128;
+
+    // Estimate how much the swap moves price of the sold token. 0.01% = 1. View, O(1)
+    fn estimate_price_impact(e: Env, in_idx: u32, out_idx: u32, in_amount: u128) -> u128;
 
-    // Transfers share_amount of pool share tokens to this contract,
-    // burns all pools share tokens in this contracts, and sends
-    // the corresponding amount of tokens to user.
+    // Check swap against all the pool guards without executing it.
+    // Returns symbols of the guards rejecting the swap, empty if swap passes. View, O(1)
+    fn check_swap(
+        e: Env,
+        user: Address,
+        in_idx: u32,
+        out_idx: u32,
+        in_amount: u128,
+        out_min: u128,
+    ) -> Vec<Symbol>;
+
+    // Withdraw the whole user share balance. Shares are transferred with the user authorization,
+    // so no allowance is needed. Returns amount of tokens withdrawn
+    fn withdraw_all(e: Env, user: Address, min_amounts: Vec<u128>) -> Vec<u128>;
+
+    // Withdraw user shares sending token a to recipient_a and token b to recipient_b.
+    // Rewards and locks are checked for the user the same way as for withdraw.
     // Returns amount of tokens withdrawn
-    fn withdraw(e: Env, user: Address, share_amount: u128, min_amounts: Vec<u128>) -> Vec<u128>;
+    fn withdraw_to(
+        e: Env,
+        user: Address,
+        share_amount: u128,
+        min_amounts: Vec<u128>,
+        recipient_a: Address,
+        recipient_b: Address,
+    ) -> Vec<u128>;
 
-    // Get pool reserves
-    fn get_reserves(e: Env) -> Vec<u128>;
+    // Withdraw without touching rewards, unclaimed rewards are forfeited.
+    // Available only in emergency mode, when regular withdraw is broken by rewards subsystem.
+    // Returns amount of tokens withdrawn
+    fn emergency_withdraw(e: Env, user: Address, share_amount: u128) -> Vec<u128>;
 
-    // Fee fraction getter. 1 = 0.01%
+    // Spot price of token a in token b scaled by 1e7, normalized for tokens decimals.
+    // Weighted pool price is (reserve_b / w_b) / (reserve_a / w_a). View, O(1)
+    fn get_price(e: Env) -> u128;
+
+    // Get reserves along with the ledger timestamp they were last written at,
+    // so off-chain consumers can tell stale reserves. View, O(1)
+    fn get_reserves_with_timestamp(e: Env) -> (u128, u128, u64);
+
+    // Add tokens to the reserves of the non-empty pool without minting shares,
+    // so the value accrues to the existing liquidity providers pro-rata.
+    // Returns amounts actually received by the pool
+    fn donate(e: Env, from: Address, amount_a: u128, amount_b: u128) -> Vec<u128>;
+
+    // Transfer token balances exceeding recorded reserves to the given address.
+    // Returns amounts transferred
+    fn skim(e: Env, to: Address) -> Vec<u128>;
+
+    // Force reserves and pool held shares to match actual token balances of the pool
+    fn sync(e: Env);
+
+    // Fee fraction getter. 1 = 0.01%. View, O(1)
     fn get_fee_fraction(e: Env) -> u32;
 
-    // Get dictionary of basic pool information: type, fee, special parameters if any.
+    // Get tokens, reserves, fee and paused state in a single call, enough to quote a swap.
+    // View, O(1)
+    fn get_quote_context(e: Env) -> QuoteContext;
+
+    // Get user shares, pool ownership, token amounts redeemable at the current reserves
+    // and pending reward. View, O(1)
+    fn get_position(e: Env, user: Address) -> UserPosition;
+
+    // Estimate swap fees earned by user liquidity in every token since the user first
+    // deposited or withdrew: growth of sqrt(k) per share, so price movement is not counted.
+    // Exact for the constant product curve only. View, O(1)
+    fn get_fee_earnings(e: Env, user: Address) -> Vec<u128>;
+
+    // Get lifetime swap statistics: volume traded and fee charged in every token,
+    // last trade timestamp. View, O(1)
+    fn get_stats(e: Env) -> Map<Symbol, i128>;
+
+    // Get dictionary of basic pool information: type, fee, reserve floor of the swaps,
+    // special parameters if any, guardrails as given by get_risk_params. View, O(1)
     fn get_info(e: Env) -> Map<Symbol, Val>;
 }
 
 pub trait UpgradeableContractTrait {
-    // Get contract version
+    // Get contract version. View, O(1)
     fn version() -> u32;
 
-    // Upgrade contract with new wasm code
+    // Upgrade contract with new wasm code. Emits the new wasm hash and the replaced version
     fn upgrade(e: Env, new_wasm_hash: BytesN<32>);
+
+    // Get version of the storage layout. Behind STORAGE_VERSION after upgrade until migrated.
+    // View, O(1)
+    fn storage_version(e: Env) -> u32;
+
+    // Migrate storage to the layout expected by the code. Every step is idempotent,
+    // so it's safe to call repeatedly. Deposits, swaps, withdrawals and claims
+    // are rejected until migrated. Returns resulting storage version
+    fn migrate(e: Env, admin: Address) -> u32;
 }
 
 pub trait RewardsTrait {
     // todo: move rewards configuration to gauge
+    // Rewards management functions accept either the admin or the rewards operator
 
     // Initialize rewards settings: token address and storage address
     // from which transfer will be made on claim
     fn initialize_rewards_config(e: Env, reward_token: Address, reward_storage: Address);
 
+    // Address reward tokens are transferred from on claim.
+    // Fails with NotInitialized before the pool and rewards are initialized. View, O(1)
+    fn get_reward_storage(e: Env) -> Address;
+
+    // Pay rewards from the new reward storage. Accrual is checkpointed before the change.
+    // Reward budget held by the pool itself is transferred to the new storage,
+    // external storage should approve the pool to spend its reward tokens
+    fn set_reward_storage(e: Env, admin: Address, new_storage: Address);
+
     // Configure rewards for pool. Every second tps of coins
     // being distributed across all liquidity providers
-    // after expired_at timestamp distribution ends
+    // after expired_at timestamp distribution ends.
+    // Whole campaign is funded upfront: spare balance of reward storage is used first,
+    // the rest is pulled from the caller. Zero tps is rejected
     fn set_rewards_config(e: Env, admin: Address, expired_at: u64, tps: u128);
 
+    // Configure rewards as sequential phases starting now: (phase_end, amount) pairs
+    // with amount distributed evenly over the phase. Phases switch on time even if nobody
+    // touches the pool for several of them. Replaces the current config,
+    // all phases are funded upfront the same way as set_rewards_config
+    fn set_rewards_schedule(e: Env, admin: Address, phases: Vec<(u64, u128)>);
+
+    // Add amount of reward tokens to the active rewards campaign.
+    // It's distributed over the rest of the campaign on top of the current tps.
+    // Funded the same way as set_rewards_config
+    fn top_up_rewards(e: Env, admin: Address, amount: u128);
+
+    // Convert swap fees set aside for rewards.
+    // If the reward token is one of the pool tokens, the other token is swapped through the pool
+    // for at least min_out reward tokens. Callable by anyone.
+    // Otherwise fees are transferred to the reward storage as is, in exchange for min_out reward
+    // tokens the reward storage adds to the budget, so it has to authorize the call
+    // and hold them on top of the current budget.
+    // Rewards are distributed over the rest of the active campaign on top of the current tps,
+    // part not divisible by the remaining duration is kept in the bucket.
+    // returns amount of reward tokens added to the campaign
+    fn convert_fees_to_rewards(e: Env, min_out: u128) -> u128;
+
+    // Move active rewards campaign end to expired_at.
+    // Rewards left undistributed are spread over the new window.
+    // Scheduled rewards can only be extended in the last phase
+    fn extend_rewards(e: Env, admin: Address, expired_at: u64);
+
+    // Stop rewards campaign now, scheduled phases included. Rewards accrued by now stay claimable.
+    // returns amount of reward tokens funded but not distributed, recoverable with
+    // withdraw_unused_rewards
+    fn stop_rewards(e: Env, admin: Address) -> u128;
+
+    // Send reward tokens funded but never distributed to the address once the campaign expired.
+    // Rewards accrued by users stay in the budget until claimed
+    fn withdraw_unused_rewards(e: Env, admin: Address, to: Address) -> u128;
+
+    // Configure referral bonus: bonus_bps of the depositor accrued rewards to both referrer and depositor,
+    // up to referrer_cap paid to the pair per referrer per rewards campaign
+    fn set_referral_bonus(e: Env, admin: Address, bonus_bps: u32, referrer_cap: u128);
+
+    // Get bonuses paid through the referrer, depositors' bonuses included:
+    // current period bonus, total bonus and referrals count
+    // View, O(1)
+    fn get_referral_stats(e: Env, referrer: Address) -> Map<Symbol, i128>;
+
     // Get rewards status for the pool,
-    // including amount available for the user
+    // including amount available for the user.
+    // Scheduled rewards report the current phase ("phase") and when it ends ("next_at"),
+    // next_at is zero in the last phase.
+    // View, O(phases)
     fn get_rewards_info(e: Env, user: Address) -> Map<Symbol, i128>;
 
+    // Same as get_rewards_info as the typed structure. View, O(phases)
+    fn get_rewards_info_v2(e: Env, user: Address) -> RewardsInfo;
+
+    // Get current rewards emission: tokens per second ("tps"), tokens per share per second
+    // scaled by 1e7 ("tps_share"), total shares ("shares") and seconds left ("left").
+    // Zeros once rewards expired or nobody provides liquidity.
+    // View, O(1)
+    fn get_reward_rate(e: Env) -> Map<Symbol, i128>;
+
+    // Get raw pool rewards data: current block, tokens accumulated, last update time
+    // and reward per share scaled by 1e18. View, O(1)
+    fn get_pool_reward_data(e: Env) -> PoolRewardData;
+
+    // Start or stop recording reward per share of every block for get_reward_checkpoints.
+    // Off by default: every pool update costs an extra persistent write while it's on.
+    // Only the latest 1000 blocks are kept
+    fn set_reward_checkpoints(e: Env, admin: Address, enabled: bool);
+
+    // Get reward per share recorded at every block, up to limit blocks from start_block.
+    // Limit is capped at 100. Blocks recorded while checkpoints were disabled
+    // or overwritten since are skipped. View, O(limit)
+    fn get_reward_checkpoints(e: Env, start_block: u64, limit: u32) -> Map<u64, u128>;
+
+    // Checkpoint pool rewards up to now. Callable by anyone, so a keeper can take the cost
+    // of catching up a quiet pool off the next user. returns updated pool rewards data
+    fn poke(e: Env) -> PoolRewardData;
+
     // Get amount of reward tokens available for the user to claim.
+    // View, O(1)
     fn get_user_reward(e: Env, user: Address) -> u128;
 
+    // Vest claimed rewards linearly over the duration instead of transferring them at once.
+    // Zero duration disables vesting. Vesting entries created earlier keep their duration.
+    // Vested rewards are always released to the user, whatever the claim recipient
+    fn set_vesting_duration(e: Env, admin: Address, duration: u64);
+
+    // Transfer unlocked part of the user vested rewards.
+    // returns amount of tokens released
+    fn release_vested(e: Env, user: Address) -> u128;
+
+    // Get total amount of reward tokens claimed by the user. View, O(1)
+    fn get_user_claimed(e: Env, user: Address) -> u128;
+
+    // Get total amount of reward tokens claimed by all the users.
+    // Referral bonuses are not included. View, O(1)
+    fn get_total_claimed(e: Env) -> u128;
+
     // Claim reward as a user.
     // returns amount of tokens rewarded to the user
     fn claim(e: Env, user: Address) -> u128;
+
+    // Claim reward as a user, sending reward tokens to the recipient.
+    // returns amount of tokens rewarded to the user
+    fn claim_to(e: Env, user: Address, recipient: Address) -> u128;
+
+    // Claim up to amount of the user reward, the rest stays available for the next claim.
+    // returns amount of tokens rewarded to the user
+    fn claim_amount(e: Env, user: Address, amount: u128) -> u128;
+
+    // Claim reward and deposit it back into the pool as a single token deposit.
+    // Available if the reward token is one of the pool tokens and rewards are not vested.
+    // returns amount of shares minted to the user
+    fn claim_and_reinvest(e: Env, user: Address, min_shares: u128) -> u128;
+
+    // Claim reward on behalf of the user as an admin or rewards operator.
+    // Reward tokens are sent to the user.
+    // returns amount of tokens rewarded to the user
+    fn claim_for(e: Env, operator: Address, user: Address) -> u128;
+
+    // Claim rewards on behalf of up to MAX_BATCH_CLAIM_SIZE users as an admin or rewards operator.
+    // returns amounts rewarded to every user, zero for users having nothing to claim
+    fn batch_claim(e: Env, operator: Address, users: Vec<Address>) -> Vec<u128>;
+
+    // Stop or resume rewards accrual for the user. While opted out, user shares are excluded
+    // from rewards distribution so other liquidity providers receive their part
+    fn set_reward_opt_out(e: Env, user: Address, opted_out: bool);
+
+    // Lock amount of user shares for duration seconds in exchange for rewards boost.
+    // Boost is proportional to the duration, up to 2x for the max lock duration.
+    // Locked shares can't be withdrawn until the lock expires, nor transferred until it's released
+    fn lock_shares(e: Env, user: Address, amount: u128, duration: u64);
+
+    // Get user shares lock if any
+    // View, O(1)
+    fn get_lock(e: Env, user: Address) -> Option<ShareLock>;
+
+    // Release expired lock removing the boost, liquidity locks included. Callable by anyone
+    fn unlock(e: Env, user: Address);
+}
+
+pub trait AdminInterfaceTrait {
+    // Stop pool instantly. Deposits and swaps are disabled, withdrawals are still available
+    fn kill_me(e: Env, admin: Address);
+
+    // Resume pool
+    fn unkill_me(e: Env, admin: Address);
+
+    // Check whether pool is killed. View, O(1)
+    fn get_is_killed(e: Env) -> bool;
+
+    // Pause or resume deposits and swaps. Withdrawals and claims are never paused
+    fn set_paused(e: Env, admin: Address, paused: bool);
+
+    // Check whether pool is paused. View, O(1)
+    fn is_paused(e: Env) -> bool;
+
+    // Enable or disable permissioned mode: only allowlisted users can deposit and swap.
+    // Withdrawals are never gated, so liquidity providers removed from the allowlist can exit
+    fn set_permissioned(e: Env, admin: Address, enabled: bool);
+
+    // Allow or forbid peer to peer transfers of the share token. Non transferable shares
+    // only move to or by the pool, so deposits, withdrawals and locks keep working.
+    // Shares are transferable by default
+    fn set_share_transferable(e: Env, admin: Address, transferable: bool);
+
+    // Allow user to deposit and swap in permissioned mode
+    fn add_to_allowlist(e: Env, admin: Address, user: Address);
+
+    // Revoke user permission to deposit and swap. User liquidity can still be withdrawn
+    fn remove_from_allowlist(e: Env, admin: Address, user: Address);
+
+    // Check whether user can deposit and swap. Always true unless pool is permissioned. View, O(1)
+    fn is_allowed(e: Env, user: Address) -> bool;
+
+    // Reject swaps moving price by more than max_price_impact. 0.01% = 1; 0 disables the guard
+    fn set_max_price_impact(e: Env, admin: Address, max_price_impact: u32);
+
+    // Get max price impact allowed for the swap. View, O(1)
+    fn get_max_price_impact(e: Env) -> u32;
+
+    // Reject swaps leaving less than min_reserve of the bought token in the pool, so the pool
+    // can't be drained to dust. Withdrawals aren't limited. 1000 by default, 0 disables the guard
+    fn set_min_reserve(e: Env, admin: Address, min_reserve: u128);
+
+    // Get reserve floor swaps can't go below. View, O(1)
+    fn get_min_reserve(e: Env) -> u128;
+
+    // Direct part of the swap fee to rewards instead of liquidity providers.
+    // 100% = 10000; 0 disables the reward fee
+    fn set_reward_fee(e: Env, admin: Address, reward_fee: u32);
+
+    // Get part of the swap fee directed to rewards. View, O(1)
+    fn get_reward_fee(e: Env) -> u32;
+
+    // Get swap fees set aside for rewards and not converted yet. View, O(1)
+    fn get_reward_fee_bucket(e: Env) -> Vec<u128>;
+
+    // Reject the first deposit setting the price of token a in token b out of the range.
+    // Prices are scaled to 7 decimals. Only available while the pool is empty,
+    // the range has no effect once the pool is seeded
+    fn set_initial_price_range(e: Env, admin: Address, min_price: u128, max_price: u128);
+
+    // Get bounds of the first deposit price if set. View, O(1)
+    fn get_initial_price_range(e: Env) -> Option<(u128, u128)>;
+
+    // Exempt user (e.g. router or market maker contract) from the swap fee or revoke the exemption
+    fn set_fee_exempt(e: Env, admin: Address, user: Address, exempt: bool);
+
+    // Check whether user swaps without the fee. View, O(1)
+    fn is_fee_exempt(e: Env, user: Address) -> bool;
+
+    // Enable or disable emergency withdrawals
+    fn set_emergency_mode(e: Env, admin: Address, value: bool);
+
+    // Check whether emergency withdrawals are enabled. View, O(1)
+    fn get_emergency_mode(e: Env) -> bool;
+
+    // Get all the pool guardrails with their current values, zero if disabled.
+    // Bootstrapper is 1 while set, initial price range is given by init_price_min and init_price_max.
+    // New guardrails are only appended to the list. View, O(1)
+    fn get_risk_params(e: Env) -> Map<Symbol, i128>;
+
+    // Delegate rewards management to the operator. Operator is able to configure rewards
+    // and claim on behalf of users, while the rest of the pool is managed by admin only
+    fn set_rewards_operator(e: Env, admin: Address, operator: Address);
+
+    // Get rewards operator if set. View, O(1)
+    fn get_rewards_operator(e: Env) -> Option<Address>;
+
+    // Get number of swaps soft rejected by every guard. Counters are kept in temporary storage.
+    // View, O(1)
+    fn get_rejection_stats(e: Env) -> Map<Symbol, u64>;
+
+    // Index share holders from before the holder index was introduced. Resumable:
+    // already indexed holders are skipped. Returns number of holders indexed.
+    // Backfill completes once indexed holders cover the whole shares supply
+    fn backfill_holders(e: Env, admin: Address, holders: Vec<Address>) -> u32;
+
+    // Whether all the share holders are indexed. View, O(1)
+    fn backfill_complete(e: Env) -> bool;
+
+    // Get number of share holders. Available once backfill is complete. View, O(1)
+    fn get_holders_count(e: Env) -> u32;
+
+    // Transfer tokens sent to the pool by mistake.
+    // Pool tokens, share token and reward token cannot be rescued
+    fn rescue_token(e: Env, admin: Address, token: Address, to: Address, amount: u128);
+
+    // Get current admin. View, O(1)
+    fn get_admin(e: Env) -> Address;
+
+    // Propose new admin. Current admin stays in charge until proposal is accepted.
+    // Pending proposal is overwritten if any
+    fn propose_admin(e: Env, admin: Address, new_admin: Address);
+
+    // Accept admin proposal as the proposed admin
+    fn accept_admin(e: Env, new_admin: Address);
+
+    // Cancel pending admin proposal
+    fn cancel_admin_proposal(e: Env, admin: Address);
+
+    // Let only the bootstrapper (e.g. the deployer seeding the pool at the market price) make the first deposit,
+    // so nobody else can seed the pool at an off-market ratio. Opt-in: with no bootstrapper set, which is
+    // the default, anyone can make the first deposit. Only available while the pool is empty
+    fn set_bootstrapper(e: Env, admin: Address, bootstrapper: Address);
+
+    // Clear the bootstrapper, opening the first deposit to anyone again
+    fn remove_bootstrapper(e: Env, admin: Address);
+
+    // Get the only address allowed to make the first deposit if set. View, O(1)
+    fn get_bootstrapper(e: Env) -> Option<Address>;
+}
+
+pub trait LiquidityMigrationTrait {
+    // Move whole pool liquidity into the successor pool over the same tokens. Pool should be killed first.
+    // Successor should be listed in the pools registry of the migration router
+    // min_shares_out_per_share: minimum amount of successor shares per one pool share, scaled up by 1e7
+    // Returns amount of successor pool shares received
+    fn migrate_liquidity(
+        e: Env,
+        admin: Address,
+        new_pool: Address,
+        min_shares_out_per_share: u128,
+    ) -> u128;
+
+    // Set router whose pools registry should list the successor pool of the liquidity migration
+    fn set_migration_router(e: Env, admin: Address, router: Address);
+
+    // Get router whose pools registry should list the successor pool. View, O(1)
+    fn get_migration_router(e: Env) -> Option<Address>;
+
+    // Exchange all the user pool shares for successor pool shares after liquidity migration.
+    // Returns amount of successor pool shares transferred to the user
+    fn claim_migrated_shares(e: Env, user: Address) -> u128;
+
+    // Move user liquidity into another pool over the same tokens in one call:
+    // withdraw share_amount from this pool and deposit the tokens into new_pool on behalf of user.
+    // Tokens not accepted by new_pool stay with the user. Fails with MigrationSharesBelowMin
+    // if new_pool mints less than min_shares.
+    // Returns amount of new_pool shares minted to the user
+    fn migrate_position(
+        e: Env,
+        user: Address,
+        new_pool: Address,
+        share_amount: u128,
+        min_a: u128,
+        min_b: u128,
+        min_shares: u128,
+    ) -> u128;
 }
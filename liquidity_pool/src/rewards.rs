@@ -1,12 +1,264 @@
+use crate::constants::{FEE_MULTIPLIER, MAX_LOCK_DURATION};
+use crate::events::{Events, LiquidityPoolEvents};
+use crate::storage::{
+    get_lock_boost, get_opted_out_shares, get_referral_bonus, get_referrer_stats,
+    get_reward_claimed, get_reward_funded, get_reward_opt_out, get_reward_vesting, get_share_lock,
+    get_vesting_duration, put_lock_boost, put_opted_out_shares, put_referrer_stats,
+    put_reward_claimed, put_reward_funded, put_reward_opt_out, put_reward_vesting, put_share_lock,
+    remove_reward_vesting, RewardVesting, ShareLock,
+};
+use rewards::storage::RewardsStorageTrait;
 use rewards::Rewards;
-use soroban_sdk::Env;
+use soroban_sdk::token::TokenClient as SorobanTokenClient;
+use soroban_sdk::{Address, Env};
+use token_share::{get_total_shares, get_user_balance_shares, lock_user_shares};
 
-#[cfg(not(test))]
-pub(crate) const PAGE_SIZE: u64 = 1000;
+pub(crate) fn get_rewards_manager(e: &Env) -> Rewards {
+    Rewards::new(e)
+}
 
-#[cfg(test)]
-pub(crate) const PAGE_SIZE: u64 = 5;
+// Pool shares participating in rewards distribution.
+// Opted out users are excluded, locked shares are counted with the boost
+pub(crate) fn get_total_reward_shares(e: &Env) -> u128 {
+    (get_total_shares(e) + get_lock_boost(e)).saturating_sub(get_opted_out_shares(e))
+}
 
-pub(crate) fn get_rewards_manager(e: &Env) -> Rewards {
-    Rewards::new(e, PAGE_SIZE)
+// User shares participating in rewards distribution
+pub(crate) fn get_user_reward_shares(e: &Env, user: &Address) -> u128 {
+    match get_reward_opt_out(e, user) {
+        Some(_) => 0,
+        None => get_user_boosted_shares(e, user),
+    }
+}
+
+// User shares with the lock boost applied. Boost only counts for the locked shares still held
+pub(crate) fn get_user_boosted_shares(e: &Env, user: &Address) -> u128 {
+    let balance = get_user_balance_shares(e, user);
+    let boost = match get_share_lock(e, user) {
+        Some(lock) => lock.boost * balance.min(lock.amount) / lock.amount,
+        None => 0,
+    };
+    balance + boost
+}
+
+// Rewards boost for locking shares: proportional to the duration, up to 2x
+pub(crate) fn get_lock_boost_amount(amount: u128, duration: u64) -> u128 {
+    amount * duration.min(MAX_LOCK_DURATION) as u128 / MAX_LOCK_DURATION as u128
+}
+
+// Refresh lock and opted out shares after user balance changed.
+// Lock is shrunk if shares left the pool bypassing the lock (emergency withdraw, migration)
+pub(crate) fn sync_reward_shares(e: &Env, user: &Address) {
+    if let Some(lock) = get_share_lock(e, user) {
+        let balance = get_user_balance_shares(e, user);
+        if balance < lock.amount {
+            let boost = lock.boost * balance / lock.amount;
+            put_lock_boost(e, get_lock_boost(e) - lock.boost + boost);
+            put_share_lock(
+                e,
+                user,
+                &ShareLock {
+                    amount: balance,
+                    unlock_at: lock.unlock_at,
+                    boost,
+                    is_liquidity_lock: lock.is_liquidity_lock,
+                },
+            );
+            lock_user_shares(e, user, balance);
+        }
+    }
+
+    if let Some(shares) = get_reward_opt_out(e, user) {
+        let boosted = get_user_boosted_shares(e, user);
+        put_opted_out_shares(e, get_opted_out_shares(e) - shares + boosted);
+        put_reward_opt_out(e, user, boosted);
+    }
+}
+
+// Whether rewards were ever configured. Initial config has zero expiration
+pub(crate) fn is_rewards_configured(e: &Env) -> bool {
+    get_rewards_manager(e)
+        .storage()
+        .get_pool_reward_config()
+        .expired_at
+        > 0
+}
+
+// Reward tokens secured for rewards distribution and not claimed yet
+pub(crate) fn get_reward_budget(e: &Env) -> u128 {
+    get_reward_funded(e) - get_reward_claimed(e)
+}
+
+// Whether reward tokens are kept by the pool itself in one of the pool tokens
+fn is_reward_pool_token(e: &Env, token: &Address) -> bool {
+    let rewards = get_rewards_manager(e);
+    rewards.storage().has_reward_token()
+        && rewards.storage().get_reward_token() == *token
+        && rewards.storage().get_reward_storage() == e.current_contract_address()
+}
+
+// Part of the pool token balance secured for rewards. It's never counted as liquidity
+pub(crate) fn get_pool_token_reward_balance(e: &Env, token: &Address) -> u128 {
+    match is_reward_pool_token(e, token) {
+        true => get_reward_budget(e),
+        false => 0,
+    }
+}
+
+// Reward tokens of the reward storage not secured by the budget.
+// If the reward token is one of the pool tokens, the rest of the balance is liquidity
+pub(crate) fn get_spare_reward_balance(e: &Env) -> u128 {
+    let rewards = get_rewards_manager(e);
+    let reward_token = rewards.storage().get_reward_token();
+    if is_reward_pool_token(e, &reward_token) {
+        return 0;
+    }
+    let balance = SorobanTokenClient::new(e, &reward_token)
+        .balance(&rewards.storage().get_reward_storage()) as u128;
+    balance.saturating_sub(get_reward_budget(e))
+}
+
+// Secure reward tokens covering everything accrued and promised till the end of rewards campaign,
+// scheduled phases included.
+// Spare balance of the reward storage is used first, the rest is pulled from the admin.
+// Rewards data should be up to date
+pub(crate) fn fund_rewards(e: &Env, admin: &Address) {
+    let rewards = get_rewards_manager(e);
+    let data = rewards.storage().get_pool_reward_data();
+    let promised = rewards.manager().get_promised_tokens();
+
+    let funded = get_reward_funded(e);
+    let required = data.accumulated + promised;
+    if required <= funded {
+        return;
+    }
+    let shortfall = required - funded;
+
+    let reward_token = SorobanTokenClient::new(e, &rewards.storage().get_reward_token());
+    let reward_storage = rewards.storage().get_reward_storage();
+    let spare = get_spare_reward_balance(e);
+    if shortfall > spare {
+        reward_token.transfer_from(
+            &e.current_contract_address(),
+            admin,
+            &reward_storage,
+            &((shortfall - spare) as i128),
+        );
+    }
+    put_reward_funded(e, required);
+}
+
+// Send reward tokens from the reward storage
+pub(crate) fn transfer_reward(e: &Env, to: &Address, amount: u128) {
+    let rewards = get_rewards_manager(e);
+    let reward_token = SorobanTokenClient::new(e, &rewards.storage().get_reward_token());
+    let reward_storage = rewards.storage().get_reward_storage();
+    if reward_storage == e.current_contract_address() {
+        reward_token.transfer(&reward_storage, to, &(amount as i128));
+    } else {
+        reward_token.transfer_from(
+            &e.current_contract_address(),
+            &reward_storage,
+            to,
+            &(amount as i128),
+        );
+    }
+}
+
+// Pay referrer and depositor bonuses for the deposit while rewards campaign is active.
+// Both bonuses are capped together per referrer per period and by spare balance of the reward storage,
+// they're funded and claimed at once, so the budget of users rewards is left intact.
+// Returns referrer bonus and depositor bonus
+pub(crate) fn pay_referral_bonus(
+    e: &Env,
+    referrer: &Address,
+    user: &Address,
+    accrued: u128,
+) -> (u128, u128) {
+    let rewards = get_rewards_manager(e);
+    let config = rewards.storage().get_pool_reward_config();
+    let bonus_config = get_referral_bonus(e);
+    if config.expired_at <= e.ledger().timestamp() || bonus_config.bonus_bps == 0 {
+        return (0, 0);
+    }
+
+    let mut stats = get_referrer_stats(e, referrer);
+    if stats.period != config.expired_at {
+        stats.period = config.expired_at;
+        stats.period_bonus = 0;
+    }
+
+    // each side gets the bonus, the pair is what counts against the cap
+    let spare = get_spare_reward_balance(e);
+    let bonus = (accrued * bonus_config.bonus_bps as u128 / FEE_MULTIPLIER * 2)
+        .min(bonus_config.referrer_cap.saturating_sub(stats.period_bonus))
+        .min(spare);
+    let user_bonus = bonus / 2;
+    let referrer_bonus = bonus - user_bonus;
+
+    stats.period_bonus += bonus;
+    stats.total_bonus += bonus;
+    stats.referrals += 1;
+    put_referrer_stats(e, referrer, &stats);
+
+    if bonus > 0 {
+        put_reward_funded(e, get_reward_funded(e) + bonus);
+        put_reward_claimed(e, get_reward_claimed(e) + bonus);
+        transfer_reward(e, referrer, referrer_bonus);
+    }
+    if user_bonus > 0 {
+        transfer_reward(e, user, user_bonus);
+    }
+    (referrer_bonus, user_bonus)
+}
+
+// Part of the vesting unlocked by now
+pub(crate) fn get_vested_amount(e: &Env, vesting: &RewardVesting) -> u128 {
+    let elapsed = e.ledger().timestamp() - vesting.start;
+    if elapsed >= vesting.duration {
+        return vesting.total;
+    }
+    vesting.total * elapsed as u128 / vesting.duration as u128
+}
+
+// Transfer unlocked and not yet released part of the user vesting
+pub(crate) fn release_vested(e: &Env, user: &Address) -> u128 {
+    let mut vesting = match get_reward_vesting(e, user) {
+        Some(vesting) => vesting,
+        None => return 0,
+    };
+    let vested = get_vested_amount(e, &vesting);
+    let amount = vested - vesting.released;
+    vesting.released = vested;
+    match vesting.released == vesting.total {
+        true => remove_reward_vesting(e, user),
+        false => put_reward_vesting(e, user, &vesting),
+    }
+
+    if amount > 0 {
+        put_reward_claimed(e, get_reward_claimed(e) + amount);
+        transfer_reward(e, user, amount);
+        Events::new(e).release_vested(user.clone(), amount);
+    }
+    amount
+}
+
+// Move claimed reward into the user vesting. Already unlocked part of the existing vesting
+// is released, the locked rest starts vesting again together with the new amount
+pub(crate) fn vest_reward(e: &Env, user: &Address, amount: u128) {
+    release_vested(e, user);
+    let locked = match get_reward_vesting(e, user) {
+        Some(vesting) => vesting.total - vesting.released,
+        None => 0,
+    };
+    put_reward_vesting(
+        e,
+        user,
+        &RewardVesting {
+            start: e.ledger().timestamp(),
+            duration: get_vesting_duration(e),
+            total: locked + amount,
+            released: 0,
+        },
+    );
 }
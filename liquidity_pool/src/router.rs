@@ -0,0 +1,8 @@
+use soroban_sdk::{contractclient, Address, BytesN, Env, Map, Vec};
+
+// Pools registry of the router deploying the pools. Only the lookup used by the pool is declared,
+//  router wasm can't be imported since the router embeds the pool wasm
+#[contractclient(name = "PoolRouterClient")]
+pub trait PoolRouter {
+    fn get_pools(e: Env, tokens: Vec<Address>) -> Map<BytesN<32>, Address>;
+}
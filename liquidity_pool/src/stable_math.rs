@@ -0,0 +1,66 @@
+use crate::constants::FEE_MULTIPLIER;
+use crate::math::mul_div_ceil;
+
+// StableSwap invariant for two coins:
+//  A * n^n * (x + y) + D = A * D * n^n + D^(n + 1) / (n^n * x * y)
+//  amplification is given as A * n^(n - 1), same as in the stableswap pool.
+//  Amounts are expected normalized to the same decimals
+const N_COINS: u128 = 2;
+
+// Invariant D of the balances, found with Newton iteration
+pub fn get_d(x: u128, y: u128, amp: u128) -> u128 {
+    // one sided pool holds no liquidity
+    if x == 0 || y == 0 {
+        return 0;
+    }
+
+    let s = x + y;
+    let ann = amp * N_COINS;
+    let mut d = s;
+    for _ in 0..255 {
+        let d_p = d * d / (x * N_COINS) * d / (y * N_COINS);
+        let d_prev = d;
+        d = (ann * s + d_p * N_COINS) * d / ((ann - 1) * d + (N_COINS + 1) * d_p);
+        // Equality with the precision of 1
+        if d.abs_diff(d_prev) <= 1 {
+            break;
+        }
+    }
+    d
+}
+
+// Balance of the other coin keeping invariant D, found with Newton iteration
+pub fn get_y(x: u128, d: u128, amp: u128) -> u128 {
+    let ann = amp * N_COINS;
+    let c = d * d / (x * N_COINS) * d / (ann * N_COINS);
+    let b = x + d / ann;
+    let mut y = d;
+    for _ in 0..255 {
+        let y_prev = y;
+        y = (y * y + c) / (2 * y + b - d);
+        // Equality with the precision of 1
+        if y.abs_diff(y_prev) <= 1 {
+            break;
+        }
+    }
+    y
+}
+
+// Calculate how much can be bought with in_amount keeping the invariant. Rounded down
+pub fn get_swap_out(
+    fee_fraction: u32,
+    amp: u128,
+    reserve_sell: u128,
+    reserve_buy: u128,
+    in_amount: u128,
+) -> u128 {
+    let d = get_d(reserve_sell, reserve_buy, amp);
+    if d == 0 {
+        return 0;
+    }
+
+    let y = get_y(reserve_sell + in_amount, d, amp);
+    // extra unit covers the iteration precision
+    let out = reserve_buy.saturating_sub(y + 1);
+    out - mul_div_ceil(out, fee_fraction as u128, FEE_MULTIPLIER)
+}
@@ -1,15 +1,199 @@
-use soroban_sdk::{contracttype, Address, Env};
+use crate::constants::{DEFAULT_MIN_RESERVE, NORMALIZED_DECIMALS};
+use soroban_sdk::{contracttype, Address, Env, Symbol, Vec};
 pub use utils::bump::bump_instance;
+use utils::bump::{bump_persistent, bump_temporary};
 
 #[derive(Clone)]
 #[contracttype]
 enum DataKey {
     TokenA,
     TokenB,
+    DecimalsA,
+    DecimalsB,
+    Curve,
     ReserveA,
     ReserveB,
     FeeFraction, // 1 = 0.01%
     Plane,
+    IsKilled,
+    IsPaused,
+    IsPermissioned,
+    Allowlisted(Address),
+    FeeExempt(Address),
+    MaxPriceImpact,
+    SwapStats,
+    IsEmergencyMode,
+    MigrationPool,
+    MigrationRouter,
+    MigratedShares,
+    MigrationShares,
+    OptedOutShares,
+    RewardFunded,
+    RewardClaimed,
+    RewardOptOut(Address),
+    Bootstrapper,
+    LockBoost,
+    ShareLock(Address),
+    ReferralBonus,
+    ReferralStats(Address),
+    HoldersCount,
+    IndexedShares,
+    BackfillComplete,
+    StorageVersion,
+    HolderShares(Address),
+    Rejections(Symbol),
+    VestingDuration,
+    RewardVesting(Address),
+    FeeEarnings(Address),
+    RewardFee,
+    RewardFeeBucket,
+    InitialPriceRange,
+    ReservesUpdatedAt,
+    MinReserve,
+}
+
+// Curve pricing the pool tokens. StableSwap is for like-kind assets and keeps the amplification A,
+//  Weighted keeps tokens weights summing up to WEIGHT_DENOMINATOR
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[contracttype]
+pub enum Curve {
+    ConstantProduct,
+    StableSwap(u128),
+    Weighted(u32, u32),
+}
+
+// Outcome of the swap with soft rejection: either amount received or the rejecting guard
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub enum SwapOutcome {
+    Swapped(u128),
+    Rejected(Symbol),
+}
+
+// Rewards status of the pool and the user
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct RewardsInfo {
+    pub tps: u128,
+    pub expired_at: u64,
+    // tokens distributed by the pool
+    pub accumulated: u128,
+    pub last_time: u64,
+    pub pool_block: u64,
+    // tokens distributed by the pool as of the user last update
+    pub pool_accumulated: u128,
+    pub user_block: u64,
+    // user shares accounted in rewards distribution, lock boost included
+    pub user_shares: u128,
+    pub to_claim: u128,
+    pub claimed: u128,
+    pub vesting: u128,
+    pub unlocked: u128,
+    pub budget: u128,
+    pub opted_out: bool,
+    // current phase of the rewards schedule, zero without schedule
+    pub phase: u32,
+    // end of the current phase if more phases follow, zero otherwise
+    pub next_phase_at: u64,
+}
+
+// Contracts the pool works with: pool tokens, share token and reward token
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct PoolContracts {
+    pub token_a: Address,
+    pub token_b: Address,
+    pub share_token: Address,
+    pub reward_token: Address,
+}
+
+// Pool state needed to quote a swap, read at once by routers
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct QuoteContext {
+    pub token_a: Address,
+    pub token_b: Address,
+    pub reserve_a: u128,
+    pub reserve_b: u128,
+    // 0.01% = 1
+    pub fee_bps: u32,
+    pub paused: bool,
+}
+
+// User liquidity valued at the current reserves
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct UserPosition {
+    pub shares: u128,
+    // share of the pool owned by user. 0.01% = 1
+    pub ownership_bps: u32,
+    pub amount_a: u128,
+    pub amount_b: u128,
+    pub pending_reward: u128,
+}
+
+// User shares locked in exchange for rewards boost.
+//  Liquidity lock (deposit_locked, lock_liquidity) gives no boost and can only be extended
+#[derive(Clone)]
+#[contracttype]
+pub struct ShareLock {
+    pub amount: u128,
+    pub unlock_at: u64,
+    // extra shares accounted in rewards distribution
+    pub boost: u128,
+    pub is_liquidity_lock: bool,
+}
+
+// Swap fees earned by user liquidity, accrued at every user deposit and withdrawal.
+//  root_k_per_share is the pool growth checkpoint the pending earnings are counted from
+#[derive(Clone)]
+#[contracttype]
+pub struct FeeEarnings {
+    pub root_k_per_share: u128,
+    pub earned_a: u128,
+    pub earned_b: u128,
+}
+
+// Bonus paid to referrers and depositors: share of the depositor accrued rewards to each,
+// capped per referrer per period
+#[derive(Clone)]
+#[contracttype]
+pub struct ReferralBonus {
+    pub bonus_bps: u32,
+    pub referrer_cap: u128,
+}
+
+// Bonuses paid through the referrer. Period is identified by the rewards campaign expiration
+#[derive(Clone)]
+#[contracttype]
+pub struct ReferralStats {
+    pub period: u64,
+    pub period_bonus: u128,
+    pub total_bonus: u128,
+    pub referrals: u32,
+}
+
+// Lifetime swap counters. Saturate instead of overflowing
+#[derive(Clone)]
+#[contracttype]
+pub struct SwapStats {
+    // amounts of token a and token b traded, both bought and sold
+    pub volume_a: u128,
+    pub volume_b: u128,
+    // swap fee charged from traders in every token, referral cut included
+    pub fee_a: u128,
+    pub fee_b: u128,
+    pub last_trade_at: u64,
+}
+
+// Claimed rewards unlocking linearly from start over the duration
+#[derive(Clone)]
+#[contracttype]
+pub struct RewardVesting {
+    pub start: u64,
+    pub duration: u64,
+    pub total: u128,
+    pub released: u128,
 }
 
 pub fn get_token_a(e: &Env) -> Address {
@@ -28,6 +212,46 @@ pub fn get_token_b(e: &Env) -> Address {
         .expect("Trying to get Token B")
 }
 
+// pools initialized before decimals were cached only had 7 decimals tokens
+pub fn get_decimals_a(e: &Env) -> u32 {
+    bump_instance(e);
+    e.storage()
+        .instance()
+        .get(&DataKey::DecimalsA)
+        .unwrap_or(NORMALIZED_DECIMALS)
+}
+
+pub fn get_decimals_b(e: &Env) -> u32 {
+    bump_instance(e);
+    e.storage()
+        .instance()
+        .get(&DataKey::DecimalsB)
+        .unwrap_or(NORMALIZED_DECIMALS)
+}
+
+pub fn put_decimals_a(e: &Env, value: u32) {
+    bump_instance(e);
+    e.storage().instance().set(&DataKey::DecimalsA, &value)
+}
+
+pub fn put_decimals_b(e: &Env, value: u32) {
+    bump_instance(e);
+    e.storage().instance().set(&DataKey::DecimalsB, &value)
+}
+
+pub fn get_curve(e: &Env) -> Curve {
+    bump_instance(e);
+    e.storage()
+        .instance()
+        .get(&DataKey::Curve)
+        .unwrap_or(Curve::ConstantProduct)
+}
+
+pub fn put_curve(e: &Env, value: Curve) {
+    bump_instance(e);
+    e.storage().instance().set(&DataKey::Curve, &value)
+}
+
 pub fn get_reserve_a(e: &Env) -> u128 {
     bump_instance(e);
     e.storage()
@@ -56,12 +280,84 @@ pub fn put_token_b(e: &Env, contract: Address) {
 
 pub fn put_reserve_a(e: &Env, amount: u128) {
     bump_instance(e);
-    e.storage().instance().set(&DataKey::ReserveA, &amount)
+    e.storage().instance().set(&DataKey::ReserveA, &amount);
+    put_reserves_updated_at(e);
 }
 
 pub fn put_reserve_b(e: &Env, amount: u128) {
     bump_instance(e);
-    e.storage().instance().set(&DataKey::ReserveB, &amount)
+    e.storage().instance().set(&DataKey::ReserveB, &amount);
+    put_reserves_updated_at(e);
+}
+
+// Ledger timestamp of the last reserves write, zero if never written
+pub fn get_reserves_updated_at(e: &Env) -> u64 {
+    bump_instance(e);
+    e.storage()
+        .instance()
+        .get(&DataKey::ReservesUpdatedAt)
+        .unwrap_or(0)
+}
+
+fn put_reserves_updated_at(e: &Env) {
+    e.storage()
+        .instance()
+        .set(&DataKey::ReservesUpdatedAt, &e.ledger().timestamp())
+}
+
+// Pool configuration and reserves read once per invocation and written back once.
+//  Only reserves are mutable, the rest is fixed during the call
+#[derive(Clone)]
+pub struct PoolState {
+    pub token_a: Address,
+    pub token_b: Address,
+    pub curve: Curve,
+    pub fee_fraction: u32,
+    pub reserve_a: u128,
+    pub reserve_b: u128,
+}
+
+impl PoolState {
+    pub fn load(e: &Env) -> PoolState {
+        bump_instance(e);
+        let storage = e.storage().instance();
+        PoolState {
+            token_a: storage
+                .get(&DataKey::TokenA)
+                .expect("Trying to get Token A"),
+            token_b: storage
+                .get(&DataKey::TokenB)
+                .expect("Trying to get Token B"),
+            curve: storage
+                .get(&DataKey::Curve)
+                .unwrap_or(Curve::ConstantProduct),
+            fee_fraction: storage
+                .get(&DataKey::FeeFraction)
+                .expect("Please initialize fee fraction"),
+            reserve_a: storage
+                .get(&DataKey::ReserveA)
+                .expect("Trying to get Reserve A"),
+            reserve_b: storage
+                .get(&DataKey::ReserveB)
+                .expect("Trying to get Reserve B"),
+        }
+    }
+
+    pub fn save(&self, e: &Env) {
+        bump_instance(e);
+        let storage = e.storage().instance();
+        storage.set(&DataKey::ReserveA, &self.reserve_a);
+        storage.set(&DataKey::ReserveB, &self.reserve_b);
+        put_reserves_updated_at(e);
+    }
+
+    pub fn tokens(&self, e: &Env) -> Vec<Address> {
+        Vec::from_array(e, [self.token_a.clone(), self.token_b.clone()])
+    }
+
+    pub fn reserves(&self, e: &Env) -> Vec<u128> {
+        Vec::from_array(e, [self.reserve_a, self.reserve_b])
+    }
 }
 
 pub fn get_fee_fraction(e: &Env) -> u32 {
@@ -95,3 +391,547 @@ pub(crate) fn has_plane(e: &Env) -> bool {
     let key = DataKey::Plane;
     e.storage().instance().has(&key)
 }
+
+pub fn get_is_killed(e: &Env) -> bool {
+    bump_instance(e);
+    e.storage()
+        .instance()
+        .get(&DataKey::IsKilled)
+        .unwrap_or(false)
+}
+
+pub fn put_is_killed(e: &Env, value: bool) {
+    bump_instance(e);
+    e.storage().instance().set(&DataKey::IsKilled, &value)
+}
+
+pub fn get_is_paused(e: &Env) -> bool {
+    bump_instance(e);
+    e.storage()
+        .instance()
+        .get(&DataKey::IsPaused)
+        .unwrap_or(false)
+}
+
+pub fn put_is_paused(e: &Env, value: bool) {
+    bump_instance(e);
+    e.storage().instance().set(&DataKey::IsPaused, &value)
+}
+
+pub fn get_is_permissioned(e: &Env) -> bool {
+    bump_instance(e);
+    e.storage()
+        .instance()
+        .get(&DataKey::IsPermissioned)
+        .unwrap_or(false)
+}
+
+pub fn put_is_permissioned(e: &Env, value: bool) {
+    bump_instance(e);
+    e.storage().instance().set(&DataKey::IsPermissioned, &value)
+}
+
+pub fn get_is_allowlisted(e: &Env, user: &Address) -> bool {
+    let key = DataKey::Allowlisted(user.clone());
+    let value = e.storage().persistent().has(&key);
+    if value {
+        bump_persistent(e, &key);
+    }
+    value
+}
+
+pub fn put_allowlisted(e: &Env, user: &Address) {
+    let key = DataKey::Allowlisted(user.clone());
+    e.storage().persistent().set(&key, &true);
+    bump_persistent(e, &key);
+}
+
+pub fn remove_allowlisted(e: &Env, user: &Address) {
+    e.storage()
+        .persistent()
+        .remove(&DataKey::Allowlisted(user.clone()))
+}
+
+pub fn get_max_price_impact(e: &Env) -> u32 {
+    bump_instance(e);
+    e.storage()
+        .instance()
+        .get(&DataKey::MaxPriceImpact)
+        .unwrap_or(0)
+}
+
+pub fn put_max_price_impact(e: &Env, value: u32) {
+    bump_instance(e);
+    e.storage().instance().set(&DataKey::MaxPriceImpact, &value)
+}
+
+// Reserve floor of the bought token for swaps. Pools get DEFAULT_MIN_RESERVE unless admin set it
+pub fn get_min_reserve(e: &Env) -> u128 {
+    bump_instance(e);
+    e.storage()
+        .instance()
+        .get(&DataKey::MinReserve)
+        .unwrap_or(DEFAULT_MIN_RESERVE)
+}
+
+pub fn put_min_reserve(e: &Env, value: u128) {
+    bump_instance(e);
+    e.storage().instance().set(&DataKey::MinReserve, &value)
+}
+
+// Bounds of the price set by the first deposit, scaled by PRICE_PRECISION
+pub fn get_initial_price_range(e: &Env) -> Option<(u128, u128)> {
+    bump_instance(e);
+    e.storage().instance().get(&DataKey::InitialPriceRange)
+}
+
+pub fn put_initial_price_range(e: &Env, value: (u128, u128)) {
+    bump_instance(e);
+    e.storage()
+        .instance()
+        .set(&DataKey::InitialPriceRange, &value)
+}
+
+// Part of the swap fee directed to rewards. 100% = FEE_MULTIPLIER
+pub fn get_reward_fee(e: &Env) -> u32 {
+    bump_instance(e);
+    e.storage().instance().get(&DataKey::RewardFee).unwrap_or(0)
+}
+
+pub fn put_reward_fee(e: &Env, value: u32) {
+    bump_instance(e);
+    e.storage().instance().set(&DataKey::RewardFee, &value)
+}
+
+// Swap fees set aside for rewards in both tokens. Held by the pool, but not part of reserves
+pub fn get_reward_fee_bucket(e: &Env) -> (u128, u128) {
+    bump_instance(e);
+    e.storage()
+        .instance()
+        .get(&DataKey::RewardFeeBucket)
+        .unwrap_or((0, 0))
+}
+
+pub fn put_reward_fee_bucket(e: &Env, value: (u128, u128)) {
+    bump_instance(e);
+    e.storage()
+        .instance()
+        .set(&DataKey::RewardFeeBucket, &value)
+}
+
+pub fn get_is_fee_exempt(e: &Env, user: &Address) -> bool {
+    let key = DataKey::FeeExempt(user.clone());
+    let value = e.storage().persistent().has(&key);
+    if value {
+        bump_persistent(e, &key);
+    }
+    value
+}
+
+pub fn put_fee_exempt(e: &Env, user: &Address) {
+    let key = DataKey::FeeExempt(user.clone());
+    e.storage().persistent().set(&key, &true);
+    bump_persistent(e, &key);
+}
+
+pub fn remove_fee_exempt(e: &Env, user: &Address) {
+    e.storage()
+        .persistent()
+        .remove(&DataKey::FeeExempt(user.clone()))
+}
+
+pub fn get_is_emergency_mode(e: &Env) -> bool {
+    bump_instance(e);
+    e.storage()
+        .instance()
+        .get(&DataKey::IsEmergencyMode)
+        .unwrap_or(false)
+}
+
+pub fn put_is_emergency_mode(e: &Env, value: bool) {
+    bump_instance(e);
+    e.storage()
+        .instance()
+        .set(&DataKey::IsEmergencyMode, &value)
+}
+
+// successor pool which received the liquidity during migration
+pub fn get_migration_pool(e: &Env) -> Address {
+    bump_instance(e);
+    e.storage()
+        .instance()
+        .get(&DataKey::MigrationPool)
+        .expect("Trying to get migration pool")
+}
+
+pub fn put_migration_pool(e: &Env, pool: &Address) {
+    bump_instance(e);
+    e.storage().instance().set(&DataKey::MigrationPool, pool)
+}
+
+pub fn has_migration_pool(e: &Env) -> bool {
+    e.storage().instance().has(&DataKey::MigrationPool)
+}
+
+// router whose pools registry the migration successor should be listed in
+pub fn get_migration_router(e: &Env) -> Option<Address> {
+    bump_instance(e);
+    e.storage().instance().get(&DataKey::MigrationRouter)
+}
+
+pub fn put_migration_router(e: &Env, router: &Address) {
+    bump_instance(e);
+    e.storage()
+        .instance()
+        .set(&DataKey::MigrationRouter, router)
+}
+
+// amount of pool shares not yet exchanged for successor pool shares
+pub fn get_migrated_shares(e: &Env) -> u128 {
+    bump_instance(e);
+    e.storage()
+        .instance()
+        .get(&DataKey::MigratedShares)
+        .unwrap_or(0)
+}
+
+pub fn put_migrated_shares(e: &Env, value: u128) {
+    bump_instance(e);
+    e.storage().instance().set(&DataKey::MigratedShares, &value)
+}
+
+// amount of successor pool shares not yet distributed across liquidity providers
+pub fn get_migration_shares(e: &Env) -> u128 {
+    bump_instance(e);
+    e.storage()
+        .instance()
+        .get(&DataKey::MigrationShares)
+        .unwrap_or(0)
+}
+
+pub fn put_migration_shares(e: &Env, value: u128) {
+    bump_instance(e);
+    e.storage()
+        .instance()
+        .set(&DataKey::MigrationShares, &value)
+}
+
+// total shares of users opted out of rewards
+pub fn get_opted_out_shares(e: &Env) -> u128 {
+    bump_instance(e);
+    e.storage()
+        .instance()
+        .get(&DataKey::OptedOutShares)
+        .unwrap_or(0)
+}
+
+pub fn put_opted_out_shares(e: &Env, value: u128) {
+    bump_instance(e);
+    e.storage().instance().set(&DataKey::OptedOutShares, &value)
+}
+
+// user shares accounted as opted out of rewards. None if user receives rewards
+pub fn get_reward_opt_out(e: &Env, user: &Address) -> Option<u128> {
+    let key = DataKey::RewardOptOut(user.clone());
+    let value = e.storage().persistent().get(&key);
+    if value.is_some() {
+        bump_persistent(e, &key);
+    }
+    value
+}
+
+pub fn put_reward_opt_out(e: &Env, user: &Address, shares: u128) {
+    let key = DataKey::RewardOptOut(user.clone());
+    e.storage().persistent().set(&key, &shares);
+    bump_persistent(e, &key);
+}
+
+pub fn remove_reward_opt_out(e: &Env, user: &Address) {
+    e.storage()
+        .persistent()
+        .remove(&DataKey::RewardOptOut(user.clone()))
+}
+
+// total amount of reward tokens secured for rewards distribution
+pub fn get_reward_funded(e: &Env) -> u128 {
+    bump_instance(e);
+    e.storage()
+        .instance()
+        .get(&DataKey::RewardFunded)
+        .unwrap_or(0)
+}
+
+pub fn put_reward_funded(e: &Env, value: u128) {
+    bump_instance(e);
+    e.storage().instance().set(&DataKey::RewardFunded, &value)
+}
+
+// total amount of reward tokens claimed by users
+pub fn get_reward_claimed(e: &Env) -> u128 {
+    bump_instance(e);
+    e.storage()
+        .instance()
+        .get(&DataKey::RewardClaimed)
+        .unwrap_or(0)
+}
+
+pub fn put_reward_claimed(e: &Env, value: u128) {
+    bump_instance(e);
+    e.storage().instance().set(&DataKey::RewardClaimed, &value)
+}
+
+// Only address allowed to make the first deposit into the empty pool
+pub fn get_bootstrapper(e: &Env) -> Option<Address> {
+    bump_instance(e);
+    e.storage().instance().get(&DataKey::Bootstrapper)
+}
+
+pub fn put_bootstrapper(e: &Env, bootstrapper: &Address) {
+    bump_instance(e);
+    e.storage()
+        .instance()
+        .set(&DataKey::Bootstrapper, bootstrapper)
+}
+
+pub fn remove_bootstrapper(e: &Env) {
+    bump_instance(e);
+    e.storage().instance().remove(&DataKey::Bootstrapper)
+}
+
+// total boost of locked shares
+pub fn get_lock_boost(e: &Env) -> u128 {
+    bump_instance(e);
+    e.storage().instance().get(&DataKey::LockBoost).unwrap_or(0)
+}
+
+pub fn put_lock_boost(e: &Env, value: u128) {
+    bump_instance(e);
+    e.storage().instance().set(&DataKey::LockBoost, &value)
+}
+
+pub fn get_share_lock(e: &Env, user: &Address) -> Option<ShareLock> {
+    let key = DataKey::ShareLock(user.clone());
+    let value = e.storage().persistent().get(&key);
+    if value.is_some() {
+        bump_persistent(e, &key);
+    }
+    value
+}
+
+pub fn put_share_lock(e: &Env, user: &Address, lock: &ShareLock) {
+    let key = DataKey::ShareLock(user.clone());
+    e.storage().persistent().set(&key, lock);
+    bump_persistent(e, &key);
+}
+
+pub fn get_fee_earnings(e: &Env, user: &Address) -> Option<FeeEarnings> {
+    let key = DataKey::FeeEarnings(user.clone());
+    let value = e.storage().persistent().get(&key);
+    if value.is_some() {
+        bump_persistent(e, &key);
+    }
+    value
+}
+
+pub fn put_fee_earnings(e: &Env, user: &Address, earnings: &FeeEarnings) {
+    let key = DataKey::FeeEarnings(user.clone());
+    e.storage().persistent().set(&key, earnings);
+    bump_persistent(e, &key);
+}
+
+pub fn remove_share_lock(e: &Env, user: &Address) {
+    e.storage()
+        .persistent()
+        .remove(&DataKey::ShareLock(user.clone()))
+}
+
+pub fn get_referral_bonus(e: &Env) -> ReferralBonus {
+    bump_instance(e);
+    e.storage()
+        .instance()
+        .get(&DataKey::ReferralBonus)
+        .unwrap_or(ReferralBonus {
+            bonus_bps: 0,
+            referrer_cap: 0,
+        })
+}
+
+pub fn put_referral_bonus(e: &Env, value: &ReferralBonus) {
+    bump_instance(e);
+    e.storage().instance().set(&DataKey::ReferralBonus, value)
+}
+
+pub fn get_referrer_stats(e: &Env, referrer: &Address) -> ReferralStats {
+    let key = DataKey::ReferralStats(referrer.clone());
+    match e.storage().persistent().get(&key) {
+        Some(value) => {
+            bump_persistent(e, &key);
+            value
+        }
+        None => ReferralStats {
+            period: 0,
+            period_bonus: 0,
+            total_bonus: 0,
+            referrals: 0,
+        },
+    }
+}
+
+pub fn put_referrer_stats(e: &Env, referrer: &Address, stats: &ReferralStats) {
+    let key = DataKey::ReferralStats(referrer.clone());
+    e.storage().persistent().set(&key, stats);
+    bump_persistent(e, &key);
+}
+
+pub fn get_swap_stats(e: &Env) -> SwapStats {
+    let key = DataKey::SwapStats;
+    match e.storage().persistent().get(&key) {
+        Some(value) => {
+            bump_persistent(e, &key);
+            value
+        }
+        None => SwapStats {
+            volume_a: 0,
+            volume_b: 0,
+            fee_a: 0,
+            fee_b: 0,
+            last_trade_at: 0,
+        },
+    }
+}
+
+pub fn put_swap_stats(e: &Env, stats: &SwapStats) {
+    let key = DataKey::SwapStats;
+    e.storage().persistent().set(&key, stats);
+    bump_persistent(e, &key);
+}
+
+// number of share holders in the holder index
+pub fn get_holders_count(e: &Env) -> u32 {
+    bump_instance(e);
+    e.storage()
+        .instance()
+        .get(&DataKey::HoldersCount)
+        .unwrap_or(0)
+}
+
+pub fn put_holders_count(e: &Env, value: u32) {
+    bump_instance(e);
+    e.storage().instance().set(&DataKey::HoldersCount, &value)
+}
+
+// total shares of the indexed holders
+pub fn get_indexed_shares(e: &Env) -> u128 {
+    bump_instance(e);
+    e.storage()
+        .instance()
+        .get(&DataKey::IndexedShares)
+        .unwrap_or(0)
+}
+
+pub fn put_indexed_shares(e: &Env, value: u128) {
+    bump_instance(e);
+    e.storage().instance().set(&DataKey::IndexedShares, &value)
+}
+
+// version of the storage layout, see STORAGE_VERSION
+pub fn get_storage_version(e: &Env) -> u32 {
+    bump_instance(e);
+    e.storage()
+        .instance()
+        .get(&DataKey::StorageVersion)
+        .unwrap_or(0)
+}
+
+pub fn put_storage_version(e: &Env, value: u32) {
+    bump_instance(e);
+    e.storage().instance().set(&DataKey::StorageVersion, &value)
+}
+
+// whether holders from before the holder index are backfilled
+pub fn get_backfill_complete(e: &Env) -> bool {
+    bump_instance(e);
+    e.storage()
+        .instance()
+        .get(&DataKey::BackfillComplete)
+        .unwrap_or(false)
+}
+
+pub fn put_backfill_complete(e: &Env, value: bool) {
+    bump_instance(e);
+    e.storage()
+        .instance()
+        .set(&DataKey::BackfillComplete, &value)
+}
+
+// holder shares accounted in the index. None if holder is not indexed
+pub fn get_holder_shares(e: &Env, user: &Address) -> Option<u128> {
+    let key = DataKey::HolderShares(user.clone());
+    let value = e.storage().persistent().get(&key);
+    if value.is_some() {
+        bump_persistent(e, &key);
+    }
+    value
+}
+
+pub fn put_holder_shares(e: &Env, user: &Address, shares: u128) {
+    let key = DataKey::HolderShares(user.clone());
+    e.storage().persistent().set(&key, &shares);
+    bump_persistent(e, &key);
+}
+
+pub fn remove_holder_shares(e: &Env, user: &Address) {
+    e.storage()
+        .persistent()
+        .remove(&DataKey::HolderShares(user.clone()))
+}
+
+// swaps soft rejected by the guard. Kept in temporary storage
+pub fn get_rejections(e: &Env, guard: &Symbol) -> u64 {
+    e.storage()
+        .temporary()
+        .get(&DataKey::Rejections(guard.clone()))
+        .unwrap_or(0)
+}
+
+pub fn put_rejections(e: &Env, guard: &Symbol, value: u64) {
+    let key = DataKey::Rejections(guard.clone());
+    e.storage().temporary().set(&key, &value);
+    bump_temporary(e, &key);
+}
+
+pub fn get_vesting_duration(e: &Env) -> u64 {
+    bump_instance(e);
+    e.storage()
+        .instance()
+        .get(&DataKey::VestingDuration)
+        .unwrap_or(0)
+}
+
+pub fn put_vesting_duration(e: &Env, value: u64) {
+    bump_instance(e);
+    e.storage()
+        .instance()
+        .set(&DataKey::VestingDuration, &value)
+}
+
+pub fn get_reward_vesting(e: &Env, user: &Address) -> Option<RewardVesting> {
+    let key = DataKey::RewardVesting(user.clone());
+    let value = e.storage().persistent().get(&key);
+    if value.is_some() {
+        bump_persistent(e, &key);
+    }
+    value
+}
+
+pub fn put_reward_vesting(e: &Env, user: &Address, vesting: &RewardVesting) {
+    let key = DataKey::RewardVesting(user.clone());
+    e.storage().persistent().set(&key, vesting);
+    bump_persistent(e, &key);
+}
+
+pub fn remove_reward_vesting(e: &Env, user: &Address) {
+    e.storage()
+        .persistent()
+        .remove(&DataKey::RewardVesting(user.clone()))
+}
@@ -1,11 +1,41 @@
 #![cfg(test)]
 extern crate std;
 
+use crate::constants::{DEFAULT_MIN_RESERVE, MAX_LOCK_DURATION};
+use crate::contract::LiquidityPoolError;
+use crate::plane::pool_plane;
+use crate::pool;
+use crate::pool_interface::UpgradeableContractTrait;
+use crate::rewards::get_rewards_manager;
+use crate::stable_math;
+use crate::storage::{
+    put_backfill_complete, put_decimals_a, put_decimals_b, put_reward_claimed, put_storage_version,
+};
 use crate::testutils::{
-    create_liqpool_contract, create_token_contract, install_token_wasm, jump, Setup, TestConfig,
+    create_liqpool_contract, create_plane_contract, create_pool_registry,
+    create_stableswap_liqpool_contract, create_token_contract, create_token_contract_with_decimals,
+    create_token_contract_with_symbol, create_weighted_liqpool_contract, install_token_wasm, jump,
+    EventsReplay, FeeOnTransferToken, FeeOnTransferTokenClient, PoolCaller, PoolCallerClient,
+    PoolTestFixture, ReentrantToken, ReentrantTokenClient, Setup, TestConfig,
+};
+use crate::{
+    LiquidityPool, LiquidityPoolClient, PoolContracts, QuoteContext, SwapOutcome, UserPosition,
+};
+use access_control::access::{AccessControl, AccessControlTrait};
+use liquidity_pool_interface::SwapResult;
+use num_integer::Roots;
+use rewards::storage::{PoolRewardConfig, RewardsStorageTrait};
+use soroban_sdk::testutils::{
+    storage::Instance as _, AuthorizedFunction, AuthorizedInvocation, Events, Ledger as _,
+    MockAuth, MockAuthInvoke,
+};
+use soroban_sdk::{
+    symbol_short, testutils::Address as _, vec, Address, BytesN, Env, FromVal, IntoVal, Map,
+    String, Symbol, Vec,
 };
-use soroban_sdk::testutils::{AuthorizedFunction, AuthorizedInvocation};
-use soroban_sdk::{testutils::Address as _, Address, IntoVal, Symbol, Vec};
+use token_share::get_balance_shares;
+use token_share::token_contract::Client;
+use utils::constant::{DAY_IN_LEDGERS, INSTANCE_BUMP_AMOUNT};
 use utils::test_utils::assert_approx_eq_abs;
 
 #[test]
@@ -38,7 +68,24 @@ fn test() {
                     Symbol::new(&e, "deposit"),
                     Vec::from_array(&e, [user1.to_val(), desired_amounts.to_val()]),
                 )),
-                sub_invocations: std::vec![],
+                sub_invocations: std::vec![
+                    AuthorizedInvocation {
+                        function: AuthorizedFunction::Contract((
+                            token1.address.clone(),
+                            symbol_short!("transfer"),
+                            (user1.clone(), liq_pool.address.clone(), 100_i128).into_val(&e),
+                        )),
+                        sub_invocations: std::vec![],
+                    },
+                    AuthorizedInvocation {
+                        function: AuthorizedFunction::Contract((
+                            token2.address.clone(),
+                            symbol_short!("transfer"),
+                            (user1.clone(), liq_pool.address.clone(), 100_i128).into_val(&e),
+                        )),
+                        sub_invocations: std::vec![],
+                    },
+                ],
             }
         )
     );
@@ -280,6 +327,54 @@ fn test_estimate_ongoing_reward() {
     assert_eq!(token_reward.balance(&users[0]) as u128, 0);
 }
 
+#[test]
+fn test_reward_18_decimals_long_gap() {
+    let one = 1_000_000_000_000_000_000_u128;
+    let config = TestConfig {
+        mint_to_user: 10 * one as i128,
+        rewards_count: 100_000_000 * one as i128,
+        ..TestConfig::default()
+    };
+    let setup = Setup::setup(&config);
+    setup.mint_tokens_for_users(&config.mint_to_user);
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let (admin, user1) = (setup.users[0].clone(), setup.users[1].clone());
+
+    // one token per second for a year, nobody touches the pool in between
+    let year = 365 * 24 * 60 * 60_u64;
+    liq_pool.set_rewards_config(&admin, &e.ledger().timestamp().saturating_add(year), &one);
+    let (_, shares) = liq_pool.deposit(&user1, &Vec::from_array(e, [one, one]));
+    assert_eq!(shares, one);
+    jump(e, year);
+
+    // precision * generated tokens and reward per share * user shares don't fit u128
+    let total_reward = one * year as u128;
+    assert_eq!(liq_pool.get_user_reward(&user1), total_reward);
+    assert_eq!(liq_pool.claim(&user1), total_reward);
+    assert_eq!(setup.token_reward.balance(&user1) as u128, total_reward);
+}
+
+#[test]
+fn test_deposit_returns_shares() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let user1 = setup.users[0].clone();
+    let user2 = setup.users[1].clone();
+
+    let (amounts, shares) = liq_pool.deposit(&user1, &Vec::from_array(e, [100, 400]));
+    assert_eq!(amounts, Vec::from_array(e, [100, 400]));
+    assert_eq!(shares, 200);
+    assert_eq!(setup.token_share.balance(&user1) as u128, shares);
+
+    // desired amounts clamped to the pool ratio
+    let (amounts, shares) = liq_pool.deposit(&user2, &Vec::from_array(e, [100, 100]));
+    assert_eq!(amounts, Vec::from_array(e, [25, 100]));
+    assert_eq!(shares, 50);
+    assert_eq!(setup.token_share.balance(&user2) as u128, shares);
+}
+
 #[test]
 fn test_simple_reward() {
     let setup = Setup::setup(&TestConfig::default());
@@ -311,12 +406,6 @@ fn test_simple_reward() {
 
     // 90 seconds. rewards ended.
     jump(&env, 70);
-    // calling set rewards config to checkpoint. should be removed
-    liq_pool.set_rewards_config(
-        &users[0],
-        &env.ledger().timestamp().saturating_add(60),
-        &0_u128,
-    );
 
     // 100 seconds. user claim reward
     jump(&env, 10);
@@ -460,7 +549,9 @@ fn test_rewards_many_users(iterations_to_simulate: u32) {
     env.budget().reset_tracker();
     let user1_claim = liq_pool.claim(&first_user);
     env.budget().print();
-    assert_eq!(user1_claim, expected_reward);
+    // expected reward floors every block, cumulative reward per share only floors once
+    assert!(user1_claim >= expected_reward);
+    assert!(user1_claim - expected_reward < iterations_to_simulate as u128);
 }
 
 #[test]
@@ -473,3 +564,6188 @@ fn test_rewards_1k() {
 fn test_rewards_50k() {
     test_rewards_many_users(50_000);
 }
+
+#[test]
+fn test_migrate_liquidity() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let admin = setup.users[0].clone();
+    let user2 = setup.users[1].clone();
+
+    setup
+        .liq_pool
+        .deposit(&admin, &Vec::from_array(e, [100, 100]));
+    setup
+        .liq_pool
+        .deposit(&user2, &Vec::from_array(e, [300, 300]));
+
+    let new_pool = create_liqpool_contract(
+        e,
+        &admin,
+        &install_token_wasm(e),
+        &Vec::from_array(
+            e,
+            [setup.token1.address.clone(), setup.token2.address.clone()],
+        ),
+        &setup.token_reward.address,
+        10,
+        &setup.plane.address,
+    );
+    let new_token_share = Client::new(e, &new_pool.share_id());
+    let registry = create_pool_registry(e, &[&new_pool]);
+    setup
+        .liq_pool
+        .set_migration_router(&admin, &registry.address);
+    assert_eq!(
+        setup.liq_pool.get_migration_router(),
+        Some(registry.address.clone())
+    );
+
+    setup.liq_pool.kill_me(&admin);
+    // only the admin signs, token transfers to the successor are authorized by the pool itself
+    e.mock_auths(&[MockAuth {
+        address: &admin,
+        invoke: &MockAuthInvoke {
+            contract: &setup.liq_pool.address,
+            fn_name: "migrate_liquidity",
+            args: (admin.clone(), new_pool.address.clone(), 1_0000000_u128).into_val(e),
+            sub_invokes: &[],
+        },
+    }]);
+    assert_eq!(
+        setup
+            .liq_pool
+            .migrate_liquidity(&admin, &new_pool.address, &1_0000000),
+        400
+    );
+    e.mock_all_auths();
+    assert_eq!(setup.liq_pool.get_reserves(), Vec::from_array(e, [0, 0]));
+    assert_eq!(
+        setup
+            .token1
+            .allowance(&setup.liq_pool.address, &new_pool.address),
+        0
+    );
+    assert_eq!(new_pool.get_reserves(), Vec::from_array(e, [400, 400]));
+    assert_eq!(setup.token1.balance(&setup.liq_pool.address), 0);
+    assert_eq!(new_token_share.balance(&setup.liq_pool.address), 400);
+
+    // first provider exchanges shares, second one doesn't claim yet
+    setup
+        .token_share
+        .approve(&admin, &setup.liq_pool.address, &100, &99999);
+    assert_eq!(setup.liq_pool.claim_migrated_shares(&admin), 100);
+    assert_eq!(setup.token_share.balance(&admin), 0);
+    assert_eq!(new_token_share.balance(&admin), 100);
+    assert_eq!(new_token_share.balance(&setup.liq_pool.address), 300);
+
+    // nothing left to exchange
+    assert_eq!(setup.liq_pool.claim_migrated_shares(&admin), 0);
+
+    setup
+        .token_share
+        .approve(&user2, &setup.liq_pool.address, &300, &99999);
+    assert_eq!(setup.liq_pool.claim_migrated_shares(&user2), 300);
+    assert_eq!(new_token_share.balance(&user2), 300);
+    assert_eq!(new_token_share.balance(&setup.liq_pool.address), 0);
+    assert_eq!(setup.token_share.balance(&user2), 0);
+}
+
+#[test]
+fn test_migrate_position() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let admin = setup.users[0].clone();
+    let user2 = setup.users[1].clone();
+
+    setup
+        .liq_pool
+        .deposit(&user2, &Vec::from_array(e, [100, 100]));
+    let new_pool = create_liqpool_contract(
+        e,
+        &admin,
+        &install_token_wasm(e),
+        &Vec::from_array(
+            e,
+            [setup.token1.address.clone(), setup.token2.address.clone()],
+        ),
+        &setup.token_reward.address,
+        10,
+        &setup.plane.address,
+    );
+    let new_token_share = Client::new(e, &new_pool.share_id());
+
+    setup
+        .token_share
+        .approve(&user2, &setup.liq_pool.address, &40, &99999);
+    assert_eq!(
+        setup
+            .liq_pool
+            .migrate_position(&user2, &new_pool.address, &40, &40, &40, &40),
+        40
+    );
+    // user authorizes the migration once, the new pool deposit is part of it
+    let user_auths: std::vec::Vec<_> = e
+        .auths()
+        .into_iter()
+        .filter(|(address, _)| address == &user2)
+        .collect();
+    assert_eq!(user_auths.len(), 1);
+    assert_eq!(
+        user_auths[0].1.function,
+        AuthorizedFunction::Contract((
+            setup.liq_pool.address.clone(),
+            Symbol::new(e, "migrate_position"),
+            (
+                user2.clone(),
+                new_pool.address.clone(),
+                40_u128,
+                40_u128,
+                40_u128,
+                40_u128
+            )
+                .into_val(e),
+        ))
+    );
+    assert_eq!(setup.liq_pool.get_reserves(), Vec::from_array(e, [60, 60]));
+    assert_eq!(new_pool.get_reserves(), Vec::from_array(e, [40, 40]));
+    assert_eq!(setup.token_share.balance(&user2), 60);
+    assert_eq!(new_token_share.balance(&user2), 40);
+    assert_eq!(setup.token1.balance(&user2), 900);
+    assert_eq!(setup.token2.balance(&user2), 900);
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #255)")]
+fn test_migrate_position_min_shares() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let user2 = setup.users[1].clone();
+
+    setup
+        .liq_pool
+        .deposit(&user2, &Vec::from_array(e, [100, 100]));
+    let new_pool = create_liqpool_contract(
+        e,
+        &setup.users[0],
+        &install_token_wasm(e),
+        &Vec::from_array(
+            e,
+            [setup.token1.address.clone(), setup.token2.address.clone()],
+        ),
+        &setup.token_reward.address,
+        10,
+        &setup.plane.address,
+    );
+
+    setup
+        .token_share
+        .approve(&user2, &setup.liq_pool.address, &40, &99999);
+    setup
+        .liq_pool
+        .migrate_position(&user2, &new_pool.address, &40, &0, &0, &41);
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #204)")]
+fn test_migrate_liquidity_not_killed() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    setup
+        .liq_pool
+        .deposit(&setup.users[0], &Vec::from_array(e, [100, 100]));
+
+    let new_pool = create_liqpool_contract(
+        e,
+        &setup.users[0],
+        &install_token_wasm(e),
+        &Vec::from_array(
+            e,
+            [setup.token1.address.clone(), setup.token2.address.clone()],
+        ),
+        &setup.token_reward.address,
+        10,
+        &setup.plane.address,
+    );
+    setup
+        .liq_pool
+        .migrate_liquidity(&setup.users[0], &new_pool.address, &0);
+}
+
+#[test]
+fn test_migrate_liquidity_rejected() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let admin = setup.users[0].clone();
+    let user2 = setup.users[1].clone();
+    let create_pool = || {
+        create_liqpool_contract(
+            e,
+            &admin,
+            &install_token_wasm(e),
+            &Vec::from_array(
+                e,
+                [setup.token1.address.clone(), setup.token2.address.clone()],
+            ),
+            &setup.token_reward.address,
+            10,
+            &setup.plane.address,
+        )
+    };
+    let new_pool = create_pool();
+    let other_pool = create_pool();
+    let registry = create_pool_registry(e, &[&new_pool]);
+    let error = |code| Ok(soroban_sdk::Error::from_contract_error(code));
+
+    liq_pool.deposit(&user2, &Vec::from_array(e, [400, 400]));
+    liq_pool.kill_me(&admin);
+
+    // router not set
+    assert_eq!(
+        liq_pool
+            .try_migrate_liquidity(&admin, &new_pool.address, &0)
+            .unwrap_err(),
+        error(251)
+    );
+    liq_pool.set_migration_router(&admin, &registry.address);
+
+    // successor is not listed in the router registry
+    assert_eq!(
+        liq_pool
+            .try_migrate_liquidity(&admin, &other_pool.address, &0)
+            .unwrap_err(),
+        error(252)
+    );
+
+    // one successor share per pool share, asked for more
+    assert_eq!(
+        liq_pool
+            .try_migrate_liquidity(&admin, &new_pool.address, &1_0000001)
+            .unwrap_err(),
+        error(255)
+    );
+
+    // successor at a different price takes only part of the reserves
+    new_pool.deposit(&user2, &Vec::from_array(e, [100, 200]));
+    assert_eq!(
+        liq_pool
+            .try_migrate_liquidity(&admin, &new_pool.address, &0)
+            .unwrap_err(),
+        error(254)
+    );
+
+    // nothing to move out of the empty pool
+    other_pool.kill_me(&admin);
+    other_pool.set_migration_router(&admin, &registry.address);
+    assert_eq!(
+        other_pool
+            .try_migrate_liquidity(&admin, &new_pool.address, &0)
+            .unwrap_err(),
+        error(253)
+    );
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #205)")]
+fn test_withdraw_after_migration() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let admin = setup.users[0].clone();
+    setup
+        .liq_pool
+        .deposit(&admin, &Vec::from_array(e, [100, 100]));
+
+    let new_pool = create_liqpool_contract(
+        e,
+        &admin,
+        &install_token_wasm(e),
+        &Vec::from_array(
+            e,
+            [setup.token1.address.clone(), setup.token2.address.clone()],
+        ),
+        &setup.token_reward.address,
+        10,
+        &setup.plane.address,
+    );
+    let registry = create_pool_registry(e, &[&new_pool]);
+    setup
+        .liq_pool
+        .set_migration_router(&admin, &registry.address);
+    setup.liq_pool.kill_me(&admin);
+    setup
+        .liq_pool
+        .migrate_liquidity(&admin, &new_pool.address, &0);
+
+    setup
+        .token_share
+        .approve(&admin, &setup.liq_pool.address, &100, &99999);
+    setup
+        .liq_pool
+        .withdraw(&admin, &100, &Vec::from_array(e, [0, 0]));
+}
+
+#[test]
+fn test_skim() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let user1 = setup.users[0].clone();
+    let user2 = setup.users[1].clone();
+
+    setup
+        .liq_pool
+        .deposit(&user1, &Vec::from_array(e, [100, 100]));
+
+    // balances match reserves, nothing to skim
+    assert_eq!(setup.liq_pool.skim(&user2), Vec::from_array(e, [0, 0]));
+
+    // tokens sent directly to the pool
+    setup.token1.transfer(&user2, &setup.liq_pool.address, &50);
+    assert_eq!(setup.token1.balance(&user2), 950);
+    assert_eq!(setup.liq_pool.skim(&user2), Vec::from_array(e, [50, 0]));
+    assert_eq!(setup.token1.balance(&user2), 1000);
+    assert_eq!(setup.token1.balance(&setup.liq_pool.address), 100);
+    assert_eq!(
+        setup.liq_pool.get_reserves(),
+        Vec::from_array(e, [100, 100])
+    );
+
+    // next deposit isn't affected by the donation
+    let (amounts, shares) = setup
+        .liq_pool
+        .deposit(&user2, &Vec::from_array(e, [100, 100]));
+    assert_eq!(amounts, Vec::from_array(e, [100, 100]));
+    assert_eq!(shares, 100);
+    assert_eq!(setup.token_share.balance(&user2), 100);
+}
+
+#[test]
+fn test_deposit_proportional() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let (user1, user2) = (setup.users[0].clone(), setup.users[1].clone());
+
+    liq_pool.deposit(&user1, &Vec::from_array(e, [100, 200]));
+    assert_eq!(liq_pool.get_total_shares(), 141);
+
+    // token a binds, the rest of token b stays with the user
+    assert_eq!(
+        liq_pool.deposit_proportional(&user2, &50, &200, &70),
+        (50, 100, 70)
+    );
+    // token b binds
+    assert_eq!(
+        liq_pool.deposit_proportional(&user2, &100, &60, &42),
+        (30, 60, 42)
+    );
+    assert_eq!(setup.token1.balance(&user2), 920);
+    assert_eq!(setup.token2.balance(&user2), 840);
+    assert_eq!(setup.token_share.balance(&user2), 112);
+    assert_eq!(liq_pool.get_reserves(), Vec::from_array(e, [180, 360]));
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #248)")]
+fn test_deposit_proportional_empty_pool() {
+    let setup = Setup::default();
+
+    setup
+        .liq_pool
+        .deposit_proportional(&setup.users[1], &100, &100, &0);
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #258)")]
+fn test_deposit_proportional_ratio_drift() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let (user1, user2) = (setup.users[0].clone(), setup.users[1].clone());
+
+    liq_pool.deposit(&user1, &Vec::from_array(e, [100, 200]));
+    // shares quoted for 50 of token a at the current ratio
+    let reserves = liq_pool.get_reserves();
+    let total_shares = liq_pool.get_total_shares() as u128;
+    let quoted_shares = (50 * total_shares / reserves.get(0).unwrap())
+        .min(200 * total_shares / reserves.get(1).unwrap());
+    assert_eq!(quoted_shares, 70);
+
+    // ratio drifts before the deposit is executed
+    liq_pool.swap(&user1, &0, &1, &50, &0);
+    liq_pool.deposit_proportional(&user2, &50, &200, &quoted_shares);
+}
+
+#[test]
+fn test_get_deposit_amounts() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let (user1, user2) = (setup.users[0].clone(), setup.users[1].clone());
+    let error = |code| Ok(soroban_sdk::Error::from_contract_error(code));
+
+    // empty pool takes desired amounts, mins still apply
+    assert_eq!(liq_pool.get_deposit_amounts(&100, &0, &150, &0), (100, 150));
+    assert_eq!(
+        liq_pool
+            .try_get_deposit_amounts(&100, &101, &150, &0)
+            .unwrap_err(),
+        error(247)
+    );
+    liq_pool.deposit(&user1, &Vec::from_array(e, [100, 150]));
+
+    // token a binds, amount b rounded up
+    assert_eq!(liq_pool.get_deposit_amounts(&10, &0, &100, &0), (10, 15));
+    assert_eq!(liq_pool.get_deposit_amounts(&3, &0, &100, &0), (3, 5));
+    // token b binds, amount a rounded up
+    assert_eq!(liq_pool.get_deposit_amounts(&100, &0, &30, &0), (20, 30));
+    assert_eq!(liq_pool.get_deposit_amounts(&100, &0, &31, &0), (21, 31));
+    // mins met exactly
+    assert_eq!(liq_pool.get_deposit_amounts(&10, &10, &100, &15), (10, 15));
+    assert_eq!(liq_pool.get_deposit_amounts(&100, &20, &30, &30), (20, 30));
+
+    // ratio forces amount below min on either side
+    assert_eq!(
+        liq_pool
+            .try_get_deposit_amounts(&10, &0, &100, &16)
+            .unwrap_err(),
+        error(247)
+    );
+    assert_eq!(
+        liq_pool
+            .try_get_deposit_amounts(&100, &21, &30, &0)
+            .unwrap_err(),
+        error(247)
+    );
+    // zero desired amounts
+    assert_eq!(
+        liq_pool
+            .try_get_deposit_amounts(&0, &0, &100, &0)
+            .unwrap_err(),
+        error(218)
+    );
+    assert_eq!(
+        liq_pool
+            .try_get_deposit_amounts(&100, &0, &0, &0)
+            .unwrap_err(),
+        error(218)
+    );
+    // only one of the reserves is empty, ratio is undefined
+    assert_eq!(
+        pool::get_deposit_amounts(10, 0, 10, 0, 0, 100),
+        Err(LiquidityPoolError::EmptyReserve)
+    );
+    assert_eq!(
+        pool::get_deposit_amounts(10, 0, 10, 0, 100, 0),
+        Err(LiquidityPoolError::EmptyReserve)
+    );
+
+    // deposit pulls exactly the quoted amounts
+    let quoted = liq_pool.get_deposit_amounts(&3, &0, &100, &0);
+    let (amounts, _) = liq_pool.deposit(&user2, &Vec::from_array(e, [3, 100]));
+    assert_eq!(amounts, Vec::from_array(e, [quoted.0, quoted.1]));
+    assert_eq!(setup.token1.balance(&user2), 997);
+    assert_eq!(setup.token2.balance(&user2), 995);
+}
+
+#[test]
+fn test_donate() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let pool = setup.liq_pool.address.clone();
+    let (user1, user2) = (setup.users[0].clone(), setup.users[1].clone());
+    let mut replay = EventsReplay::default();
+
+    setup
+        .liq_pool
+        .deposit(&user1, &Vec::from_array(e, [100, 100]));
+
+    // donation grows reserves per share, no shares minted
+    assert_eq!(
+        setup.liq_pool.donate(&user2, &50, &20),
+        Vec::from_array(e, [50, 20])
+    );
+    assert_eq!(setup.token1.balance(&user2), 950);
+    assert_eq!(setup.token2.balance(&user2), 980);
+    assert_eq!(setup.token_share.balance(&user2), 0);
+    assert_eq!(setup.token_share.balance(&user1), 100);
+    assert_eq!(
+        setup.liq_pool.get_reserves(),
+        Vec::from_array(e, [150, 120])
+    );
+    assert_eq!(
+        vec![e, e.events().all().last().unwrap()],
+        vec![
+            e,
+            (
+                pool.clone(),
+                (symbol_short!("donate"), user2.clone()).into_val(e),
+                Vec::from_array(e, [50_u128, 20]).into_val(e),
+            ),
+        ]
+    );
+    replay.apply(e, &pool);
+    assert_replay_matches(&setup, &replay);
+
+    // nothing to skim: donation is accounted in reserves
+    assert_eq!(setup.liq_pool.skim(&user2), Vec::from_array(e, [0, 0]));
+
+    // existing liquidity provider withdraws the donation
+    setup
+        .token_share
+        .approve(&user1, &setup.liq_pool.address, &100, &99999);
+    assert_eq!(
+        setup
+            .liq_pool
+            .withdraw(&user1, &100, &Vec::from_array(e, [0, 0])),
+        Vec::from_array(e, [150, 120])
+    );
+}
+
+#[test]
+#[should_panic(expected = "pool is empty")]
+fn test_donate_empty_pool() {
+    let setup = Setup::default();
+    let user1 = setup.users[1].clone();
+
+    setup.liq_pool.donate(&user1, &50, &50);
+}
+
+fn assert_replay_matches(setup: &Setup, replay: &EventsReplay) {
+    assert_eq!(
+        setup.liq_pool.get_reserves(),
+        Vec::from_array(&setup.env, replay.reserves)
+    );
+    let mut total_shares = 0;
+    for user in setup.users.iter() {
+        let user_shares = setup.token_share.balance(user) as u128;
+        assert_eq!(user_shares, replay.get_user_shares(user));
+        assert_eq!(
+            setup.token_reward.balance(user) as u128,
+            replay.get_user_claimed(user)
+        );
+        total_shares += user_shares;
+    }
+    assert_eq!(replay.total_shares, total_shares);
+}
+
+#[test]
+fn test_events_replay() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let pool = setup.liq_pool.address.clone();
+    let user1 = setup.users[0].clone();
+    let user2 = setup.users[1].clone();
+    let mut replay = EventsReplay::default();
+
+    setup
+        .liq_pool
+        .deposit(&user1, &Vec::from_array(e, [100, 100]));
+    replay.apply(e, &pool);
+    assert_replay_matches(&setup, &replay);
+
+    setup
+        .liq_pool
+        .deposit(&user2, &Vec::from_array(e, [200, 300]));
+    replay.apply(e, &pool);
+    assert_replay_matches(&setup, &replay);
+
+    setup.liq_pool.swap(&user2, &0, &1, &50, &0);
+    replay.apply(e, &pool);
+    assert_replay_matches(&setup, &replay);
+
+    jump(e, 30);
+    setup.liq_pool.claim(&user1);
+    replay.apply(e, &pool);
+    assert_replay_matches(&setup, &replay);
+
+    setup
+        .token_share
+        .approve(&user1, &setup.liq_pool.address, &50, &99999);
+    setup
+        .liq_pool
+        .withdraw(&user1, &50, &Vec::from_array(e, [0, 0]));
+    replay.apply(e, &pool);
+    assert_replay_matches(&setup, &replay);
+
+    setup.liq_pool.swap(&user1, &1, &0, &20, &0);
+    replay.apply(e, &pool);
+    assert_replay_matches(&setup, &replay);
+
+    jump(e, 30);
+    setup.liq_pool.claim(&user2);
+    replay.apply(e, &pool);
+    assert_replay_matches(&setup, &replay);
+}
+
+#[test]
+fn test_sync() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let user1 = setup.users[0].clone();
+    let mut replay = EventsReplay::default();
+
+    setup
+        .liq_pool
+        .deposit(&user1, &Vec::from_array(e, [100, 100]));
+    replay.apply(e, &setup.liq_pool.address);
+
+    // pool balance drifts below recorded reserves, swaps are broken
+    setup.token1.burn(&setup.liq_pool.address, &10);
+    assert!(setup.liq_pool.try_swap(&user1, &0, &1, &10, &0).is_err());
+
+    setup.liq_pool.sync();
+    assert_eq!(setup.liq_pool.get_reserves(), Vec::from_array(e, [90, 100]));
+    replay.apply(e, &setup.liq_pool.address);
+    assert_replay_matches(&setup, &replay);
+
+    let out = setup.liq_pool.estimate_swap(&0, &1, &10);
+    assert_eq!(setup.liq_pool.swap(&user1, &0, &1, &10, &out), out);
+    assert_eq!(
+        setup.liq_pool.get_reserves(),
+        Vec::from_array(e, [100, 100 - out])
+    );
+}
+
+#[test]
+fn test_pause() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let admin = setup.users[0].clone();
+    let user = setup.users[1].clone();
+
+    setup
+        .liq_pool
+        .deposit(&user, &Vec::from_array(e, [100, 100]));
+    assert!(!setup.liq_pool.is_paused());
+
+    setup.liq_pool.set_paused(&admin, &true);
+    assert!(setup.liq_pool.is_paused());
+    assert!(setup.liq_pool.try_swap(&user, &0, &1, &10, &0).is_err());
+    assert!(setup
+        .liq_pool
+        .try_deposit(&user, &Vec::from_array(e, [100, 100]))
+        .is_err());
+
+    // exit is always available
+    jump(e, 30);
+    assert!(setup.liq_pool.claim(&user) > 0);
+    setup
+        .token_share
+        .approve(&user, &setup.liq_pool.address, &50, &99999);
+    assert_eq!(
+        setup
+            .liq_pool
+            .withdraw(&user, &50, &Vec::from_array(e, [0, 0])),
+        Vec::from_array(e, [50, 50])
+    );
+
+    setup.liq_pool.set_paused(&admin, &false);
+    assert!(!setup.liq_pool.is_paused());
+    let out = setup.liq_pool.estimate_swap(&0, &1, &10);
+    assert_eq!(setup.liq_pool.swap(&user, &0, &1, &10, &0), out);
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #208)")]
+fn test_swap_paused() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    setup
+        .liq_pool
+        .deposit(&setup.users[1], &Vec::from_array(e, [100, 100]));
+    setup.liq_pool.set_paused(&setup.users[0], &true);
+    setup.liq_pool.swap(&setup.users[1], &0, &1, &10, &0);
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #102)")]
+fn test_pause_not_admin() {
+    let setup = Setup::default();
+    setup.liq_pool.set_paused(&setup.users[1], &true);
+}
+
+const VIEW_CPU_LIMIT: u64 = 10_000_000;
+const VIEW_MEM_LIMIT: u64 = 5_000_000;
+
+#[test]
+fn test_views_budget() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let user1 = setup.users[0].clone();
+    let user2 = setup.users[1].clone();
+
+    liq_pool.deposit(&user1, &Vec::from_array(e, [100, 100]));
+    // build long reward history, so user1 misses a lot of blocks
+    for _ in 0..50 {
+        liq_pool.deposit(&user2, &Vec::from_array(e, [5, 5]));
+        jump(e, 1);
+    }
+    liq_pool.swap(&user2, &0, &1, &10, &0);
+
+    macro_rules! assert_view_budget {
+        ($call:expr) => {
+            e.budget().reset_limits(VIEW_CPU_LIMIT, VIEW_MEM_LIMIT);
+            $call;
+            e.budget().reset_unlimited();
+        };
+    }
+
+    assert_view_budget!(liq_pool.pool_type());
+    assert_view_budget!(liq_pool.share_id());
+    assert_view_budget!(liq_pool.get_tokens());
+    assert_view_budget!(liq_pool.get_reserves());
+    assert_view_budget!(liq_pool.get_fee_fraction());
+    assert_view_budget!(liq_pool.get_info());
+    assert_view_budget!(liq_pool.estimate_swap(&0, &1, &10));
+    assert_view_budget!(liq_pool.get_is_killed());
+    assert_view_budget!(liq_pool.is_paused());
+    assert_view_budget!(liq_pool.get_rewards_info(&user1));
+    assert_view_budget!(liq_pool.get_rewards_info_v2(&user1));
+    assert_view_budget!(liq_pool.get_position(&user1));
+    assert_view_budget!(liq_pool.get_user_reward(&user1));
+
+    // views are read-only estimations matching state-changing claim
+    let user_reward = liq_pool.get_user_reward(&user1);
+    let rewards_info = liq_pool.get_rewards_info(&user1);
+    assert_eq!(
+        rewards_info.get(Symbol::new(e, "to_claim")).unwrap(),
+        user_reward as i128
+    );
+    assert_eq!(liq_pool.get_rewards_info_v2(&user1).to_claim, user_reward);
+    assert!(user_reward > 0);
+    assert_eq!(liq_pool.claim(&user1), user_reward);
+    assert_eq!(liq_pool.get_user_reward(&user1), 0);
+}
+
+// pool state is read and written once per call, so state changing calls stay well under the network limits
+const SWAP_CPU_LIMIT: u64 = 40_000_000;
+const SWAP_MEM_LIMIT: u64 = 20_000_000;
+
+#[test]
+fn test_swap_budget() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let user1 = setup.users[0].clone();
+    let user2 = setup.users[1].clone();
+
+    liq_pool.deposit(&user1, &Vec::from_array(e, [100, 100]));
+    jump(e, 1);
+
+    let expected_out = liq_pool.estimate_swap(&0, &1, &10);
+    e.budget().reset_limits(SWAP_CPU_LIMIT, SWAP_MEM_LIMIT);
+    assert_eq!(liq_pool.swap(&user2, &0, &1, &10, &0), expected_out);
+    e.budget().print();
+    e.budget().reset_unlimited();
+    assert_eq!(
+        liq_pool.get_reserves(),
+        Vec::from_array(e, [110, 100 - expected_out])
+    );
+
+    e.budget().reset_limits(SWAP_CPU_LIMIT, SWAP_MEM_LIMIT);
+    liq_pool.deposit(&user2, &Vec::from_array(e, [10, 10]));
+    e.budget().reset_unlimited();
+
+    let share_token = Client::new(e, &liq_pool.share_id());
+    let shares = share_token.balance(&user2);
+    share_token.approve(&user2, &liq_pool.address, &shares, &99999);
+    e.budget().reset_limits(SWAP_CPU_LIMIT, SWAP_MEM_LIMIT);
+    liq_pool.withdraw(&user2, &(shares as u128), &Vec::from_array(e, [0, 0]));
+    e.budget().reset_unlimited();
+}
+
+#[test]
+fn test_withdraw_to() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let user1 = setup.users[1].clone();
+    let recipient_a = Address::generate(e);
+    let recipient_b = Address::generate(e);
+    let reward_tps = TestConfig::default().reward_tps;
+
+    liq_pool.deposit(&user1, &Vec::from_array(e, [100, 100]));
+    jump(e, 10);
+    setup
+        .token_share
+        .approve(&user1, &liq_pool.address, &50, &99999);
+    let amounts = liq_pool.withdraw_to(
+        &user1,
+        &50,
+        &Vec::from_array(e, [0, 0]),
+        &recipient_a,
+        &recipient_b,
+    );
+    assert_eq!(amounts, Vec::from_array(e, [50, 50]));
+    assert_eq!(setup.token1.balance(&recipient_a), 50);
+    assert_eq!(setup.token2.balance(&recipient_a), 0);
+    assert_eq!(setup.token1.balance(&recipient_b), 0);
+    assert_eq!(setup.token2.balance(&recipient_b), 50);
+    assert_eq!(setup.token1.balance(&user1), 900);
+    assert_eq!(setup.token2.balance(&user1), 900);
+    assert_eq!(setup.token_share.balance(&user1), 50);
+
+    // rewards accrued by the user shares stay with the user
+    assert_eq!(liq_pool.claim(&user1), reward_tps * 10);
+    jump(e, 10);
+    assert_eq!(liq_pool.get_user_reward(&user1), reward_tps * 10);
+}
+
+#[test]
+fn test_withdraw_to_same_recipient() {
+    let withdraw = |to_self: bool| {
+        let setup = Setup::default();
+        let e = &setup.env;
+        let liq_pool = &setup.liq_pool;
+        let user1 = setup.users[1].clone();
+
+        liq_pool.deposit(&user1, &Vec::from_array(e, [100, 300]));
+        liq_pool.swap(&user1, &0, &1, &17, &0);
+        setup
+            .token_share
+            .approve(&user1, &liq_pool.address, &77, &99999);
+        let min_amounts = Vec::from_array(e, [0, 0]);
+        let amounts = match to_self {
+            true => liq_pool.withdraw_to(&user1, &77, &min_amounts, &user1, &user1),
+            false => liq_pool.withdraw(&user1, &77, &min_amounts),
+        };
+        let reserves = liq_pool.get_reserves();
+        (
+            (amounts.get(0).unwrap(), amounts.get(1).unwrap()),
+            (reserves.get(0).unwrap(), reserves.get(1).unwrap()),
+            setup.token1.balance(&user1),
+            setup.token2.balance(&user1),
+            setup.token_share.balance(&user1),
+        )
+    };
+    assert_eq!(withdraw(true), withdraw(false));
+}
+
+#[test]
+#[should_panic(expected = "min not satisfied")]
+fn test_withdraw_to_min_amounts() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let user1 = setup.users[1].clone();
+
+    liq_pool.deposit(&user1, &Vec::from_array(e, [100, 100]));
+    setup
+        .token_share
+        .approve(&user1, &liq_pool.address, &50, &99999);
+    liq_pool.withdraw_to(
+        &user1,
+        &50,
+        &Vec::from_array(e, [50, 51]),
+        &Address::generate(e),
+        &Address::generate(e),
+    );
+}
+
+#[test]
+fn test_withdraw_all() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let user1 = setup.users[0].clone();
+    let user2 = setup.users[1].clone();
+    let share_token = Client::new(e, &liq_pool.share_id());
+
+    liq_pool.deposit(&user1, &Vec::from_array(e, [100, 100]));
+    liq_pool.deposit(&user2, &Vec::from_array(e, [50, 50]));
+    assert_eq!(liq_pool.get_total_shares(), 150);
+
+    // no allowance needed
+    assert_eq!(
+        liq_pool.withdraw_all(&user1, &Vec::from_array(e, [100, 100])),
+        Vec::from_array(e, [100, 100])
+    );
+    assert_eq!(share_token.balance(&user1), 0);
+    assert_eq!(share_token.balance(&liq_pool.address), 0);
+    assert_eq!(liq_pool.get_total_shares(), 50);
+    assert_eq!(setup.token1.balance(&user1), 1000);
+    assert_eq!(setup.token2.balance(&user1), 1000);
+
+    assert_eq!(
+        liq_pool
+            .try_withdraw_all(&user1, &Vec::from_array(e, [0, 0]))
+            .unwrap_err(),
+        Ok(soroban_sdk::Error::from_contract_error(238))
+    );
+}
+
+#[test]
+fn test_fee_earnings() {
+    let setup = Setup::new_with_config(&TestConfig {
+        mint_to_user: 1000000_0000000,
+        ..TestConfig::default()
+    });
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let user1 = setup.users[0].clone();
+    let user2 = setup.users[1].clone();
+    let assert_close = |actual: u128, expected: u128, tolerance: u128| {
+        assert!(
+            actual.abs_diff(expected) <= tolerance,
+            "{} != {}",
+            actual,
+            expected
+        );
+    };
+
+    liq_pool.deposit(
+        &user1,
+        &Vec::from_array(e, [100000_0000000, 100000_0000000]),
+    );
+    assert_eq!(
+        liq_pool.get_fee_earnings(&user1),
+        Vec::from_array(e, [0, 0])
+    );
+
+    // round trip leaves the price close to the initial one, fees stay in the pool
+    let out = liq_pool.swap(&user2, &0, &1, &10000_0000000, &0);
+    liq_pool.swap(&user2, &1, &0, &out, &0);
+
+    // user owns the whole pool, so earnings are the sqrt(k) growth share of the reserves
+    let reserves = liq_pool.get_reserves();
+    let (reserve_a, reserve_b) = (reserves.get(0).unwrap(), reserves.get(1).unwrap());
+    let root_k_before = 100000_0000000_u128;
+    let root_k_after = (reserve_a * reserve_b).sqrt();
+    let earnings = liq_pool.get_fee_earnings(&user1);
+    let (earned_a, earned_b) = (earnings.get(0).unwrap(), earnings.get(1).unwrap());
+    assert_close(
+        earned_a,
+        reserve_a * (root_k_after - root_k_before) / root_k_after,
+        2,
+    );
+    assert_close(
+        earned_b,
+        reserve_b * (root_k_after - root_k_before) / root_k_after,
+        2,
+    );
+
+    // 0.3% fee of both swaps, price is close to 1
+    let stats = liq_pool.get_stats();
+    let fees = (stats.get(symbol_short!("fee_a")).unwrap()
+        + stats.get(symbol_short!("fee_b")).unwrap()) as u128;
+    assert_close(earned_a + earned_b, fees, fees / 50);
+
+    // new liquidity doesn't earn past fees and doesn't change earnings of others
+    liq_pool.deposit(&user2, &Vec::from_array(e, [10000_0000000, 10000_0000000]));
+    assert_eq!(
+        liq_pool.get_fee_earnings(&user2),
+        Vec::from_array(e, [0, 0])
+    );
+    let earnings = liq_pool.get_fee_earnings(&user1);
+    assert_close(earnings.get(0).unwrap(), earned_a, 2);
+    assert_close(earnings.get(1).unwrap(), earned_b, 2);
+
+    // earnings are kept after the position is closed
+    liq_pool.withdraw_all(&user1, &Vec::from_array(e, [0, 0]));
+    assert_eq!(liq_pool.get_fee_earnings(&user1), earnings);
+}
+
+#[test]
+fn test_shared_interface_client() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let user1 = setup.users[0].clone();
+    let caller = PoolCallerClient::new(e, &e.register_contract(None, PoolCaller {}));
+
+    let shares = caller.deposit(&liq_pool.address, &user1, &Vec::from_array(e, [100, 100]));
+    assert_eq!(shares, 100);
+    assert_eq!(setup.token_share.balance(&user1), 100);
+    assert_eq!(
+        caller.pool_state(&liq_pool.address),
+        (liq_pool.share_id(), Vec::from_array(e, [100, 100]))
+    );
+
+    let quote = liq_pool.estimate_swap(&0, &1, &10);
+    assert_eq!(
+        caller.swap(&liq_pool.address, &user1, &0, &1, &10, &quote),
+        quote
+    );
+    assert!(caller
+        .try_swap(&liq_pool.address, &user1, &0, &1, &10, &(quote + 1))
+        .is_err());
+
+    setup
+        .token_share
+        .approve(&user1, &liq_pool.address, &100, &99999);
+    // user owns the whole pool
+    let reserves = liq_pool.get_reserves();
+    assert_eq!(caller.withdraw(&liq_pool.address, &user1, &100), reserves);
+    assert_eq!(setup.token_share.balance(&user1), 0);
+}
+
+#[test]
+fn test_deposit_locked() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let user1 = setup.users[0].clone();
+    let error = |code| Ok(soroban_sdk::Error::from_contract_error(code));
+    let no_min = Vec::from_array(e, [0, 0]);
+    let now = e.ledger().timestamp();
+
+    let (_, shares) =
+        liq_pool.deposit_locked(&user1, &Vec::from_array(e, [100, 100]), &(now + 100));
+    assert_eq!(shares, 100);
+    assert_eq!(liq_pool.get_locked(&user1), (100, now + 100));
+    assert_eq!(
+        vec![e, e.events().all().last().unwrap()],
+        vec![
+            e,
+            (
+                liq_pool.address.clone(),
+                (Symbol::new(e, "lock_liquidity"), user1.clone()).into_val(e),
+                (100_u128, now + 100).into_val(e),
+            )
+        ]
+    );
+
+    // withdrawal blocked before expiry
+    setup
+        .token_share
+        .approve(&user1, &liq_pool.address, &1000, &99999);
+    assert_eq!(
+        liq_pool.try_withdraw(&user1, &1, &no_min).unwrap_err(),
+        error(220)
+    );
+    assert_eq!(
+        liq_pool.try_withdraw_all(&user1, &no_min).unwrap_err(),
+        error(220)
+    );
+
+    // shares above the locked amount can be withdrawn
+    liq_pool.deposit(&user1, &Vec::from_array(e, [50, 50]));
+    assert_eq!(
+        liq_pool.try_withdraw(&user1, &51, &no_min).unwrap_err(),
+        error(220)
+    );
+    liq_pool.withdraw(&user1, &50, &no_min);
+    assert_eq!(setup.token_share.balance(&user1), 100);
+
+    // lock can be extended, but not shortened or made larger than the balance
+    assert_eq!(
+        liq_pool
+            .try_lock_liquidity(&user1, &0, &(now + 50))
+            .unwrap_err(),
+        error(221)
+    );
+    assert_eq!(
+        liq_pool
+            .try_lock_liquidity(&user1, &1, &(now + 200))
+            .unwrap_err(),
+        error(221)
+    );
+    liq_pool.lock_liquidity(&user1, &0, &(now + 200));
+    assert_eq!(liq_pool.get_locked(&user1), (100, now + 200));
+
+    jump(e, 150);
+    assert_eq!(
+        liq_pool.try_withdraw(&user1, &1, &no_min).unwrap_err(),
+        error(220)
+    );
+
+    // liquidity lock is a share lock without boost, so it can't be combined with lock_shares
+    let lock = liq_pool.get_lock(&user1).unwrap();
+    assert_eq!((lock.amount, lock.boost), (100, 0));
+    assert!(lock.is_liquidity_lock);
+    assert_eq!(
+        liq_pool.try_lock_shares(&user1, &10, &100).unwrap_err(),
+        error(221)
+    );
+    assert_eq!(
+        setup
+            .token_share
+            .try_transfer(&user1, &setup.users[1], &1)
+            .unwrap_err(),
+        error(311)
+    );
+
+    // allowed after expiry
+    jump(e, 50);
+    assert_eq!(liq_pool.get_locked(&user1), (0, 0));
+    liq_pool.withdraw(&user1, &50, &no_min);
+    assert_eq!(setup.token_share.balance(&user1), 50);
+
+    // released lock frees the shares for transfer and boost locks
+    liq_pool.unlock(&user1);
+    assert!(liq_pool.get_lock(&user1).is_none());
+    setup.token_share.transfer(&user1, &setup.users[1], &10);
+    liq_pool.lock_shares(&user1, &40, &100);
+    assert_eq!(
+        liq_pool
+            .try_lock_liquidity(&user1, &0, &(now + 1000))
+            .unwrap_err(),
+        error(221)
+    );
+}
+
+#[test]
+fn test_share_accounting_mirror() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let user1 = setup.users[0].clone();
+    let user2 = setup.users[1].clone();
+    let share_token = Client::new(e, &liq_pool.share_id());
+    let get_pool_shares = || e.as_contract(&liq_pool.address, || get_balance_shares(e)) as i128;
+
+    let assert_mirror = || {
+        let pool_balance = share_token.balance(&liq_pool.address);
+        assert_eq!(
+            liq_pool.get_total_shares(),
+            share_token.balance(&user1) + share_token.balance(&user2) + pool_balance
+        );
+        assert_eq!(get_pool_shares(), pool_balance);
+    };
+
+    liq_pool.deposit(&user1, &Vec::from_array(e, [100, 100]));
+    liq_pool.deposit(&user2, &Vec::from_array(e, [50, 50]));
+    assert_eq!(liq_pool.get_total_shares(), 150);
+    assert_mirror();
+
+    share_token.approve(&user1, &liq_pool.address, &100, &99999);
+    liq_pool.withdraw(&user1, &40, &Vec::from_array(e, [0, 0]));
+    assert_eq!(liq_pool.get_total_shares(), 110);
+    assert_mirror();
+
+    // shares sent to the pool directly are only counted after sync
+    share_token.transfer(&user2, &liq_pool.address, &10);
+    assert_eq!(get_pool_shares(), 0);
+    liq_pool.sync();
+    assert_mirror();
+
+    // next withdrawal redeems the stray shares as well
+    assert_eq!(
+        liq_pool.withdraw(&user1, &10, &Vec::from_array(e, [0, 0])),
+        Vec::from_array(e, [20, 20])
+    );
+    assert_eq!(share_token.balance(&liq_pool.address), 0);
+    assert_eq!(liq_pool.get_total_shares(), 90);
+    assert_mirror();
+}
+
+#[test]
+fn test_transfer_admin() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let admin = setup.users[0].clone();
+    let new_admin = Address::generate(e);
+
+    assert_eq!(liq_pool.get_admin(), admin);
+    liq_pool.propose_admin(&admin, &new_admin);
+    assert_eq!(
+        vec![e, e.events().all().last().unwrap()],
+        vec![
+            e,
+            (
+                liq_pool.address.clone(),
+                (Symbol::new(e, "admin_proposed"),).into_val(e),
+                (admin.clone(), new_admin.clone()).into_val(e),
+            ),
+        ]
+    );
+    // current admin stays in charge until proposal is accepted
+    liq_pool.set_paused(&admin, &true);
+    assert!(liq_pool.try_set_paused(&new_admin, &false).is_err());
+    assert_eq!(liq_pool.get_admin(), admin);
+
+    liq_pool.accept_admin(&new_admin);
+    assert_eq!(
+        vec![e, e.events().all().last().unwrap()],
+        vec![
+            e,
+            (
+                liq_pool.address.clone(),
+                (Symbol::new(e, "admin_changed"),).into_val(e),
+                (admin.clone(), new_admin.clone()).into_val(e),
+            ),
+        ]
+    );
+    assert_eq!(liq_pool.get_admin(), new_admin);
+    assert!(liq_pool.try_set_paused(&admin, &false).is_err());
+    liq_pool.set_paused(&new_admin, &false);
+    assert!(!liq_pool.is_paused());
+
+    // proposal is consumed on acceptance
+    assert!(liq_pool.try_accept_admin(&new_admin).is_err());
+}
+
+#[test]
+fn test_get_admin() {
+    let e = Env::default();
+    let fixture = PoolTestFixture::setup(&e);
+    assert_eq!(fixture.liq_pool.get_admin(), fixture.admin);
+}
+
+#[test]
+fn test_upgrade() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let new_wasm_hash = install_token_wasm(e);
+
+    liq_pool.upgrade(&new_wasm_hash);
+    assert_eq!(
+        vec![e, e.events().all().last().unwrap()],
+        vec![
+            e,
+            (
+                liq_pool.address.clone(),
+                (symbol_short!("upgrade"),).into_val(e),
+                (new_wasm_hash, LiquidityPool::version()).into_val(e),
+            ),
+        ]
+    );
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #104)")]
+fn test_accept_admin_wrong_user() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let admin = setup.users[0].clone();
+
+    setup.liq_pool.propose_admin(&admin, &Address::generate(e));
+    setup.liq_pool.accept_admin(&setup.users[1]);
+}
+
+#[test]
+fn test_propose_admin_overwrite() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let admin = setup.users[0].clone();
+    let first_candidate = Address::generate(e);
+    let second_candidate = Address::generate(e);
+
+    liq_pool.propose_admin(&admin, &first_candidate);
+    liq_pool.propose_admin(&admin, &second_candidate);
+    assert!(liq_pool.try_accept_admin(&first_candidate).is_err());
+
+    liq_pool.accept_admin(&second_candidate);
+    liq_pool.set_paused(&second_candidate, &true);
+    assert!(liq_pool.is_paused());
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #103)")]
+fn test_cancel_admin_proposal() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let admin = setup.users[0].clone();
+    let new_admin = Address::generate(e);
+
+    setup.liq_pool.propose_admin(&admin, &new_admin);
+    setup.liq_pool.cancel_admin_proposal(&admin);
+    setup.liq_pool.accept_admin(&new_admin);
+}
+
+#[test]
+fn test_reward_opt_out() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let user1 = setup.users[0].clone();
+    let user2 = setup.users[1].clone();
+    let reward_tps = TestConfig::default().reward_tps;
+
+    liq_pool.deposit(&user1, &Vec::from_array(e, [100, 100]));
+    liq_pool.deposit(&user2, &Vec::from_array(e, [100, 100]));
+    jump(e, 10);
+
+    liq_pool.set_reward_opt_out(&user2, &true);
+    let user2_reward = liq_pool.get_user_reward(&user2);
+    assert_eq!(user2_reward, reward_tps * 10 / 2);
+    assert!(liq_pool.get_rewards_info_v2(&user2).opted_out);
+    jump(e, 10);
+
+    // opted out user doesn't accrue anymore, the other one receives the whole reward
+    assert_eq!(liq_pool.get_user_reward(&user2), user2_reward);
+    assert_eq!(liq_pool.claim(&user2), user2_reward);
+    assert_eq!(
+        liq_pool.claim(&user1),
+        reward_tps * 10 / 2 + reward_tps * 10
+    );
+
+    // accrual continues after opting back in
+    liq_pool.set_reward_opt_out(&user2, &false);
+    assert!(!liq_pool.get_rewards_info_v2(&user2).opted_out);
+    jump(e, 10);
+    assert_eq!(liq_pool.get_user_reward(&user1), reward_tps * 10 / 2);
+    assert_eq!(liq_pool.get_user_reward(&user2), reward_tps * 10 / 2);
+}
+
+#[test]
+fn test_emergency_withdraw() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let admin = setup.users[0].clone();
+    let user1 = setup.users[1].clone();
+
+    liq_pool.deposit(&user1, &Vec::from_array(e, [100, 100]));
+    assert!(liq_pool.try_emergency_withdraw(&user1, &50).is_err());
+
+    // broken rewards config makes rewards calculation overflow.
+    // it's unfundable, so written directly bypassing set_rewards_config
+    e.as_contract(&liq_pool.address, || {
+        get_rewards_manager(e)
+            .storage()
+            .set_pool_reward_config(&PoolRewardConfig {
+                tps: u128::MAX,
+                expired_at: e.ledger().timestamp() + 100,
+            });
+    });
+    jump(e, 10);
+    assert!(liq_pool
+        .try_withdraw(&user1, &50, &Vec::from_array(e, [0, 0]))
+        .is_err());
+
+    liq_pool.set_emergency_mode(&admin, &true);
+    assert!(liq_pool.get_emergency_mode());
+    assert_eq!(
+        liq_pool.emergency_withdraw(&user1, &50),
+        Vec::from_array(e, [50, 50])
+    );
+    assert_eq!(setup.token_share.balance(&user1), 50);
+    assert_eq!(setup.token1.balance(&user1), 950);
+    assert_eq!(setup.token2.balance(&user1), 950);
+    assert_eq!(liq_pool.get_reserves(), Vec::from_array(e, [50, 50]));
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #209)")]
+fn test_emergency_withdraw_disabled() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let user1 = setup.users[1].clone();
+
+    setup
+        .liq_pool
+        .deposit(&user1, &Vec::from_array(e, [100, 100]));
+    setup.liq_pool.emergency_withdraw(&user1, &50);
+}
+
+#[test]
+fn test_initialize_share_token_failure() {
+    let e = Env::default();
+    e.mock_all_auths();
+    e.budget().reset_unlimited();
+
+    let admin = Address::generate(&e);
+    let mut token1 = create_token_contract(&e, &admin);
+    let mut token2 = create_token_contract(&e, &admin);
+    if &token2.address < &token1.address {
+        std::mem::swap(&mut token1, &mut token2);
+    }
+    let tokens = Vec::from_array(&e, [token1.address.clone(), token2.address.clone()]);
+    let token_reward = create_token_contract(&e, &admin);
+    let plane = create_plane_contract(&e);
+
+    let liq_pool =
+        LiquidityPoolClient::new(&e, &e.register_contract(None, crate::LiquidityPool {}));
+    // share token wasm is not uploaded, so share token deployment fails
+    let missing_wasm_hash = BytesN::from_array(&e, &[0; 32]);
+    assert!(liq_pool
+        .try_initialize_all(
+            &admin,
+            &missing_wasm_hash,
+            &tokens,
+            &30,
+            &token_reward.address,
+            &liq_pool.address,
+            &plane.address,
+        )
+        .is_err());
+    assert!(!e.as_contract(&liq_pool.address, || AccessControl::new(&e).has_admin()));
+
+    liq_pool.initialize_all(
+        &admin,
+        &install_token_wasm(&e),
+        &tokens,
+        &30,
+        &token_reward.address,
+        &liq_pool.address,
+        &plane.address,
+    );
+    assert_eq!(liq_pool.get_tokens(), tokens);
+    assert_eq!(liq_pool.get_reserves(), Vec::from_array(&e, [0, 0]));
+}
+
+#[test]
+fn test_initialize_invalid_config() {
+    let e = Env::default();
+    e.mock_all_auths();
+    e.budget().reset_unlimited();
+
+    let admin = Address::generate(&e);
+    let mut token1 = create_token_contract(&e, &admin);
+    let mut token2 = create_token_contract(&e, &admin);
+    if &token2.address < &token1.address {
+        std::mem::swap(&mut token1, &mut token2);
+    }
+    let token_wasm_hash = install_token_wasm(&e);
+    let error = |code| Ok(soroban_sdk::Error::from_contract_error(code));
+
+    let liq_pool =
+        LiquidityPoolClient::new(&e, &e.register_contract(None, crate::LiquidityPool {}));
+    liq_pool.set_pools_plane(&create_plane_contract(&e).address);
+    let same_tokens = Vec::from_array(&e, [token1.address.clone(), token1.address.clone()]);
+    assert_eq!(
+        liq_pool
+            .try_initialize(&admin, &token_wasm_hash, &same_tokens, &30)
+            .unwrap_err(),
+        error(233)
+    );
+    let unsorted_tokens = Vec::from_array(&e, [token2.address.clone(), token1.address.clone()]);
+    assert_eq!(
+        liq_pool
+            .try_initialize(&admin, &token_wasm_hash, &unsorted_tokens, &30)
+            .unwrap_err(),
+        error(234)
+    );
+
+    // wasm deploys, but doesn't implement the share token interface
+    let plane_wasm_hash = e.deployer().upload_contract_wasm(pool_plane::WASM);
+    let tokens = Vec::from_array(&e, [token1.address.clone(), token2.address.clone()]);
+    assert_eq!(
+        liq_pool
+            .try_initialize(&admin, &plane_wasm_hash, &tokens, &30)
+            .unwrap_err(),
+        error(236)
+    );
+
+    liq_pool.initialize(&admin, &token_wasm_hash, &tokens, &30);
+    let share_id = liq_pool.share_id();
+    let token_reward = create_token_contract(&e, &admin);
+    assert_eq!(
+        liq_pool
+            .try_initialize_rewards_config(&share_id, &liq_pool.address)
+            .unwrap_err(),
+        error(235)
+    );
+    assert_eq!(
+        liq_pool
+            .try_initialize_rewards_config(&token_reward.address, &share_id)
+            .unwrap_err(),
+        error(235)
+    );
+    liq_pool.initialize_rewards_config(&token_reward.address, &liq_pool.address);
+}
+
+#[test]
+fn test_share_token_metadata() {
+    let e = Env::default();
+    e.mock_all_auths();
+    e.budget().reset_unlimited();
+
+    let admin = Address::generate(&e);
+    let token_wasm_hash = install_token_wasm(&e);
+    let init_pool =
+        |symbol_a: &str, symbol_b: &str, share_name: Option<&str>, share_symbol: Option<&str>| {
+            let mut token1 = create_token_contract_with_symbol(&e, &admin, symbol_a);
+            let mut token2 = create_token_contract_with_symbol(&e, &admin, symbol_b);
+            if &token2.address < &token1.address {
+                std::mem::swap(&mut token1, &mut token2);
+            }
+            let tokens = Vec::from_array(&e, [token1.address.clone(), token2.address.clone()]);
+            let liq_pool =
+                LiquidityPoolClient::new(&e, &e.register_contract(None, crate::LiquidityPool {}));
+            liq_pool.set_pools_plane(&create_plane_contract(&e).address);
+            let result = liq_pool.try_initialize_with_share_metadata(
+                &admin,
+                &token_wasm_hash,
+                &tokens,
+                &30,
+                &share_name.map(|name| String::from_str(&e, name)),
+                &share_symbol.map(|symbol| String::from_str(&e, symbol)),
+            );
+            if let Err(error) = result {
+                return Err(error);
+            }
+            let share_token = Client::new(&e, &liq_pool.share_id());
+            // tokens are sorted by address, so pair order is the order of the symbols in the pool
+            let symbols = (token1.symbol(), token2.symbol());
+            Ok((symbols, share_token.name(), share_token.symbol()))
+        };
+    let string = |value: &str| String::from_str(&e, value);
+
+    let ((symbol_a, _), name, symbol) = init_pool("XLM", "USDC", None, None).unwrap();
+    let pair = if symbol_a == string("XLM") {
+        "XLM-USDC"
+    } else {
+        "USDC-XLM"
+    };
+    assert_eq!(symbol, string(pair));
+    assert_eq!(name, string(&std::format!("{} LP", pair)));
+
+    // long symbols are truncated to fit the share token symbol limit
+    let ((symbol_a, _), name, symbol) = init_pool("LONGTOKEN", "ANOTHERTOKEN", None, None).unwrap();
+    let pair = if symbol_a == string("LONGTOKEN") {
+        "LONGT-ANOTH"
+    } else {
+        "ANOTH-LONGT"
+    };
+    assert_eq!(symbol, string(pair));
+    assert_eq!(name, string(&std::format!("{} LP", pair)));
+
+    // symbols which can't be used fall back to the generic metadata
+    let long_symbol = "X".repeat(40);
+    for (symbol_a, symbol_b) in [("XLM", "U$DC"), ("XLM", ""), ("XLM", long_symbol.as_str())] {
+        let (_, name, symbol) = init_pool(symbol_a, symbol_b, None, None).unwrap();
+        assert_eq!(name, string("Pool Share Token"));
+        assert_eq!(symbol, string("POOL"));
+    }
+
+    // caller override
+    let (_, name, symbol) =
+        init_pool("XLM", "USDC", Some("Stellar Dollar Pool"), Some("XLMUSD")).unwrap();
+    assert_eq!(name, string("Stellar Dollar Pool"));
+    assert_eq!(symbol, string("XLMUSD"));
+    let (_, name, symbol) = init_pool("XLM", "USDC", None, Some("XLMUSD")).unwrap();
+    assert_eq!(symbol, string("XLMUSD"));
+    assert!(name == string("XLM-USDC LP") || name == string("USDC-XLM LP"));
+    assert_eq!(
+        init_pool("XLM", "USDC", None, Some("TOOLONGSYMBOL")).unwrap_err(),
+        Ok(soroban_sdk::Error::from_contract_error(237))
+    );
+    assert_eq!(
+        init_pool("XLM", "USDC", Some(""), None).unwrap_err(),
+        Ok(soroban_sdk::Error::from_contract_error(237))
+    );
+}
+
+#[test]
+fn test_get_contracts() {
+    let e = Env::default();
+    e.mock_all_auths();
+    e.budget().reset_unlimited();
+
+    let admin = Address::generate(&e);
+    let mut token1 = create_token_contract(&e, &admin);
+    let mut token2 = create_token_contract(&e, &admin);
+    if &token2.address < &token1.address {
+        std::mem::swap(&mut token1, &mut token2);
+    }
+    let tokens = Vec::from_array(&e, [token1.address.clone(), token2.address.clone()]);
+    let token_reward = create_token_contract(&e, &admin);
+    let plane = create_plane_contract(&e);
+
+    let liq_pool =
+        LiquidityPoolClient::new(&e, &e.register_contract(None, crate::LiquidityPool {}));
+    let not_initialized = Ok(soroban_sdk::Error::from_contract_error(232));
+    assert_eq!(liq_pool.try_get_contracts().unwrap_err(), not_initialized);
+    assert_eq!(
+        liq_pool.try_get_reward_storage().unwrap_err(),
+        not_initialized
+    );
+
+    liq_pool.initialize_all(
+        &admin,
+        &install_token_wasm(&e),
+        &tokens,
+        &30,
+        &token_reward.address,
+        &liq_pool.address,
+        &plane.address,
+    );
+    assert_eq!(
+        liq_pool.get_contracts(),
+        PoolContracts {
+            token_a: token1.address.clone(),
+            token_b: token2.address.clone(),
+            share_token: liq_pool.share_id(),
+            reward_token: token_reward.address.clone(),
+        }
+    );
+    assert_eq!(liq_pool.get_reward_storage(), liq_pool.address);
+}
+
+#[test]
+fn test_not_initialized() {
+    let e = Env::default();
+    e.mock_all_auths();
+    e.budget().reset_unlimited();
+
+    let admin = Address::generate(&e);
+    let user = Address::generate(&e);
+    let mut token1 = create_token_contract(&e, &admin);
+    let mut token2 = create_token_contract(&e, &admin);
+    if &token2.address < &token1.address {
+        std::mem::swap(&mut token1, &mut token2);
+    }
+    let tokens = Vec::from_array(&e, [token1.address.clone(), token2.address.clone()]);
+    let amounts = Vec::from_array(&e, [100_u128, 100_u128]);
+
+    let liq_pool =
+        LiquidityPoolClient::new(&e, &e.register_contract(None, crate::LiquidityPool {}));
+    let not_initialized = Ok(soroban_sdk::Error::from_contract_error(232));
+    for result in [
+        liq_pool.try_share_id().unwrap_err(),
+        liq_pool.try_get_reserves().unwrap_err(),
+        liq_pool.try_get_tokens().unwrap_err(),
+        liq_pool.try_get_total_shares().unwrap_err(),
+        liq_pool.try_get_price().unwrap_err(),
+        liq_pool.try_estimate_swap(&0, &1, &100).unwrap_err(),
+        liq_pool.try_deposit(&user, &amounts).unwrap_err(),
+        liq_pool.try_swap(&user, &0, &1, &100, &0).unwrap_err(),
+        liq_pool.try_withdraw(&user, &100, &amounts).unwrap_err(),
+        liq_pool.try_get_position(&user).unwrap_err(),
+        liq_pool.try_storage_version().unwrap_err(),
+        liq_pool.try_migrate(&admin).unwrap_err(),
+        liq_pool.try_kill_me(&admin).unwrap_err(),
+        liq_pool.try_get_is_killed().unwrap_err(),
+        liq_pool.try_get_risk_params().unwrap_err(),
+        liq_pool
+            .try_set_rewards_config(&admin, &100, &1)
+            .unwrap_err(),
+        liq_pool.try_get_rewards_info(&user).unwrap_err(),
+        liq_pool.try_get_user_reward(&user).unwrap_err(),
+        liq_pool.try_claim(&user).unwrap_err(),
+        liq_pool.try_claim_migrated_shares(&user).unwrap_err(),
+        liq_pool.try_get_pools_plane().unwrap_err(),
+    ] {
+        assert_eq!(result, not_initialized);
+    }
+
+    // pool initialized without rewards config: pool entrypoints work, rewards ones don't
+    liq_pool.set_pools_plane(&create_plane_contract(&e).address);
+    liq_pool.initialize(&admin, &install_token_wasm(&e), &tokens, &30);
+    assert_eq!(liq_pool.get_reserves(), Vec::from_array(&e, [0, 0]));
+    assert!(!liq_pool.get_is_killed());
+    for result in [
+        liq_pool
+            .try_set_rewards_config(&admin, &100, &1)
+            .unwrap_err(),
+        liq_pool.try_get_user_reward(&user).unwrap_err(),
+        liq_pool.try_claim(&user).unwrap_err(),
+    ] {
+        assert_eq!(result, not_initialized);
+    }
+
+    let token_reward = create_token_contract(&e, &admin);
+    liq_pool.initialize_rewards_config(&token_reward.address, &liq_pool.address);
+    assert_eq!(liq_pool.get_user_reward(&user), 0);
+}
+
+#[test]
+fn test_derive_share_address() {
+    let e = Env::default();
+    e.mock_all_auths();
+    e.budget().reset_unlimited();
+
+    let admin = Address::generate(&e);
+    let mut token1 = create_token_contract(&e, &admin);
+    let mut token2 = create_token_contract(&e, &admin);
+    if &token2.address < &token1.address {
+        std::mem::swap(&mut token1, &mut token2);
+    }
+    let tokens = Vec::from_array(&e, [token1.address.clone(), token2.address.clone()]);
+
+    // share token address is known before the pool is initialized
+    let liq_pool =
+        LiquidityPoolClient::new(&e, &e.register_contract(None, crate::LiquidityPool {}));
+    let predicted = liq_pool.derive_share_address(&token1.address, &token2.address);
+    liq_pool.set_pools_plane(&create_plane_contract(&e).address);
+    liq_pool.initialize(&admin, &install_token_wasm(&e), &tokens, &30);
+    assert_eq!(liq_pool.share_id(), predicted);
+    assert!(liq_pool.verify_share_token(&predicted));
+
+    // other tokens give other address, foreign share token isn't verified
+    let token3 = create_token_contract(&e, &admin);
+    let mismatched = liq_pool.derive_share_address(&token1.address, &token3.address);
+    assert_ne!(mismatched, predicted);
+    assert_ne!(
+        liq_pool.derive_share_address(&token2.address, &token1.address),
+        predicted
+    );
+    assert!(!liq_pool.verify_share_token(&mismatched));
+    assert!(!liq_pool.verify_share_token(&token1.address));
+    let other_pool = create_liqpool_contract(
+        &e,
+        &admin,
+        &install_token_wasm(&e),
+        &tokens,
+        &token3.address,
+        30,
+        &create_plane_contract(&e).address,
+    );
+    assert!(!liq_pool.verify_share_token(&other_pool.share_id()));
+}
+
+// Pool with the share token deployed beforehand. Tokens are sorted
+fn create_external_share_token_pool<'a>(
+    e: &Env,
+    admin: &Address,
+) -> (LiquidityPoolClient<'a>, Vec<Address>) {
+    let mut token1 = create_token_contract(e, admin);
+    let mut token2 = create_token_contract(e, admin);
+    if &token2.address < &token1.address {
+        std::mem::swap(&mut token1, &mut token2);
+    }
+    let tokens = Vec::from_array(e, [token1.address.clone(), token2.address.clone()]);
+    let liq_pool = LiquidityPoolClient::new(e, &e.register_contract(None, crate::LiquidityPool {}));
+    liq_pool.set_pools_plane(&create_plane_contract(e).address);
+    (liq_pool, tokens)
+}
+
+fn create_external_share_token<'a>(e: &Env, admin: &Address) -> Client<'a> {
+    let share_token = Client::new(
+        e,
+        &e.register_contract_wasm(None, token_share::token_contract::WASM),
+    );
+    share_token.initialize(
+        admin,
+        &7,
+        &String::from_str(e, "Compliant LP"),
+        &String::from_str(e, "CLP"),
+        &false,
+    );
+    share_token
+}
+
+#[test]
+fn test_initialize_with_share_token() {
+    let e = Env::default();
+    e.mock_all_auths();
+    e.budget().reset_unlimited();
+
+    let admin = Address::generate(&e);
+    let user = Address::generate(&e);
+    let (liq_pool, tokens) = create_external_share_token_pool(&e, &admin);
+    let share_token = create_external_share_token(&e, &liq_pool.address);
+    let token_reward = create_token_contract(&e, &admin);
+
+    liq_pool.initialize_with_share_token(&admin, &share_token.address, &tokens, &30);
+    liq_pool.initialize_rewards_config(&token_reward.address, &liq_pool.address);
+    assert_eq!(liq_pool.share_id(), share_token.address);
+    assert_eq!(liq_pool.get_admin(), admin);
+    assert_eq!(share_token.total_supply(), 0);
+
+    // shares are minted and burned on the external token the same way
+    let token1 = Client::new(&e, &tokens.get(0).unwrap());
+    let token2 = Client::new(&e, &tokens.get(1).unwrap());
+    for token in [&token1, &token2] {
+        token.mint(&user, &1000);
+        token.approve(&user, &liq_pool.address, &1000, &99999);
+    }
+    liq_pool.deposit(&user, &Vec::from_array(&e, [100, 100]));
+    assert_eq!(share_token.balance(&user), 100);
+    assert_eq!(share_token.total_supply(), 100);
+    assert_eq!(liq_pool.get_total_shares(), 100);
+
+    share_token.approve(&user, &liq_pool.address, &40, &99999);
+    liq_pool.withdraw(&user, &40, &Vec::from_array(&e, [40, 40]));
+    assert_eq!(share_token.balance(&user), 60);
+    assert_eq!(share_token.total_supply(), 60);
+    assert_eq!(liq_pool.get_total_shares(), 60);
+    assert_eq!(token1.balance(&user), 940);
+    assert_eq!(token2.balance(&user), 940);
+}
+
+#[test]
+fn test_initialize_with_share_token_rejected() {
+    let e = Env::default();
+    e.mock_all_auths();
+    e.budget().reset_unlimited();
+
+    let admin = Address::generate(&e);
+    let (liq_pool, tokens) = create_external_share_token_pool(&e, &admin);
+
+    // share token already has holders, pool can't track them
+    let minted_share_token = create_external_share_token(&e, &liq_pool.address);
+    minted_share_token.mint(&admin, &100);
+    assert_eq!(
+        liq_pool
+            .try_initialize_with_share_token(&admin, &minted_share_token.address, &tokens, &30)
+            .unwrap_err(),
+        Ok(soroban_sdk::Error::from_contract_error(246))
+    );
+
+    // pool lacks mint rights. auths are enforced, so the token admin can't be mocked
+    let foreign_share_token = create_external_share_token(&e, &admin);
+    e.set_auths(&[]);
+    assert_eq!(
+        liq_pool
+            .try_initialize_with_share_token(&admin, &foreign_share_token.address, &tokens, &30)
+            .unwrap_err(),
+        Ok(soroban_sdk::Error::from_contract_error(246))
+    );
+    assert!(!e.as_contract(&liq_pool.address, || AccessControl::new(&e).has_admin()));
+}
+
+#[test]
+fn test_rescue_token() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let admin = setup.users[0].clone();
+    let receiver = Address::generate(e);
+
+    let token3 = create_token_contract(e, &admin);
+    token3.mint(&setup.liq_pool.address, &100);
+
+    setup
+        .liq_pool
+        .rescue_token(&admin, &token3.address, &receiver, &60);
+    assert_eq!(token3.balance(&receiver), 60);
+    assert_eq!(token3.balance(&setup.liq_pool.address), 40);
+}
+
+#[test]
+fn test_rescue_protected_token() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let admin = setup.users[0].clone();
+    let receiver = Address::generate(e);
+
+    setup
+        .liq_pool
+        .deposit(&setup.users[1], &Vec::from_array(e, [100, 100]));
+    for token in [
+        setup.token1.address.clone(),
+        setup.token2.address.clone(),
+        setup.token_share.address.clone(),
+        setup.token_reward.address.clone(),
+    ] {
+        assert!(setup
+            .liq_pool
+            .try_rescue_token(&admin, &token, &receiver, &1)
+            .is_err());
+    }
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #210)")]
+fn test_rescue_pool_token() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let admin = setup.users[0].clone();
+
+    setup
+        .liq_pool
+        .rescue_token(&admin, &setup.token1.address, &Address::generate(e), &1);
+}
+
+fn assert_risk_param_event(setup: &Setup, name: Symbol, value: i128) {
+    let e = &setup.env;
+    assert_eq!(
+        vec![e, e.events().all().last().unwrap()],
+        vec![
+            e,
+            (
+                setup.liq_pool.address.clone(),
+                (Symbol::new(e, "risk_param"), name).into_val(e),
+                value.into_val(e),
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_risk_params() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let admin = setup.users[0].clone();
+
+    let default_params = Map::from_array(
+        e,
+        [
+            (symbol_short!("killed"), 0),
+            (symbol_short!("paused"), 0),
+            (symbol_short!("emergency"), 0),
+            (Symbol::new(e, "permissioned"), 0),
+            (Symbol::new(e, "price_impact"), 0),
+            (Symbol::new(e, "min_reserve"), 0),
+            (Symbol::new(e, "reward_fee"), 0),
+            (Symbol::new(e, "share_transferable"), 1),
+            (Symbol::new(e, "bootstrapper"), 0),
+            (Symbol::new(e, "init_price_min"), 0),
+            (Symbol::new(e, "init_price_max"), 0),
+        ],
+    );
+    // pool info carries the same guardrails
+    let info_risk_params = || {
+        Map::<Symbol, i128>::from_val(
+            e,
+            &liq_pool
+                .get_info()
+                .get(Symbol::new(e, "risk_params"))
+                .unwrap(),
+        )
+    };
+    assert_eq!(info_risk_params(), default_params);
+    let risk_params = |changed: &[(Symbol, i128)]| {
+        let mut params = default_params.clone();
+        for (name, value) in changed {
+            params.set(name.clone(), *value);
+        }
+        params
+    };
+    assert_eq!(liq_pool.get_risk_params(), default_params);
+
+    liq_pool.kill_me(&admin);
+    assert_risk_param_event(&setup, symbol_short!("killed"), 1);
+    assert_eq!(
+        liq_pool.get_risk_params(),
+        risk_params(&[(symbol_short!("killed"), 1)])
+    );
+    assert_eq!(info_risk_params(), liq_pool.get_risk_params());
+    liq_pool.unkill_me(&admin);
+    assert_risk_param_event(&setup, symbol_short!("killed"), 0);
+    assert_eq!(liq_pool.get_risk_params(), default_params);
+
+    liq_pool.set_paused(&admin, &true);
+    assert_risk_param_event(&setup, symbol_short!("paused"), 1);
+    assert_eq!(
+        liq_pool.get_risk_params(),
+        risk_params(&[(symbol_short!("paused"), 1)])
+    );
+    liq_pool.set_paused(&admin, &false);
+    assert_risk_param_event(&setup, symbol_short!("paused"), 0);
+    assert_eq!(liq_pool.get_risk_params(), default_params);
+
+    liq_pool.set_emergency_mode(&admin, &true);
+    assert_risk_param_event(&setup, symbol_short!("emergency"), 1);
+    assert_eq!(
+        liq_pool.get_risk_params(),
+        risk_params(&[(symbol_short!("emergency"), 1)])
+    );
+    liq_pool.set_emergency_mode(&admin, &false);
+    assert_risk_param_event(&setup, symbol_short!("emergency"), 0);
+    assert_eq!(liq_pool.get_risk_params(), default_params);
+
+    liq_pool.set_permissioned(&admin, &true);
+    assert_risk_param_event(&setup, Symbol::new(e, "permissioned"), 1);
+    assert_eq!(
+        liq_pool.get_risk_params(),
+        risk_params(&[(Symbol::new(e, "permissioned"), 1)])
+    );
+    liq_pool.set_permissioned(&admin, &false);
+    assert_risk_param_event(&setup, Symbol::new(e, "permissioned"), 0);
+    assert_eq!(liq_pool.get_risk_params(), default_params);
+
+    liq_pool.set_max_price_impact(&admin, &500);
+    assert_risk_param_event(&setup, Symbol::new(e, "price_impact"), 500);
+    assert_eq!(
+        liq_pool.get_risk_params(),
+        risk_params(&[(Symbol::new(e, "price_impact"), 500)])
+    );
+    liq_pool.set_max_price_impact(&admin, &0);
+    assert_risk_param_event(&setup, Symbol::new(e, "price_impact"), 0);
+    assert_eq!(liq_pool.get_risk_params(), default_params);
+
+    liq_pool.set_min_reserve(&admin, &1000);
+    assert_risk_param_event(&setup, Symbol::new(e, "min_reserve"), 1000);
+    assert_eq!(
+        liq_pool.get_risk_params(),
+        risk_params(&[(Symbol::new(e, "min_reserve"), 1000)])
+    );
+    liq_pool.set_min_reserve(&admin, &0);
+    assert_risk_param_event(&setup, Symbol::new(e, "min_reserve"), 0);
+    assert_eq!(liq_pool.get_risk_params(), default_params);
+
+    liq_pool.set_reward_fee(&admin, &5000);
+    assert_risk_param_event(&setup, Symbol::new(e, "reward_fee"), 5000);
+    assert_eq!(
+        liq_pool.get_risk_params(),
+        risk_params(&[(Symbol::new(e, "reward_fee"), 5000)])
+    );
+    liq_pool.set_reward_fee(&admin, &0);
+    assert_risk_param_event(&setup, Symbol::new(e, "reward_fee"), 0);
+    assert_eq!(liq_pool.get_risk_params(), default_params);
+
+    liq_pool.set_share_transferable(&admin, &false);
+    assert_risk_param_event(&setup, Symbol::new(e, "share_transferable"), 0);
+    assert_eq!(
+        liq_pool.get_risk_params(),
+        risk_params(&[(Symbol::new(e, "share_transferable"), 0)])
+    );
+    liq_pool.set_share_transferable(&admin, &true);
+    assert_risk_param_event(&setup, Symbol::new(e, "share_transferable"), 1);
+    assert_eq!(liq_pool.get_risk_params(), default_params);
+
+    liq_pool.set_bootstrapper(&admin, &setup.users[1]);
+    assert_eq!(
+        liq_pool.get_risk_params(),
+        risk_params(&[(Symbol::new(e, "bootstrapper"), 1)])
+    );
+    liq_pool.remove_bootstrapper(&admin);
+    assert_eq!(liq_pool.get_risk_params(), default_params);
+
+    liq_pool.set_initial_price_range(&admin, &100, &300);
+    assert_eq!(
+        liq_pool.get_risk_params(),
+        risk_params(&[
+            (Symbol::new(e, "init_price_min"), 100),
+            (Symbol::new(e, "init_price_max"), 300),
+        ])
+    );
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #211)")]
+fn test_deposit_insufficient_balance_a() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let user1 = setup.users[1].clone();
+
+    setup
+        .token1
+        .approve(&user1, &setup.liq_pool.address, &2000, &99999);
+    setup
+        .token2
+        .approve(&user1, &setup.liq_pool.address, &2000, &99999);
+    setup.token2.mint(&user1, &1000);
+    setup
+        .liq_pool
+        .deposit(&user1, &Vec::from_array(e, [2000, 2000]));
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #212)")]
+fn test_deposit_insufficient_balance_b() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let user1 = setup.users[1].clone();
+
+    setup
+        .token1
+        .approve(&user1, &setup.liq_pool.address, &2000, &99999);
+    setup
+        .token2
+        .approve(&user1, &setup.liq_pool.address, &2000, &99999);
+    setup.token1.mint(&user1, &1000);
+    setup
+        .liq_pool
+        .deposit(&user1, &Vec::from_array(e, [2000, 2000]));
+}
+
+#[test]
+fn test_deposit_no_allowance_left() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let user1 = setup.users[0].clone();
+    let user2 = setup.users[1].clone();
+
+    // no allowance needed at all
+    setup.token1.approve(&user1, &liq_pool.address, &0, &99999);
+    setup.token2.approve(&user1, &liq_pool.address, &0, &99999);
+    liq_pool.deposit(&user1, &Vec::from_array(e, [100, 400]));
+    assert_eq!(setup.token1.allowance(&user1, &liq_pool.address), 0);
+    assert_eq!(setup.token2.allowance(&user1, &liq_pool.address), 0);
+
+    // desired amount of token1 is clamped to the pool ratio, exactly computed amounts are pulled
+    setup.token1.approve(&user2, &liq_pool.address, &0, &99999);
+    setup.token2.approve(&user2, &liq_pool.address, &0, &99999);
+    let (amounts, _) = liq_pool.deposit(&user2, &Vec::from_array(e, [100, 100]));
+    assert_eq!(amounts, Vec::from_array(e, [25, 100]));
+    assert_eq!(setup.token1.balance(&user2), 1000 - 25);
+    assert_eq!(setup.token2.balance(&user2), 1000 - 100);
+    assert_eq!(setup.token1.allowance(&user2, &liq_pool.address), 0);
+    assert_eq!(setup.token2.allowance(&user2, &liq_pool.address), 0);
+}
+
+#[test]
+fn test_fee_on_transfer_token() {
+    let e = Env::default();
+    e.mock_all_auths();
+    e.budget().reset_unlimited();
+
+    let users = Setup::generate_random_users(&e, 2);
+    let admin = users[0].clone();
+    let user1 = users[1].clone();
+    let fee_token =
+        FeeOnTransferTokenClient::new(&e, &e.register_contract(None, FeeOnTransferToken));
+    let token = create_token_contract(&e, &admin);
+    let token_reward = create_token_contract(&e, &admin);
+    let (fee_idx, idx) = match fee_token.address < token.address {
+        true => (0, 1),
+        false => (1, 0),
+    };
+    let ordered = |fee_amount: u128, amount: u128| match fee_idx {
+        0 => Vec::from_array(&e, [fee_amount, amount]),
+        _ => Vec::from_array(&e, [amount, fee_amount]),
+    };
+    let tokens = match fee_idx {
+        0 => Vec::from_array(&e, [fee_token.address.clone(), token.address.clone()]),
+        _ => Vec::from_array(&e, [token.address.clone(), fee_token.address.clone()]),
+    };
+    let plane = create_plane_contract(&e);
+    let liq_pool = create_liqpool_contract(
+        &e,
+        &admin,
+        &install_token_wasm(&e),
+        &tokens,
+        &token_reward.address,
+        30,
+        &plane.address,
+    );
+    let check_reserves = || {
+        let reserves = liq_pool.get_reserves();
+        assert_eq!(
+            reserves.get(fee_idx).unwrap(),
+            fee_token.balance(&liq_pool.address) as u128
+        );
+        assert_eq!(
+            reserves.get(idx).unwrap(),
+            token.balance(&liq_pool.address) as u128
+        );
+        reserves
+    };
+
+    fee_token.mint(&user1, &1_000_000);
+    token.mint(&user1, &1_000_000);
+    fee_token.approve(&user1, &liq_pool.address, &1_000_000, &99999);
+    token.approve(&user1, &liq_pool.address, &1_000_000, &99999);
+
+    // only received amount counts
+    let (amounts, _) = liq_pool.deposit(&user1, &ordered(100_000, 100_000));
+    assert_eq!(amounts, ordered(99_000, 100_000));
+    assert_eq!(check_reserves(), ordered(99_000, 100_000));
+
+    // fee token sold: estimation is made before the transfer fee
+    let estimate = liq_pool.estimate_swap(&fee_idx, &idx, &10_000);
+    let expected_out = liq_pool.estimate_swap(&fee_idx, &idx, &9_900);
+    assert!(liq_pool
+        .try_swap(&user1, &fee_idx, &idx, &10_000, &estimate)
+        .is_err());
+    assert_eq!(
+        liq_pool.swap(&user1, &fee_idx, &idx, &10_000, &expected_out),
+        expected_out
+    );
+    assert_eq!(check_reserves(), ordered(108_900, 100_000 - expected_out));
+
+    // fee token bought: pool pays out the full amount, user receives it less the fee
+    let fee_balance = fee_token.balance(&user1) as u128;
+    let estimate = liq_pool.estimate_swap(&idx, &fee_idx, &10_000);
+    let out = liq_pool.swap(&user1, &idx, &fee_idx, &10_000, &estimate);
+    assert_eq!(out, estimate);
+    assert_eq!(
+        fee_token.balance(&user1) as u128,
+        fee_balance + out - out / 100
+    );
+    assert_eq!(
+        check_reserves(),
+        ordered(108_900 - out, 110_000 - expected_out)
+    );
+
+    // withdraw drains the pool completely
+    let share_token = Client::new(&e, &liq_pool.share_id());
+    let shares = share_token.balance(&user1);
+    share_token.approve(&user1, &liq_pool.address, &shares, &99999);
+    let fee_balance = fee_token.balance(&user1) as u128;
+    let amounts = liq_pool.withdraw(&user1, &(shares as u128), &ordered(0, 0));
+    assert_eq!(amounts, ordered(108_900 - out, 110_000 - expected_out));
+    let fee_out = amounts.get(fee_idx).unwrap();
+    assert_eq!(
+        fee_token.balance(&user1) as u128,
+        fee_balance + fee_out - fee_out / 100
+    );
+    assert_eq!(check_reserves(), ordered(0, 0));
+}
+
+#[test]
+fn test_claim_to() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let user1 = setup.users[0].clone();
+    let user2 = setup.users[1].clone();
+    let recipient = Address::generate(e);
+    let reward_tps = TestConfig::default().reward_tps;
+
+    liq_pool.deposit(&user1, &Vec::from_array(e, [100, 100]));
+    liq_pool.deposit(&user2, &Vec::from_array(e, [100, 100]));
+    jump(e, 10);
+
+    // claim to self matches regular claim
+    assert_eq!(liq_pool.claim_to(&user1, &user1), reward_tps * 10 / 2);
+    assert_eq!(
+        setup.token_reward.balance(&user1) as u128,
+        reward_tps * 10 / 2
+    );
+
+    assert_eq!(liq_pool.claim_to(&user2, &recipient), reward_tps * 10 / 2);
+    assert_eq!(
+        setup.token_reward.balance(&recipient) as u128,
+        reward_tps * 10 / 2
+    );
+    assert_eq!(setup.token_reward.balance(&user2), 0);
+    assert_eq!(liq_pool.get_user_reward(&user2), 0);
+    assert_eq!(liq_pool.claim(&user2), 0);
+}
+
+#[test]
+fn test_claim_amount() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let user1 = setup.users[0].clone();
+    let user2 = setup.users[1].clone();
+    let reward_tps = TestConfig::default().reward_tps;
+
+    liq_pool.deposit(&user1, &Vec::from_array(e, [100, 100]));
+    jump(e, 10);
+    let accrued = reward_tps * 10;
+
+    // partial claim leaves the rest for the next one
+    assert_eq!(liq_pool.claim_amount(&user1, &1000), 1000);
+    assert_eq!(setup.token_reward.balance(&user1), 1000);
+    assert_eq!(liq_pool.get_user_reward(&user1), accrued - 1000);
+    assert_eq!(liq_pool.claim(&user1), accrued - 1000);
+    assert_eq!(setup.token_reward.balance(&user1) as u128, accrued);
+
+    // over-claim is clamped to the accrued reward
+    jump(e, 10);
+    assert_eq!(liq_pool.claim_amount(&user1, &u128::MAX), accrued);
+    assert_eq!(setup.token_reward.balance(&user1) as u128, accrued * 2);
+    assert_eq!(liq_pool.get_total_claimed(), accrued * 2);
+
+    // nothing accrued
+    assert_eq!(liq_pool.claim_amount(&user2, &1000), 0);
+    assert_eq!(
+        liq_pool.try_claim_amount(&user1, &0).unwrap_err(),
+        Ok(soroban_sdk::Error::from_contract_error(239))
+    );
+
+    e.set_auths(&[]);
+    assert!(liq_pool.try_claim_amount(&user1, &1000).is_err());
+}
+
+#[test]
+fn test_claim_and_reinvest() {
+    let e = Env::default();
+    e.mock_all_auths();
+    e.budget().reset_unlimited();
+
+    let users = Setup::generate_random_users(&e, 2);
+    let admin = users[0].clone();
+    let user1 = users[1].clone();
+    let mut token1 = create_token_contract(&e, &admin);
+    let mut token2 = create_token_contract(&e, &admin);
+    if token2.address < token1.address {
+        std::mem::swap(&mut token1, &mut token2);
+    }
+    let plane = create_plane_contract(&e);
+    let liq_pool = create_liqpool_contract(
+        &e,
+        &admin,
+        &install_token_wasm(&e),
+        &Vec::from_array(&e, [token1.address.clone(), token2.address.clone()]),
+        &token1.address,
+        30,
+        &plane.address,
+    );
+
+    let reward_tps = 10_0000000_u128;
+    let total_reward = reward_tps * 60;
+    token1.mint(&admin, &(total_reward as i128));
+    token1.approve(&admin, &liq_pool.address, &(total_reward as i128), &99999);
+    for token in [&token1, &token2] {
+        token.mint(&user1, &1000_0000000);
+        token.approve(&user1, &liq_pool.address, &1000_0000000, &99999);
+    }
+    liq_pool.set_rewards_config(&admin, &(e.ledger().timestamp() + 60), &reward_tps);
+    liq_pool.deposit(&user1, &Vec::from_array(&e, [100_0000000, 100_0000000]));
+    let share_token = Client::new(&e, &liq_pool.share_id());
+
+    jump(&e, 10);
+    let reward = reward_tps * 10;
+    let shares_before = share_token.balance(&user1) as u128;
+    let balance_before = token1.balance(&user1);
+
+    // shares are minted for the reward deposited as token a
+    let shares = liq_pool.claim_and_reinvest(&user1, &1);
+    assert!(shares > 0);
+    // user authorizes the reinvest once, the deposit is part of it
+    let user_auths: std::vec::Vec<_> = e
+        .auths()
+        .into_iter()
+        .filter(|(address, _)| address == &user1)
+        .collect();
+    assert_eq!(user_auths.len(), 1);
+    assert_eq!(
+        user_auths[0].1.function,
+        AuthorizedFunction::Contract((
+            liq_pool.address.clone(),
+            Symbol::new(&e, "claim_and_reinvest"),
+            (user1.clone(), 1_u128).into_val(&e),
+        ))
+    );
+    assert_eq!(share_token.balance(&user1) as u128, shares_before + shares);
+    assert_eq!(token1.balance(&user1), balance_before);
+    assert_eq!(liq_pool.get_user_reward(&user1), 0);
+    assert_eq!(
+        liq_pool.get_reserves(),
+        Vec::from_array(&e, [100_0000000 + reward, 100_0000000])
+    );
+    // nothing left to reinvest
+    assert_eq!(liq_pool.claim_and_reinvest(&user1, &1), 0);
+
+    // min shares protection reverts the claim as well
+    jump(&e, 10);
+    assert!(liq_pool
+        .try_claim_and_reinvest(&user1, &(shares * 2))
+        .is_err());
+    // reward is not lost, up to the reward per share rounding
+    assert!(reward - liq_pool.get_user_reward(&user1) <= 1);
+}
+
+#[test]
+fn test_claim_and_reinvest_not_pool_token() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let user1 = setup.users[0].clone();
+
+    setup
+        .liq_pool
+        .deposit(&user1, &Vec::from_array(e, [100, 100]));
+    jump(e, 10);
+    assert_eq!(
+        setup
+            .liq_pool
+            .try_claim_and_reinvest(&user1, &0)
+            .unwrap_err(),
+        Ok(soroban_sdk::Error::from_contract_error(240))
+    );
+}
+
+#[test]
+fn test_convert_fees_to_rewards() {
+    let e = Env::default();
+    e.mock_all_auths();
+    e.budget().reset_unlimited();
+
+    let users = Setup::generate_random_users(&e, 2);
+    let admin = users[0].clone();
+    let user1 = users[1].clone();
+    let mut token1 = create_token_contract(&e, &admin);
+    let mut token2 = create_token_contract(&e, &admin);
+    if token2.address < token1.address {
+        std::mem::swap(&mut token1, &mut token2);
+    }
+    let plane = create_plane_contract(&e);
+    let liq_pool = create_liqpool_contract(
+        &e,
+        &admin,
+        &install_token_wasm(&e),
+        &Vec::from_array(&e, [token1.address.clone(), token2.address.clone()]),
+        &token1.address,
+        30,
+        &plane.address,
+    );
+
+    let reward_tps = 10_0000000_u128;
+    let total_reward = reward_tps * 60;
+    token1.mint(&admin, &(total_reward as i128));
+    token1.approve(&admin, &liq_pool.address, &(total_reward as i128), &99999);
+    for token in [&token1, &token2] {
+        token.mint(&user1, &1000_0000000);
+        token.approve(&user1, &liq_pool.address, &1000_0000000, &99999);
+    }
+    liq_pool.set_rewards_config(&admin, &(e.ledger().timestamp() + 60), &reward_tps);
+    liq_pool.deposit(&user1, &Vec::from_array(&e, [100_0000000, 100_0000000]));
+
+    // only admin sets the reward fee
+    assert_eq!(
+        liq_pool.try_set_reward_fee(&user1, &5000).unwrap_err(),
+        Ok(soroban_sdk::Error::from_contract_error(102))
+    );
+    assert!(liq_pool.try_set_reward_fee(&admin, &10001).is_err());
+    liq_pool.set_reward_fee(&admin, &5000);
+    assert_eq!(liq_pool.get_reward_fee(), 5000);
+
+    // half of 0.3% fee is set aside on every swap
+    liq_pool.swap(&user1, &1, &0, &10_0000000, &0);
+    liq_pool.swap(&user1, &1, &0, &10_0000000, &0);
+    liq_pool.swap(&user1, &0, &1, &10_0000000, &0);
+    assert_eq!(
+        liq_pool.get_reward_fee_bucket(),
+        Vec::from_array(&e, [15000, 30000])
+    );
+    // bucket is not counted as liquidity
+    let reserves = liq_pool.get_reserves();
+    assert_eq!(
+        reserves,
+        Vec::from_array(
+            &e,
+            [
+                token1.balance(&liq_pool.address) as u128 - total_reward - 15000,
+                token2.balance(&liq_pool.address) as u128 - 30000,
+            ]
+        )
+    );
+
+    // token b fees are swapped through the pool for the reward token, no less than min_out
+    jump(&e, 10);
+    let out = liq_pool.estimate_swap(&1, &0, &30000);
+    assert!(liq_pool.try_convert_fees_to_rewards(&(out + 1)).is_err());
+    let reward = liq_pool.convert_fees_to_rewards(&out);
+    // only the part paid out over the remaining 50 seconds is funded
+    let converted = 15000 + out;
+    assert_eq!(reward, converted / 50 * 50);
+    assert_eq!(
+        liq_pool.get_rewards_info_v2(&user1).tps,
+        reward_tps + converted / 50
+    );
+    assert_eq!(
+        vec![&e, e.events().all().last().unwrap()],
+        vec![
+            &e,
+            (
+                liq_pool.address.clone(),
+                (Symbol::new(&e, "convert_fees"),).into_val(&e),
+                (Vec::from_array(&e, [15000_u128, 30000_u128]), reward).into_val(&e),
+            ),
+        ]
+    );
+    assert_eq!(
+        liq_pool.get_reward_fee_bucket(),
+        Vec::from_array(&e, [converted - reward, 0])
+    );
+    assert_eq!(
+        liq_pool.get_reserves(),
+        Vec::from_array(
+            &e,
+            [
+                reserves.get(0).unwrap() - out,
+                reserves.get(1).unwrap() + 30000
+            ]
+        )
+    );
+    // remainder is too small to add to tps, so it waits in the bucket
+    assert_eq!(liq_pool.convert_fees_to_rewards(&0), 0);
+    assert_eq!(
+        liq_pool.get_reward_fee_bucket(),
+        Vec::from_array(&e, [converted - reward, 0])
+    );
+
+    // converted fees are distributed over the rest of the campaign
+    jump(&e, 50);
+    let claimed = liq_pool.claim(&user1);
+    assert!(claimed <= total_reward + reward);
+    assert!(claimed >= total_reward + reward - 100);
+}
+
+#[test]
+fn test_convert_fees_to_rewards_not_pool_token() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let admin = setup.users[0].clone();
+    let user1 = setup.users[1].clone();
+    let liq_pool = &setup.liq_pool;
+
+    liq_pool.set_reward_fee(&admin, &10000);
+    liq_pool.deposit(&user1, &Vec::from_array(e, [500, 500]));
+    liq_pool.swap(&user1, &0, &1, &100, &0);
+    assert_eq!(liq_pool.get_reward_fee_bucket(), Vec::from_array(e, [0, 0]));
+    liq_pool.swap(&user1, &0, &1, &400, &0);
+    assert_eq!(liq_pool.get_reward_fee_bucket(), Vec::from_array(e, [1, 0]));
+
+    // fees are kept until there is a reward storage to send them to
+    assert_eq!(
+        liq_pool.try_convert_fees_to_rewards(&600).unwrap_err(),
+        Ok(soroban_sdk::Error::from_contract_error(241))
+    );
+
+    // reward storage takes the fees, adding min_out reward tokens to the campaign budget.
+    //  it holds just the current budget, so there is nothing to add yet
+    liq_pool.set_reward_storage(&admin, &admin);
+    let info = liq_pool.get_rewards_info_v2(&user1);
+    assert_eq!(setup.token_reward.balance(&admin) as u128, info.budget);
+    assert_eq!(
+        liq_pool.try_convert_fees_to_rewards(&605).unwrap_err(),
+        Ok(soroban_sdk::Error::from_contract_error(257))
+    );
+
+    setup.token_reward.mint(&admin, &600);
+    let balance = setup.token1.balance(&admin);
+    assert_eq!(liq_pool.convert_fees_to_rewards(&605), 600);
+    assert_eq!(
+        e.auths()[0],
+        (
+            admin.clone(),
+            AuthorizedInvocation {
+                function: AuthorizedFunction::Contract((
+                    liq_pool.address.clone(),
+                    Symbol::new(e, "convert_fees_to_rewards"),
+                    (605_u128,).into_val(e),
+                )),
+                sub_invocations: std::vec![],
+            }
+        )
+    );
+    assert_eq!(setup.token1.balance(&admin), balance + 1);
+    assert_eq!(liq_pool.get_reward_fee_bucket(), Vec::from_array(e, [0, 0]));
+    let updated = liq_pool.get_rewards_info_v2(&user1);
+    assert_eq!(updated.tps, info.tps + 10);
+    assert_eq!(updated.budget, info.budget + 600);
+    // funded amount is held by the reward storage
+    assert_eq!(setup.token_reward.balance(&admin) as u128, updated.budget);
+}
+
+fn setup_swap_strict<'a>() -> Setup<'a> {
+    let setup = Setup::new_with_config(&TestConfig {
+        mint_to_user: 10_000_0000000,
+        ..TestConfig::default()
+    });
+    let e = &setup.env;
+    setup.liq_pool.deposit(
+        &setup.users[0],
+        &Vec::from_array(e, [1000_0000000, 1000_0000000]),
+    );
+    setup
+}
+
+fn assert_swap_slippage_event(setup: &Setup, user: &Address, quote: u128, out: u128, bps: u32) {
+    let e = &setup.env;
+    assert_eq!(
+        vec![e, e.events().all().last().unwrap()],
+        vec![
+            e,
+            (
+                setup.liq_pool.address.clone(),
+                (Symbol::new(e, "swap_slippage"), user.clone()).into_val(e),
+                (quote, out, bps).into_val(e),
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_swap_strict() {
+    let setup = setup_swap_strict();
+    let liq_pool = &setup.liq_pool;
+    let user1 = setup.users[1].clone();
+    let out = 10_0000000;
+
+    // nothing traded since the quote
+    let quote = liq_pool.estimate_swap_in(&0, &1, &out);
+    let balance = setup.token2.balance(&user1);
+    assert_eq!(
+        liq_pool.swap_strict(&user1, &0, &1, &out, &quote, &0),
+        quote
+    );
+    assert!(setup.token2.balance(&user1) >= balance + out as i128);
+    assert_swap_slippage_event(&setup, &user1, quote, quote, 0);
+
+    // interleaved trade moves the price within the tolerance
+    let quote = liq_pool.estimate_swap_in(&0, &1, &out);
+    liq_pool.swap(&setup.users[0], &0, &1, &2_0000000, &0);
+    let in_amount = liq_pool.swap_strict(&user1, &0, &1, &out, &quote, &100);
+    assert!(in_amount > quote);
+    let slippage = ((in_amount - quote) * 10000).div_ceil(quote) as u32;
+    assert!(slippage > 0 && slippage <= 100);
+    assert_swap_slippage_event(&setup, &user1, quote, in_amount, slippage);
+
+    // whole reserve can't be bought
+    assert_eq!(
+        liq_pool
+            .try_swap_strict(&user1, &0, &1, &1_000_0000000, &quote, &100)
+            .unwrap_err(),
+        Ok(soroban_sdk::Error::from_contract_error(249))
+    );
+}
+
+#[test]
+#[should_panic(expected = "in amount is over max")]
+fn test_swap_strict_beyond_slippage() {
+    let setup = setup_swap_strict();
+    let liq_pool = &setup.liq_pool;
+    let user1 = setup.users[1].clone();
+
+    // interleaved trade moves the price by ~10%
+    let quote = liq_pool.estimate_swap_in(&0, &1, &10_0000000);
+    liq_pool.swap(&setup.users[0], &0, &1, &50_0000000, &0);
+    liq_pool.swap_strict(&user1, &0, &1, &10_0000000, &quote, &100);
+}
+
+#[test]
+fn test_swap_v2() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let user1 = setup.users[1].clone();
+    let liq_pool = &setup.liq_pool;
+
+    liq_pool.deposit(&user1, &Vec::from_array(e, [500, 500]));
+    let estimate = liq_pool.estimate_swap(&0, &1, &100);
+    let balance = setup.token2.balance(&user1);
+
+    let result = liq_pool.swap_v2(&user1, &0, &1, &100, &estimate);
+    assert_eq!(
+        result,
+        SwapResult {
+            amount_in: 100,
+            amount_out: estimate,
+            new_reserve_a: 600,
+            new_reserve_b: 500 - estimate,
+        }
+    );
+    assert_eq!(
+        liq_pool.get_reserves(),
+        Vec::from_array(e, [result.new_reserve_a, result.new_reserve_b])
+    );
+    assert_eq!(
+        setup.token2.balance(&user1),
+        balance + result.amount_out as i128
+    );
+    assert_eq!(
+        vec![e, e.events().all().last().unwrap()],
+        vec![
+            e,
+            (
+                liq_pool.address.clone(),
+                (symbol_short!("swap"), user1.clone()).into_val(e),
+                (0_u32, 1_u32, result.amount_in, result.amount_out).into_val(e),
+            ),
+        ]
+    );
+
+    // out_min protection is the same as for swap
+    assert!(liq_pool
+        .try_swap_v2(
+            &user1,
+            &1,
+            &0,
+            &100,
+            &(liq_pool.estimate_swap(&1, &0, &100) + 1)
+        )
+        .is_err());
+}
+
+#[test]
+fn test_pool_test_fixture() {
+    let e = Env::default();
+    let fixture = PoolTestFixture::setup(&e);
+    let user = &fixture.users[0];
+
+    assert!(fixture.token_a.address < fixture.token_b.address);
+    assert_eq!(
+        fixture.liq_pool.get_tokens(),
+        Vec::from_array(
+            &e,
+            [
+                fixture.token_a.address.clone(),
+                fixture.token_b.address.clone()
+            ]
+        )
+    );
+    assert_eq!(fixture.liq_pool.get_fee_fraction(), 30);
+
+    let shares = fixture.seed_liquidity(user, 1000_0000000, 1000_0000000);
+    assert_eq!(fixture.token_share.balance(user) as u128, shares);
+
+    let estimate = fixture.liq_pool.estimate_swap(&1, &0, &10_0000000);
+    let balance_a = fixture.token_a.balance(&fixture.users[1]);
+    assert_eq!(
+        fixture.do_swap(&fixture.users[1], true, 10_0000000),
+        estimate
+    );
+    assert_eq!(
+        fixture.token_a.balance(&fixture.users[1]),
+        balance_a + estimate as i128
+    );
+    assert!(fixture.do_swap(&fixture.users[1], false, 10_0000000) > 0);
+}
+
+#[test]
+fn test_quote_context() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let admin = setup.users[0].clone();
+    let user1 = setup.users[1].clone();
+    let liq_pool = &setup.liq_pool;
+
+    liq_pool.deposit(&user1, &Vec::from_array(e, [100, 100]));
+    let context = liq_pool.get_quote_context();
+    assert_eq!(
+        context,
+        QuoteContext {
+            token_a: setup.token1.address.clone(),
+            token_b: setup.token2.address.clone(),
+            reserve_a: 100,
+            reserve_b: 100,
+            fee_bps: 30,
+            paused: false,
+        }
+    );
+
+    // reserves follow the swap
+    let out = liq_pool.swap(&user1, &0, &1, &10, &0);
+    let context = liq_pool.get_quote_context();
+    assert_eq!((context.reserve_a, context.reserve_b), (110, 100 - out));
+    assert_eq!(
+        Vec::from_array(e, [context.reserve_a, context.reserve_b]),
+        liq_pool.get_reserves()
+    );
+
+    liq_pool.set_paused(&admin, &true);
+    assert!(liq_pool.get_quote_context().paused);
+    liq_pool.set_paused(&admin, &false);
+    assert!(!liq_pool.get_quote_context().paused);
+
+    // fee is fixed at initialization, so the fee change is a pool with another fee
+    let fixture = PoolTestFixture::new(e, 100, 1);
+    assert_eq!(fixture.liq_pool.get_quote_context().fee_bps, 100);
+}
+
+#[test]
+fn test_reserves_with_timestamp() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let user1 = setup.users[1].clone();
+    let liq_pool = &setup.liq_pool;
+
+    jump(e, 10);
+    let deposited_at = e.ledger().timestamp();
+    liq_pool.deposit(&user1, &Vec::from_array(e, [100, 100]));
+    assert_eq!(
+        liq_pool.get_reserves_with_timestamp(),
+        (100, 100, deposited_at)
+    );
+
+    // read-only calls don't touch the timestamp
+    jump(e, 10);
+    liq_pool.get_reserves();
+    liq_pool.get_price();
+    liq_pool.estimate_swap(&0, &1, &10);
+    assert_eq!(liq_pool.get_reserves_with_timestamp().2, deposited_at);
+
+    let swapped_at = e.ledger().timestamp();
+    liq_pool.swap(&user1, &0, &1, &10, &0);
+    let (reserve_a, reserve_b, updated_at) = liq_pool.get_reserves_with_timestamp();
+    assert_eq!(
+        Vec::from_array(e, [reserve_a, reserve_b]),
+        liq_pool.get_reserves()
+    );
+    assert_eq!(updated_at, swapped_at);
+
+    jump(e, 10);
+    let withdrawn_at = e.ledger().timestamp();
+    setup
+        .token_share
+        .approve(&user1, &liq_pool.address, &50, &99999);
+    liq_pool.withdraw(&user1, &50, &Vec::from_array(e, [0, 0]));
+    assert_eq!(liq_pool.get_reserves_with_timestamp().2, withdrawn_at);
+}
+
+#[test]
+fn test_initial_price_range() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let admin = setup.users[0].clone();
+    let user1 = setup.users[1].clone();
+    let liq_pool = &setup.liq_pool;
+
+    assert_eq!(liq_pool.get_initial_price_range(), None);
+    assert_eq!(
+        liq_pool
+            .try_set_initial_price_range(&user1, &9000000, &11000000)
+            .unwrap_err(),
+        Ok(soroban_sdk::Error::from_contract_error(102))
+    );
+    assert_eq!(
+        liq_pool
+            .try_set_initial_price_range(&admin, &11000000, &9000000)
+            .unwrap_err(),
+        Ok(soroban_sdk::Error::from_contract_error(243))
+    );
+    liq_pool.set_initial_price_range(&admin, &9000000, &11000000);
+    assert_eq!(
+        liq_pool.get_initial_price_range(),
+        Some((9000000, 11000000))
+    );
+
+    // typo'd first deposit is rejected
+    assert_eq!(
+        liq_pool
+            .try_deposit(&user1, &Vec::from_array(e, [100, 1000]))
+            .unwrap_err(),
+        Ok(soroban_sdk::Error::from_contract_error(242))
+    );
+    assert_eq!(
+        liq_pool
+            .try_deposit(&user1, &Vec::from_array(e, [100, 80]))
+            .unwrap_err(),
+        Ok(soroban_sdk::Error::from_contract_error(242))
+    );
+    liq_pool.deposit(&user1, &Vec::from_array(e, [100, 105]));
+    assert_eq!(liq_pool.get_price(), 10500000);
+
+    // pool is seeded, the range has no effect anymore
+    assert_eq!(
+        liq_pool
+            .try_set_initial_price_range(&admin, &0, &u128::MAX)
+            .unwrap_err(),
+        Ok(soroban_sdk::Error::from_contract_error(244))
+    );
+    liq_pool.swap(&user1, &0, &1, &200, &0);
+    assert!(liq_pool.get_price() < 9000000);
+    liq_pool.deposit(&admin, &Vec::from_array(e, [100, 100]));
+}
+
+#[test]
+#[should_panic(expected = "Error(Auth, InvalidAction)")]
+fn test_claim_to_not_authorized() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let user1 = setup.users[0].clone();
+
+    setup
+        .liq_pool
+        .deposit(&user1, &Vec::from_array(e, [100, 100]));
+    jump(e, 10);
+
+    e.set_auths(&[]);
+    setup.liq_pool.claim_to(&user1, &setup.users[1]);
+}
+
+#[test]
+#[should_panic(expected = "Error(Auth, InvalidAction)")]
+fn test_claim_not_authorized() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let user1 = setup.users[1].clone();
+
+    setup
+        .liq_pool
+        .deposit(&user1, &Vec::from_array(e, [100, 100]));
+    jump(e, 10);
+
+    e.set_auths(&[]);
+    setup.liq_pool.claim(&user1);
+}
+
+#[test]
+fn test_rewards_views_read_only() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let user1 = setup.users[1].clone();
+
+    liq_pool.deposit(&user1, &Vec::from_array(e, [100, 100]));
+    jump(e, 10);
+
+    let read_storage = || {
+        e.as_contract(&liq_pool.address, || {
+            let rewards = get_rewards_manager(e);
+            let pool_data = rewards.storage().get_pool_reward_data();
+            let user_data = rewards.storage().get_user_reward_data(&user1).unwrap();
+            (
+                pool_data.block,
+                pool_data.accumulated,
+                pool_data.last_time,
+                pool_data.reward_per_share,
+                user_data.to_claim,
+                user_data.reward_per_share,
+            )
+        })
+    };
+    let before = read_storage();
+    let info = liq_pool.get_rewards_info(&user1);
+    assert_eq!(liq_pool.get_rewards_info(&user1), info);
+    let user_reward = liq_pool.get_user_reward(&user1);
+    assert_eq!(liq_pool.get_user_reward(&user1), user_reward);
+    assert_eq!(read_storage(), before);
+
+    // views predict exactly what the mutating path produces
+    assert_eq!(
+        info.get(symbol_short!("to_claim")).unwrap(),
+        user_reward as i128
+    );
+    assert_eq!(liq_pool.claim(&user1), user_reward);
+    let (block, accumulated, last_time, ..) = read_storage();
+    assert_eq!(block as i128, info.get(symbol_short!("block")).unwrap() + 1);
+    assert_eq!(accumulated as i128, info.get(symbol_short!("acc")).unwrap());
+    assert_eq!(
+        last_time as i128,
+        info.get(symbol_short!("last_time")).unwrap()
+    );
+}
+
+#[test]
+fn test_claimed_rewards() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let admin = setup.users[0].clone();
+    let user1 = setup.users[1].clone();
+    let reward_tps = TestConfig::default().reward_tps;
+
+    // no history
+    assert_eq!(liq_pool.get_user_claimed(&user1), 0);
+    assert_eq!(liq_pool.get_total_claimed(), 0);
+
+    liq_pool.deposit(&admin, &Vec::from_array(e, [100, 100]));
+    liq_pool.deposit(&user1, &Vec::from_array(e, [100, 100]));
+    jump(e, 10);
+    let user1_first = liq_pool.claim(&user1);
+    jump(e, 10);
+    let user1_second = liq_pool.claim(&user1);
+    let admin_claim = liq_pool.claim(&admin);
+
+    assert_eq!(user1_first + user1_second, reward_tps * 10);
+    assert_eq!(liq_pool.get_user_claimed(&user1), reward_tps * 10);
+    assert_eq!(liq_pool.get_user_claimed(&admin), admin_claim);
+    assert_eq!(
+        liq_pool.get_total_claimed(),
+        liq_pool.get_user_claimed(&user1) + liq_pool.get_user_claimed(&admin)
+    );
+    assert_eq!(
+        liq_pool.get_rewards_info_v2(&user1).claimed,
+        reward_tps * 10
+    );
+}
+
+#[test]
+fn test_rewards_vesting() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let admin = setup.users[0].clone();
+    let user1 = setup.users[1].clone();
+    let reward = TestConfig::default().reward_tps * 10;
+
+    liq_pool.set_vesting_duration(&admin, &100);
+    liq_pool.deposit(&user1, &Vec::from_array(e, [100, 100]));
+    jump(e, 10);
+
+    // reward is vested instead of transferred
+    assert_eq!(liq_pool.claim(&user1), reward);
+    assert_eq!(setup.token_reward.balance(&user1), 0);
+    let info = liq_pool.get_rewards_info_v2(&user1);
+    assert_eq!(info.vesting, reward);
+    assert_eq!(info.unlocked, 0);
+
+    jump(e, 50);
+    let info = liq_pool.get_rewards_info_v2(&user1);
+    assert_eq!(info.unlocked, reward / 2);
+    assert_eq!(liq_pool.release_vested(&user1), reward / 2);
+    assert_eq!(liq_pool.release_vested(&user1), 0);
+    assert_eq!(setup.token_reward.balance(&user1) as u128, reward / 2);
+
+    jump(e, 50);
+    assert_eq!(liq_pool.release_vested(&user1), reward / 2);
+    assert_eq!(setup.token_reward.balance(&user1) as u128, reward);
+    let info = liq_pool.get_rewards_info_v2(&user1);
+    assert_eq!(info.vesting, 0);
+    assert_eq!(liq_pool.release_vested(&user1), 0);
+}
+
+#[test]
+fn test_rewards_vesting_merge() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let admin = setup.users[0].clone();
+    let user1 = setup.users[1].clone();
+    let reward_tps = TestConfig::default().reward_tps;
+
+    liq_pool.set_vesting_duration(&admin, &100);
+    liq_pool.deposit(&user1, &Vec::from_array(e, [100, 100]));
+    jump(e, 10);
+    assert_eq!(liq_pool.claim(&user1), reward_tps * 10);
+
+    // half of the first claim is unlocked and released,
+    //  the locked half vests again together with the second claim
+    jump(e, 50);
+    assert_eq!(liq_pool.claim(&user1), reward_tps * 50);
+    assert_eq!(setup.token_reward.balance(&user1) as u128, reward_tps * 5);
+    let info = liq_pool.get_rewards_info_v2(&user1);
+    assert_eq!(info.vesting, reward_tps * 55);
+    assert_eq!(info.unlocked, 0);
+
+    jump(e, 50);
+    assert_eq!(liq_pool.release_vested(&user1), reward_tps * 55 / 2);
+    jump(e, 100);
+    assert_eq!(liq_pool.release_vested(&user1), reward_tps * 55 / 2);
+    assert_eq!(setup.token_reward.balance(&user1) as u128, reward_tps * 60);
+    assert_eq!(liq_pool.get_user_claimed(&user1), reward_tps * 60);
+}
+
+#[test]
+fn test_reward_token_is_pool_token() {
+    let e = Env::default();
+    e.mock_all_auths();
+    e.budget().reset_unlimited();
+
+    let users = Setup::generate_random_users(&e, 2);
+    let admin = users[0].clone();
+    let user1 = users[1].clone();
+    let mut token1 = create_token_contract(&e, &admin);
+    let mut token2 = create_token_contract(&e, &admin);
+    if token2.address < token1.address {
+        std::mem::swap(&mut token1, &mut token2);
+    }
+    let plane = create_plane_contract(&e);
+    // pool incentivized with its own token
+    let liq_pool = create_liqpool_contract(
+        &e,
+        &admin,
+        &install_token_wasm(&e),
+        &Vec::from_array(&e, [token1.address.clone(), token2.address.clone()]),
+        &token1.address,
+        30,
+        &plane.address,
+    );
+
+    let reward_tps = 10_0000000_u128;
+    let total_reward = reward_tps * 60;
+    token1.mint(&admin, &(total_reward as i128));
+    token1.approve(&admin, &liq_pool.address, &(total_reward as i128), &99999);
+    for token in [&token1, &token2] {
+        token.mint(&user1, &1000_0000000);
+        token.approve(&user1, &liq_pool.address, &1000_0000000, &99999);
+    }
+
+    liq_pool.set_rewards_config(&admin, &(e.ledger().timestamp() + 60), &reward_tps);
+    assert_eq!(token1.balance(&liq_pool.address) as u128, total_reward);
+
+    // reward tokens are not swept into reserves
+    liq_pool.deposit(&user1, &Vec::from_array(&e, [100_0000000, 100_0000000]));
+    assert_eq!(
+        liq_pool.get_reserves(),
+        Vec::from_array(&e, [100_0000000, 100_0000000])
+    );
+    assert_eq!(liq_pool.skim(&admin), Vec::from_array(&e, [0, 0]));
+
+    jump(&e, 30);
+    let estimate = liq_pool.estimate_swap(&1, &0, &10_0000000);
+    assert_eq!(liq_pool.swap(&user1, &1, &0, &10_0000000, &0), estimate);
+    assert_eq!(
+        liq_pool.get_reserves(),
+        Vec::from_array(&e, [100_0000000 - estimate, 110_0000000])
+    );
+
+    jump(&e, 40);
+    assert_eq!(liq_pool.claim(&user1), total_reward);
+    let info = liq_pool.get_rewards_info_v2(&user1);
+    assert_eq!(info.budget, 0);
+
+    // whatever is left in the pool belongs to liquidity providers
+    let share_token = Client::new(&e, &liq_pool.share_id());
+    let user_shares = share_token.balance(&user1);
+    share_token.approve(&user1, &liq_pool.address, &user_shares, &99999);
+    assert_eq!(
+        liq_pool.withdraw(&user1, &(user_shares as u128), &Vec::from_array(&e, [0, 0])),
+        Vec::from_array(&e, [100_0000000 - estimate, 110_0000000])
+    );
+    assert_eq!(token1.balance(&liq_pool.address), 0);
+}
+
+#[test]
+fn test_claim_for() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let admin = setup.users[0].clone();
+    let user1 = setup.users[1].clone();
+    let reward_tps = TestConfig::default().reward_tps;
+
+    liq_pool.deposit(&user1, &Vec::from_array(e, [100, 100]));
+    jump(e, 10);
+
+    // only admin may claim on behalf of the user
+    assert!(liq_pool.try_claim_for(&user1, &user1).is_err());
+    assert_eq!(liq_pool.claim_for(&admin, &user1), reward_tps * 10);
+    assert_eq!(
+        e.auths(),
+        std::vec![(
+            admin.clone(),
+            AuthorizedInvocation {
+                function: AuthorizedFunction::Contract((
+                    liq_pool.address.clone(),
+                    Symbol::new(e, "claim_for"),
+                    (admin.clone(), user1.clone()).into_val(e),
+                )),
+                sub_invocations: std::vec![],
+            },
+        )]
+    );
+    assert_eq!(setup.token_reward.balance(&user1) as u128, reward_tps * 10);
+    assert_eq!(setup.token_reward.balance(&admin), 0);
+}
+
+#[test]
+fn test_batch_claim() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let admin = setup.users[0].clone();
+    let user1 = setup.users[1].clone();
+    let idle_user = Address::generate(e);
+    let reward_tps = TestConfig::default().reward_tps;
+
+    liq_pool.deposit(&admin, &Vec::from_array(e, [100, 100]));
+    liq_pool.deposit(&user1, &Vec::from_array(e, [300, 300]));
+    jump(e, 10);
+
+    let users = Vec::from_array(e, [user1.clone(), idle_user.clone(), admin.clone()]);
+    assert_eq!(
+        liq_pool.batch_claim(&admin, &users),
+        Vec::from_array(e, [reward_tps * 30 / 4, 0, reward_tps * 10 / 4])
+    );
+    assert_eq!(
+        setup.token_reward.balance(&user1) as u128,
+        reward_tps * 30 / 4
+    );
+    assert_eq!(setup.token_reward.balance(&idle_user), 0);
+    // everything is claimed already
+    assert_eq!(
+        liq_pool.batch_claim(&admin, &users),
+        Vec::from_array(e, [0, 0, 0])
+    );
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #226)")]
+fn test_batch_claim_too_large() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let admin = setup.users[0].clone();
+
+    let users = Vec::from_slice(e, &Setup::generate_random_users(e, 21));
+    setup.liq_pool.batch_claim(&admin, &users);
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #102)")]
+fn test_batch_claim_not_operator() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let user1 = setup.users[1].clone();
+
+    setup
+        .liq_pool
+        .batch_claim(&user1, &Vec::from_array(e, [user1.clone()]));
+}
+
+#[test]
+fn test_rewards_operator() {
+    let setup = Setup::setup(&TestConfig::default());
+    setup.mint_tokens_for_users(&TestConfig::default().mint_to_user);
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let admin = setup.users[0].clone();
+    let user1 = setup.users[1].clone();
+    let operator = Address::generate(e);
+    let reward_tps = TestConfig::default().reward_tps;
+
+    assert_eq!(liq_pool.get_rewards_operator(), None);
+    assert!(liq_pool
+        .try_set_rewards_config(&operator, &(e.ledger().timestamp() + 60), &reward_tps)
+        .is_err());
+    liq_pool.set_rewards_operator(&admin, &operator);
+    assert_eq!(liq_pool.get_rewards_operator(), Some(operator.clone()));
+
+    liq_pool.deposit(&user1, &Vec::from_array(e, [100, 100]));
+    liq_pool.set_rewards_config(&operator, &(e.ledger().timestamp() + 60), &reward_tps);
+    jump(e, 10);
+    assert_eq!(liq_pool.claim_for(&operator, &user1), reward_tps * 10);
+
+    // the rest of the pool is not managed by the operator
+    assert!(liq_pool.try_set_paused(&operator, &true).is_err());
+    assert!(liq_pool
+        .try_set_rewards_operator(&operator, &operator)
+        .is_err());
+
+    // admin keeps rewards access as well
+    liq_pool.extend_rewards(&admin, &(e.ledger().timestamp() + 100));
+}
+
+#[test]
+fn test_rewards_operator_rotation() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let admin = setup.users[0].clone();
+    let old_operator = Address::generate(e);
+    let new_operator = Address::generate(e);
+
+    liq_pool.set_rewards_operator(&admin, &old_operator);
+    liq_pool.set_referral_bonus(&old_operator, &100, &1000);
+    liq_pool.set_rewards_operator(&admin, &new_operator);
+
+    assert!(matches!(
+        liq_pool.try_set_referral_bonus(&old_operator, &100, &1000),
+        Err(Ok(error)) if error == soroban_sdk::Error::from_contract_error(105)
+    ));
+    liq_pool.set_referral_bonus(&new_operator, &100, &1000);
+}
+
+#[test]
+#[should_panic(expected = "Error(Auth, InvalidAction)")]
+fn test_rewards_operator_upgrade() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let admin = setup.users[0].clone();
+    let operator = Address::generate(e);
+    setup.liq_pool.set_rewards_operator(&admin, &operator);
+
+    // only operator authorizes the upgrade
+    let new_wasm_hash = BytesN::from_array(e, &[0; 32]);
+    e.mock_auths(&[MockAuth {
+        address: &operator,
+        invoke: &MockAuthInvoke {
+            contract: &setup.liq_pool.address,
+            fn_name: "upgrade",
+            args: (new_wasm_hash.clone(),).into_val(e),
+            sub_invokes: &[],
+        },
+    }]);
+    e.as_contract(&setup.liq_pool.address, || {
+        LiquidityPool::upgrade(e.clone(), new_wasm_hash);
+    });
+}
+
+#[test]
+fn test_check_swap() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let admin = setup.users[0].clone();
+    let user1 = setup.users[1].clone();
+
+    liq_pool.deposit(&user1, &Vec::from_array(e, [100, 100]));
+    let violations = |names: &[&str]| {
+        let mut result = Vec::new(e);
+        for name in names {
+            result.push_back(Symbol::new(e, name));
+        }
+        result
+    };
+
+    let out = liq_pool.estimate_swap(&0, &1, &10);
+    assert_eq!(
+        liq_pool.check_swap(&user1, &0, &1, &10, &out),
+        violations(&[])
+    );
+    assert_eq!(
+        liq_pool.check_swap(&user1, &0, &1, &10, &(out + 1)),
+        violations(&["out_min"])
+    );
+    assert_eq!(
+        liq_pool.check_swap(&user1, &0, &1, &1001, &0),
+        violations(&["balance", "allowance"])
+    );
+
+    setup.token2.approve(&user1, &liq_pool.address, &5, &99999);
+    assert_eq!(
+        liq_pool.check_swap(&user1, &1, &0, &10, &0),
+        violations(&["allowance"])
+    );
+    assert!(liq_pool.try_swap(&user1, &1, &0, &10, &0).is_err());
+    assert_eq!(
+        liq_pool.check_swap(&user1, &0, &1, &10, &0),
+        violations(&[])
+    );
+
+    liq_pool.set_paused(&admin, &true);
+    assert_eq!(
+        liq_pool.check_swap(&user1, &0, &1, &10, &0),
+        violations(&["paused"])
+    );
+    liq_pool.kill_me(&admin);
+    assert_eq!(
+        liq_pool.check_swap(&user1, &0, &1, &10, &(out + 1)),
+        violations(&["killed", "paused", "out_min"])
+    );
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #214)")]
+fn test_swap_insufficient_allowance() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let user1 = setup.users[1].clone();
+
+    setup
+        .liq_pool
+        .deposit(&user1, &Vec::from_array(e, [100, 100]));
+    setup
+        .token2
+        .approve(&user1, &setup.liq_pool.address, &5, &99999);
+    setup.liq_pool.swap(&user1, &1, &0, &10, &0);
+}
+
+#[test]
+fn test_top_up_rewards() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let admin = setup.users[0].clone();
+    let user1 = setup.users[1].clone();
+    let reward_tps = TestConfig::default().reward_tps;
+
+    liq_pool.deposit(&user1, &Vec::from_array(e, [100, 100]));
+    jump(e, 20);
+    // 40 seconds left
+    liq_pool.top_up_rewards(&admin, &(40 * 2_0000000));
+    jump(e, 20);
+    assert_eq!(liq_pool.claim(&user1), reward_tps * 40 + 2_0000000 * 20);
+    jump(e, 100);
+    assert_eq!(liq_pool.claim(&user1), (reward_tps + 2_0000000) * 20);
+    assert_eq!(
+        setup.token_reward.balance(&user1) as u128,
+        reward_tps * 60 + 40 * 2_0000000
+    );
+}
+
+#[test]
+fn test_extend_rewards() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let admin = setup.users[0].clone();
+    let user1 = setup.users[1].clone();
+    let reward_tps = TestConfig::default().reward_tps;
+
+    liq_pool.deposit(&user1, &Vec::from_array(e, [100, 100]));
+    jump(e, 20);
+    // stretch 40 seconds left to 80 seconds
+    liq_pool.extend_rewards(&admin, &(e.ledger().timestamp() + 80));
+    jump(e, 40);
+    assert_eq!(
+        liq_pool.claim(&user1),
+        reward_tps * 20 + reward_tps / 2 * 40
+    );
+    jump(e, 100);
+    liq_pool.claim(&user1);
+    assert_eq!(setup.token_reward.balance(&user1) as u128, reward_tps * 60);
+}
+
+#[test]
+fn test_set_reward_storage() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let admin = setup.users[0].clone();
+    let user1 = setup.users[1].clone();
+    let new_storage = Address::generate(e);
+    let reward_tps = TestConfig::default().reward_tps;
+
+    liq_pool.deposit(&user1, &Vec::from_array(e, [100, 100]));
+    jump(e, 20);
+    let pool_balance = setup.token_reward.balance(&liq_pool.address);
+    liq_pool.set_reward_storage(&admin, &new_storage);
+    assert_eq!(liq_pool.get_reward_storage(), new_storage);
+    // whole campaign budget moves to the new storage, accrual is kept
+    let budget = (reward_tps * 60) as i128;
+    assert_eq!(setup.token_reward.balance(&new_storage), budget);
+    assert_eq!(
+        setup.token_reward.balance(&liq_pool.address),
+        pool_balance - budget
+    );
+    assert_eq!(liq_pool.get_user_reward(&user1), reward_tps * 20);
+
+    jump(e, 20);
+    setup
+        .token_reward
+        .approve(&new_storage, &liq_pool.address, &budget, &99999);
+    assert_eq!(liq_pool.claim(&user1), reward_tps * 40);
+    assert_eq!(
+        setup.token_reward.balance(&new_storage),
+        budget - (reward_tps * 40) as i128
+    );
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #102)")]
+fn test_set_reward_storage_not_admin() {
+    let setup = Setup::default();
+    let user1 = setup.users[1].clone();
+    setup.liq_pool.set_reward_storage(&user1, &user1);
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #215)")]
+fn test_top_up_expired_rewards() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let admin = setup.users[0].clone();
+
+    jump(e, 60);
+    setup.liq_pool.top_up_rewards(&admin, &1_0000000);
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #216)")]
+fn test_extend_rewards_shorter() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let admin = setup.users[0].clone();
+
+    setup
+        .liq_pool
+        .extend_rewards(&admin, &(e.ledger().timestamp() + 30));
+}
+
+#[test]
+fn test_rewards_last_second_deposit() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let user1 = setup.users[0].clone();
+    let user2 = setup.users[1].clone();
+    let reward_tps = TestConfig::default().reward_tps;
+
+    // user1 provides liquidity for the whole campaign, user2 brings 10x for the last second
+    liq_pool.deposit(&user1, &Vec::from_array(e, [100, 100]));
+    jump(e, 59);
+    liq_pool.deposit(&user2, &Vec::from_array(e, [1000, 1000]));
+    assert_eq!(liq_pool.get_user_reward(&user2), 0);
+    jump(e, 100);
+
+    assert_eq!(liq_pool.claim(&user2), reward_tps * 1000 / 1100);
+    assert_eq!(
+        liq_pool.claim(&user1),
+        reward_tps * 59 + reward_tps * 100 / 1100
+    );
+}
+
+#[test]
+fn test_rewards_time_weighted() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let user1 = setup.users[0].clone();
+    let user2 = setup.users[1].clone();
+    let reward_tps = TestConfig::default().reward_tps;
+
+    // user1 never touches the pool after the deposit
+    liq_pool.deposit(&user1, &Vec::from_array(e, [100, 100]));
+    jump(e, 20);
+    liq_pool.deposit(&user2, &Vec::from_array(e, [100, 100]));
+    jump(e, 20);
+    setup
+        .token_share
+        .approve(&user2, &liq_pool.address, &100, &99999);
+    liq_pool.withdraw(&user2, &100, &Vec::from_array(e, [0, 0]));
+    // liquidity provided for the interval only
+    assert_eq!(liq_pool.get_user_reward(&user2), reward_tps * 10);
+    jump(e, 100);
+
+    assert_eq!(liq_pool.claim(&user2), reward_tps * 10);
+    assert_eq!(liq_pool.claim(&user1), reward_tps * 50);
+}
+
+#[test]
+fn test_reward_checkpoints() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let user1 = setup.users[0].clone();
+    let user2 = setup.users[1].clone();
+
+    // nothing is recorded until enabled
+    liq_pool.deposit(&user1, &Vec::from_array(e, [100, 100]));
+    jump(e, 10);
+    liq_pool.deposit(&user2, &Vec::from_array(e, [10, 10]));
+    assert!(liq_pool.get_reward_checkpoints(&0, &100).is_empty());
+    liq_pool.set_reward_checkpoints(&user1, &true);
+    let first_block = liq_pool.get_pool_reward_data().block;
+
+    // every pool update starts a new block, expiration in between adds the catchup block
+    for _ in 0..6 {
+        jump(e, 10);
+        liq_pool.deposit(&user2, &Vec::from_array(e, [10, 10]));
+    }
+    let data = liq_pool.get_pool_reward_data();
+    assert_eq!(data.last_time, e.ledger().timestamp());
+    assert!(data.block > 8);
+
+    // pages are continuous and accumulator never decreases
+    let mut next_block = first_block;
+    let mut last_reward_per_share = 0;
+    loop {
+        let page = liq_pool.get_reward_checkpoints(&next_block, &3);
+        if page.is_empty() {
+            break;
+        }
+        assert!(page.len() <= 3);
+        for (block, reward_per_share) in page.iter() {
+            assert_eq!(block, next_block);
+            assert!(reward_per_share >= last_reward_per_share);
+            next_block += 1;
+            last_reward_per_share = reward_per_share;
+        }
+    }
+    assert_eq!(next_block, data.block + 1);
+    assert_eq!(last_reward_per_share, data.reward_per_share);
+    assert!(last_reward_per_share > 0);
+
+    // limit is capped, pages past the last block are empty
+    assert_eq!(
+        liq_pool.get_reward_checkpoints(&0, &u32::MAX).len() as u64,
+        data.block + 1 - first_block
+    );
+    assert!(liq_pool
+        .get_reward_checkpoints(&(data.block + 1), &10)
+        .is_empty());
+
+    // disabled again, new blocks aren't recorded
+    liq_pool.set_reward_checkpoints(&user1, &false);
+    jump(e, 10);
+    liq_pool.deposit(&user2, &Vec::from_array(e, [10, 10]));
+    assert!(liq_pool.get_pool_reward_data().block > data.block);
+    assert!(liq_pool
+        .get_reward_checkpoints(&(data.block + 1), &10)
+        .is_empty());
+}
+
+// 100 tokens over 10 seconds, 400 over 20 seconds, 50 over 10 seconds
+fn set_rewards_schedule(setup: &Setup) -> u64 {
+    let e = &setup.env;
+    let start = e.ledger().timestamp();
+    setup.liq_pool.set_rewards_schedule(
+        &setup.users[0],
+        &Vec::from_array(
+            e,
+            [
+                (start + 10, 100_0000000),
+                (start + 30, 400_0000000),
+                (start + 40, 50_0000000),
+            ],
+        ),
+    );
+    start
+}
+
+#[test]
+fn test_rewards_schedule_no_interactions() {
+    let config = TestConfig::default();
+    let setup = Setup::setup(&config);
+    setup.mint_tokens_for_users(&config.mint_to_user);
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let user1 = setup.users[1].clone();
+
+    liq_pool.deposit(&user1, &Vec::from_array(e, [100, 100]));
+    let start = set_rewards_schedule(&setup);
+    let info = liq_pool.get_rewards_info_v2(&user1);
+    assert_eq!((info.phase, info.next_phase_at), (1, start + 10));
+    assert_eq!(info.budget, 550_0000000);
+
+    // nobody touches the pool across the first boundary
+    jump(e, 25);
+    let info = liq_pool.get_rewards_info_v2(&user1);
+    assert_eq!((info.phase, info.next_phase_at), (2, start + 30));
+    assert_eq!(info.tps, 20_0000000);
+    assert_eq!(info.to_claim, 100_0000000 + 15 * 20_0000000);
+    let info = liq_pool.get_rewards_info(&user1);
+    assert_eq!(info.get(symbol_short!("phase")), Some(2));
+    assert_eq!(
+        info.get(symbol_short!("next_at")),
+        Some((start + 30) as i128)
+    );
+
+    // and across the rest of the schedule
+    jump(e, 100);
+    let info = liq_pool.get_rewards_info_v2(&user1);
+    assert_eq!((info.phase, info.next_phase_at), (3, 0));
+    assert_eq!(liq_pool.claim(&user1), 550_0000000);
+    assert_eq!(
+        liq_pool.get_reward_rate().get(symbol_short!("tps")),
+        Some(0)
+    );
+}
+
+#[test]
+fn test_rewards_schedule_deposit_mid_phase() {
+    let config = TestConfig::default();
+    let setup = Setup::setup(&config);
+    setup.mint_tokens_for_users(&config.mint_to_user);
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let user1 = setup.users[0].clone();
+    let user2 = setup.users[1].clone();
+
+    liq_pool.deposit(&user1, &Vec::from_array(e, [100, 100]));
+    set_rewards_schedule(&setup);
+    // second phase started 10 seconds ago
+    jump(e, 20);
+    liq_pool.deposit(&user2, &Vec::from_array(e, [100, 100]));
+    assert_eq!(
+        liq_pool.get_reward_rate().get(symbol_short!("tps")),
+        Some(20_0000000)
+    );
+    jump(e, 20);
+
+    // 100 + 200 alone, then 200 and 50 shared
+    assert_eq!(liq_pool.claim(&user1), 425_0000000);
+    assert_eq!(liq_pool.claim(&user2), 125_0000000);
+}
+
+#[test]
+fn test_rewards_schedule_total_distribution() {
+    let config = TestConfig::default();
+    let setup = Setup::setup(&config);
+    setup.mint_tokens_for_users(&config.mint_to_user);
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let admin = setup.users[0].clone();
+    let user1 = setup.users[1].clone();
+
+    liq_pool.deposit(&admin, &Vec::from_array(e, [100, 100]));
+    set_rewards_schedule(&setup);
+    assert_eq!(liq_pool.get_rewards_info_v2(&admin).budget, 550_0000000);
+
+    jump(e, 5);
+    liq_pool.deposit(&user1, &Vec::from_array(e, [300, 300]));
+    jump(e, 17);
+    assert_eq!(liq_pool.claim(&admin), 122_5000000);
+    jump(e, 11);
+    setup
+        .token_share
+        .approve(&user1, &liq_pool.address, &150, &99999);
+    liq_pool.withdraw(&user1, &150, &Vec::from_array(e, [0, 0]));
+    jump(e, 100);
+    assert_eq!(liq_pool.claim(&admin), 57_7500000);
+    assert_eq!(liq_pool.claim(&user1), 369_7500000);
+
+    // whole schedule is distributed, nothing is left to withdraw
+    assert_eq!(setup.token_reward.balance(&admin), 180_2500000);
+    assert_eq!(setup.token_reward.balance(&user1), 369_7500000);
+    assert_eq!(liq_pool.get_rewards_info_v2(&admin).budget, 0);
+    assert_eq!(liq_pool.withdraw_unused_rewards(&admin, &admin), 0);
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #216)")]
+fn test_rewards_schedule_unordered() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let start = e.ledger().timestamp();
+    setup.liq_pool.set_rewards_schedule(
+        &setup.users[0],
+        &Vec::from_array(e, [(start + 30, 100_0000000), (start + 20, 100_0000000)]),
+    );
+}
+
+#[test]
+fn test_rewards_funded_from_admin() {
+    let config = TestConfig {
+        rewards_count: 0,
+        ..TestConfig::default()
+    };
+    let setup = Setup::setup(&config);
+    setup.mint_tokens_for_users(&config.mint_to_user);
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let admin = setup.users[0].clone();
+    let user1 = setup.users[1].clone();
+    let total_reward = config.reward_tps * 60;
+
+    setup.token_reward.mint(&admin, &(total_reward as i128));
+    setup
+        .token_reward
+        .approve(&admin, &liq_pool.address, &(total_reward as i128), &99999);
+    liq_pool.set_rewards_config(&admin, &(e.ledger().timestamp() + 60), &config.reward_tps);
+    assert_eq!(setup.token_reward.balance(&admin), 0);
+    assert_eq!(
+        setup.token_reward.balance(&liq_pool.address) as u128,
+        total_reward
+    );
+    assert_eq!(liq_pool.get_rewards_info_v2(&user1).budget, total_reward);
+
+    liq_pool.deposit(&user1, &Vec::from_array(e, [100, 100]));
+    jump(e, 100);
+    assert_eq!(liq_pool.claim(&user1), total_reward);
+    assert_eq!(liq_pool.get_rewards_info_v2(&user1).budget, 0);
+}
+
+#[test]
+fn test_rewards_underfunded() {
+    let config = TestConfig {
+        rewards_count: 0,
+        ..TestConfig::default()
+    };
+    let setup = Setup::setup(&config);
+    let e = &setup.env;
+    let admin = setup.users[0].clone();
+
+    // admin holds only half of the campaign
+    let total_reward = config.reward_tps * 60;
+    setup.token_reward.mint(&admin, &(total_reward as i128 / 2));
+    setup.token_reward.approve(
+        &admin,
+        &setup.liq_pool.address,
+        &(total_reward as i128),
+        &99999,
+    );
+    assert!(setup
+        .liq_pool
+        .try_set_rewards_config(&admin, &(e.ledger().timestamp() + 60), &config.reward_tps)
+        .is_err());
+    assert_eq!(setup.token_reward.balance(&admin) as u128, total_reward / 2);
+}
+
+#[test]
+fn test_rewards_unspent_budget_reused() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let admin = setup.users[0].clone();
+    let user1 = setup.users[1].clone();
+    let reward_tps = TestConfig::default().reward_tps;
+    let pool_balance = setup.token_reward.balance(&liq_pool.address);
+
+    liq_pool.deposit(&user1, &Vec::from_array(e, [100, 100]));
+    jump(e, 30);
+    liq_pool.claim(&user1);
+    jump(e, 100);
+
+    // campaign expired. budget still covers what the user has not claimed yet
+    assert_eq!(liq_pool.get_rewards_info_v2(&user1).budget, reward_tps * 30);
+
+    // new campaign is funded from the spare balance, nothing is pulled from the admin
+    setup.token_reward.mint(&admin, &1_0000000);
+    setup
+        .token_reward
+        .approve(&admin, &liq_pool.address, &1_0000000, &99999);
+    liq_pool.set_rewards_config(&admin, &(e.ledger().timestamp() + 60), &reward_tps);
+    assert_eq!(setup.token_reward.balance(&admin), 1_0000000);
+    assert_eq!(liq_pool.get_rewards_info_v2(&user1).budget, reward_tps * 90);
+
+    jump(e, 100);
+    assert_eq!(liq_pool.claim(&user1), reward_tps * 90);
+    assert_eq!(
+        setup.token_reward.balance(&liq_pool.address) as u128,
+        pool_balance as u128 - reward_tps * 120
+    );
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #217)")]
+fn test_claim_over_budget() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let user1 = setup.users[1].clone();
+
+    liq_pool.deposit(&user1, &Vec::from_array(e, [100, 100]));
+    jump(e, 30);
+    // budget accounting drifted from the rewards accrual
+    e.as_contract(&liq_pool.address, || put_reward_claimed(e, 1));
+    jump(e, 100);
+    liq_pool.claim(&user1);
+}
+
+#[test]
+fn test_first_deposit_shares() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let user1 = setup.users[1].clone();
+
+    // value at implied price is measured in sqrt(a * b) units regardless of skew
+    assert_eq!(
+        setup
+            .liq_pool
+            .deposit(&user1, &Vec::from_array(e, [100, 400]))
+            .1,
+        200
+    );
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #218)")]
+fn test_first_deposit_zero_shares() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let user1 = setup.users[1].clone();
+
+    setup
+        .liq_pool
+        .deposit(&user1, &Vec::from_array(e, [0, 100]));
+}
+
+#[test]
+fn test_different_decimals() {
+    let e = Env::default();
+    e.mock_all_auths();
+    e.budget().reset_unlimited();
+
+    let users = Setup::generate_random_users(&e, 2);
+    let admin = users[0].clone();
+    let user1 = users[1].clone();
+    let token7 = create_token_contract(&e, &admin);
+    let token18 = create_token_contract_with_decimals(&e, &admin, 18);
+    let token_reward = create_token_contract(&e, &admin);
+    let (idx7, idx18) = match token7.address < token18.address {
+        true => (0, 1),
+        false => (1, 0),
+    };
+    let ordered = |amount7: u128, amount18: u128| match idx7 {
+        0 => Vec::from_array(&e, [amount7, amount18]),
+        _ => Vec::from_array(&e, [amount18, amount7]),
+    };
+    let tokens = match idx7 {
+        0 => Vec::from_array(&e, [token7.address.clone(), token18.address.clone()]),
+        _ => Vec::from_array(&e, [token18.address.clone(), token7.address.clone()]),
+    };
+    let plane = create_plane_contract(&e);
+    let liq_pool = create_liqpool_contract(
+        &e,
+        &admin,
+        &install_token_wasm(&e),
+        &tokens,
+        &token_reward.address,
+        30,
+        &plane.address,
+    );
+    token7.mint(&user1, &1_000_0000000);
+    token18.mint(&user1, &1_000_000000000000000000);
+
+    // one token of each side is worth a single share, same as for 7 decimals pair
+    let (_, shares) = liq_pool.deposit(&user1, &ordered(1_0000000, 1_000000000000000000));
+    assert_eq!(shares, 1_0000000);
+    assert_eq!(liq_pool.get_price(), 1_0000000);
+
+    // price follows the normalized reserves ratio
+    liq_pool.deposit(&user1, &ordered(1_0000000, 1_000000000000000000));
+    let reserves = liq_pool.get_reserves();
+    assert_eq!(reserves, ordered(2_0000000, 2_000000000000000000));
+    let out = liq_pool.swap(&user1, &idx7, &idx18, &2_0000000, &0);
+    let reserve18 = 2_000000000000000000 - out;
+    assert_eq!(liq_pool.get_reserves(), ordered(4_0000000, reserve18));
+    // token7 reserve doubled, so it's worth ~0.25 token18 now
+    let normalized18 = reserve18 / 100000000000;
+    let expected_price = match idx7 {
+        0 => normalized18 * 1_0000000 / 4_0000000,
+        _ => 4_0000000 * 1_0000000 / normalized18,
+    };
+    assert_eq!(liq_pool.get_price(), expected_price);
+    assert_approx_eq_abs(normalized18 * 1_0000000 / 4_0000000, 2500000, 10000);
+}
+
+#[test]
+fn test_first_depositor_advantage() {
+    // market price is 1:1, first depositor seeds the pool at 1:skew
+    for skew in [1_u128, 2, 4, 10, 100] {
+        let config = TestConfig {
+            users_count: 3,
+            mint_to_user: 1_000_000_000_0000000,
+            liq_pool_fee: 0,
+            ..TestConfig::default()
+        };
+        let setup = Setup::setup(&config);
+        setup.mint_tokens_for_users(&config.mint_to_user);
+        let e = &setup.env;
+        let liq_pool = &setup.liq_pool;
+        let (first, arbitrager, second) = (
+            setup.users[0].clone(),
+            setup.users[1].clone(),
+            setup.users[2].clone(),
+        );
+
+        let seed = 1_000_0000000_u128;
+        let (first_amounts, first_shares) =
+            liq_pool.deposit(&first, &Vec::from_array(e, [seed, seed * skew]));
+        let first_value = first_amounts.get(0).unwrap() + first_amounts.get(1).unwrap();
+
+        // arbitrage brings pool back to the market price: a = b = sqrt(k)
+        let balanced = (seed * seed * skew).sqrt();
+        if balanced > seed {
+            liq_pool.swap(&arbitrager, &0, &1, &(balanced - seed), &0);
+        }
+
+        let (second_amounts, second_shares) =
+            liq_pool.deposit(&second, &Vec::from_array(e, [seed, seed]));
+        let second_value = second_amounts.get(0).unwrap() + second_amounts.get(1).unwrap();
+
+        setup
+            .token_share
+            .approve(&first, &liq_pool.address, &(first_shares as i128), &99999);
+        setup
+            .token_share
+            .approve(&second, &liq_pool.address, &(second_shares as i128), &99999);
+        let first_out = liq_pool.withdraw(&first, &first_shares, &Vec::from_array(e, [0, 0]));
+        let second_out = liq_pool.withdraw(&second, &second_shares, &Vec::from_array(e, [0, 0]));
+
+        // first depositor pays for the off-market seeding: 2 * sqrt(skew) <= 1 + skew
+        let first_value_out = first_out.get(0).unwrap() + first_out.get(1).unwrap();
+        assert_approx_eq_abs(first_value_out, 2 * balanced, 10);
+        assert!(first_value_out <= first_value);
+        // later depositor gets exactly the value deposited, no matter how the pool was seeded
+        let second_value_out = second_out.get(0).unwrap() + second_out.get(1).unwrap();
+        assert_approx_eq_abs(second_value_out, second_value, 10);
+    }
+}
+
+#[test]
+fn test_first_depositor_bootstrap() {
+    // market price is 1:1. Attacker seeds the pool at 1:skew, victim deposits at the pool ratio,
+    //  then attacker arbitrages the pool back to the market price.
+    // Returns attacker gain and victim loss at the market price
+    let run = |skew: u128, bootstrap: bool| -> (i128, i128) {
+        let config = TestConfig {
+            users_count: 4,
+            mint_to_user: 1_000_000_000_0000000,
+            liq_pool_fee: 0,
+            ..TestConfig::default()
+        };
+        let setup = Setup::setup(&config);
+        setup.mint_tokens_for_users(&config.mint_to_user);
+        let e = &setup.env;
+        let liq_pool = &setup.liq_pool;
+        let (admin, attacker, victim, bootstrapper) = (
+            setup.users[0].clone(),
+            setup.users[1].clone(),
+            setup.users[2].clone(),
+            setup.users[3].clone(),
+        );
+        let value = |user: &Address| setup.token1.balance(user) + setup.token2.balance(user);
+        let (attacker_before, victim_before) = (value(&attacker), value(&victim));
+        let withdraw_all = |user: &Address| {
+            let shares = setup.token_share.balance(user);
+            setup
+                .token_share
+                .approve(user, &liq_pool.address, &shares, &99999);
+            liq_pool.withdraw(user, &(shares as u128), &Vec::from_array(e, [0, 0]));
+        };
+
+        let seed = 1_0000000_u128;
+        let seed_amounts = Vec::from_array(e, [seed, seed * skew]);
+        if bootstrap {
+            liq_pool.set_bootstrapper(&admin, &bootstrapper);
+            assert_eq!(liq_pool.get_bootstrapper(), Some(bootstrapper.clone()));
+            assert_eq!(
+                liq_pool.try_deposit(&attacker, &seed_amounts).unwrap_err(),
+                Ok(soroban_sdk::Error::from_contract_error(256))
+            );
+            liq_pool.deposit(&bootstrapper, &Vec::from_array(e, [seed, seed]));
+        } else {
+            liq_pool.deposit(&attacker, &seed_amounts);
+        }
+
+        let deposit = 1_000_0000000_u128;
+        liq_pool.deposit(&victim, &Vec::from_array(e, [deposit, deposit * skew]));
+
+        let reserves = liq_pool.get_reserves();
+        let (reserve_a, reserve_b) = (reserves.get(0).unwrap(), reserves.get(1).unwrap());
+        let balanced = (reserve_a * reserve_b).sqrt();
+        if balanced > reserve_a {
+            liq_pool.swap(&attacker, &0, &1, &(balanced - reserve_a), &0);
+        }
+
+        withdraw_all(&victim);
+        if !bootstrap {
+            withdraw_all(&attacker);
+        }
+        (
+            value(&attacker) - attacker_before,
+            victim_before - value(&victim),
+        )
+    };
+
+    let deposit = 1_000_0000000_u128;
+    for skew in [1_u128, 2, 4, 10, 100] {
+        // attacker takes from the victim v * (1 + skew - 2 * sqrt(skew))
+        let expected = (deposit * (1 + skew) - 2 * (deposit * deposit * skew).sqrt()) as i128;
+        let (attacker_gain, victim_loss) = run(skew, false);
+        assert!((victim_loss - expected).abs() <= 1);
+        assert!((attacker_gain - expected).abs() <= 1);
+
+        // bootstrapper seeds at the market price, nothing to take
+        assert_eq!(run(skew, true), (0, 0));
+    }
+}
+
+#[test]
+fn test_set_bootstrapper_not_empty() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let admin = setup.users[0].clone();
+    let user1 = setup.users[1].clone();
+
+    liq_pool.deposit(&user1, &Vec::from_array(e, [100, 100]));
+    assert_eq!(
+        liq_pool.try_set_bootstrapper(&admin, &user1).unwrap_err(),
+        Ok(soroban_sdk::Error::from_contract_error(244))
+    );
+    assert_eq!(liq_pool.get_bootstrapper(), None);
+}
+
+#[test]
+fn test_remove_bootstrapper() {
+    let setup = Setup::new_with_config(&TestConfig {
+        users_count: 3,
+        ..TestConfig::default()
+    });
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let admin = setup.users[0].clone();
+    let (user1, user2) = (setup.users[1].clone(), setup.users[2].clone());
+
+    // opt-in: anyone may seed the pool unless admin sets the bootstrapper
+    assert_eq!(liq_pool.get_bootstrapper(), None);
+    liq_pool.set_bootstrapper(&admin, &user1);
+    assert_eq!(
+        liq_pool
+            .try_deposit(&user2, &Vec::from_array(e, [100, 100]))
+            .unwrap_err(),
+        Ok(soroban_sdk::Error::from_contract_error(256))
+    );
+
+    liq_pool.remove_bootstrapper(&admin);
+    assert_eq!(
+        vec![e, e.events().all().last().unwrap()],
+        vec![
+            e,
+            (
+                liq_pool.address.clone(),
+                (Symbol::new(e, "remove_bootstrapper"),).into_val(e),
+                ().into_val(e),
+            )
+        ]
+    );
+    assert_eq!(liq_pool.get_bootstrapper(), None);
+    liq_pool.deposit(&user2, &Vec::from_array(e, [100, 100]));
+    assert_eq!(setup.token_share.balance(&user2), 100);
+}
+
+#[test]
+fn test_withdraw_unused_rewards() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let admin = setup.users[0].clone();
+    let user1 = setup.users[1].clone();
+    let reward_tps = TestConfig::default().reward_tps;
+
+    liq_pool.deposit(&user1, &Vec::from_array(e, [100, 100]));
+    jump(e, 20);
+    assert_eq!(liq_pool.claim(&user1), reward_tps * 20);
+
+    // campaign cut short: 60 seconds were funded, 30 seconds distributed
+    liq_pool.set_rewards_config(&admin, &(e.ledger().timestamp() + 10), &reward_tps);
+    assert!(liq_pool
+        .try_withdraw_unused_rewards(&admin, &admin)
+        .is_err());
+
+    jump(e, 20);
+    assert_eq!(
+        liq_pool.withdraw_unused_rewards(&admin, &admin),
+        reward_tps * 30
+    );
+    assert_eq!(setup.token_reward.balance(&admin) as u128, reward_tps * 30);
+    assert_eq!(liq_pool.withdraw_unused_rewards(&admin, &admin), 0);
+
+    // reward accrued but not claimed yet is untouched
+    assert_eq!(liq_pool.claim(&user1), reward_tps * 10);
+    assert_eq!(liq_pool.get_rewards_info_v2(&user1).budget, 0);
+}
+
+#[test]
+fn test_stop_rewards() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let admin = setup.users[0].clone();
+    let user1 = setup.users[1].clone();
+    let reward_tps = TestConfig::default().reward_tps;
+
+    liq_pool.deposit(&user1, &Vec::from_array(e, [100, 100]));
+    jump(e, 10);
+    liq_pool.deposit(&admin, &Vec::from_array(e, [100, 100]));
+    jump(e, 10);
+
+    // 60 seconds were funded, 20 seconds distributed
+    assert_eq!(liq_pool.stop_rewards(&admin), reward_tps * 40);
+    assert_eq!(
+        liq_pool
+            .get_reward_rate()
+            .get(symbol_short!("tps"))
+            .unwrap(),
+        0
+    );
+    assert_eq!(
+        liq_pool.try_stop_rewards(&admin).unwrap_err(),
+        Ok(soroban_sdk::Error::from_contract_error(215))
+    );
+
+    // only accrual made before the stop is paid
+    jump(e, 30);
+    assert_eq!(liq_pool.claim(&user1), reward_tps * 15);
+    assert_eq!(liq_pool.claim(&admin), reward_tps * 5);
+    assert_eq!(
+        liq_pool.withdraw_unused_rewards(&admin, &admin),
+        reward_tps * 40
+    );
+    assert_eq!(liq_pool.get_rewards_info_v2(&user1).budget, 0);
+}
+
+#[test]
+fn test_stop_rewards_schedule() {
+    let config = TestConfig::default();
+    let setup = Setup::setup(&config);
+    setup.mint_tokens_for_users(&config.mint_to_user);
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let admin = setup.users[0].clone();
+    let user1 = setup.users[1].clone();
+
+    // 550 funded, 100 of the first phase and half of the second distributed
+    liq_pool.deposit(&user1, &Vec::from_array(e, [100, 100]));
+    let start = set_rewards_schedule(&setup);
+    jump(e, 15);
+    assert_eq!(liq_pool.stop_rewards(&admin), 350_0000000);
+    assert_eq!(liq_pool.get_rewards_info_v2(&user1).phase, 0);
+
+    // new campaign rewards only the time after it starts
+    liq_pool.set_rewards_config(&admin, &(start + 35), &config.reward_tps);
+    jump(e, 10);
+    assert_eq!(liq_pool.claim(&user1), 200_0000000 + config.reward_tps * 10);
+}
+
+#[test]
+fn test_poke() {
+    let config = TestConfig::default();
+    let setup = Setup::setup(&config);
+    setup.mint_tokens_for_users(&config.mint_to_user);
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let user1 = setup.users[1].clone();
+
+    liq_pool.deposit(&user1, &Vec::from_array(e, [100, 100]));
+    set_rewards_schedule(&setup);
+    let block = liq_pool.get_pool_reward_data().block;
+
+    // nobody touches the pool for all the phases
+    jump(e, 100);
+    let data = liq_pool.poke();
+    assert!(data.block > block);
+    assert_eq!(data.last_time, e.ledger().timestamp());
+    assert_eq!(data.accumulated, 550_0000000);
+    assert_eq!(
+        vec![e, e.events().all().last().unwrap()],
+        vec![
+            e,
+            (
+                liq_pool.address.clone(),
+                (symbol_short!("poke"),).into_val(e),
+                (data.block, 550_0000000_u128).into_val(e),
+            ),
+        ]
+    );
+    assert_eq!(liq_pool.get_rewards_info_v2(&user1).phase, 3);
+    assert_eq!(liq_pool.claim(&user1), 550_0000000);
+}
+
+#[test]
+fn test_rewards_zero_shares_before_first_deposit() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let admin = setup.users[0].clone();
+    let user1 = setup.users[1].clone();
+    let reward_tps = TestConfig::default().reward_tps;
+
+    // 20 seconds of emission nobody could earn
+    jump(e, 20);
+    liq_pool.deposit(&user1, &Vec::from_array(e, [100, 100]));
+    assert_eq!(liq_pool.get_user_reward(&user1), 0);
+
+    jump(e, 60);
+    assert_eq!(liq_pool.claim(&user1), reward_tps * 40);
+    assert_eq!(
+        liq_pool.withdraw_unused_rewards(&admin, &admin),
+        reward_tps * 20
+    );
+}
+
+#[test]
+fn test_rewards_zero_shares_everyone_exits() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let admin = setup.users[0].clone();
+    let user1 = setup.users[1].clone();
+    let reward_tps = TestConfig::default().reward_tps;
+
+    liq_pool.deposit(&user1, &Vec::from_array(e, [100, 100]));
+    jump(e, 20);
+    setup
+        .token_share
+        .approve(&user1, &liq_pool.address, &100, &99999);
+    liq_pool.withdraw(&user1, &100, &Vec::from_array(e, [0, 0]));
+
+    // pool is empty for 20 seconds, then admin rejoins
+    jump(e, 20);
+    liq_pool.deposit(&admin, &Vec::from_array(e, [100, 100]));
+    assert_eq!(liq_pool.get_user_reward(&admin), 0);
+    jump(e, 30);
+
+    assert_eq!(liq_pool.claim(&user1), reward_tps * 20);
+    assert_eq!(liq_pool.claim(&admin), reward_tps * 20);
+    assert_eq!(
+        liq_pool.withdraw_unused_rewards(&admin, &admin),
+        reward_tps * 20
+    );
+    assert_eq!(liq_pool.get_rewards_info_v2(&user1).budget, 0);
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #219)")]
+fn test_withdraw_unused_rewards_active() {
+    let setup = Setup::default();
+    let admin = setup.users[0].clone();
+
+    setup.liq_pool.withdraw_unused_rewards(&admin, &admin);
+}
+
+#[test]
+fn test_rounding_favors_pool() {
+    // deterministic pseudo random sequence
+    fn next(state: &mut u64, max: u128) -> u128 {
+        *state = state
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (*state >> 33) as u128 % max
+    }
+    // dust amounts mixed with regular ones
+    fn amount(state: &mut u64) -> u128 {
+        match next(state, 3) {
+            0 => next(state, 10) + 1,
+            _ => next(state, 1_000_000) + 1,
+        }
+    }
+
+    for seed in [1_u64, 2, 3] {
+        let config = TestConfig {
+            users_count: 3,
+            mint_to_user: 100_000_000,
+            ..TestConfig::default()
+        };
+        let setup = Setup::setup(&config);
+        setup.mint_tokens_for_users(&config.mint_to_user);
+        let e = &setup.env;
+        let liq_pool = &setup.liq_pool;
+        let total_shares = || {
+            setup
+                .users
+                .iter()
+                .map(|user| setup.token_share.balance(user) as u128)
+                .sum::<u128>()
+        };
+        let mut state = seed;
+
+        liq_pool.deposit(&setup.users[0], &Vec::from_array(e, [1_000_000, 1_000_000]));
+        for _ in 0..60 {
+            let user = &setup.users[next(&mut state, 3) as usize];
+            let reserves = liq_pool.get_reserves();
+            let (reserve_a, reserve_b) = (reserves.get(0).unwrap(), reserves.get(1).unwrap());
+            let shares = total_shares();
+
+            match next(&mut state, 3) {
+                0 => {
+                    let amounts = Vec::from_array(e, [amount(&mut state), amount(&mut state)]);
+                    // zero shares deposit is rejected
+                    let _ = liq_pool.try_deposit(user, &amounts);
+                }
+                1 => {
+                    let in_idx = next(&mut state, 2) as u32;
+                    let in_amount = amount(&mut state);
+                    let _ = liq_pool.try_swap(user, &in_idx, &(1 - in_idx), &in_amount, &0);
+                }
+                _ => {
+                    let balance = setup.token_share.balance(user) as u128;
+                    let share_amount = next(&mut state, balance + 1);
+                    if share_amount == 0 || share_amount == shares {
+                        continue;
+                    }
+                    setup.token_share.approve(
+                        user,
+                        &liq_pool.address,
+                        &(share_amount as i128),
+                        &99999,
+                    );
+                    liq_pool.withdraw(user, &share_amount, &Vec::from_array(e, [0, 0]));
+                }
+            }
+
+            let new_reserves = liq_pool.get_reserves();
+            let (new_reserve_a, new_reserve_b) =
+                (new_reserves.get(0).unwrap(), new_reserves.get(1).unwrap());
+            let new_shares = total_shares();
+
+            // liquidity per share never decreases
+            assert!(
+                new_reserve_a * new_reserve_b * shares * shares
+                    >= reserve_a * reserve_b * new_shares * new_shares
+            );
+            // pool is always able to pay out the reserves
+            assert!(setup.token1.balance(&liq_pool.address) as u128 >= new_reserve_a);
+            assert!(setup.token2.balance(&liq_pool.address) as u128 >= new_reserve_b);
+        }
+    }
+}
+
+#[test]
+fn test_lock_shares_boost() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let (user1, user2) = (setup.users[0].clone(), setup.users[1].clone());
+    let total_reward = TestConfig::default().reward_tps * 60;
+
+    liq_pool.deposit(&user1, &Vec::from_array(e, [100, 100]));
+    liq_pool.deposit(&user2, &Vec::from_array(e, [100, 100]));
+    // max duration doubles locked shares weight: 200 vs 100
+    liq_pool.lock_shares(&user1, &100, &MAX_LOCK_DURATION);
+    let lock = liq_pool.get_lock(&user1).unwrap();
+    assert_eq!(lock.amount, 100);
+    assert_eq!(lock.boost, 100);
+    assert_eq!(lock.unlock_at, e.ledger().timestamp() + MAX_LOCK_DURATION);
+    assert!(liq_pool.get_lock(&user2).is_none());
+
+    jump(e, 100);
+    assert_approx_eq_abs(liq_pool.claim(&user1), total_reward * 2 / 3, 10);
+    assert_approx_eq_abs(liq_pool.claim(&user2), total_reward / 3, 10);
+}
+
+#[test]
+fn test_lock_shares_partial_boost() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let user1 = setup.users[1].clone();
+
+    liq_pool.deposit(&user1, &Vec::from_array(e, [100, 100]));
+    liq_pool.lock_shares(&user1, &80, &(MAX_LOCK_DURATION / 4));
+    assert_eq!(liq_pool.get_lock(&user1).unwrap().boost, 20);
+}
+
+#[test]
+fn test_lock_shares_withdraw() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let user1 = setup.users[1].clone();
+
+    liq_pool.deposit(&user1, &Vec::from_array(e, [100, 100]));
+    liq_pool.lock_shares(&user1, &60, &100);
+    setup
+        .token_share
+        .approve(&user1, &liq_pool.address, &100, &99999);
+
+    // unlocked shares are free to go, locked ones are not
+    liq_pool.withdraw(&user1, &40, &Vec::from_array(e, [0, 0]));
+    assert!(matches!(
+        liq_pool.try_withdraw(&user1, &1, &Vec::from_array(e, [0, 0])),
+        Err(Ok(error)) if error == soroban_sdk::Error::from_contract_error(220)
+    ));
+    assert!(matches!(
+        liq_pool.try_unlock(&user1),
+        Err(Ok(error)) if error == soroban_sdk::Error::from_contract_error(220)
+    ));
+
+    // lock expired. anyone may release it
+    jump(e, 100);
+    e.set_auths(&[]);
+    liq_pool.unlock(&user1);
+    assert!(liq_pool.get_lock(&user1).is_none());
+    e.mock_all_auths();
+    liq_pool.withdraw(&user1, &60, &Vec::from_array(e, [0, 0]));
+    assert_eq!(setup.token_share.balance(&user1), 0);
+}
+
+#[test]
+fn test_lock_shares_transfer() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let token_share = &setup.token_share;
+    let (user1, user2) = (setup.users[1].clone(), setup.users[0].clone());
+
+    liq_pool.deposit(&user1, &Vec::from_array(e, [100, 100]));
+    liq_pool.lock_shares(&user1, &60, &100);
+    assert_eq!(token_share.locked(&user1), 60);
+
+    // unlocked shares can be transferred, locked ones stay with the user
+    token_share.transfer(&user1, &user2, &40);
+    assert_eq!(
+        token_share.try_transfer(&user1, &user2, &1).unwrap_err(),
+        Ok(soroban_sdk::Error::from_contract_error(311))
+    );
+    token_share.approve(&user1, &liq_pool.address, &60, &99999);
+    assert_eq!(
+        liq_pool
+            .try_withdraw(&user1, &60, &Vec::from_array(e, [0, 0]))
+            .unwrap_err(),
+        Ok(soroban_sdk::Error::from_contract_error(220))
+    );
+    assert_eq!(liq_pool.get_lock(&user1).unwrap().amount, 60);
+
+    // released with the lock
+    jump(e, 100);
+    liq_pool.unlock(&user1);
+    assert_eq!(token_share.locked(&user1), 0);
+    liq_pool.withdraw(&user1, &30, &Vec::from_array(e, [0, 0]));
+    token_share.transfer(&user1, &user2, &30);
+    assert_eq!(token_share.balance(&user1), 0);
+    assert_eq!(token_share.balance(&user2), 70);
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #221)")]
+fn test_lock_shares_over_balance() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let user1 = setup.users[1].clone();
+
+    setup
+        .liq_pool
+        .deposit(&user1, &Vec::from_array(e, [100, 100]));
+    setup.liq_pool.lock_shares(&user1, &101, &100);
+}
+
+#[test]
+fn test_rewards_not_configured() {
+    let setup = Setup::setup(&TestConfig::default());
+    setup.mint_tokens_for_users(&TestConfig::default().mint_to_user);
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let user1 = setup.users[1].clone();
+
+    jump(e, 10);
+    assert_eq!(liq_pool.get_user_reward(&user1), 0);
+    assert_eq!(liq_pool.get_rewards_info_v2(&user1).to_claim, 0);
+    assert_eq!(liq_pool.claim(&user1), 0);
+    // no user reward entries created
+    e.as_contract(&liq_pool.address, || {
+        assert!(get_rewards_manager(e)
+            .storage()
+            .get_user_reward_data(&user1)
+            .is_none());
+    });
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #222)")]
+fn test_set_rewards_config_zero_tps() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let admin = setup.users[0].clone();
+
+    setup
+        .liq_pool
+        .set_rewards_config(&admin, &(e.ledger().timestamp() + 60), &0);
+}
+
+#[test]
+fn test_referral_bonus() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let admin = setup.users[0].clone();
+    let user1 = setup.users[1].clone();
+    let referrer = Address::generate(e);
+    let reward_tps = TestConfig::default().reward_tps;
+    let pool_balance = setup.token_reward.balance(&liq_pool.address) as u128;
+    let amounts = Vec::from_array(e, [100, 100]);
+
+    let user_balance = setup.token_reward.balance(&user1) as u128;
+
+    // half of depositor accrued rewards to each side, up to 16 seconds of emission per referrer
+    liq_pool.set_referral_bonus(&admin, &5000, &(reward_tps * 16));
+    liq_pool.deposit(&user1, &amounts);
+
+    jump(e, 10);
+    liq_pool.deposit_with_referrer(&user1, &amounts, &Some(referrer.clone()));
+    assert_eq!(
+        setup.token_reward.balance(&referrer) as u128,
+        reward_tps * 5
+    );
+    assert_eq!(
+        setup.token_reward.balance(&user1) as u128,
+        user_balance + reward_tps * 5
+    );
+
+    // 20 seconds of emission to the pair is over the cap left
+    jump(e, 10);
+    liq_pool.deposit_with_referrer(&user1, &amounts, &Some(referrer.clone()));
+    assert_eq!(
+        setup.token_reward.balance(&referrer) as u128,
+        reward_tps * 8
+    );
+    assert_eq!(
+        setup.token_reward.balance(&user1) as u128,
+        user_balance + reward_tps * 8
+    );
+
+    // no bonus once the campaign is over
+    jump(e, 100);
+    liq_pool.deposit_with_referrer(&user1, &amounts, &Some(referrer.clone()));
+    let stats = liq_pool.get_referral_stats(&referrer);
+    assert_eq!(
+        stats.get(symbol_short!("bonus")).unwrap() as u128,
+        reward_tps * 16
+    );
+    assert_eq!(
+        stats.get(symbol_short!("total")).unwrap() as u128,
+        reward_tps * 16
+    );
+    assert_eq!(stats.get(symbol_short!("referrals")).unwrap(), 3);
+
+    // bonus doesn't eat users rewards budget
+    assert_eq!(liq_pool.get_rewards_info_v2(&user1).budget, reward_tps * 60);
+    assert_eq!(liq_pool.claim(&user1), reward_tps * 60);
+    assert_eq!(
+        setup.token_reward.balance(&liq_pool.address) as u128,
+        pool_balance - reward_tps * 76
+    );
+
+    // cap is reset for the new campaign
+    liq_pool.set_rewards_config(&admin, &(e.ledger().timestamp() + 60), &reward_tps);
+    let stats = liq_pool.get_referral_stats(&referrer);
+    assert_eq!(stats.get(symbol_short!("bonus")).unwrap(), 0);
+    assert_eq!(
+        stats.get(symbol_short!("total")).unwrap() as u128,
+        reward_tps * 16
+    );
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #223)")]
+fn test_self_referral() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let user1 = setup.users[1].clone();
+
+    setup.liq_pool.deposit_with_referrer(
+        &user1,
+        &Vec::from_array(e, [100, 100]),
+        &Some(user1.clone()),
+    );
+}
+
+#[test]
+fn test_get_reward_rate() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let user1 = setup.users[1].clone();
+    let reward_tps = TestConfig::default().reward_tps;
+
+    // nobody to distribute to
+    let rate = liq_pool.get_reward_rate();
+    assert_eq!(rate.get(symbol_short!("tps")).unwrap(), 0);
+    assert_eq!(rate.get(symbol_short!("tps_share")).unwrap(), 0);
+
+    liq_pool.deposit(&user1, &Vec::from_array(e, [300, 300]));
+    let rate = liq_pool.get_reward_rate();
+    assert_eq!(rate.get(symbol_short!("tps")).unwrap() as u128, reward_tps);
+    assert_eq!(rate.get(symbol_short!("shares")).unwrap(), 300);
+    assert_eq!(rate.get(symbol_short!("left")).unwrap(), 60);
+
+    // reported rate matches actual accrual over the window
+    let tps_share = rate.get(symbol_short!("tps_share")).unwrap() as u128;
+    jump(e, 20);
+    assert_approx_eq_abs(
+        liq_pool.get_user_reward(&user1),
+        tps_share * 300 * 20 / 1_0000000,
+        1000,
+    );
+
+    jump(e, 50);
+    let rate = liq_pool.get_reward_rate();
+    assert_eq!(rate.get(symbol_short!("tps")).unwrap(), 0);
+    assert_eq!(rate.get(symbol_short!("left")).unwrap(), 0);
+}
+
+#[test]
+fn test_rewards_update_cost_bounded() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let admin = setup.users[0].clone();
+    let user1 = setup.users[1].clone();
+    let reward_tps = TestConfig::default().reward_tps;
+
+    liq_pool.set_rewards_config(&admin, &(e.ledger().timestamp() + 5000), &reward_tps);
+    liq_pool.deposit(&user1, &Vec::from_array(e, [100, 100]));
+    jump(e, 1);
+
+    let measure_deposit = || {
+        e.budget().reset_unlimited();
+        liq_pool.deposit(&user1, &Vec::from_array(e, [1, 1]));
+        let cost = (
+            e.budget().cpu_instruction_cost(),
+            e.budget().memory_bytes_cost(),
+        );
+        jump(e, 1);
+        cost
+    };
+    let (early_cpu, early_mem) = measure_deposit();
+
+    // thousands of blocks user1 didn't participate in
+    for _ in 0..2000 {
+        liq_pool.claim(&admin);
+        jump(e, 1);
+    }
+
+    let (late_cpu, late_mem) = measure_deposit();
+    assert!(late_cpu <= early_cpu * 11 / 10);
+    assert!(late_mem <= early_mem * 11 / 10);
+    assert!(liq_pool.get_user_reward(&user1) > 0);
+}
+
+#[test]
+fn test_rewards_interleaved_payouts() {
+    let config = TestConfig::default();
+    let setup = Setup::setup(&config);
+    setup.mint_tokens_for_users(&config.mint_to_user);
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let user1 = setup.users[0].clone();
+    let user2 = setup.users[1].clone();
+    let tps = config.reward_tps;
+
+    liq_pool.deposit(&user1, &Vec::from_array(e, [100, 100]));
+    liq_pool.deposit(&user2, &Vec::from_array(e, [300, 300]));
+    liq_pool.set_rewards_config(&user1, &(e.ledger().timestamp() + 100), &tps);
+
+    // 20 seconds split 1:3
+    jump(e, 20);
+    assert_eq!(liq_pool.claim(&user1), 5 * tps);
+
+    // 20 seconds split 1:1
+    setup
+        .token_share
+        .approve(&user2, &liq_pool.address, &200, &99999);
+    liq_pool.withdraw(&user2, &200, &Vec::from_array(e, [0, 0]));
+    jump(e, 20);
+
+    // 40 seconds split 3:1 and another 40 seconds after rewards expiration
+    liq_pool.deposit(&user1, &Vec::from_array(e, [200, 200]));
+    jump(e, 80);
+
+    // every period is paid pro rata to the shares held during it
+    assert_eq!(liq_pool.get_user_reward(&user1), (10 + 30 + 15) * tps);
+    assert_eq!(liq_pool.get_user_reward(&user2), (15 + 10 + 10 + 5) * tps);
+    assert_eq!(liq_pool.claim(&user1), 55 * tps);
+    assert_eq!(liq_pool.claim(&user2), 40 * tps);
+    assert_eq!(liq_pool.get_user_reward(&user1), 0);
+    assert_eq!(liq_pool.get_user_reward(&user2), 0);
+}
+
+#[test]
+fn test_backfill_holders() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let admin = setup.users[0].clone();
+    let user1 = setup.users[1].clone();
+
+    liq_pool.deposit(&user1, &Vec::from_array(e, [100, 100]));
+    assert!(liq_pool.backfill_complete());
+    assert_eq!(liq_pool.get_holders_count(), 1);
+
+    // pool from before the holder index: shares minted bypassing the index
+    let legacy_holders = Setup::generate_random_users(e, 6);
+    e.as_contract(&liq_pool.address, || {
+        for holder in legacy_holders.iter() {
+            token_share::mint_shares(e, holder.clone(), 10);
+        }
+        put_backfill_complete(e, false);
+    });
+    assert!(liq_pool.try_get_holders_count().is_err());
+
+    for (idx, chunk) in legacy_holders.chunks(2).enumerate() {
+        let holders = Vec::from_slice(e, chunk);
+        assert_eq!(liq_pool.backfill_holders(&admin, &holders), 2);
+        // resubmitted chunk is skipped
+        assert_eq!(liq_pool.backfill_holders(&admin, &holders), 0);
+        assert_eq!(liq_pool.backfill_complete(), idx == 2);
+    }
+    assert_eq!(liq_pool.get_holders_count(), 7);
+
+    // holders leaving the pool are dropped from the index
+    setup
+        .token_share
+        .approve(&user1, &liq_pool.address, &100, &99999);
+    liq_pool.withdraw(&user1, &100, &Vec::from_array(e, [0, 0]));
+    assert_eq!(liq_pool.get_holders_count(), 6);
+}
+
+#[test]
+fn test_swap_soft_reject() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let admin = setup.users[0].clone();
+    let user1 = setup.users[1].clone();
+    let rejected = |name: &str| SwapOutcome::Rejected(Symbol::new(e, name));
+
+    liq_pool.deposit(&admin, &Vec::from_array(e, [100, 100]));
+    assert_eq!(
+        liq_pool.swap_or_reject(&user1, &0, &1, &10, &0),
+        SwapOutcome::Swapped(9)
+    );
+
+    assert_eq!(
+        liq_pool.swap_or_reject(&user1, &0, &1, &10, &100),
+        rejected("out_min")
+    );
+    assert_eq!(
+        liq_pool.swap_or_reject(&user1, &0, &1, &1000, &0),
+        rejected("balance")
+    );
+    setup.token1.approve(&user1, &liq_pool.address, &5, &99999);
+    assert_eq!(
+        liq_pool.swap_or_reject(&user1, &0, &1, &10, &0),
+        rejected("allowance")
+    );
+    liq_pool.set_paused(&admin, &true);
+    assert_eq!(
+        liq_pool.swap_or_reject(&user1, &0, &1, &1, &0),
+        rejected("paused")
+    );
+    liq_pool.set_paused(&admin, &false);
+    liq_pool.kill_me(&admin);
+    assert_eq!(
+        liq_pool.swap_or_reject(&user1, &0, &1, &1, &0),
+        rejected("killed")
+    );
+    assert_eq!(
+        liq_pool.swap_or_reject(&user1, &0, &1, &1, &0),
+        rejected("killed")
+    );
+
+    // rejected swaps move no funds
+    assert_eq!(setup.token1.balance(&user1), 990);
+    assert_eq!(liq_pool.get_reserves(), Vec::from_array(e, [110, 91]));
+
+    let stats = liq_pool.get_rejection_stats();
+    assert_eq!(stats.get(Symbol::new(e, "killed")).unwrap(), 2);
+    assert_eq!(stats.get(Symbol::new(e, "paused")).unwrap(), 1);
+    assert_eq!(stats.get(Symbol::new(e, "out_min")).unwrap(), 1);
+    assert_eq!(stats.get(Symbol::new(e, "balance")).unwrap(), 1);
+    assert_eq!(stats.get(Symbol::new(e, "allowance")).unwrap(), 1);
+}
+
+#[test]
+fn test_stableswap_near_peg_slippage() {
+    let config = TestConfig {
+        mint_to_user: 2_000_000_0000000,
+        ..TestConfig::default()
+    };
+    let setup = Setup::setup(&config);
+    setup.mint_tokens_for_users(&config.mint_to_user);
+    let e = &setup.env;
+    let user1 = setup.users[1].clone();
+    let tokens = Vec::from_array(
+        e,
+        [setup.token1.address.clone(), setup.token2.address.clone()],
+    );
+    let stable_pool = create_stableswap_liqpool_contract(
+        e,
+        &setup.users[0],
+        &tokens,
+        &setup.token_reward.address,
+        config.liq_pool_fee,
+        100,
+    );
+    for token in [&setup.token1, &setup.token2] {
+        token.approve(&user1, &stable_pool.address, &config.mint_to_user, &99999);
+    }
+    let info = stable_pool.get_info();
+    let curve = info.get(symbol_short!("curve")).unwrap();
+    assert_eq!(Symbol::from_val(e, &curve), symbol_short!("stable"));
+    let amp = info.get(symbol_short!("amp")).unwrap();
+    assert_eq!(u128::from_val(e, &amp), 100);
+
+    let reserve = 1_000_000_0000000_u128;
+    for pool in [&setup.liq_pool, &stable_pool] {
+        pool.deposit(&user1, &Vec::from_array(e, [reserve, reserve]));
+    }
+
+    // 1% of the reserve sold at 1:1 market price
+    let in_amount = 10_000_0000000_u128;
+    let fee_free_out = in_amount * (10000 - config.liq_pool_fee as u128) / 10000;
+    let product_out = setup.liq_pool.swap(&user1, &0, &1, &in_amount, &0);
+    let stable_out = stable_pool.swap(&user1, &0, &1, &in_amount, &0);
+    assert!(stable_out < fee_free_out);
+    assert!((fee_free_out - stable_out) * 50 < fee_free_out - product_out);
+}
+
+#[test]
+fn test_stableswap_invariant_never_decreases() {
+    let config = TestConfig {
+        mint_to_user: 2_000_000_0000000,
+        ..TestConfig::default()
+    };
+    let setup = Setup::setup(&config);
+    setup.mint_tokens_for_users(&config.mint_to_user);
+    let e = &setup.env;
+    let user1 = setup.users[1].clone();
+    let tokens = Vec::from_array(
+        e,
+        [setup.token1.address.clone(), setup.token2.address.clone()],
+    );
+    let amp = 100;
+    let liq_pool = create_stableswap_liqpool_contract(
+        e,
+        &setup.users[0],
+        &tokens,
+        &setup.token_reward.address,
+        config.liq_pool_fee,
+        amp,
+    );
+    for token in [&setup.token1, &setup.token2] {
+        token.approve(&user1, &liq_pool.address, &config.mint_to_user, &99999);
+    }
+    let get_d = || {
+        let reserves = liq_pool.get_reserves();
+        stable_math::get_d(reserves.get(0).unwrap(), reserves.get(1).unwrap(), amp)
+    };
+
+    // balanced liquidity is valued at its sum
+    let (_, shares) = liq_pool.deposit(
+        &user1,
+        &Vec::from_array(e, [1_000_000_0000000, 1_000_000_0000000]),
+    );
+    assert_eq!(shares, 2_000_000_0000000);
+
+    let mut d = get_d();
+    for (i, in_amount) in [
+        1_u128,
+        10,
+        1_000_0000000,
+        50_000_0000000,
+        300_000_0000000,
+        7,
+    ]
+    .iter()
+    .enumerate()
+    {
+        let in_idx = i as u32 % 2;
+        liq_pool.swap(&user1, &in_idx, &(1 - in_idx), in_amount, &0);
+        let new_d = get_d();
+        assert!(new_d >= d);
+        d = new_d;
+    }
+
+    // imbalanced pool still values deposits by the invariant growth
+    let (_, new_shares) =
+        liq_pool.deposit(&user1, &Vec::from_array(e, [1_000_0000000, 1_000_0000000]));
+    assert!(new_shares > 0);
+    assert!(get_d() >= d);
+}
+
+fn setup_weighted_pool<'a>(setup: &Setup<'a>, weights: [u32; 2]) -> LiquidityPoolClient<'a> {
+    let e = &setup.env;
+    let liq_pool = create_weighted_liqpool_contract(
+        e,
+        &setup.users[0],
+        &Vec::from_array(
+            e,
+            [setup.token1.address.clone(), setup.token2.address.clone()],
+        ),
+        &setup.token_reward.address,
+        30,
+        &Vec::from_array(e, weights),
+    );
+    for user in setup.users.iter() {
+        for token in [&setup.token1, &setup.token2] {
+            token.approve(user, &liq_pool.address, &token.balance(user), &99999);
+        }
+    }
+    liq_pool
+}
+
+#[test]
+fn test_weighted_equal_weights_match_constant_product() {
+    let config = TestConfig {
+        mint_to_user: 1_000_000_0000000,
+        ..TestConfig::default()
+    };
+    let setup = Setup::setup(&config);
+    setup.mint_tokens_for_users(&config.mint_to_user);
+    let e = &setup.env;
+    let weighted_pool = setup_weighted_pool(&setup, [50, 50]);
+    let user1 = setup.users[1].clone();
+    let weighted_share = Client::new(e, &weighted_pool.share_id());
+
+    for pool in [&setup.liq_pool, &weighted_pool] {
+        pool.deposit(&user1, &Vec::from_array(e, [1_000_0000000, 3_000_0000000]));
+    }
+    let in_amounts = [1_u128, 7, 100_0000000, 2_345_6789012, 13];
+    for (i, in_amount) in in_amounts.iter().enumerate() {
+        let in_idx = i as u32 % 2;
+        assert_eq!(
+            setup
+                .liq_pool
+                .estimate_swap(&in_idx, &(1 - in_idx), in_amount),
+            weighted_pool.estimate_swap(&in_idx, &(1 - in_idx), in_amount),
+        );
+        assert_eq!(
+            setup
+                .liq_pool
+                .swap(&user1, &in_idx, &(1 - in_idx), in_amount, &0),
+            weighted_pool.swap(&user1, &in_idx, &(1 - in_idx), in_amount, &0),
+        );
+        assert_eq!(setup.liq_pool.get_price(), weighted_pool.get_price());
+    }
+    assert_eq!(
+        setup
+            .liq_pool
+            .deposit(&user1, &Vec::from_array(e, [123_4567890, 98_7654321])),
+        weighted_pool.deposit(&user1, &Vec::from_array(e, [123_4567890, 98_7654321])),
+    );
+
+    setup
+        .token_share
+        .approve(&user1, &setup.liq_pool.address, &1_000_0000000, &99999);
+    weighted_share.approve(&user1, &weighted_pool.address, &1_000_0000000, &99999);
+    assert_eq!(
+        setup
+            .liq_pool
+            .withdraw(&user1, &1_000_0000000, &Vec::from_array(e, [0, 0])),
+        weighted_pool.withdraw(&user1, &1_000_0000000, &Vec::from_array(e, [0, 0])),
+    );
+    assert_eq!(setup.liq_pool.get_reserves(), weighted_pool.get_reserves());
+    assert_eq!(
+        setup.token_share.balance(&user1),
+        weighted_share.balance(&user1)
+    );
+}
+
+#[test]
+fn test_weighted_pool() {
+    let config = TestConfig {
+        mint_to_user: 1_000_000_0000000,
+        ..TestConfig::default()
+    };
+    let setup = Setup::setup(&config);
+    setup.mint_tokens_for_users(&config.mint_to_user);
+    let e = &setup.env;
+    let liq_pool = setup_weighted_pool(&setup, [80, 20]);
+    let user1 = setup.users[1].clone();
+
+    let info = liq_pool.get_info();
+    let curve = info.get(symbol_short!("curve")).unwrap();
+    assert_eq!(Symbol::from_val(e, &curve), symbol_short!("weighted"));
+
+    // weighted geometric mean: 800^0.8 * 200^0.2
+    let (_, shares) = liq_pool.deposit(&user1, &Vec::from_array(e, [800_0000000, 200_0000000]));
+    assert_eq!(shares, 606_2866266);
+    // 80% of the value is in token a, so 800 a : 200 b is priced 1:1
+    assert_eq!(liq_pool.get_price(), 1_0000000);
+
+    // out = 200 * (1 - (800 / (800 + 0.997)) ^ 4), rounded down
+    assert_eq!(liq_pool.estimate_swap(&0, &1, &1_0000000), 9939014);
+    assert_eq!(liq_pool.estimate_swap(&1, &0, &1_0000000), 9939052);
+    assert_eq!(liq_pool.swap(&user1, &0, &1, &1_0000000, &0), 9939014);
+    assert_eq!(
+        liq_pool.get_reserves(),
+        Vec::from_array(e, [801_0000000, 200_0000000 - 9939014])
+    );
+}
+
+#[test]
+fn test_weighted_single_deposit() {
+    let config = TestConfig {
+        mint_to_user: 1_000_000_0000000,
+        ..TestConfig::default()
+    };
+    let setup = Setup::setup(&config);
+    setup.mint_tokens_for_users(&config.mint_to_user);
+    let e = &setup.env;
+    let liq_pool = setup_weighted_pool(&setup, [80, 20]);
+    let share_token = Client::new(e, &liq_pool.share_id());
+    let (user1, user2) = (setup.users[0].clone(), setup.users[1].clone());
+
+    // nothing to price the single token against
+    assert!(liq_pool
+        .try_deposit_single(&user2, &0, &8_0000000, &0)
+        .is_err());
+
+    let (_, total_shares) =
+        liq_pool.deposit(&user1, &Vec::from_array(e, [800_0000000, 200_0000000]));
+
+    // shares = total * ((1 + 8 * (1 - 0.2 * 0.003) / 800) ^ 0.8 - 1), rounded down
+    assert!(liq_pool
+        .try_deposit_single(&user2, &0, &8_0000000, &48425577)
+        .is_err());
+    assert_eq!(
+        liq_pool.deposit_single(&user2, &0, &8_0000000, &48425576),
+        48425576
+    );
+    assert_eq!(share_token.balance(&user2), 48425576);
+    assert_eq!(
+        liq_pool.get_reserves(),
+        Vec::from_array(e, [808_0000000, 200_0000000])
+    );
+    // 1% of token a is 0.8% of the pool value, implicit swap makes it worth a bit less
+    assert!(48425576 < total_shares * 8 / 1000);
+
+    // deposit_single and withdraw is a swap paying the fee, so it's never profitable
+    share_token.approve(&user2, &liq_pool.address, &48425576, &99999);
+    let amounts = liq_pool.withdraw(&user2, &48425576, &Vec::from_array(e, [0, 0]));
+    let swapped_back = liq_pool.swap(&user2, &1, &0, &amounts.get(1).unwrap(), &0);
+    assert!(amounts.get(0).unwrap() + swapped_back < 8_0000000);
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #227)")]
+fn test_single_deposit_stableswap() {
+    let config = TestConfig {
+        mint_to_user: 1_000_0000000,
+        ..TestConfig::default()
+    };
+    let setup = Setup::setup(&config);
+    setup.mint_tokens_for_users(&config.mint_to_user);
+    let e = &setup.env;
+    let user1 = setup.users[1].clone();
+    let liq_pool = create_stableswap_liqpool_contract(
+        e,
+        &setup.users[0],
+        &Vec::from_array(
+            e,
+            [setup.token1.address.clone(), setup.token2.address.clone()],
+        ),
+        &setup.token_reward.address,
+        30,
+        100,
+    );
+    for token in [&setup.token1, &setup.token2] {
+        token.approve(&user1, &liq_pool.address, &config.mint_to_user, &99999);
+    }
+    liq_pool.deposit(&user1, &Vec::from_array(e, [100_0000000, 100_0000000]));
+    liq_pool.deposit_single(&user1, &0, &1_0000000, &0);
+}
+
+#[test]
+fn test_swap_with_referral() {
+    let config = TestConfig {
+        users_count: 3,
+        mint_to_user: 10_000_000,
+        ..TestConfig::default()
+    };
+    // same swaps on the twin pools: plain one and the one paying referrer
+    let setup = Setup::setup(&config);
+    setup.mint_tokens_for_users(&config.mint_to_user);
+    let twin = Setup::setup(&config);
+    twin.mint_tokens_for_users(&config.mint_to_user);
+    for s in [&setup, &twin] {
+        s.liq_pool.deposit(
+            &s.users[0],
+            &Vec::from_array(&s.env, [1_000_000, 1_000_000]),
+        );
+    }
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let (user1, referrer) = (setup.users[1].clone(), setup.users[2].clone());
+
+    // zero referral is the plain swap
+    let out = liq_pool.swap_with_referral(&user1, &0, &1, &100_000, &0, &referrer, &0);
+    assert_eq!(
+        twin.liq_pool.swap(&twin.users[1], &0, &1, &100_000, &0),
+        out
+    );
+    assert_eq!(setup.token1.balance(&referrer), 10_000_000);
+    assert_eq!(liq_pool.get_reserves(), twin.liq_pool.get_reserves());
+
+    // max referral: trader gets the same amount, pool fee income shrinks by the referral amount
+    let estimate = liq_pool.estimate_swap(&0, &1, &100_000);
+    let out = liq_pool.swap_with_referral(&user1, &0, &1, &100_000, &estimate, &referrer, &10);
+    assert_eq!(out, estimate);
+    assert_eq!(
+        twin.liq_pool.swap(&twin.users[1], &0, &1, &100_000, &0),
+        out
+    );
+    assert_eq!(setup.token1.balance(&referrer), 10_000_000 + 100);
+    assert_eq!(
+        liq_pool.get_reserves().get(0).unwrap() + 100,
+        twin.liq_pool.get_reserves().get(0).unwrap()
+    );
+    assert_eq!(
+        liq_pool.get_reserves().get(1).unwrap(),
+        twin.liq_pool.get_reserves().get(1).unwrap()
+    );
+    assert_eq!(
+        vec![e, e.events().all().last().unwrap()],
+        vec![
+            e,
+            (
+                liq_pool.address.clone(),
+                (Symbol::new(e, "swap_referral"), referrer.clone()).into_val(e),
+                (user1.clone(), 100_u128).into_val(e),
+            ),
+        ]
+    );
+
+    // trader referring itself gets the cut back
+    let balance = setup.token2.balance(&user1);
+    let estimate = liq_pool.estimate_swap(&1, &0, &50_000);
+    let token1_balance = setup.token1.balance(&user1);
+    liq_pool.swap_with_referral(&user1, &1, &0, &50_000, &0, &user1, &10);
+    assert_eq!(setup.token2.balance(&user1), balance - 50_000 + 50);
+    assert_eq!(
+        setup.token1.balance(&user1),
+        token1_balance + estimate as i128
+    );
+}
+
+#[test]
+fn test_swap_with_referral_too_high() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let (user1, referrer) = (setup.users[0].clone(), setup.users[1].clone());
+    liq_pool.deposit(&user1, &Vec::from_array(e, [100, 100]));
+
+    assert_eq!(
+        liq_pool
+            .try_swap_with_referral(&user1, &0, &1, &10, &0, &referrer, &11)
+            .unwrap_err(),
+        Ok(soroban_sdk::Error::from_contract_error(228))
+    );
+
+    // referral can't exceed the pool fee
+    let config = TestConfig {
+        liq_pool_fee: 5,
+        ..TestConfig::default()
+    };
+    let setup = Setup::setup(&config);
+    setup.mint_tokens_for_users(&config.mint_to_user);
+    let e = &setup.env;
+    let (user1, referrer) = (setup.users[0].clone(), setup.users[1].clone());
+    setup
+        .liq_pool
+        .deposit(&user1, &Vec::from_array(e, [100, 100]));
+    assert_eq!(
+        setup
+            .liq_pool
+            .try_swap_with_referral(&user1, &0, &1, &10, &0, &referrer, &6)
+            .unwrap_err(),
+        Ok(soroban_sdk::Error::from_contract_error(228))
+    );
+    setup
+        .liq_pool
+        .swap_with_referral(&user1, &0, &1, &10, &0, &referrer, &5);
+}
+
+#[test]
+fn test_share_transferable() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let share_token = &setup.token_share;
+    let admin = setup.users[0].clone();
+    let user1 = setup.users[1].clone();
+    let user2 = Address::generate(e);
+    let error = Ok(soroban_sdk::Error::from_contract_error(310));
+
+    assert_eq!(
+        liq_pool
+            .try_set_share_transferable(&user1, &false)
+            .unwrap_err(),
+        Ok(soroban_sdk::Error::from_contract_error(102))
+    );
+    liq_pool.set_share_transferable(&admin, &false);
+    assert!(!share_token.transferable());
+
+    // deposit and withdraw move shares through the pool
+    liq_pool.deposit(&user1, &Vec::from_array(e, [100, 100]));
+    share_token.approve(&user1, &liq_pool.address, &20, &99999);
+    liq_pool.withdraw(&user1, &20, &Vec::from_array(e, [0, 0]));
+    assert_eq!(share_token.balance(&user1), 80);
+    liq_pool.deposit(&admin, &Vec::from_array(e, [50, 50]));
+    liq_pool.withdraw_all(&admin, &Vec::from_array(e, [0, 0]));
+    assert_eq!(share_token.balance(&admin), 0);
+
+    // peer to peer transfers are blocked
+    assert_eq!(
+        share_token.try_transfer(&user1, &user2, &10).unwrap_err(),
+        error
+    );
+    share_token.approve(&user1, &user2, &10, &99999);
+    assert_eq!(
+        share_token
+            .try_transfer_from(&user2, &user1, &user2, &10)
+            .unwrap_err(),
+        error
+    );
+
+    liq_pool.set_share_transferable(&admin, &true);
+    share_token.transfer(&user1, &user2, &10);
+    assert_eq!(share_token.balance(&user2), 10);
+}
+
+#[test]
+fn test_permissioned_pool() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let (admin, user1) = (setup.users[0].clone(), setup.users[1].clone());
+    let not_allowed = || Ok(soroban_sdk::Error::from_contract_error(229));
+
+    // everyone is allowed unless pool is permissioned
+    assert!(liq_pool.is_allowed(&user1));
+    liq_pool.set_permissioned(&admin, &true);
+    assert!(!liq_pool.is_allowed(&user1));
+
+    let amounts = Vec::from_array(e, [100, 100]);
+    assert_eq!(
+        liq_pool.try_deposit(&user1, &amounts).unwrap_err(),
+        not_allowed()
+    );
+    assert_eq!(
+        liq_pool
+            .try_deposit_with_referrer(&user1, &amounts, &Some(admin.clone()))
+            .unwrap_err(),
+        not_allowed()
+    );
+    assert_eq!(
+        liq_pool.try_swap(&user1, &0, &1, &10, &0).unwrap_err(),
+        not_allowed()
+    );
+    assert_eq!(
+        liq_pool
+            .try_swap_with_referral(&user1, &0, &1, &10, &0, &admin, &0)
+            .unwrap_err(),
+        not_allowed()
+    );
+    assert_eq!(
+        liq_pool.swap_or_reject(&user1, &0, &1, &10, &0),
+        SwapOutcome::Rejected(Symbol::new(e, "allowlist"))
+    );
+    assert_eq!(
+        liq_pool.check_swap(&user1, &0, &1, &10, &0),
+        Vec::from_array(e, [Symbol::new(e, "allowlist")])
+    );
+
+    liq_pool.add_to_allowlist(&admin, &user1);
+    assert!(liq_pool.is_allowed(&user1));
+    liq_pool.deposit(&user1, &amounts);
+    assert_eq!(
+        liq_pool
+            .try_deposit_single(&admin, &0, &10, &0)
+            .unwrap_err(),
+        not_allowed()
+    );
+    liq_pool.deposit_single(&user1, &0, &10, &0);
+    liq_pool.swap(&user1, &0, &1, &10, &0);
+
+    // exit is never blocked
+    liq_pool.remove_from_allowlist(&admin, &user1);
+    assert!(!liq_pool.is_allowed(&user1));
+    assert_eq!(
+        liq_pool.try_deposit(&user1, &amounts).unwrap_err(),
+        not_allowed()
+    );
+    let shares = setup.token_share.balance(&user1);
+    setup
+        .token_share
+        .approve(&user1, &liq_pool.address, &shares, &99999);
+    liq_pool.withdraw(&user1, &(shares as u128), &Vec::from_array(e, [0, 0]));
+    assert_eq!(setup.token_share.balance(&user1), 0);
+
+    // disabling permissioned mode lets everyone in again
+    liq_pool.set_permissioned(&admin, &false);
+    assert!(liq_pool.is_allowed(&user1));
+    liq_pool.deposit(&user1, &amounts);
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #102)")]
+fn test_allowlist_not_admin() {
+    let setup = Setup::default();
+    let user1 = setup.users[1].clone();
+
+    setup.liq_pool.add_to_allowlist(&user1, &user1);
+}
+
+#[test]
+fn test_fee_exempt_swap() {
+    let config = TestConfig {
+        users_count: 3,
+        mint_to_user: 10_000_000,
+        ..TestConfig::default()
+    };
+    let setup = Setup::setup(&config);
+    setup.mint_tokens_for_users(&config.mint_to_user);
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let (admin, router, user2) = (
+        setup.users[0].clone(),
+        setup.users[1].clone(),
+        setup.users[2].clone(),
+    );
+    liq_pool.deposit(&admin, &Vec::from_array(e, [1_000_000, 1_000_000]));
+
+    assert!(!liq_pool.is_fee_exempt(&router));
+    liq_pool.set_fee_exempt(&admin, &router, &true);
+    assert!(liq_pool.is_fee_exempt(&router));
+    assert_eq!(
+        vec![e, e.events().all().last().unwrap()],
+        vec![
+            e,
+            (
+                liq_pool.address.clone(),
+                (Symbol::new(e, "fee_exempt"), router.clone()).into_val(e),
+                true.into_val(e),
+            ),
+        ]
+    );
+
+    // quotes match the fee charged from the user
+    let estimate = liq_pool.estimate_swap(&0, &1, &1_000_000);
+    assert_eq!(estimate, 499248);
+    assert_eq!(
+        liq_pool.estimate_swap_for(&user2, &0, &1, &1_000_000),
+        estimate
+    );
+    assert_eq!(
+        liq_pool.estimate_swap_for(&router, &0, &1, &1_000_000),
+        500_000
+    );
+
+    // exempt swaps leave constant product exactly as it was
+    assert_eq!(
+        liq_pool.swap(&router, &0, &1, &1_000_000, &500_000),
+        500_000
+    );
+    assert_eq!(
+        liq_pool.get_reserves(),
+        Vec::from_array(e, [2_000_000, 500_000])
+    );
+    assert_eq!(liq_pool.swap(&router, &1, &0, &500_000, &0), 1_000_000);
+    assert_eq!(
+        liq_pool.get_reserves(),
+        Vec::from_array(e, [1_000_000, 1_000_000])
+    );
+
+    // exempt user doesn't pay fee, so there's nothing to share with referrer
+    assert_eq!(
+        liq_pool
+            .try_swap_with_referral(&router, &0, &1, &100, &0, &user2, &1)
+            .unwrap_err(),
+        Ok(soroban_sdk::Error::from_contract_error(228))
+    );
+
+    // other users pay the fee as usual
+    assert_eq!(liq_pool.swap(&user2, &0, &1, &1_000_000, &0), estimate);
+    let reserves = liq_pool.get_reserves();
+    assert!(reserves.get(0).unwrap() * reserves.get(1).unwrap() > 1_000_000 * 1_000_000);
+
+    // revoking exemption restores the fee
+    liq_pool.set_fee_exempt(&admin, &router, &false);
+    assert!(!liq_pool.is_fee_exempt(&router));
+    let estimate = liq_pool.estimate_swap(&1, &0, &100_000);
+    assert_eq!(
+        liq_pool.estimate_swap_for(&router, &1, &0, &100_000),
+        estimate
+    );
+    assert_eq!(liq_pool.swap(&router, &1, &0, &100_000, &0), estimate);
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #102)")]
+fn test_fee_exempt_not_admin() {
+    let setup = Setup::default();
+    let user1 = setup.users[1].clone();
+
+    setup.liq_pool.set_fee_exempt(&user1, &user1, &true);
+}
+
+#[test]
+fn test_max_price_impact() {
+    let config = TestConfig {
+        mint_to_user: 10_000_000,
+        ..TestConfig::default()
+    };
+    let setup = Setup::setup(&config);
+    setup.mint_tokens_for_users(&config.mint_to_user);
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let (admin, user1) = (setup.users[0].clone(), setup.users[1].clone());
+    liq_pool.deposit(&admin, &Vec::from_array(e, [1_000_000, 1_000_000]));
+
+    // guard is disabled by default
+    assert_eq!(liq_pool.get_max_price_impact(), 0);
+    liq_pool.set_max_price_impact(&admin, &500);
+    assert_eq!(liq_pool.get_max_price_impact(), 500);
+
+    // small swap passes
+    assert_eq!(liq_pool.estimate_price_impact(&0, &1, &1_000), 20);
+    assert_eq!(liq_pool.swap(&user1, &0, &1, &1_000, &0), 996);
+
+    // swap buying 30% of the reserves moves price by ~51%
+    let in_amount = 430_000;
+    assert_eq!(liq_pool.estimate_price_impact(&0, &1, &in_amount), 5103);
+    assert_eq!(
+        liq_pool.check_swap(&user1, &0, &1, &in_amount, &0),
+        Vec::from_array(e, [Symbol::new(e, "price_impact")])
+    );
+    assert_eq!(
+        liq_pool
+            .try_swap(&user1, &0, &1, &in_amount, &0)
+            .unwrap_err(),
+        Ok(soroban_sdk::Error::from_contract_error(230))
+    );
+    assert_eq!(
+        liq_pool.swap_or_reject(&user1, &0, &1, &in_amount, &0),
+        SwapOutcome::Rejected(Symbol::new(e, "price_impact"))
+    );
+    assert_eq!(
+        liq_pool.get_reserves(),
+        Vec::from_array(e, [1_001_000, 999_004])
+    );
+
+    // zero disables the guard
+    liq_pool.set_max_price_impact(&admin, &0);
+    assert!(liq_pool
+        .check_swap(&user1, &0, &1, &in_amount, &0)
+        .is_empty());
+    assert!(liq_pool.swap(&user1, &0, &1, &in_amount, &0) > 299_000);
+}
+
+#[test]
+fn test_price_impact_large_reserves() {
+    // 18 decimals reserves, the reserves products don't fit u128
+    let reserve = 1_000_000_000000000000000000_u128;
+    let in_amount = 1_000_000000000000000000;
+    let out = pool::get_swap_out(30, reserve, reserve, in_amount);
+    assert_eq!(out, 996_006981039903216493);
+    assert_eq!(pool::get_price_impact(reserve, reserve, in_amount, out), 20);
+
+    // same swap on the 7 decimals scale
+    let out = pool::get_swap_out(30, 1_000_000, 1_000_000, 1_000);
+    assert_eq!(pool::get_price_impact(1_000_000, 1_000_000, 1_000, out), 20);
+
+    let in_amount = 430_000_000000000000000000;
+    let out = pool::get_swap_out(30, reserve, reserve, in_amount);
+    assert_eq!(
+        pool::get_price_impact(reserve, reserve, in_amount, out),
+        5106
+    );
+}
+
+#[test]
+fn test_min_reserve() {
+    let config = TestConfig {
+        mint_to_user: 10_000_000,
+        min_reserve: None,
+        ..TestConfig::default()
+    };
+    let setup = Setup::setup(&config);
+    setup.mint_tokens_for_users(&config.mint_to_user);
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let (admin, user1) = (setup.users[0].clone(), setup.users[1].clone());
+    liq_pool.deposit(&admin, &Vec::from_array(e, [1_000, 1_000]));
+
+    // guard is enabled by default
+    assert_eq!(liq_pool.get_min_reserve(), DEFAULT_MIN_RESERVE);
+    assert_eq!(
+        liq_pool.check_swap(&user1, &0, &1, &10, &0),
+        Vec::from_array(e, [Symbol::new(e, "min_reserve")])
+    );
+    liq_pool.set_min_reserve(&admin, &100);
+    assert_risk_param_event(&setup, Symbol::new(e, "min_reserve"), 100);
+    assert_eq!(liq_pool.get_min_reserve(), 100);
+    assert_eq!(
+        u128::from_val(
+            e,
+            &liq_pool
+                .get_info()
+                .get(Symbol::new(e, "min_reserve"))
+                .unwrap()
+        ),
+        100
+    );
+
+    // swap leaving 10 units of token b is rejected
+    let in_amount = 100_000;
+    assert_eq!(liq_pool.estimate_swap(&0, &1, &in_amount), 990);
+    assert_eq!(
+        liq_pool.check_swap(&user1, &0, &1, &in_amount, &0),
+        Vec::from_array(e, [Symbol::new(e, "min_reserve")])
+    );
+    assert_eq!(
+        liq_pool
+            .try_swap(&user1, &0, &1, &in_amount, &0)
+            .unwrap_err(),
+        Ok(soroban_sdk::Error::from_contract_error(249))
+    );
+    assert_eq!(liq_pool.swap(&user1, &0, &1, &1_000, &0), 499);
+    assert_eq!(liq_pool.get_reserves(), Vec::from_array(e, [2_000, 501]));
+
+    // liquidity providers still withdraw everything
+    setup
+        .token_share
+        .approve(&admin, &liq_pool.address, &1_000, &99999);
+    assert_eq!(
+        liq_pool.withdraw(&admin, &1_000, &Vec::from_array(e, [0, 0])),
+        Vec::from_array(e, [2_000, 501])
+    );
+    assert_eq!(liq_pool.get_reserves(), Vec::from_array(e, [0, 0]));
+}
+
+#[test]
+fn test_swap_pct() {
+    let config = TestConfig {
+        mint_to_user: 10_000_000,
+        ..TestConfig::default()
+    };
+    let setup = Setup::setup(&config);
+    setup.mint_tokens_for_users(&config.mint_to_user);
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let (admin, user1) = (setup.users[0].clone(), setup.users[1].clone());
+    liq_pool.deposit(&admin, &Vec::from_array(e, [1_000_000, 1_000_000]));
+
+    // 1% of the token b reserve
+    assert_eq!(liq_pool.estimate_swap_pct(&0, &1, &100), (10_132, 10_000));
+    assert_eq!(liq_pool.estimate_swap(&0, &1, &10_132), 10_000);
+
+    // reserves move before the order is executed, it still buys 1% of the current reserve
+    assert_eq!(liq_pool.swap(&admin, &0, &1, &100_000, &0), 90_661);
+    assert_eq!(
+        liq_pool.get_reserves(),
+        Vec::from_array(e, [1_100_000, 909_339])
+    );
+    assert!(liq_pool
+        .try_swap_pct(&user1, &0, &1, &100, &10_132)
+        .is_err());
+    assert_eq!(liq_pool.estimate_swap_pct(&0, &1, &100), (11_145, 9_093));
+    assert_eq!(
+        liq_pool.swap_pct(&user1, &0, &1, &100, &11_145),
+        SwapResult {
+            amount_in: 11_145,
+            amount_out: 9_093,
+            new_reserve_a: 1_111_145,
+            new_reserve_b: 900_246,
+        }
+    );
+    assert_eq!(setup.token1.balance(&user1), 10_000_000 - 11_145);
+    assert_eq!(setup.token2.balance(&user1), 10_000_000 + 9_093);
+}
+
+#[test]
+fn test_swap_pct_cap() {
+    let config = TestConfig {
+        mint_to_user: 10_000_000,
+        ..TestConfig::default()
+    };
+    let setup = Setup::setup(&config);
+    setup.mint_tokens_for_users(&config.mint_to_user);
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let (admin, user1) = (setup.users[0].clone(), setup.users[1].clone());
+    liq_pool.deposit(&admin, &Vec::from_array(e, [1_000_000, 1_000_000]));
+    let error = Ok(soroban_sdk::Error::from_contract_error(250));
+
+    assert_eq!(
+        liq_pool.try_estimate_swap_pct(&0, &1, &0).unwrap_err(),
+        error
+    );
+    assert_eq!(
+        liq_pool.try_estimate_swap_pct(&0, &1, &3001).unwrap_err(),
+        error
+    );
+    assert_eq!(
+        liq_pool
+            .try_swap_pct(&user1, &1, &0, &3001, &10_000_000)
+            .unwrap_err(),
+        error
+    );
+
+    // 30% of the reserve is the most one order can buy
+    let (in_amount, out) = liq_pool.estimate_swap_pct(&1, &0, &3000);
+    assert_eq!(out, 300_000);
+    let result = liq_pool.swap_pct(&user1, &1, &0, &3000, &in_amount);
+    assert_eq!((result.amount_in, result.amount_out), (in_amount, out));
+    assert_eq!(
+        liq_pool.get_reserves(),
+        Vec::from_array(e, [700_000, 1_000_000 + in_amount])
+    );
+}
+
+#[test]
+fn test_swap_stats() {
+    let config = TestConfig {
+        mint_to_user: 10_000_000,
+        ..TestConfig::default()
+    };
+    let setup = Setup::setup(&config);
+    setup.mint_tokens_for_users(&config.mint_to_user);
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let user1 = setup.users[1].clone();
+    liq_pool.deposit(&setup.users[0], &Vec::from_array(e, [1_000_000, 1_000_000]));
+
+    let stats = |volume_a: i128, volume_b: i128, fee_a: i128, fee_b: i128, last_trade: u64| {
+        Map::from_array(
+            e,
+            [
+                (Symbol::new(e, "volume_a"), volume_a),
+                (Symbol::new(e, "volume_b"), volume_b),
+                (symbol_short!("fee_a"), fee_a),
+                (symbol_short!("fee_b"), fee_b),
+                (Symbol::new(e, "last_trade"), last_trade as i128),
+            ],
+        )
+    };
+    assert_eq!(liq_pool.get_stats(), stats(0, 0, 0, 0, 0));
+
+    jump(e, 100);
+    let out1 = liq_pool.swap(&user1, &0, &1, &100_000, &0);
+    jump(e, 100);
+    let out2 = liq_pool.swap(&user1, &1, &0, &50_000, &0);
+    let out3 = liq_pool.swap(&user1, &0, &1, &33_333, &0);
+
+    // 0.3% fee is charged from the amount sold, rounded up
+    assert_eq!(
+        liq_pool.get_stats(),
+        stats(
+            100_000 + out2 as i128 + 33_333,
+            out1 as i128 + 50_000 + out3 as i128,
+            300 + 100,
+            150,
+            e.ledger().timestamp(),
+        )
+    );
+}
+
+#[test]
+fn test_get_position() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let (user1, user2) = (setup.users[0].clone(), setup.users[1].clone());
+    let reward_tps = TestConfig::default().reward_tps;
+
+    // single liquidity provider owns the whole pool
+    liq_pool.deposit(&user1, &Vec::from_array(e, [300, 300]));
+    assert_eq!(
+        liq_pool.get_position(&user1),
+        UserPosition {
+            shares: 300,
+            ownership_bps: 10_000,
+            amount_a: 300,
+            amount_b: 300,
+            pending_reward: 0,
+        }
+    );
+
+    // 75/25 split between two providers, pending reward matches the claimable amount
+    jump(e, 10);
+    liq_pool.deposit(&user2, &Vec::from_array(e, [100, 100]));
+    let position = liq_pool.get_position(&user1);
+    assert_eq!(position.ownership_bps, 7_500);
+    assert_eq!((position.amount_a, position.amount_b), (300, 300));
+    assert_eq!(position.pending_reward, reward_tps * 10);
+    assert_eq!(
+        liq_pool.get_position(&user2),
+        UserPosition {
+            shares: 100,
+            ownership_bps: 2_500,
+            amount_a: 100,
+            amount_b: 100,
+            pending_reward: 0,
+        }
+    );
+
+    // no liquidity, no position
+    assert_eq!(
+        liq_pool.get_position(&Address::generate(e)),
+        UserPosition {
+            shares: 0,
+            ownership_bps: 0,
+            amount_a: 0,
+            amount_b: 0,
+            pending_reward: 0,
+        }
+    );
+}
+
+#[test]
+fn test_get_position_without_rewards() {
+    let e = Env::default();
+    e.mock_all_auths();
+    e.budget().reset_unlimited();
+
+    let admin = Address::generate(&e);
+    let user = Address::generate(&e);
+    let mut token1 = create_token_contract(&e, &admin);
+    let mut token2 = create_token_contract(&e, &admin);
+    if &token2.address < &token1.address {
+        std::mem::swap(&mut token1, &mut token2);
+    }
+    let tokens = Vec::from_array(&e, [token1.address.clone(), token2.address.clone()]);
+
+    // pool initialized without rewards config
+    let liq_pool =
+        LiquidityPoolClient::new(&e, &e.register_contract(None, crate::LiquidityPool {}));
+    liq_pool.set_pools_plane(&create_plane_contract(&e).address);
+    liq_pool.initialize(&admin, &install_token_wasm(&e), &tokens, &30);
+    assert_eq!(
+        liq_pool.get_position(&user),
+        UserPosition {
+            shares: 0,
+            ownership_bps: 0,
+            amount_a: 0,
+            amount_b: 0,
+            pending_reward: 0,
+        }
+    );
+
+    for token in [&token1, &token2] {
+        token.mint(&user, &10_000);
+    }
+    liq_pool.deposit(&user, &Vec::from_array(&e, [5_000, 5_000]));
+    assert_eq!(
+        liq_pool.get_position(&user),
+        UserPosition {
+            shares: 5_000,
+            ownership_bps: 10_000,
+            amount_a: 5_000,
+            amount_b: 5_000,
+            pending_reward: 0,
+        }
+    );
+}
+
+#[test]
+fn test_storage_ttl_extended_by_usage() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let (user1, user2) = (setup.users[0].clone(), setup.users[1].clone());
+
+    liq_pool.deposit(&user1, &Vec::from_array(e, [100, 100]));
+
+    // routine usage keeps pool alive long past the single bump
+    for _ in 0..3 {
+        e.ledger().with_mut(|li| {
+            li.sequence_number += 15 * DAY_IN_LEDGERS;
+            li.timestamp += 15 * 24 * 60 * 60;
+        });
+        liq_pool.deposit(&user2, &Vec::from_array(e, [10, 10]));
+        liq_pool.claim(&user1);
+    }
+    // well past the ttl set on initialization, instance storage is still live
+    assert!(e.ledger().sequence() > INSTANCE_BUMP_AMOUNT);
+    e.as_contract(&liq_pool.address, || {
+        assert!(!e.storage().instance().all().is_empty());
+    });
+    assert_eq!(liq_pool.get_reserves(), Vec::from_array(e, [130, 130]));
+
+    // user entries created on the first deposit are still live
+    setup.token_share.approve(
+        &user1,
+        &liq_pool.address,
+        &100,
+        &(e.ledger().sequence() + 100),
+    );
+    assert_eq!(
+        liq_pool.withdraw(&user1, &100, &Vec::from_array(e, [0, 0])),
+        Vec::from_array(e, [100, 100])
+    );
+}
+
+#[test]
+fn test_withdraw_large_reserves() {
+    let e = Env::default();
+    e.mock_all_auths();
+    e.budget().reset_unlimited();
+
+    let users = Setup::generate_random_users(&e, 3);
+    let (admin, user1, user2) = (users[0].clone(), users[1].clone(), users[2].clone());
+    let token1 = create_token_contract_with_decimals(&e, &admin, 18);
+    let token2 = create_token_contract_with_decimals(&e, &admin, 18);
+    let (token_a, token_b) = match token1.address < token2.address {
+        true => (token1, token2),
+        false => (token2, token1),
+    };
+    let token_reward = create_token_contract(&e, &admin);
+    let plane = create_plane_contract(&e);
+    let liq_pool = create_liqpool_contract(
+        &e,
+        &admin,
+        &install_token_wasm(&e),
+        &Vec::from_array(&e, [token_a.address.clone(), token_b.address.clone()]),
+        &token_reward.address,
+        30,
+        &plane.address,
+    );
+    // 18 decimals reserves, reserve_a * reserve_b doesn't fit u128
+    let amount = 1_000_000_000000000000000000_u128;
+    for user in [&user1, &user2] {
+        token_a.mint(user, &(amount as i128));
+        token_b.mint(user, &(amount as i128));
+    }
+    liq_pool.deposit(&user1, &Vec::from_array(&e, [amount, amount]));
+
+    // fees of the round trip are tracked through the sqrt(k) growth
+    let out = liq_pool.swap(&user2, &0, &1, &1_000_000000000000000000, &0);
+    liq_pool.swap(&user2, &1, &0, &out, &0);
+    let earnings = liq_pool.get_fee_earnings(&user1);
+    assert!(earnings.get(0).unwrap() > 0);
+    assert!(earnings.get(1).unwrap() > 0);
+
+    let reserves = liq_pool.get_reserves();
+    assert!(reserves.get(0).unwrap() > amount);
+    assert_eq!(
+        liq_pool.withdraw_all(&user1, &Vec::from_array(&e, [0, 0])),
+        reserves
+    );
+    assert_eq!(liq_pool.get_reserves(), Vec::from_array(&e, [0, 0]));
+    assert_eq!(liq_pool.get_fee_earnings(&user1), earnings);
+}
+
+#[test]
+fn test_migrate_storage() {
+    let e = Env::default();
+    e.mock_all_auths();
+    e.budget().reset_unlimited();
+
+    let users = Setup::generate_random_users(&e, 2);
+    let admin = users[0].clone();
+    let user1 = users[1].clone();
+    let token7 = create_token_contract(&e, &admin);
+    let token18 = create_token_contract_with_decimals(&e, &admin, 18);
+    let token_reward = create_token_contract(&e, &admin);
+    let (tokens, amounts) = match token7.address < token18.address {
+        true => (
+            Vec::from_array(&e, [token7.address.clone(), token18.address.clone()]),
+            Vec::from_array(&e, [1_0000000, 1_000000000000000000]),
+        ),
+        false => (
+            Vec::from_array(&e, [token18.address.clone(), token7.address.clone()]),
+            Vec::from_array(&e, [1_000000000000000000, 1_0000000]),
+        ),
+    };
+    let plane = create_plane_contract(&e);
+    let liq_pool = create_liqpool_contract(
+        &e,
+        &admin,
+        &install_token_wasm(&e),
+        &tokens,
+        &token_reward.address,
+        30,
+        &plane.address,
+    );
+    token7.mint(&user1, &1_000_0000000);
+    token18.mint(&user1, &1_000_000000000000000000);
+    assert_eq!(liq_pool.storage_version(), 2);
+    liq_pool.deposit(&user1, &amounts);
+    assert_eq!(liq_pool.get_price(), 1_0000000);
+
+    // simulate pool deployed before storage versioning: decimals weren't cached
+    e.as_contract(&liq_pool.address, || {
+        put_storage_version(&e, 0);
+        put_decimals_a(&e, 7);
+        put_decimals_b(&e, 7);
+    });
+    assert_eq!(liq_pool.storage_version(), 0);
+    assert_ne!(liq_pool.get_price(), 1_0000000);
+    let migration_required = || Ok(soroban_sdk::Error::from_contract_error(231));
+    assert_eq!(
+        liq_pool.try_deposit(&user1, &amounts).unwrap_err(),
+        migration_required()
+    );
+    assert_eq!(
+        liq_pool.try_swap(&user1, &0, &1, &100, &0).unwrap_err(),
+        migration_required()
+    );
+    assert_eq!(
+        liq_pool
+            .try_withdraw(&user1, &100, &Vec::from_array(&e, [0, 0]))
+            .unwrap_err(),
+        migration_required()
+    );
+    assert_eq!(
+        liq_pool.try_claim(&user1).unwrap_err(),
+        migration_required()
+    );
+    assert_eq!(
+        liq_pool
+            .try_set_rewards_config(&admin, &(e.ledger().timestamp() + 60), &1)
+            .unwrap_err(),
+        migration_required()
+    );
+
+    assert_eq!(liq_pool.migrate(&admin), 2);
+    assert_eq!(
+        vec![&e, e.events().all().last().unwrap()],
+        vec![
+            &e,
+            (
+                liq_pool.address.clone(),
+                (symbol_short!("migrate"),).into_val(&e),
+                (0_u32, 2_u32).into_val(&e),
+            ),
+        ]
+    );
+    assert_eq!(liq_pool.storage_version(), 2);
+    assert_eq!(liq_pool.get_price(), 1_0000000);
+
+    // migration is idempotent
+    assert_eq!(liq_pool.migrate(&admin), 2);
+    assert_eq!(liq_pool.get_price(), 1_0000000);
+    liq_pool.deposit(&user1, &amounts);
+    assert_eq!(liq_pool.get_price(), 1_0000000);
+}
+
+#[test]
+fn test_migrate_legacy_pool_withdraw() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let admin = setup.users[0].clone();
+    let user1 = setup.users[1].clone();
+
+    liq_pool.deposit(&user1, &Vec::from_array(e, [100, 100]));
+    // pool deployed before storage versioning is upgraded to the current code
+    e.as_contract(&liq_pool.address, || put_storage_version(e, 0));
+    assert_eq!(liq_pool.storage_version(), 0);
+    setup
+        .token_share
+        .approve(&user1, &liq_pool.address, &100, &99999);
+    assert_eq!(
+        liq_pool
+            .try_withdraw(&user1, &100, &Vec::from_array(e, [0, 0]))
+            .unwrap_err(),
+        Ok(soroban_sdk::Error::from_contract_error(231))
+    );
+
+    assert_eq!(liq_pool.migrate(&admin), 2);
+    assert_eq!(
+        liq_pool.withdraw(&user1, &100, &Vec::from_array(e, [0, 0])),
+        Vec::from_array(e, [100, 100])
+    );
+    assert_eq!(setup.token1.balance(&user1), 1000);
+    assert_eq!(setup.token2.balance(&user1), 1000);
+}
+
+// Rewards storage layout before the cumulative reward per share
+mod legacy_rewards {
+    use soroban_sdk::{contracttype, Address};
+
+    #[contracttype]
+    pub enum DataKey {
+        PoolRewardData,
+        UserRewardData(Address),
+        RewardInvData(u32, u64),
+    }
+
+    #[contracttype]
+    pub struct PoolRewardData {
+        pub block: u64,
+        pub accumulated: u128,
+        pub last_time: u64,
+    }
+
+    #[contracttype]
+    pub struct UserRewardData {
+        pub pool_accumulated: u128,
+        pub to_claim: u128,
+        pub last_block: u64,
+    }
+}
+
+#[test]
+fn test_migrate_legacy_rewards() {
+    let setup = Setup::default();
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let admin = setup.users[0].clone();
+    let user1 = setup.users[1].clone();
+    liq_pool.deposit(&user1, &Vec::from_array(e, [100, 100]));
+    liq_pool.deposit(&admin, &Vec::from_array(e, [100, 100]));
+
+    // pool written by the old code: 100 tokens generated in each of blocks 1-5 over 200 shares,
+    //  invariant is 1000 * 100 / 200. user1 synced at block 2, admin at block 5
+    let now = e.ledger().timestamp();
+    e.as_contract(&liq_pool.address, || {
+        put_storage_version(e, 0);
+        e.storage().instance().set(
+            &legacy_rewards::DataKey::PoolRewardData,
+            &legacy_rewards::PoolRewardData {
+                block: 5,
+                accumulated: 500,
+                last_time: now,
+            },
+        );
+        let mut page = Map::new(e);
+        page.set(0_u64, 0_u128);
+        for block in 1..6_u64 {
+            page.set(block, 500_u128);
+        }
+        e.storage()
+            .persistent()
+            .set(&legacy_rewards::DataKey::RewardInvData(0, 0), &page);
+        for (user, pool_accumulated, to_claim, last_block) in
+            [(&user1, 200, 40, 2), (&admin, 500, 0, 5)]
+        {
+            e.storage().persistent().set(
+                &legacy_rewards::DataKey::UserRewardData(user.clone()),
+                &legacy_rewards::UserRewardData {
+                    pool_accumulated,
+                    to_claim,
+                    last_block,
+                },
+            );
+        }
+    });
+
+    assert_eq!(liq_pool.migrate(&admin), 2);
+    let pool_reward_data = || {
+        e.as_contract(&liq_pool.address, || {
+            get_rewards_manager(e).storage().get_pool_reward_data()
+        })
+    };
+    let pool_data = pool_reward_data();
+    assert_eq!(
+        (
+            pool_data.block,
+            pool_data.accumulated,
+            pool_data.last_time,
+            pool_data.reward_per_share
+        ),
+        (5, 500, now, 0)
+    );
+    // user1 missed blocks 3-5: 1500 * 100 / 1000 on top of 40 left to claim
+    assert_eq!(liq_pool.get_user_reward(&user1), 190);
+    assert_eq!(liq_pool.get_user_reward(&admin), 0);
+    assert_eq!(liq_pool.get_user_claimed(&user1), 0);
+
+    // second run changes nothing
+    assert_eq!(liq_pool.migrate(&admin), 2);
+    assert_eq!(pool_reward_data().block, 5);
+    assert_eq!(liq_pool.get_user_reward(&user1), 190);
+
+    // users are converted on the first touch and earn with the new accumulator after that
+    assert_eq!(liq_pool.claim(&user1), 190);
+    assert_eq!(liq_pool.claim(&admin), 0);
+    assert_eq!(liq_pool.get_user_claimed(&user1), 190);
+    jump(e, 10);
+    let reward_tps = TestConfig::default().reward_tps;
+    assert_eq!(liq_pool.claim(&user1), reward_tps * 10 / 2);
+    assert_eq!(liq_pool.claim(&admin), reward_tps * 10 / 2);
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #102)")]
+fn test_migrate_not_admin() {
+    let setup = Setup::default();
+    let user1 = setup.users[1].clone();
+
+    setup.liq_pool.migrate(&user1);
+}
+
+#[test]
+fn test_reentrancy_rejected() {
+    let e = Env::default();
+    e.mock_all_auths();
+    e.budget().reset_unlimited();
+
+    let users = Setup::generate_random_users(&e, 2);
+    let admin = users[0].clone();
+    let user1 = users[1].clone();
+    let token = create_token_contract(&e, &admin);
+    let reentrant = ReentrantTokenClient::new(&e, &e.register_contract(None, ReentrantToken {}));
+    let token_reward = create_token_contract(&e, &admin);
+    let (tokens, reentrant_idx) = match reentrant.address < token.address {
+        true => (
+            Vec::from_array(&e, [reentrant.address.clone(), token.address.clone()]),
+            0,
+        ),
+        false => (
+            Vec::from_array(&e, [token.address.clone(), reentrant.address.clone()]),
+            1,
+        ),
+    };
+    let plane = create_plane_contract(&e);
+    let liq_pool = create_liqpool_contract(
+        &e,
+        &admin,
+        &install_token_wasm(&e),
+        &tokens,
+        &token_reward.address,
+        30,
+        &plane.address,
+    );
+    token.mint(&user1, &10_000);
+    token.approve(&user1, &liq_pool.address, &10_000, &99999);
+    reentrant.mint(&user1, &10_000);
+    let (_, shares) = liq_pool.deposit(&user1, &Vec::from_array(&e, [1_000, 1_000]));
+    // swaps below have to reach the token to be rejected by it
+    liq_pool.set_min_reserve(&admin, &0);
+
+    // token calling back into the pool fails the whole invocation
+    reentrant.set_target(&liq_pool.address);
+    assert!(liq_pool
+        .try_deposit(&user1, &Vec::from_array(&e, [1_000, 1_000]))
+        .is_err());
+    assert!(liq_pool
+        .try_swap(&user1, &reentrant_idx, &(1 - reentrant_idx), &100, &0)
+        .is_err());
+    assert!(liq_pool
+        .try_swap(&user1, &(1 - reentrant_idx), &reentrant_idx, &100, &0)
+        .is_err());
+    Client::new(&e, &liq_pool.share_id()).approve(
+        &user1,
+        &liq_pool.address,
+        &(shares as i128),
+        &99999,
+    );
+    assert!(liq_pool
+        .try_withdraw(&user1, &shares, &Vec::from_array(&e, [0, 0]))
+        .is_err());
+    assert_eq!(liq_pool.get_reserves(), Vec::from_array(&e, [1_000, 1_000]));
+    assert_eq!(reentrant.balance(&liq_pool.address), 1_000);
+}
+
+#[test]
+fn test_swap_large_reserves() {
+    // invariant products of reserves this large don't fit u128
+    let reserve = 1_000_000_000_000_000_000;
+    let config = TestConfig {
+        mint_to_user: 2 * reserve,
+        ..TestConfig::default()
+    };
+    let setup = Setup::setup(&config);
+    setup.mint_tokens_for_users(&config.mint_to_user);
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let user1 = setup.users[1].clone();
+    liq_pool.deposit(
+        &setup.users[0],
+        &Vec::from_array(e, [reserve as u128, reserve as u128]),
+    );
+
+    let in_amount = 1_000_000_000_000_000;
+    let estimate = liq_pool.estimate_swap(&0, &1, &in_amount);
+    assert_eq!(
+        liq_pool.swap(&user1, &0, &1, &in_amount, &estimate),
+        estimate
+    );
+    let estimate_back = liq_pool.estimate_swap(&1, &0, &estimate);
+    assert_eq!(
+        liq_pool.swap(&user1, &1, &0, &estimate, &estimate_back),
+        estimate_back
+    );
+
+    // both swaps paid the fee, so pool ends up with more than it started with
+    let reserves = liq_pool.get_reserves();
+    assert_eq!(reserves.get(1).unwrap(), reserve as u128);
+    assert!(reserves.get(0).unwrap() > reserve as u128);
+}
@@ -0,0 +1,168 @@
+#![cfg(test)]
+extern crate std;
+
+use crate::testutils::{jump, Setup, TestConfig};
+use soroban_sdk::{Env, Vec};
+use std::println;
+
+// Upper bounds of the resources consumed by the hot paths: (call, cpu instructions, memory bytes).
+//  Network limit per transaction is 100M instructions and 40MB of memory, bounds leave room
+//  for the router or the aggregator calling the pool. Lower them once the costs go down
+const BUDGET_LIMITS: [(&str, u64, u64); 4] = [
+    ("deposit", 40_000_000, 20_000_000),
+    ("swap", 40_000_000, 20_000_000),
+    ("withdraw", 40_000_000, 20_000_000),
+    ("claim", 40_000_000, 20_000_000),
+];
+
+const USERS_COUNT: u32 = 4;
+const HISTORY_LENGTH: u32 = 30;
+
+struct Measurement {
+    call: &'static str,
+    cpu: u64,
+    mem: u64,
+}
+
+// Run the call with the default network budget and record resources it consumed
+fn measure<T>(e: &Env, call: &'static str, f: impl FnOnce() -> T) -> Measurement {
+    e.budget().reset_default();
+    f();
+    let measurement = Measurement {
+        call,
+        cpu: e.budget().cpu_instruction_cost(),
+        mem: e.budget().memory_bytes_cost(),
+    };
+    e.budget().reset_unlimited();
+    measurement
+}
+
+// Print consumed resources next to the bounds. Run with --nocapture to see the table
+fn print_budget_table(measurements: &[Measurement]) {
+    println!(
+        "{:<10} {:>12} {:>12} {:>12} {:>12}",
+        "call", "cpu", "cpu limit", "mem", "mem limit"
+    );
+    for measurement in measurements {
+        let (_, cpu_limit, mem_limit) = get_limits(measurement.call);
+        println!(
+            "{:<10} {:>12} {:>12} {:>12} {:>12}",
+            measurement.call, measurement.cpu, cpu_limit, measurement.mem, mem_limit
+        );
+    }
+}
+
+fn get_limits(call: &str) -> (&str, u64, u64) {
+    *BUDGET_LIMITS
+        .iter()
+        .find(|(name, _, _)| *name == call)
+        .expect("no budget limits for the call")
+}
+
+// Pool with liquidity, active rewards campaign and users with long deposit and swap history
+fn setup_with_history<'a>() -> Setup<'a> {
+    let setup = Setup::new_with_config(&TestConfig {
+        users_count: USERS_COUNT,
+        mint_to_user: 1_000_000_0000000,
+        ..TestConfig::default()
+    });
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+
+    liq_pool.deposit(
+        &setup.users[0],
+        &Vec::from_array(e, [1000_0000000, 1000_0000000]),
+    );
+    for i in 0..HISTORY_LENGTH as usize {
+        let user = &setup.users[i % USERS_COUNT as usize];
+        liq_pool.deposit(user, &Vec::from_array(e, [10_0000000, 10_0000000]));
+        liq_pool.swap(user, &(i as u32 % 2), &(1 - i as u32 % 2), &1_0000000, &0);
+        jump(e, 1);
+    }
+    liq_pool.claim(&setup.users[0]);
+    jump(e, 1);
+    setup
+}
+
+#[test]
+fn test_hot_paths_budget() {
+    let setup = setup_with_history();
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let user1 = setup.users[0].clone();
+    let user2 = setup.users[1].clone();
+    let user3 = setup.users[2].clone();
+
+    let mut measurements = std::vec![];
+    measurements.push(measure(e, "deposit", || {
+        liq_pool.deposit(&user1, &Vec::from_array(e, [10_0000000, 10_0000000]))
+    }));
+    measurements.push(measure(e, "swap", || {
+        liq_pool.swap(&user2, &0, &1, &10_0000000, &0)
+    }));
+    let shares = setup.token_share.balance(&user1) / 2;
+    setup
+        .token_share
+        .approve(&user1, &liq_pool.address, &shares, &99999);
+    measurements.push(measure(e, "withdraw", || {
+        liq_pool.withdraw(&user1, &(shares as u128), &Vec::from_array(e, [0, 0]))
+    }));
+    measurements.push(measure(e, "claim", || liq_pool.claim(&user3)));
+
+    print_budget_table(&measurements);
+    for measurement in measurements.iter() {
+        let (call, cpu_limit, mem_limit) = get_limits(measurement.call);
+        assert!(
+            measurement.cpu <= cpu_limit,
+            "{} cpu {} over {}",
+            call,
+            measurement.cpu,
+            cpu_limit
+        );
+        assert!(
+            measurement.mem <= mem_limit,
+            "{} memory {} over {}",
+            call,
+            measurement.mem,
+            mem_limit
+        );
+    }
+}
+
+// Deposit cost after the whole rewards schedule passed without pool updates
+fn get_deposit_cost_after_gap(poke: bool) -> u64 {
+    let config = TestConfig::default();
+    let setup = Setup::setup(&config);
+    setup.mint_tokens_for_users(&config.mint_to_user);
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+
+    liq_pool.deposit(&setup.users[0], &Vec::from_array(e, [100, 100]));
+    let start = e.ledger().timestamp();
+    liq_pool.set_rewards_schedule(
+        &setup.users[0],
+        &Vec::from_array(
+            e,
+            [
+                (start + 10, 100_0000000),
+                (start + 30, 400_0000000),
+                (start + 40, 50_0000000),
+            ],
+        ),
+    );
+    jump(e, 1000);
+    if poke {
+        liq_pool.poke();
+    }
+    measure(e, "deposit", || {
+        liq_pool.deposit(&setup.users[1], &Vec::from_array(e, [100, 100]))
+    })
+    .cpu
+}
+
+#[test]
+fn test_poke_amortizes_catchup() {
+    let cost = get_deposit_cost_after_gap(false);
+    let poked_cost = get_deposit_cost_after_gap(true);
+    assert!(poked_cost < cost);
+}
@@ -0,0 +1,352 @@
+#![cfg(test)]
+extern crate std;
+
+use crate::constants::{MAX_REFERRAL_FEE, WEIGHT_DENOMINATOR};
+use crate::pool;
+use crate::testutils::{create_weighted_liqpool_contract, jump, Setup, TestConfig};
+use crate::weighted_math;
+use proptest::prelude::*;
+use soroban_sdk::{Address, Vec};
+use std::format;
+use std::vec;
+
+const USERS_COUNT: usize = 3;
+const MINT_TO_USER: i128 = 1_000_000_000_0000000;
+const MAX_AMOUNT: u128 = 1_000_000_0000000;
+const REWARDS_DURATION: u128 = 60;
+
+// Operation applied to the pool by one of the users. Failing operations are skipped,
+//  invariants are checked after every step either way
+#[derive(Clone, Debug)]
+enum Op {
+    Deposit {
+        user: usize,
+        amount_a: u128,
+        amount_b: u128,
+    },
+    Swap {
+        user: usize,
+        in_idx: u32,
+        amount: u128,
+    },
+    Withdraw {
+        user: usize,
+        shares_bps: u128,
+    },
+    Claim {
+        user: usize,
+    },
+    LockShares {
+        user: usize,
+        shares_bps: u128,
+        duration: u64,
+    },
+    Jump {
+        time: u64,
+    },
+}
+
+fn op_strategy() -> impl Strategy<Value = Op> {
+    prop_oneof![
+        3 => (0..USERS_COUNT, 1..MAX_AMOUNT, 1..MAX_AMOUNT).prop_map(
+            |(user, amount_a, amount_b)| Op::Deposit {
+                user,
+                amount_a,
+                amount_b,
+            }
+        ),
+        4 => (0..USERS_COUNT, 0..2_u32, 1..MAX_AMOUNT).prop_map(|(user, in_idx, amount)| {
+            Op::Swap {
+                user,
+                in_idx,
+                amount,
+            }
+        }),
+        2 => (0..USERS_COUNT, 1..=10000_u128)
+            .prop_map(|(user, shares_bps)| Op::Withdraw { user, shares_bps }),
+        2 => (0..USERS_COUNT).prop_map(|user| Op::Claim { user }),
+        1 => (0..USERS_COUNT, 1..=10000_u128, 1..120_u64).prop_map(
+            |(user, shares_bps, duration)| Op::LockShares {
+                user,
+                shares_bps,
+                duration,
+            }
+        ),
+        2 => (1..30_u64).prop_map(|time| Op::Jump { time }),
+    ]
+}
+
+// Replay the operations against a fresh pool asserting global invariants after every step.
+//  A failing sequence printed by proptest can be passed here as is to get a regression test
+fn run(ops: &[Op]) -> Result<(), TestCaseError> {
+    let config = TestConfig {
+        users_count: USERS_COUNT as u32,
+        mint_to_user: MINT_TO_USER,
+        ..TestConfig::default()
+    };
+    let setup = Setup::new_with_config(&config);
+    let e = &setup.env;
+    let liq_pool = &setup.liq_pool;
+    let budget = config.reward_tps * REWARDS_DURATION;
+    let mut claimed = 0;
+
+    for (step, op) in ops.iter().enumerate() {
+        let history = &ops[..=step];
+        let reserves_before = liq_pool.get_reserves();
+
+        let swapped = match op.clone() {
+            Op::Deposit {
+                user,
+                amount_a,
+                amount_b,
+            } => {
+                let _ = liq_pool.try_deposit(
+                    &setup.users[user],
+                    &Vec::from_array(e, [amount_a, amount_b]),
+                );
+                false
+            }
+            Op::Swap {
+                user,
+                in_idx,
+                amount,
+            } => liq_pool
+                .try_swap(&setup.users[user], &in_idx, &(1 - in_idx), &amount, &0)
+                .is_ok(),
+            Op::Withdraw { user, shares_bps } => {
+                let user = &setup.users[user];
+                let shares = setup.token_share.balance(user) as u128 * shares_bps / 10000;
+                if shares > 0 {
+                    setup
+                        .token_share
+                        .approve(user, &liq_pool.address, &(shares as i128), &99999);
+                    let _ = liq_pool.try_withdraw(user, &shares, &Vec::from_array(e, [0, 0]));
+                }
+                false
+            }
+            Op::Claim { user } => {
+                if let Ok(Ok(amount)) = liq_pool.try_claim(&setup.users[user]) {
+                    claimed += amount;
+                }
+                false
+            }
+            Op::LockShares {
+                user,
+                shares_bps,
+                duration,
+            } => {
+                let user = &setup.users[user];
+                let shares = setup.token_share.balance(user) as u128 * shares_bps / 10000;
+                let _ = liq_pool.try_lock_shares(user, &shares, &duration);
+                false
+            }
+            Op::Jump { time } => {
+                jump(e, time);
+                false
+            }
+        };
+
+        // fee stays in the pool, so the product of reserves never decreases with a swap
+        let reserves = liq_pool.get_reserves();
+        if swapped {
+            let k_before = reserves_before.get(0).unwrap() * reserves_before.get(1).unwrap();
+            let k_after = reserves.get(0).unwrap() * reserves.get(1).unwrap();
+            prop_assert!(
+                k_after >= k_before,
+                "k decreased {} -> {}, operations: {:?}",
+                k_before,
+                k_after,
+                history
+            );
+        }
+
+        // reserves are backed by the pool balances
+        for (token, reserve) in [&setup.token1, &setup.token2].iter().zip(reserves.iter()) {
+            let balance = token.balance(&liq_pool.address) as u128;
+            prop_assert!(
+                balance >= reserve,
+                "balance {} below reserve {}, operations: {:?}",
+                balance,
+                reserve,
+                history
+            );
+        }
+
+        // locked shares stay on the user balances, redeemed shares are burnt by the pool
+        let users_shares: i128 = setup
+            .users
+            .iter()
+            .map(|user: &Address| setup.token_share.balance(user))
+            .sum();
+        let pool_shares = setup.token_share.balance(&liq_pool.address);
+        prop_assert_eq!(
+            liq_pool.get_total_shares(),
+            users_shares + pool_shares,
+            "shares supply mismatch, operations: {:?}",
+            history
+        );
+
+        // nothing is paid or owed above the rewards budget
+        let claimable: u128 = setup
+            .users
+            .iter()
+            .map(|user| liq_pool.get_user_reward(user))
+            .sum();
+        prop_assert!(
+            claimed + claimable <= budget,
+            "rewards {} + {} exceed budget {}, operations: {:?}",
+            claimed,
+            claimable,
+            budget,
+            history
+        );
+    }
+    Ok(())
+}
+
+// Swaps through the weighted pool with the referral cut and the reward fee leaving the pool.
+//  What's left still has to keep reserve_a^weight_a * reserve_b^weight_b from decreasing
+fn run_weighted(
+    weight_a: u32,
+    referral_bps: u32,
+    reward_fee: u32,
+    swaps: &[(u32, u128)],
+) -> Result<(), TestCaseError> {
+    let config = TestConfig {
+        users_count: USERS_COUNT as u32,
+        mint_to_user: MINT_TO_USER,
+        ..TestConfig::default()
+    };
+    let setup = Setup::new_with_config(&config);
+    let e = &setup.env;
+    let (admin, user, referrer) = (&setup.users[0], &setup.users[1], &setup.users[2]);
+    let weights = [weight_a, WEIGHT_DENOMINATOR - weight_a];
+    let liq_pool = create_weighted_liqpool_contract(
+        e,
+        admin,
+        &Vec::from_array(
+            e,
+            [setup.token1.address.clone(), setup.token2.address.clone()],
+        ),
+        &setup.token_reward.address,
+        30,
+        &Vec::from_array(e, weights),
+    );
+    for token in [&setup.token1, &setup.token2] {
+        token.approve(user, &liq_pool.address, &MINT_TO_USER, &99999);
+    }
+    liq_pool.set_reward_fee(admin, &reward_fee);
+    liq_pool.deposit(
+        user,
+        &Vec::from_array(e, [100 * MAX_AMOUNT, 300 * MAX_AMOUNT]),
+    );
+
+    for (step, (in_idx, amount)) in swaps.iter().enumerate() {
+        let history = &swaps[..=step];
+        let reserves_before = liq_pool.get_reserves();
+        let swapped = liq_pool
+            .try_swap_with_referral(
+                user,
+                in_idx,
+                &(1 - in_idx),
+                amount,
+                &0,
+                referrer,
+                &referral_bps,
+            )
+            .is_ok();
+        if !swapped {
+            continue;
+        }
+
+        let reserves = liq_pool.get_reserves();
+        let (sell, buy) = (*in_idx, 1 - *in_idx);
+        let min_reserve_buy = weighted_math::get_min_reserve_buy(
+            weights[sell as usize],
+            weights[buy as usize],
+            reserves_before.get(sell).unwrap(),
+            reserves_before.get(buy).unwrap(),
+            reserves.get(sell).unwrap(),
+        );
+        prop_assert!(
+            reserves.get(buy).unwrap() >= min_reserve_buy,
+            "weighted invariant decreased, reserves {:?} -> {:?}, weights {:?}, swaps: {:?}",
+            reserves_before,
+            reserves,
+            weights,
+            history
+        );
+    }
+    Ok(())
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig {
+        cases: 32,
+        failure_persistence: None,
+        ..ProptestConfig::default()
+    })]
+
+    #[test]
+    fn test_pool_invariants(ops in proptest::collection::vec(op_strategy(), 1..40)) {
+        run(&ops)?;
+    }
+
+    #[test]
+    fn test_weighted_swaps_keep_invariant(
+        weight_a in (1..WEIGHT_DENOMINATOR)
+            .prop_filter("unequal weights", |w| *w * 2 != WEIGHT_DENOMINATOR),
+        referral_bps in 0..=MAX_REFERRAL_FEE,
+        reward_fee in 0..=10000_u32,
+        swaps in proptest::collection::vec((0..2_u32, 1..MAX_AMOUNT), 1..20),
+    ) {
+        run_weighted(weight_a, referral_bps, reward_fee, &swaps)?;
+    }
+
+    // constant product math without Env, cheap enough for wide amounts and fast shrinking
+    #[test]
+    fn test_swap_out_keeps_k(
+        fee_fraction in 0..=1000_u32,
+        reserve_sell in 1..1_u128 << 60,
+        reserve_buy in 1..1_u128 << 60,
+        in_amount in 0..1_u128 << 60,
+    ) {
+        let out = pool::get_swap_out(fee_fraction, reserve_sell, reserve_buy, in_amount);
+        prop_assert!(out < reserve_buy);
+        prop_assert!((reserve_sell + in_amount) * (reserve_buy - out) >= reserve_sell * reserve_buy);
+    }
+}
+
+#[test]
+fn test_pool_invariants_replay() {
+    run(&vec![
+        Op::Deposit {
+            user: 0,
+            amount_a: 1000,
+            amount_b: 1,
+        },
+        Op::Swap {
+            user: 1,
+            in_idx: 1,
+            amount: 1,
+        },
+        Op::LockShares {
+            user: 0,
+            shares_bps: 5000,
+            duration: 100,
+        },
+        Op::Jump { time: 30 },
+        Op::Withdraw {
+            user: 0,
+            shares_bps: 10000,
+        },
+        Op::Claim { user: 0 },
+        Op::Jump { time: 100 },
+        Op::Withdraw {
+            user: 0,
+            shares_bps: 10000,
+        },
+        Op::Claim { user: 0 },
+    ])
+    .unwrap();
+}
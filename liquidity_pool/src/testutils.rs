@@ -1,11 +1,17 @@
 #![cfg(test)]
 extern crate std;
-use crate::plane::{pool_plane, PoolPlaneClient};
+pub(crate) use crate::fixture::{
+    create_liqpool_contract, create_plane_contract, create_token_contract, install_token_wasm,
+    jump, PoolTestFixture,
+};
+use crate::plane::PoolPlaneClient;
 use crate::LiquidityPoolClient;
 use soroban_sdk::{
-    testutils::{Address as _, Ledger, LedgerInfo},
-    Address, BytesN, Env, Vec,
+    symbol_short,
+    testutils::{Address as _, Events},
+    Address, Env, IntoVal, String, Symbol, Val, Vec,
 };
+use std::collections::BTreeMap;
 use std::vec;
 use token_share::token_contract::{Client, WASM};
 
@@ -15,6 +21,9 @@ pub(crate) struct TestConfig {
     pub(crate) rewards_count: i128,
     pub(crate) liq_pool_fee: u32,
     pub(crate) reward_tps: u128,
+    // swap reserve floor set by admin, tests trade amounts below the pool default.
+    //  None keeps the pool default
+    pub(crate) min_reserve: Option<u128>,
 }
 
 impl Default for TestConfig {
@@ -25,6 +34,7 @@ impl Default for TestConfig {
             rewards_count: 1_000_000_0000000,
             liq_pool_fee: 30,
             reward_tps: 10_5000000_u128,
+            min_reserve: Some(0),
         }
     }
 }
@@ -62,34 +72,25 @@ impl Setup<'_> {
     /// Mint reward token (1_000_000_0000000) & approve for liquidity_pool token
     pub(crate) fn setup(config: &TestConfig) -> Self {
         let e: Env = Env::default();
-        e.mock_all_auths();
         e.budget().reset_unlimited();
 
-        let users = Self::generate_random_users(&e, config.users_count);
-
-        let mut token_admin1 = Address::generate(&e);
-        let mut token_admin2 = Address::generate(&e);
-
-        let mut token1 = create_token_contract(&e, &token_admin1);
-        let mut token2 = create_token_contract(&e, &token_admin2);
-        let token_reward = create_token_contract(&e, &token_admin1);
-
-        let plane = create_plane_contract(&e);
-
-        if &token2.address < &token1.address {
-            std::mem::swap(&mut token1, &mut token2);
-            std::mem::swap(&mut token_admin1, &mut token_admin2);
+        // pool admin is the first user
+        let PoolTestFixture {
+            env: e,
+            admin,
+            users,
+            token_a: token1,
+            token_b: token2,
+            token_reward,
+            token_share,
+            liq_pool,
+            plane,
+        } = PoolTestFixture::new(&e, config.liq_pool_fee, config.users_count - 1);
+        if let Some(min_reserve) = config.min_reserve {
+            liq_pool.set_min_reserve(&admin, &min_reserve);
         }
+        let users = [vec![admin], users].concat();
 
-        let liq_pool = create_liqpool_contract(
-            &e,
-            &users[0],
-            &install_token_wasm(&e),
-            &Vec::from_array(&e, [token1.address.clone(), token2.address.clone()]),
-            &token_reward.address,
-            config.liq_pool_fee,
-            &plane.address,
-        );
         token_reward.mint(&liq_pool.address, &config.rewards_count);
 
         token_reward.approve(
@@ -99,8 +100,6 @@ impl Setup<'_> {
             &99999,
         );
 
-        let token_share = Client::new(&e, &liq_pool.share_id());
-
         Self {
             env: e,
             users,
@@ -145,51 +144,315 @@ impl Setup<'_> {
     }
 }
 
-pub fn create_token_contract<'a>(e: &Env, admin: &Address) -> Client<'a> {
-    Client::new(e, &e.register_stellar_asset_contract(admin.clone()))
+pub fn create_token_contract_with_symbol<'a>(e: &Env, admin: &Address, symbol: &str) -> Client<'a> {
+    let token = Client::new(e, &e.register_contract_wasm(None, WASM));
+    token.initialize(
+        admin,
+        &7,
+        &"Token".into_val(e),
+        &String::from_str(e, symbol),
+        &false,
+    );
+    token
+}
+
+pub fn create_token_contract_with_decimals<'a>(
+    e: &Env,
+    admin: &Address,
+    decimals: u32,
+) -> Client<'a> {
+    let token = Client::new(e, &e.register_contract_wasm(None, WASM));
+    token.initialize(
+        admin,
+        &decimals,
+        &"Token".into_val(e),
+        &"TKN".into_val(e),
+        &false,
+    );
+    token
 }
 
-fn create_plane_contract<'a>(e: &Env) -> PoolPlaneClient<'a> {
-    PoolPlaneClient::new(e, &e.register_contract_wasm(None, pool_plane::WASM))
+pub fn create_stableswap_liqpool_contract<'a>(
+    e: &Env,
+    admin: &Address,
+    tokens: &Vec<Address>,
+    token_reward: &Address,
+    fee_fraction: u32,
+    amp: u128,
+) -> LiquidityPoolClient<'a> {
+    let liqpool = LiquidityPoolClient::new(e, &e.register_contract(None, crate::LiquidityPool {}));
+    liqpool.set_pools_plane(&create_plane_contract(e).address);
+    liqpool.initialize_stableswap(admin, &install_token_wasm(e), tokens, &fee_fraction, &amp);
+    liqpool.initialize_rewards_config(token_reward, &liqpool.address);
+    liqpool
 }
 
-pub fn create_liqpool_contract<'a>(
+pub fn create_weighted_liqpool_contract<'a>(
     e: &Env,
     admin: &Address,
-    token_wasm_hash: &BytesN<32>,
     tokens: &Vec<Address>,
     token_reward: &Address,
     fee_fraction: u32,
-    plane: &Address,
+    weights: &Vec<u32>,
 ) -> LiquidityPoolClient<'a> {
     let liqpool = LiquidityPoolClient::new(e, &e.register_contract(None, crate::LiquidityPool {}));
-    liqpool.initialize_all(
+    liqpool.set_pools_plane(&create_plane_contract(e).address);
+    liqpool.initialize_weighted(
         admin,
-        token_wasm_hash,
+        &install_token_wasm(e),
         tokens,
         &fee_fraction,
-        token_reward,
-        &liqpool.address,
-        plane,
+        weights,
     );
+    liqpool.initialize_rewards_config(token_reward, &liqpool.address);
     liqpool
 }
 
-pub fn install_token_wasm(e: &Env) -> BytesN<32> {
-    e.deployer().upload_contract_wasm(WASM)
+/// Token burning 1% of every transferred amount, so the recipient gets less than sent
+mod fee_on_transfer_token {
+    use soroban_sdk::{contract, contractimpl, contracttype, Address, Env};
+
+    #[contract]
+    pub struct FeeOnTransferToken;
+
+    #[derive(Clone)]
+    #[contracttype]
+    enum FeeTokenDataKey {
+        Balance(Address),
+        Allowance(Address, Address),
+    }
+
+    #[contractimpl]
+    impl FeeOnTransferToken {
+        pub fn mint(e: Env, to: Address, amount: i128) {
+            let balance = Self::balance(e.clone(), to.clone());
+            e.storage()
+                .instance()
+                .set(&FeeTokenDataKey::Balance(to), &(balance + amount));
+        }
+
+        pub fn decimals(_e: Env) -> u32 {
+            7
+        }
+
+        pub fn balance(e: Env, id: Address) -> i128 {
+            e.storage()
+                .instance()
+                .get(&FeeTokenDataKey::Balance(id))
+                .unwrap_or(0)
+        }
+
+        pub fn allowance(e: Env, from: Address, spender: Address) -> i128 {
+            e.storage()
+                .instance()
+                .get(&FeeTokenDataKey::Allowance(from, spender))
+                .unwrap_or(0)
+        }
+
+        pub fn approve(e: Env, from: Address, spender: Address, amount: i128, _expiration: u32) {
+            from.require_auth();
+            e.storage()
+                .instance()
+                .set(&FeeTokenDataKey::Allowance(from, spender), &amount);
+        }
+
+        pub fn transfer(e: Env, from: Address, to: Address, amount: i128) {
+            from.require_auth();
+            Self::move_funds(&e, from, to, amount);
+        }
+
+        pub fn transfer_from(e: Env, spender: Address, from: Address, to: Address, amount: i128) {
+            spender.require_auth();
+            let allowance = Self::allowance(e.clone(), from.clone(), spender.clone());
+            if allowance < amount {
+                panic!("insufficient allowance");
+            }
+            e.storage().instance().set(
+                &FeeTokenDataKey::Allowance(from.clone(), spender),
+                &(allowance - amount),
+            );
+            Self::move_funds(&e, from, to, amount);
+        }
+    }
+
+    impl FeeOnTransferToken {
+        fn move_funds(e: &Env, from: Address, to: Address, amount: i128) {
+            let from_balance = Self::balance(e.clone(), from.clone());
+            if from_balance < amount {
+                panic!("insufficient balance");
+            }
+            e.storage()
+                .instance()
+                .set(&FeeTokenDataKey::Balance(from), &(from_balance - amount));
+            Self::mint(e.clone(), to, amount - amount / 100);
+        }
+    }
+}
+
+pub(crate) use fee_on_transfer_token::{FeeOnTransferToken, FeeOnTransferTokenClient};
+
+/// Token calling back into the pool on every transfer touching it, once the pool is set as target
+mod reentrant_token {
+    use crate::LiquidityPoolClient;
+    use soroban_sdk::{contract, contractimpl, contracttype, Address, Env};
+
+    #[contract]
+    pub struct ReentrantToken;
+
+    #[derive(Clone)]
+    #[contracttype]
+    enum ReentrantTokenDataKey {
+        Balance(Address),
+        Target,
+    }
+
+    #[contractimpl]
+    impl ReentrantToken {
+        pub fn mint(e: Env, to: Address, amount: i128) {
+            let balance = Self::balance(e.clone(), to.clone());
+            e.storage()
+                .instance()
+                .set(&ReentrantTokenDataKey::Balance(to), &(balance + amount));
+        }
+
+        pub fn set_target(e: Env, pool: Address) {
+            e.storage()
+                .instance()
+                .set(&ReentrantTokenDataKey::Target, &pool);
+        }
+
+        pub fn decimals(_e: Env) -> u32 {
+            7
+        }
+
+        pub fn balance(e: Env, id: Address) -> i128 {
+            e.storage()
+                .instance()
+                .get(&ReentrantTokenDataKey::Balance(id))
+                .unwrap_or(0)
+        }
+
+        pub fn allowance(_e: Env, _from: Address, _spender: Address) -> i128 {
+            i128::MAX
+        }
+
+        pub fn transfer(e: Env, from: Address, to: Address, amount: i128) {
+            from.require_auth();
+            Self::move_funds(&e, from, to, amount);
+        }
+
+        pub fn transfer_from(e: Env, spender: Address, from: Address, to: Address, amount: i128) {
+            spender.require_auth();
+            Self::move_funds(&e, from, to, amount);
+        }
+    }
+
+    impl ReentrantToken {
+        fn move_funds(e: &Env, from: Address, to: Address, amount: i128) {
+            let from_balance = Self::balance(e.clone(), from.clone());
+            if from_balance < amount {
+                panic!("insufficient balance");
+            }
+            e.storage().instance().set(
+                &ReentrantTokenDataKey::Balance(from.clone()),
+                &(from_balance - amount),
+            );
+            Self::mint(e.clone(), to.clone(), amount);
+
+            let target: Option<Address> =
+                e.storage().instance().get(&ReentrantTokenDataKey::Target);
+            if let Some(pool) = target {
+                if from == pool || to == pool {
+                    // pool state observed in the middle of the pool call
+                    LiquidityPoolClient::new(e, &pool).get_reserves();
+                }
+            }
+        }
+    }
+}
+
+pub(crate) use reentrant_token::{ReentrantToken, ReentrantTokenClient};
+
+/// Pool state reconstructed purely from the events published by the pool contract
+#[derive(Default)]
+pub(crate) struct EventsReplay {
+    pub(crate) reserves: [u128; 2],
+    pub(crate) total_shares: u128,
+    pub(crate) user_shares: BTreeMap<Address, u128>,
+    pub(crate) user_claimed: BTreeMap<Address, u128>,
+    seen: Option<Vec<(Address, Vec<Val>, Val)>>,
 }
 
-pub fn jump(e: &Env, time: u64) {
-    e.ledger().set(LedgerInfo {
-        timestamp: e.ledger().timestamp().saturating_add(time),
-        protocol_version: 20,
-        sequence_number: e.ledger().sequence(),
-        network_id: Default::default(),
-        base_reserve: 10,
-        min_temp_entry_ttl: 999999,
-        min_persistent_entry_ttl: 999999,
-        max_entry_ttl: u32::MAX,
-    });
+impl EventsReplay {
+    /// Apply pool events published since the previous call.
+    ///
+    /// Already applied events are skipped if the events buffer still contains them
+    pub(crate) fn apply(&mut self, e: &Env, pool: &Address) {
+        let all = e.events().all();
+        let new_events = match &self.seen {
+            Some(seen) if all.len() >= seen.len() && all.slice(0..seen.len()) == *seen => {
+                all.slice(seen.len()..)
+            }
+            _ => all.clone(),
+        };
+        self.seen = Some(all);
+
+        for (contract, topics, data) in new_events.iter() {
+            if contract != *pool {
+                continue;
+            }
+
+            let name: Symbol = topics.get(0).unwrap().into_val(e);
+            if name == symbol_short!("deposit") {
+                let user: Address = topics.get(1).unwrap().into_val(e);
+                let (amounts, share_amount): (Vec<u128>, u128) = data.into_val(e);
+                self.reserves[0] += amounts.get(0).unwrap();
+                self.reserves[1] += amounts.get(1).unwrap();
+                self.total_shares += share_amount;
+                *self.user_shares.entry(user).or_insert(0) += share_amount;
+            } else if name == symbol_short!("swap") {
+                let (in_idx, out_idx, in_amount, out_amount): (u32, u32, u128, u128) =
+                    data.into_val(e);
+                self.reserves[in_idx as usize] += in_amount;
+                self.reserves[out_idx as usize] -= out_amount;
+            } else if name == symbol_short!("withdraw") {
+                let user: Address = topics.get(1).unwrap().into_val(e);
+                let (share_amount, amounts): (u128, Vec<u128>) = data.into_val(e);
+                self.reserves[0] -= amounts.get(0).unwrap();
+                self.reserves[1] -= amounts.get(1).unwrap();
+                self.total_shares -= share_amount;
+                *self.user_shares.entry(user).or_insert(0) -= share_amount;
+            } else if name == symbol_short!("claim") {
+                let user: Address = topics.get(1).unwrap().into_val(e);
+                let amount: u128 = data.into_val(e);
+                *self.user_claimed.entry(user).or_insert(0) += amount;
+            } else if name == symbol_short!("donate") {
+                let amounts: Vec<u128> = data.into_val(e);
+                self.reserves[0] += amounts.get(0).unwrap();
+                self.reserves[1] += amounts.get(1).unwrap();
+            } else if name == symbol_short!("sync") {
+                let (_old_reserves, new_reserves): (Vec<u128>, Vec<u128>) = data.into_val(e);
+                self.reserves = [new_reserves.get(0).unwrap(), new_reserves.get(1).unwrap()];
+            } else if name == Symbol::new(e, "migrate_liquidity") {
+                let (amounts, _new_share_amount): (Vec<u128>, u128) = data.into_val(e);
+                self.reserves[0] -= amounts.get(0).unwrap();
+                self.reserves[1] -= amounts.get(1).unwrap();
+            } else if name == Symbol::new(e, "claim_migrated_shares") {
+                let user: Address = topics.get(1).unwrap().into_val(e);
+                let (share_amount, _new_share_amount): (u128, u128) = data.into_val(e);
+                self.total_shares -= share_amount;
+                *self.user_shares.entry(user).or_insert(0) -= share_amount;
+            }
+        }
+    }
+
+    pub(crate) fn get_user_shares(&self, user: &Address) -> u128 {
+        self.user_shares.get(user).copied().unwrap_or(0)
+    }
+
+    pub(crate) fn get_user_claimed(&self, user: &Address) -> u128 {
+        self.user_claimed.get(user).copied().unwrap_or(0)
+    }
 }
 
 #[test]
@@ -200,6 +463,87 @@ fn test() {
         rewards_count: 1_000_000_0000000,
         liq_pool_fee: 30,
         reward_tps: 10_5000000_u128,
+        min_reserve: Some(0),
     };
     let _setup = Setup::new_with_config(&config);
 }
+
+/// External contract composing with the pool only through the shared interface client
+mod pool_caller {
+    use liquidity_pool_interface::LiquidityPoolClient as PoolInterfaceClient;
+    use soroban_sdk::{contract, contractimpl, Address, Env, Vec};
+
+    #[contract]
+    pub struct PoolCaller;
+
+    #[contractimpl]
+    impl PoolCaller {
+        pub fn pool_state(e: Env, pool: Address) -> (Address, Vec<u128>) {
+            let client = PoolInterfaceClient::new(&e, &pool);
+            (client.share_id(), client.get_reserves())
+        }
+
+        pub fn deposit(e: Env, pool: Address, user: Address, desired_amounts: Vec<u128>) -> u128 {
+            let (_, shares) = PoolInterfaceClient::new(&e, &pool).deposit(&user, &desired_amounts);
+            shares
+        }
+
+        // swap only if the pool quote is good enough
+        pub fn swap(
+            e: Env,
+            pool: Address,
+            user: Address,
+            in_idx: u32,
+            out_idx: u32,
+            in_amount: u128,
+            out_min: u128,
+        ) -> u128 {
+            let client = PoolInterfaceClient::new(&e, &pool);
+            if client.estimate_swap(&in_idx, &out_idx, &in_amount) < out_min {
+                panic!("quote is too low");
+            }
+            client.swap(&user, &in_idx, &out_idx, &in_amount, &out_min)
+        }
+
+        pub fn withdraw(e: Env, pool: Address, user: Address, share_amount: u128) -> Vec<u128> {
+            PoolInterfaceClient::new(&e, &pool).withdraw(
+                &user,
+                &share_amount,
+                &Vec::from_array(&e, [0, 0]),
+            )
+        }
+    }
+}
+
+pub(crate) use pool_caller::{PoolCaller, PoolCallerClient};
+
+/// Router pools registry listing the pools registered by the test
+mod pool_registry {
+    use soroban_sdk::{contract, contractimpl, Address, BytesN, Env, Map, Vec};
+
+    #[contract]
+    pub struct PoolRegistry;
+
+    #[contractimpl]
+    impl PoolRegistry {
+        pub fn add_pool(e: Env, tokens: Vec<Address>, pool: Address) {
+            let mut pools = Self::get_pools(e.clone(), tokens.clone());
+            pools.set(BytesN::from_array(&e, &[pools.len() as u8; 32]), pool);
+            e.storage().instance().set(&tokens, &pools);
+        }
+
+        pub fn get_pools(e: Env, tokens: Vec<Address>) -> Map<BytesN<32>, Address> {
+            e.storage().instance().get(&tokens).unwrap_or(Map::new(&e))
+        }
+    }
+}
+
+pub(crate) use pool_registry::{PoolRegistry, PoolRegistryClient};
+
+pub fn create_pool_registry<'a>(e: &Env, pools: &[&LiquidityPoolClient]) -> PoolRegistryClient<'a> {
+    let registry = PoolRegistryClient::new(e, &e.register_contract(None, PoolRegistry {}));
+    for pool in pools {
+        registry.add_pool(&pool.get_tokens(), &pool.address);
+    }
+    registry
+}
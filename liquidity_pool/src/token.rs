@@ -1,34 +1,134 @@
-use crate::storage::{get_token_a, get_token_b};
+use crate::constants::{MAX_TOKEN_SYMBOL_LENGTH, SHARE_SYMBOL_PART_LENGTH};
+use crate::rewards::get_pool_token_reward_balance;
+use crate::storage::{get_reward_fee_bucket, get_token_a, get_token_b, PoolState};
 use soroban_sdk::token::TokenClient as Client;
-use soroban_sdk::{xdr::ToXdr, Address, Bytes, BytesN, Env};
+use soroban_sdk::{xdr::ToXdr, Address, Bytes, BytesN, Env, String};
 use utils::bump::bump_instance;
 
+// Salt of the share token deployment: sha256(xdr(token_a) || xdr(token_b)).
+//  The pool deploys the share token itself, so its address is derived from the pool address and
+//  the salt: sha256(xdr(HashIdPreimage::ContractId { network_id, FromAddress(pool, salt) })).
+//  Changing the layout changes share addresses of the new pools only
+pub fn get_share_salt(e: &Env, token_a: &Address, token_b: &Address) -> BytesN<32> {
+    let mut salt = Bytes::new(e);
+    salt.append(&token_a.to_xdr(e));
+    salt.append(&token_b.to_xdr(e));
+    e.crypto().sha256(&salt)
+}
+
+// Address of the share token the pool deploys for the tokens. Known before the deployment
+pub fn get_share_address(e: &Env, token_a: &Address, token_b: &Address) -> Address {
+    e.deployer()
+        .with_current_contract(get_share_salt(e, token_a, token_b))
+        .deployed_address()
+}
+
 pub fn create_contract(
     e: &Env,
     token_wasm_hash: BytesN<32>,
     token_a: &Address,
     token_b: &Address,
 ) -> Address {
-    let mut salt = Bytes::new(e);
-    salt.append(&token_a.to_xdr(e));
-    salt.append(&token_b.to_xdr(e));
-    let salt = e.crypto().sha256(&salt);
     e.deployer()
-        .with_current_contract(salt)
+        .with_current_contract(get_share_salt(e, token_a, token_b))
         .deploy(token_wasm_hash)
 }
 
+// First characters of the token symbol. None if symbol is unreadable, too long or not alphanumeric
+fn read_symbol_part(
+    e: &Env,
+    token: &Address,
+    part: &mut [u8; SHARE_SYMBOL_PART_LENGTH],
+) -> Option<usize> {
+    let symbol = match Client::new(e, token).try_symbol() {
+        Ok(Ok(symbol)) => symbol,
+        _ => return None,
+    };
+    let symbol_len = symbol.len() as usize;
+    if symbol_len == 0 || symbol_len > MAX_TOKEN_SYMBOL_LENGTH {
+        return None;
+    }
+
+    let mut buf = [0u8; MAX_TOKEN_SYMBOL_LENGTH];
+    symbol.copy_into_slice(&mut buf[..symbol_len]);
+    let part_len = symbol_len.min(SHARE_SYMBOL_PART_LENGTH);
+    if !buf[..part_len].iter().all(u8::is_ascii_alphanumeric) {
+        return None;
+    }
+    part[..part_len].copy_from_slice(&buf[..part_len]);
+    Some(part_len)
+}
+
+// Share token name and symbol built from the pool tokens: "XLM-USDC LP" / "XLM-USDC".
+//  Generic metadata is used if any of the token symbols can't be read
+pub fn get_share_metadata(e: &Env, token_a: &Address, token_b: &Address) -> (String, String) {
+    let mut part_a = [0u8; SHARE_SYMBOL_PART_LENGTH];
+    let mut part_b = [0u8; SHARE_SYMBOL_PART_LENGTH];
+    let (len_a, len_b) = match (
+        read_symbol_part(e, token_a, &mut part_a),
+        read_symbol_part(e, token_b, &mut part_b),
+    ) {
+        (Some(len_a), Some(len_b)) => (len_a, len_b),
+        _ => {
+            return (
+                String::from_str(e, "Pool Share Token"),
+                String::from_str(e, "POOL"),
+            )
+        }
+    };
+
+    let mut buf = [0u8; 2 * SHARE_SYMBOL_PART_LENGTH + 4];
+    let mut len = 0;
+    let chunks: [&[u8]; 4] = [&part_a[..len_a], b"-", &part_b[..len_b], b" LP"];
+    for chunk in chunks {
+        buf[len..len + chunk.len()].copy_from_slice(chunk);
+        len += chunk.len();
+    }
+    // only ascii characters are copied, so it's always valid utf-8
+    let name = core::str::from_utf8(&buf[..len]).unwrap();
+    (
+        String::from_str(e, name),
+        String::from_str(e, &name[..len - 3]),
+    )
+}
+
 pub fn get_balance(e: &Env, contract: Address) -> u128 {
     bump_instance(e);
     Client::new(e, &contract).balance(&e.current_contract_address()) as u128
 }
 
+// Pool balance of the token excluding reward tokens if it's the reward token as well
+//  and swap fees set aside for rewards
+fn get_pool_balance(e: &Env, token: Address, reward_fees: u128) -> u128 {
+    get_balance(e, token.clone())
+        .saturating_sub(get_pool_token_reward_balance(e, &token))
+        .saturating_sub(reward_fees)
+}
+
 pub fn get_balance_a(e: &Env) -> u128 {
-    get_balance(e, get_token_a(e))
+    get_pool_balance(e, get_token_a(e), get_reward_fee_bucket(e).0)
 }
 
 pub fn get_balance_b(e: &Env) -> u128 {
-    get_balance(e, get_token_b(e))
+    get_pool_balance(e, get_token_b(e), get_reward_fee_bucket(e).1)
+}
+
+// Pool balances of both tokens with the addresses already loaded
+pub fn get_pool_balances(e: &Env, state: &PoolState) -> (u128, u128) {
+    let (reward_fees_a, reward_fees_b) = get_reward_fee_bucket(e);
+    (
+        get_pool_balance(e, state.token_a.clone(), reward_fees_a),
+        get_pool_balance(e, state.token_b.clone(), reward_fees_b),
+    )
+}
+
+// User balance and allowance for the pool
+pub fn get_user_funds(e: &Env, contract: Address, user: &Address) -> (u128, u128) {
+    let client = Client::new(e, &contract);
+    (
+        client.balance(user) as u128,
+        client.allowance(user, &e.current_contract_address()) as u128,
+    )
 }
 
 fn transfer(e: &Env, token: Address, to: Address, amount: i128) {
@@ -42,3 +142,7 @@ pub fn transfer_a(e: &Env, to: Address, amount: u128) {
 pub fn transfer_b(e: &Env, to: Address, amount: u128) {
     transfer(e, get_token_b(e), to, amount as i128);
 }
+
+pub fn transfer_token(e: &Env, token: Address, to: Address, amount: u128) {
+    transfer(e, token, to, amount as i128);
+}
@@ -0,0 +1,100 @@
+use crate::constants::{FEE_MULTIPLIER, WEIGHT_DENOMINATOR};
+use crate::math::{mul_div_ceil, mul_div_floor, pow_down, pow_up, ONE};
+use crate::pool;
+
+// Weighted product invariant: balance_a^w_a * balance_b^w_b.
+//  Weights sum up to WEIGHT_DENOMINATOR, equal weights are exactly the constant product curve
+
+// Calculate how much can be bought with in_amount keeping the invariant. Rounded down:
+//  out = reserve_buy * (1 - (reserve_sell / (reserve_sell + in_amount)) ^ (w_sell / w_buy))
+pub fn get_swap_out(
+    fee_fraction: u32,
+    weight_sell: u32,
+    weight_buy: u32,
+    reserve_sell: u128,
+    reserve_buy: u128,
+    in_amount: u128,
+) -> u128 {
+    // same results as constant product pool bit-for-bit
+    if weight_sell == weight_buy {
+        return pool::get_swap_out(fee_fraction, reserve_sell, reserve_buy, in_amount);
+    }
+    if reserve_sell == 0 {
+        return 0;
+    }
+
+    let multiplier_with_fee = FEE_MULTIPLIER - fee_fraction as u128;
+    let in_amount = mul_div_floor(in_amount, multiplier_with_fee, FEE_MULTIPLIER);
+    let base = mul_div_ceil(reserve_sell, ONE, reserve_sell + in_amount);
+    let exponent = mul_div_floor(weight_sell as u128, ONE, weight_buy as u128);
+    let power = pow_up(base, exponent).min(ONE);
+    mul_div_floor(reserve_buy, ONE - power, ONE)
+}
+
+// Least buy reserve keeping the invariant once the sell reserve grew to new_reserve_sell. Rounded up:
+//  reserve_buy * (reserve_sell / new_reserve_sell) ^ (w_sell / w_buy)
+//  Rounding matches get_swap_out, so the reserve left by its result always passes
+pub fn get_min_reserve_buy(
+    weight_sell: u32,
+    weight_buy: u32,
+    reserve_sell: u128,
+    reserve_buy: u128,
+    new_reserve_sell: u128,
+) -> u128 {
+    if reserve_sell == 0 {
+        return 0;
+    }
+
+    let base = mul_div_ceil(reserve_sell, ONE, new_reserve_sell);
+    let exponent = mul_div_floor(weight_sell as u128, ONE, weight_buy as u128);
+    let power = pow_up(base, exponent).min(ONE);
+    reserve_buy - mul_div_floor(reserve_buy, ONE - power, ONE)
+}
+
+// Shares minted for single token deposit. Rounded down:
+//  shares = total_shares * ((1 + in_amount / reserve_in) ^ w_in - 1)
+//  Part of the amount not matching the pool proportion is swapped implicitly, so it's charged with the fee
+pub fn get_single_deposit_shares(
+    fee_fraction: u32,
+    weight_in: u32,
+    reserve_in: u128,
+    total_shares: u128,
+    in_amount: u128,
+) -> u128 {
+    let swapped = mul_div_ceil(
+        in_amount,
+        (WEIGHT_DENOMINATOR - weight_in) as u128,
+        WEIGHT_DENOMINATOR as u128,
+    );
+    let fee = mul_div_ceil(swapped, fee_fraction as u128, FEE_MULTIPLIER);
+    let base = mul_div_floor(reserve_in + in_amount - fee, ONE, reserve_in);
+    let exponent = mul_div_floor(weight_in as u128, ONE, WEIGHT_DENOMINATOR as u128);
+    let power = pow_down(base, exponent);
+    if power <= ONE {
+        return 0;
+    }
+    mul_div_floor(total_shares, power - ONE, ONE)
+}
+
+// Shares minted for the first deposit: weighted geometric mean of the amounts.
+//  Equal weights give sqrt(amount_a * amount_b), same as constant product pool
+pub fn get_initial_shares(weight_a: u32, weight_b: u32, amount_a: u128, amount_b: u128) -> u128 {
+    if weight_a == weight_b {
+        return pool::get_initial_shares(amount_a, amount_b);
+    }
+    if amount_a == 0 || amount_b == 0 {
+        return 0;
+    }
+
+    // a^w_a * b^w_b = a * (b / a)^w_b, larger amount is taken out so the power doesn't overflow
+    let (amount_major, amount_minor, weight_minor) = match amount_a >= amount_b {
+        true => (amount_a, amount_b, weight_b),
+        false => (amount_b, amount_a, weight_a),
+    };
+    let base = mul_div_floor(amount_minor, ONE, amount_major);
+    if base == 0 {
+        return 0;
+    }
+    let exponent = mul_div_floor(weight_minor as u128, ONE, WEIGHT_DENOMINATOR as u128);
+    mul_div_floor(amount_major, pow_down(base, exponent), ONE)
+}
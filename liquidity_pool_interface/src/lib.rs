@@ -0,0 +1,62 @@
+#![no_std]
+
+use soroban_sdk::{contractclient, contracttype, Address, Env, Vec};
+
+// Swap amounts and the pool reserves after the swap
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct SwapResult {
+    // amount actually received by the pool, less than sent for tokens charging transfer fee
+    pub amount_in: u128,
+    pub amount_out: u128,
+    pub new_reserve_a: u128,
+    pub new_reserve_b: u128,
+}
+
+// Pool entrypoints other contracts (router, zappers, aggregators) compose with.
+//  Implemented by the liquidity pool contract, so the client is generated in one place
+#[contractclient(name = "LiquidityPoolClient")]
+pub trait LiquidityPoolInterface {
+    // Returns the token contract address for the pool share token. View, O(1)
+    fn share_id(e: Env) -> Address;
+
+    // Get pool reserves. View, O(1)
+    fn get_reserves(e: Env) -> Vec<u128>;
+
+    // Deposit tokens and mint pool shares to the user.
+    // Tokens are transferred directly with the user authorization, no allowance is needed.
+    // Returns amounts deposited and the amount of shares minted
+    fn deposit(e: Env, user: Address, desired_amounts: Vec<u128>) -> (Vec<u128>, u128);
+
+    // Swap in_amount of token in_idx for at least out_min of token out_idx.
+    // Returns amount of token out_idx received
+    fn swap(
+        e: Env,
+        user: Address,
+        in_idx: u32,
+        out_idx: u32,
+        in_amount: u128,
+        out_min: u128,
+    ) -> u128;
+
+    // Same as swap, returns amounts swapped and the pool reserves after the swap,
+    // so callers get the realized price without extra calls
+    fn swap_v2(
+        e: Env,
+        user: Address,
+        in_idx: u32,
+        out_idx: u32,
+        in_amount: u128,
+        out_min: u128,
+    ) -> SwapResult;
+
+    // Estimate amount of token out_idx the swap returns. View, O(1)
+    // Estimation is optimistic for tokens charging transfer fee: it's made before the fee,
+    // while swap is priced by the amount actually received by the pool
+    fn estimate_swap(e: Env, in_idx: u32, out_idx: u32, in_amount: u128) -> u128;
+
+    // Burn share_amount of user pool shares and send the corresponding amount of tokens to user.
+    // Shares are pulled with transfer_from, so allowance for the pool is required.
+    // Returns amount of tokens withdrawn
+    fn withdraw(e: Env, user: Address, share_amount: u128, min_amounts: Vec<u128>) -> Vec<u128>;
+}
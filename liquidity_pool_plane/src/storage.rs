@@ -2,6 +2,9 @@ use soroban_sdk::{contracttype, symbol_short, Address, Env, Symbol, Vec};
 
 const DAY_IN_LEDGERS: u32 = 17280;
 
+const INSTANCE_BUMP_AMOUNT: u32 = 30 * DAY_IN_LEDGERS;
+const INSTANCE_LIFETIME_THRESHOLD: u32 = INSTANCE_BUMP_AMOUNT - DAY_IN_LEDGERS;
+
 const PERSISTENT_BUMP_AMOUNT: u32 = 30 * DAY_IN_LEDGERS;
 const PERSISTENT_LIFETIME_THRESHOLD: u32 = PERSISTENT_BUMP_AMOUNT - DAY_IN_LEDGERS;
 
@@ -19,6 +22,13 @@ pub struct PoolPlane {
     pub reserves: Vec<u128>,
 }
 
+// plane keeps nothing in the instance storage, but the contract itself lives as long as the instance
+fn bump_instance(e: &Env) {
+    e.storage()
+        .instance()
+        .extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT);
+}
+
 fn bump_persistent(e: &Env, key: &DataKey) {
     e.storage()
         .persistent()
@@ -34,12 +44,14 @@ fn get_default_pool(e: &Env) -> PoolPlane {
 }
 
 pub(crate) fn update(e: &Env, contract: Address, pool: &PoolPlane) {
+    bump_instance(e);
     let key = DataKey::PoolData(contract);
     e.storage().persistent().set(&key, pool);
     bump_persistent(e, &key);
 }
 
 pub(crate) fn get(e: &Env, contract: Address) -> PoolPlane {
+    bump_instance(e);
     let key = DataKey::PoolData(contract);
 
     // return standard pool with zero reserves if data not provided
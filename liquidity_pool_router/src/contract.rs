@@ -2,27 +2,31 @@ use crate::constants::CONSTANT_PRODUCT_FEE_AVAILABLE;
 use crate::events::{Events, LiquidityPoolRouterEvents};
 use crate::pool_interface::{
     LiquidityPoolInterfaceTrait, PoolPlaneInterface, PoolsManagementTrait, RewardsInterfaceTrait,
-    SwapRouterInterface,
+    RouteInterface, SwapRouterInterface,
 };
 use crate::pool_utils::{
     deploy_stableswap_pool, deploy_standard_pool, get_custom_salt, get_stableswap_pool_salt,
     get_standard_pool_salt, pool_salt,
 };
 use crate::rewards::get_rewards_manager;
+use crate::route::{estimate_hop, SwapPath};
 use crate::router_interface::{AdminInterface, UpgradeableContract};
 use crate::storage::{
     add_pool, get_init_pool_payment_address, get_init_pool_payment_amount,
-    get_init_pool_payment_token, get_pool, get_pool_plane, get_pools_plain, get_swap_router,
-    has_pool, remove_pool, set_constant_product_pool_hash, set_init_pool_payment_address,
-    set_init_pool_payment_amount, set_init_pool_payment_token, set_pool_plane,
-    set_stableswap_pool_hash, set_swap_router, set_token_hash, LiquidityPoolType,
+    get_init_pool_payment_token, get_pool, get_pool_plane, get_pools_plain, get_route_tokens,
+    get_swap_router, has_pool, remove_pool, set_constant_product_pool_hash,
+    set_init_pool_payment_address, set_init_pool_payment_amount, set_init_pool_payment_token,
+    set_pool_plane, set_route_tokens, set_stableswap_pool_hash, set_swap_router, set_token_hash,
+    LiquidityPoolType, PoolError,
 };
 use crate::swap_router::SwapRouterClient;
 use access_control::access::{AccessControl, AccessControlTrait};
+use liquidity_pool_interface::LiquidityPoolClient;
 use rewards::storage::RewardsStorageTrait;
 use soroban_sdk::token::Client as SorobanTokenClient;
 use soroban_sdk::{
-    contract, contractimpl, symbol_short, Address, BytesN, Env, IntoVal, Map, Symbol, Val, Vec,
+    contract, contractimpl, panic_with_error, symbol_short, Address, BytesN, Env, IntoVal, Map,
+    Symbol, Val, Vec,
 };
 use utils::utils::check_vec_ordered;
 
@@ -47,12 +51,12 @@ impl LiquidityPoolInterfaceTrait for LiquidityPoolRouter {
 
     fn share_id(e: Env, tokens: Vec<Address>, pool_index: BytesN<32>) -> Address {
         let pool_id = get_pool(&e, tokens, pool_index).expect("Pool doesn't exist");
-        e.invoke_contract(&pool_id, &Symbol::new(&e, "share_id"), Vec::new(&e))
+        LiquidityPoolClient::new(&e, &pool_id).share_id()
     }
 
     fn get_reserves(e: Env, tokens: Vec<Address>, pool_index: BytesN<32>) -> Vec<u128> {
         let pool_id = get_pool(&e, tokens, pool_index).expect("Pool doesn't exist");
-        e.invoke_contract(&pool_id, &Symbol::new(&e, "get_reserves"), Vec::new(&e))
+        LiquidityPoolClient::new(&e, &pool_id).get_reserves()
     }
 
     fn get_tokens(e: Env, tokens: Vec<Address>, pool_index: BytesN<32>) -> Vec<Address> {
@@ -71,14 +75,8 @@ impl LiquidityPoolInterfaceTrait for LiquidityPoolRouter {
 
         let pool_id = get_pool(&e, tokens.clone(), pool_index).expect("unable to find pool");
 
-        let (amounts, share_amount): (Vec<u128>, u128) = e.invoke_contract(
-            &pool_id,
-            &symbol_short!("deposit"),
-            Vec::from_array(
-                &e,
-                [user.clone().into_val(&e), desired_amounts.into_val(&e)],
-            ),
-        );
+        let (amounts, share_amount) =
+            LiquidityPoolClient::new(&e, &pool_id).deposit(&user, &desired_amounts);
         Events::new(&e).deposit(tokens, user, pool_id, amounts.clone(), share_amount);
         (amounts, share_amount)
     }
@@ -100,25 +98,12 @@ impl LiquidityPoolInterfaceTrait for LiquidityPoolRouter {
         let pool_id = get_pool(&e, tokens.clone(), pool_index.clone()).expect("Pool doesn't exist");
         let tokens: Vec<Address> = Self::get_tokens(e.clone(), tokens.clone(), pool_index.clone());
 
-        let out_amt = e.invoke_contract(
-            &pool_id,
-            &symbol_short!("swap"),
-            Vec::from_array(
-                &e,
-                [
-                    user.clone().into_val(&e),
-                    tokens
-                        .first_index_of(token_in.clone())
-                        .unwrap()
-                        .into_val(&e),
-                    tokens
-                        .first_index_of(token_out.clone())
-                        .unwrap()
-                        .into_val(&e),
-                    in_amount.into_val(&e),
-                    out_min.into_val(&e),
-                ],
-            ),
+        let out_amt = LiquidityPoolClient::new(&e, &pool_id).swap(
+            &user,
+            &tokens.first_index_of(token_in.clone()).unwrap(),
+            &tokens.first_index_of(token_out.clone()).unwrap(),
+            &in_amount,
+            &out_min,
         );
 
         Events::new(&e).swap(
@@ -138,23 +123,10 @@ impl LiquidityPoolInterfaceTrait for LiquidityPoolRouter {
         let pool_id = get_pool(&e, tokens.clone(), pool_index.clone()).expect("Pool doesn't exist");
         let tokens: Vec<Address> = Self::get_tokens(e.clone(), tokens.clone(), pool_index.clone());
 
-        e.invoke_contract(
-            &pool_id,
-            &Symbol::new(&e, "estimate_swap"),
-            Vec::from_array(
-                &e,
-                [
-                    tokens
-                        .first_index_of(token_in.clone())
-                        .unwrap()
-                        .into_val(&e),
-                    tokens
-                        .first_index_of(token_out.clone())
-                        .unwrap()
-                        .into_val(&e),
-                    in_amount.into_val(&e),
-                ],
-            ),
+        LiquidityPoolClient::new(&e, &pool_id).estimate_swap(
+            &tokens.first_index_of(token_in.clone()).unwrap(),
+            &tokens.first_index_of(token_out.clone()).unwrap(),
+            &in_amount,
         )
     }
 
@@ -169,18 +141,8 @@ impl LiquidityPoolInterfaceTrait for LiquidityPoolRouter {
         user.require_auth();
         let pool_id = get_pool(&e, tokens.clone(), pool_index.clone()).expect("Pool doesn't exist");
 
-        let amounts: Vec<u128> = e.invoke_contract(
-            &pool_id,
-            &symbol_short!("withdraw"),
-            Vec::from_array(
-                &e,
-                [
-                    user.clone().into_val(&e),
-                    share_amount.into_val(&e),
-                    min_amounts.into_val(&e),
-                ],
-            ),
-        );
+        let amounts =
+            LiquidityPoolClient::new(&e, &pool_id).withdraw(&user, &share_amount, &min_amounts);
 
         Events::new(&e).withdraw(tokens, user, pool_id, amounts.clone(), share_amount);
         amounts
@@ -527,25 +489,12 @@ impl SwapRouterInterface for LiquidityPoolRouter {
 
         let tokens: Vec<Address> = Self::get_tokens(e.clone(), tokens.clone(), pool_index.clone());
 
-        let out_amt = e.invoke_contract(
-            &pool_id,
-            &symbol_short!("swap"),
-            Vec::from_array(
-                &e,
-                [
-                    user.into_val(&e),
-                    tokens
-                        .first_index_of(token_in.clone())
-                        .unwrap()
-                        .into_val(&e),
-                    tokens
-                        .first_index_of(token_out.clone())
-                        .unwrap()
-                        .into_val(&e),
-                    in_amount.into_val(&e),
-                    out_min.into_val(&e),
-                ],
-            ),
+        let out_amt = LiquidityPoolClient::new(&e, &pool_id).swap(
+            &user,
+            &tokens.first_index_of(token_in.clone()).unwrap(),
+            &tokens.first_index_of(token_out.clone()).unwrap(),
+            &in_amount,
+            &out_min,
         );
 
         Events::new(&e).swap(
@@ -554,3 +503,119 @@ impl SwapRouterInterface for LiquidityPoolRouter {
         out_amt
     }
 }
+
+#[contractimpl]
+impl RouteInterface for LiquidityPoolRouter {
+    fn set_route_tokens(e: Env, admin: Address, tokens: Vec<Address>) {
+        let access_control = AccessControl::new(&e);
+        admin.require_auth();
+        access_control.check_admin(&admin);
+        set_route_tokens(&e, &tokens);
+    }
+
+    fn get_route_tokens(e: Env) -> Vec<Address> {
+        get_route_tokens(&e)
+    }
+
+    fn estimate_swap_path(e: Env, path: Vec<Address>, in_amount: u128) -> u128 {
+        SwapPath::new(&e, path).estimate(&e, in_amount)
+    }
+
+    fn swap_path(
+        e: Env,
+        user: Address,
+        path: Vec<Address>,
+        in_amount: u128,
+        out_min: u128,
+        expiration_ledger: u32,
+    ) -> u128 {
+        user.require_auth();
+
+        let path = SwapPath::new(&e, path);
+        let mut amount = in_amount;
+        for i in 0..path.hops_count() {
+            let (token_in, token_out) = path.hop_tokens(i);
+            let hop = match estimate_hop(&e, &token_in, &token_out, amount) {
+                Some(hop) => hop,
+                None => panic_with_error!(&e, PoolError::NoRouteFound),
+            };
+
+            SorobanTokenClient::new(&e, &hop.token_in).approve(
+                &user,
+                &hop.pool,
+                &(amount as i128),
+                &expiration_ledger,
+            );
+            let out_amt = LiquidityPoolClient::new(&e, &hop.pool).swap(
+                &user,
+                &hop.in_idx,
+                &hop.out_idx,
+                &amount,
+                &0,
+            );
+
+            let tokens = match hop.in_idx {
+                0 => Vec::from_array(&e, [hop.token_in.clone(), hop.token_out.clone()]),
+                _ => Vec::from_array(&e, [hop.token_out.clone(), hop.token_in.clone()]),
+            };
+            Events::new(&e).swap(
+                tokens,
+                user.clone(),
+                hop.pool,
+                hop.token_in,
+                hop.token_out,
+                amount,
+                out_amt,
+            );
+            amount = out_amt;
+        }
+
+        if amount < out_min {
+            panic!("out amount is less than min")
+        }
+        amount
+    }
+
+    fn find_best_route(
+        e: Env,
+        token_in: Address,
+        token_out: Address,
+        in_amount: u128,
+    ) -> (Vec<Address>, u128) {
+        let mut candidates = Vec::from_array(
+            &e,
+            [Vec::from_array(&e, [token_in.clone(), token_out.clone()])],
+        );
+        for token in get_route_tokens(&e) {
+            if token == token_in || token == token_out {
+                continue;
+            }
+            candidates.push_back(Vec::from_array(
+                &e,
+                [token_in.clone(), token, token_out.clone()],
+            ));
+        }
+
+        let mut best: Option<(Vec<Address>, u128)> = None;
+        for tokens in candidates {
+            let path = SwapPath::new(&e, tokens);
+            let out_amount = match path.try_estimate(&e, in_amount) {
+                Some(value) => value,
+                None => continue,
+            };
+            // shorter path wins on equal output since it's checked first
+            let is_better = match &best {
+                Some((_, best_amount)) => out_amount > *best_amount,
+                None => true,
+            };
+            if is_better {
+                best = Some((path.tokens(), out_amount));
+            }
+        }
+
+        match best {
+            Some(value) => value,
+            None => panic_with_error!(&e, PoolError::NoRouteFound),
+        }
+    }
+}
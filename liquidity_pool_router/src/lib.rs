@@ -7,6 +7,7 @@ mod pool_contract;
 mod pool_interface;
 mod pool_utils;
 mod rewards;
+mod route;
 mod router_interface;
 mod storage;
 mod swap_router;
@@ -185,3 +185,38 @@ pub trait SwapRouterInterface {
     // Get swap router address
     fn get_swap_router(e: Env) -> Address;
 }
+
+pub trait RouteInterface {
+    // Configure intermediate tokens considered by find_best_route
+    fn set_route_tokens(e: Env, admin: Address, tokens: Vec<Address>);
+
+    // Get intermediate tokens considered by find_best_route
+    fn get_route_tokens(e: Env) -> Vec<Address>;
+
+    // Estimate swap along the path of tokens.
+    //  every consecutive pair of tokens is resolved to the best pool available for it,
+    //  swap direction is inferred from tokens order
+    fn estimate_swap_path(e: Env, path: Vec<Address>, in_amount: u128) -> u128;
+
+    // Swap tokens along the path of tokens using best pool available for every hop
+    //   out_min is checked against the amount received on the last hop
+    //   expiration_ledger is argument for sub invocation of token.approve
+    fn swap_path(
+        e: Env,
+        user: Address,
+        path: Vec<Address>,
+        in_amount: u128,
+        out_min: u128,
+        expiration_ledger: u32,
+    ) -> u128;
+
+    // Find path giving the best output: either direct swap or
+    //  two hops through one of the route tokens.
+    //  returns path of tokens and estimated out value
+    fn find_best_route(
+        e: Env,
+        token_in: Address,
+        token_out: Address,
+        in_amount: u128,
+    ) -> (Vec<Address>, u128);
+}
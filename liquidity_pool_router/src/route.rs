@@ -0,0 +1,110 @@
+use crate::pool_utils::pool_salt;
+use crate::storage::{get_pools_plain, get_swap_router, PoolError};
+use crate::swap_router::SwapRouterClient;
+use soroban_sdk::{panic_with_error, Address, Env, Vec};
+
+// Path of tokens to swap through. Every consecutive pair of tokens is a single hop
+#[derive(Clone)]
+pub(crate) struct SwapPath {
+    tokens: Vec<Address>,
+}
+
+// Single hop of the path resolved to the pool
+#[derive(Clone)]
+pub(crate) struct SwapHop {
+    pub pool: Address,
+    pub token_in: Address,
+    pub token_out: Address,
+    pub in_idx: u32,
+    pub out_idx: u32,
+    pub out_amount: u128,
+}
+
+impl SwapPath {
+    pub(crate) fn new(e: &Env, tokens: Vec<Address>) -> SwapPath {
+        if tokens.len() < 2 {
+            panic_with_error!(e, PoolError::InvalidPath);
+        }
+        for i in 0..tokens.len() - 1 {
+            if tokens.get_unchecked(i) == tokens.get_unchecked(i + 1) {
+                panic_with_error!(e, PoolError::InvalidPath);
+            }
+        }
+        SwapPath { tokens }
+    }
+
+    pub(crate) fn tokens(&self) -> Vec<Address> {
+        self.tokens.clone()
+    }
+
+    pub(crate) fn hops_count(&self) -> u32 {
+        self.tokens.len() - 1
+    }
+
+    pub(crate) fn hop_tokens(&self, idx: u32) -> (Address, Address) {
+        (
+            self.tokens.get_unchecked(idx),
+            self.tokens.get_unchecked(idx + 1),
+        )
+    }
+
+    // Estimate the whole path. None if any of the hops has no pool
+    pub(crate) fn try_estimate(&self, e: &Env, in_amount: u128) -> Option<u128> {
+        let mut amount = in_amount;
+        for i in 0..self.hops_count() {
+            let (token_in, token_out) = self.hop_tokens(i);
+            amount = estimate_hop(e, &token_in, &token_out, amount)?.out_amount;
+        }
+        Some(amount)
+    }
+
+    pub(crate) fn estimate(&self, e: &Env, in_amount: u128) -> u128 {
+        match self.try_estimate(e, in_amount) {
+            Some(value) => value,
+            None => panic_with_error!(e, PoolError::NoRouteFound),
+        }
+    }
+}
+
+// Resolve pair of tokens to the best pool available for the given amount.
+//  pools tokens are sorted, so direction is inferred from tokens order
+pub(crate) fn estimate_hop(
+    e: &Env,
+    token_in: &Address,
+    token_out: &Address,
+    in_amount: u128,
+) -> Option<SwapHop> {
+    let (tokens, in_idx, out_idx) = if token_in < token_out {
+        (
+            Vec::from_array(e, [token_in.clone(), token_out.clone()]),
+            0,
+            1,
+        )
+    } else {
+        (
+            Vec::from_array(e, [token_out.clone(), token_in.clone()]),
+            1,
+            0,
+        )
+    };
+
+    let pools = get_pools_plain(e, &pool_salt(e, tokens));
+    if pools.is_empty() {
+        return None;
+    }
+
+    let (pool, out_amount) = SwapRouterClient::new(e, &get_swap_router(e)).estimate_swap(
+        &pools.values(),
+        &in_idx,
+        &out_idx,
+        &in_amount,
+    );
+    Some(SwapHop {
+        pool,
+        token_in: token_in.clone(),
+        token_out: token_out.clone(),
+        in_idx,
+        out_idx,
+        out_amount,
+    })
+}
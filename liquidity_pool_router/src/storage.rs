@@ -39,6 +39,7 @@ enum DataKey {
     StableSwapCounter,
     PoolPlane,
     SwapRouter,
+    RouteTokens,
 }
 
 #[contracterror]
@@ -47,6 +48,8 @@ enum DataKey {
 pub enum PoolError {
     PoolAlreadyExists = 401,
     PoolNotFound = 404,
+    NoRouteFound = 405,
+    InvalidPath = 406,
 }
 
 fn get_pools(e: &Env, salt: &BytesN<32>) -> Map<BytesN<32>, LiquidityPoolData> {
@@ -90,6 +93,19 @@ generate_instance_storage_getter_and_setter_with_default!(
 generate_instance_storage_getter_and_setter!(pool_plane, DataKey::PoolPlane, Address);
 generate_instance_storage_getter_and_setter!(swap_router, DataKey::SwapRouter, Address);
 
+pub fn get_route_tokens(e: &Env) -> Vec<Address> {
+    bump_instance(e);
+    e.storage()
+        .instance()
+        .get(&DataKey::RouteTokens)
+        .unwrap_or(Vec::new(e))
+}
+
+pub fn set_route_tokens(e: &Env, tokens: &Vec<Address>) {
+    bump_instance(e);
+    e.storage().instance().set(&DataKey::RouteTokens, tokens)
+}
+
 // pool hash
 pub fn get_stableswap_pool_hash(e: &Env, num_tokens: u32) -> BytesN<32> {
     if num_tokens == 1 || num_tokens > 3 {
@@ -2,7 +2,9 @@
 extern crate std;
 
 use crate::constants::{CONSTANT_PRODUCT_FEE_AVAILABLE, MAX_POOLS_FOR_PAIR, STABLESWAP_MAX_POOLS};
+use crate::pool_contract::StandardLiquidityPoolClient;
 use crate::LiquidityPoolRouterClient;
+use soroban_liquidity_pool_contract::fixture::PoolTestFixture;
 use soroban_sdk::testutils::{Events, Ledger, LedgerInfo};
 use soroban_sdk::{
     symbol_short, testutils::Address as _, vec, Address, BytesN, Env, FromVal, IntoVal, Symbol,
@@ -157,6 +159,8 @@ fn test_constant_product_pool() {
 
     assert_eq!(token_share.balance(&user1), 0);
 
+    // swaps below drain the reserves under the pool default floor
+    StandardLiquidityPoolClient::new(&e, &pool_address).set_min_reserve(&admin, &0);
     let desired_amounts = Vec::from_array(&e, [100, 100]);
     router.deposit(&user1, &tokens, &pool_hash, &desired_amounts);
 
@@ -830,6 +834,8 @@ fn test_custom_pool() {
 
     assert_eq!(token_share.balance(&user1), 0);
 
+    // swaps below drain the reserves under the pool default floor
+    StandardLiquidityPoolClient::new(&e, &custom_pool_address).set_min_reserve(&admin, &0);
     let desired_amounts = Vec::from_array(&e, [100, 100]);
     router.deposit(&user1, &tokens, &pool_hash, &desired_amounts);
 
@@ -858,6 +864,61 @@ fn test_custom_pool() {
     );
 }
 
+#[test]
+fn test_custom_pool_from_fixture() {
+    let e = Env::default();
+    e.budget().reset_unlimited();
+
+    // pool built natively by the pool crate fixture
+    let fixture = PoolTestFixture::setup(&e);
+    let user1 = fixture.users[0].clone();
+    let tokens = Vec::from_array(
+        &e,
+        [
+            fixture.token_a.address.clone(),
+            fixture.token_b.address.clone(),
+        ],
+    );
+
+    let admin = Address::generate(&e);
+    let router = create_liqpool_router_contract(&e);
+    router.init_admin(&admin);
+
+    let pool_hash = router.add_custom_pool(
+        &admin,
+        &tokens,
+        &fixture.liq_pool.address,
+        &symbol_short!("custom"),
+        &Vec::<Val>::new(&e),
+    );
+    assert_eq!(
+        router.share_id(&tokens, &pool_hash),
+        fixture.token_share.address
+    );
+
+    let desired_amounts = Vec::from_array(&e, [1000_0000000, 1000_0000000]);
+    let (_, shares) = router.deposit(&user1, &tokens, &pool_hash, &desired_amounts);
+    assert_eq!(fixture.token_share.balance(&user1), shares as i128);
+
+    let out = fixture.liq_pool.estimate_swap(&0, &1, &10_0000000);
+    assert_eq!(
+        router.swap(
+            &user1,
+            &tokens,
+            &fixture.token_a.address,
+            &fixture.token_b.address,
+            &pool_hash,
+            &10_0000000_u128,
+            &out,
+        ),
+        out
+    );
+    assert_eq!(
+        fixture.liq_pool.get_reserves(),
+        Vec::from_array(&e, [1010_0000000, 1000_0000000 - out])
+    );
+}
+
 #[test]
 fn test_simple_ongoing_reward() {
     let e = Env::default();
@@ -1176,7 +1237,7 @@ fn test_event_correct() {
         ]
     );
 
-    reward_token.mint(&router.address, &1_000_000_0000000);
+    reward_token.mint(&pool_address, &1_000_000_0000000);
     let reward_1_tps = 10_5000000_u128;
     router.set_rewards_config(
         &admin,
@@ -1198,6 +1259,8 @@ fn test_event_correct() {
     // 10 seconds passed since config, user depositing
     jump(&e, 10);
 
+    // swaps below drain the reserves under the pool default floor
+    StandardLiquidityPoolClient::new(&e, &pool_address).set_min_reserve(&admin, &0);
     let desired_amounts = Vec::from_array(&e, [100, 100]);
 
     let (amounts, share_amount) = router.deposit(&user1, &tokens, &pool_hash, &desired_amounts);
@@ -1406,3 +1469,184 @@ fn test_swap_routed() {
     e.budget().print();
     assert_eq!(swap_result, best_result);
 }
+
+fn create_routing_setup<'a>(
+    e: &Env,
+) -> (
+    LiquidityPoolRouterClient<'a>,
+    Address,
+    Address,
+    std::vec::Vec<test_token::Client<'a>>,
+) {
+    let admin = Address::generate(e);
+    let user = Address::generate(e);
+    let reward_token = create_token_contract(e, &Address::generate(e));
+
+    let plane = create_plane_contract(e);
+    let swap_router = create_swap_router_contract(e);
+    swap_router.init_admin(&admin);
+    swap_router.set_pools_plane(&admin, &plane.address);
+    let router = create_liqpool_router_contract(e);
+    router.init_admin(&admin);
+    router.set_pool_hash(&install_liq_pool_hash(e));
+    router.set_token_hash(&install_token_wasm(e));
+    router.set_reward_token(&reward_token.address);
+    router.set_pools_plane(&admin, &plane.address);
+    router.set_swap_router(&admin, &swap_router.address);
+
+    let mut tokens = std::vec::Vec::new();
+    for _ in 0..3 {
+        let token = create_token_contract(e, &Address::generate(e));
+        token.mint(&user, &100000_0000000);
+        tokens.push(token);
+    }
+    (router, admin, user, tokens)
+}
+
+fn create_routing_pool(
+    e: &Env,
+    router: &LiquidityPoolRouterClient,
+    user: &Address,
+    token_a: &test_token::Client,
+    token_b: &test_token::Client,
+    amount: u128,
+) {
+    let mut pair = [token_a.address.clone(), token_b.address.clone()];
+    pair.sort();
+    let tokens = Vec::from_array(e, pair);
+    let (pool_hash, pool_address) = router.init_standard_pool(user, &tokens, &30);
+    token_a.approve(user, &pool_address, &(amount as i128), &99999);
+    token_b.approve(user, &pool_address, &(amount as i128), &99999);
+    router.deposit(
+        user,
+        &tokens,
+        &pool_hash,
+        &Vec::from_array(e, [amount, amount]),
+    );
+}
+
+#[test]
+fn test_find_best_route_direct() {
+    let e = Env::default();
+    e.mock_all_auths();
+    e.budget().reset_unlimited();
+
+    let (router, admin, user, tokens) = create_routing_setup(&e);
+    let (token_a, token_b, token_c) = (&tokens[0], &tokens[1], &tokens[2]);
+    create_routing_pool(&e, &router, &user, token_a, token_b, 1000_0000000);
+    create_routing_pool(&e, &router, &user, token_a, token_c, 100_0000000);
+    create_routing_pool(&e, &router, &user, token_c, token_b, 100_0000000);
+    router.set_route_tokens(&admin, &Vec::from_array(&e, [token_c.address.clone()]));
+
+    let direct_path = Vec::from_array(&e, [token_a.address.clone(), token_b.address.clone()]);
+    let two_hop_path = Vec::from_array(
+        &e,
+        [
+            token_a.address.clone(),
+            token_c.address.clone(),
+            token_b.address.clone(),
+        ],
+    );
+    let direct_out = router.estimate_swap_path(&direct_path, &10_0000000);
+    let two_hop_out = router.estimate_swap_path(&two_hop_path, &10_0000000);
+    assert!(direct_out > two_hop_out);
+
+    let (path, out) = router.find_best_route(&token_a.address, &token_b.address, &10_0000000);
+    assert_eq!(path, direct_path);
+    assert_eq!(out, direct_out);
+
+    let balance_before = token_b.balance(&user);
+    assert_eq!(
+        router.swap_path(
+            &user,
+            &path,
+            &10_0000000,
+            &out,
+            &(e.ledger().sequence() + 5)
+        ),
+        out
+    );
+    assert_eq!(token_b.balance(&user) - balance_before, out as i128);
+}
+
+#[test]
+fn test_find_best_route_two_hops() {
+    let e = Env::default();
+    e.mock_all_auths();
+    e.budget().reset_unlimited();
+
+    let (router, admin, user, tokens) = create_routing_setup(&e);
+    let (token_a, token_b, token_c) = (&tokens[0], &tokens[1], &tokens[2]);
+    create_routing_pool(&e, &router, &user, token_a, token_b, 100_0000000);
+    create_routing_pool(&e, &router, &user, token_a, token_c, 1000_0000000);
+    create_routing_pool(&e, &router, &user, token_c, token_b, 1000_0000000);
+    router.set_route_tokens(&admin, &Vec::from_array(&e, [token_c.address.clone()]));
+
+    let direct_path = Vec::from_array(&e, [token_b.address.clone(), token_a.address.clone()]);
+    let two_hop_path = Vec::from_array(
+        &e,
+        [
+            token_b.address.clone(),
+            token_c.address.clone(),
+            token_a.address.clone(),
+        ],
+    );
+    let direct_out = router.estimate_swap_path(&direct_path, &10_0000000);
+    let two_hop_out = router.estimate_swap_path(&two_hop_path, &10_0000000);
+    assert!(two_hop_out > direct_out);
+
+    let (path, out) = router.find_best_route(&token_b.address, &token_a.address, &10_0000000);
+    assert_eq!(path, two_hop_path);
+    assert_eq!(out, two_hop_out);
+
+    let balance_before = token_a.balance(&user);
+    let balance_c_before = token_c.balance(&user);
+    assert_eq!(
+        router.swap_path(
+            &user,
+            &path,
+            &10_0000000,
+            &out,
+            &(e.ledger().sequence() + 5)
+        ),
+        out
+    );
+    assert_eq!(token_a.balance(&user) - balance_before, out as i128);
+    assert_eq!(token_c.balance(&user), balance_c_before);
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #405)")]
+fn test_swap_path_no_route() {
+    let e = Env::default();
+    e.mock_all_auths();
+    e.budget().reset_unlimited();
+
+    let (router, _admin, user, tokens) = create_routing_setup(&e);
+    let (token_a, token_b, token_c) = (&tokens[0], &tokens[1], &tokens[2]);
+    create_routing_pool(&e, &router, &user, token_a, token_b, 100_0000000);
+
+    let path = Vec::from_array(
+        &e,
+        [
+            token_a.address.clone(),
+            token_b.address.clone(),
+            token_c.address.clone(),
+        ],
+    );
+    router.swap_path(&user, &path, &10_0000000, &0, &(e.ledger().sequence() + 5));
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #406)")]
+fn test_swap_path_too_short() {
+    let e = Env::default();
+    e.mock_all_auths();
+    e.budget().reset_unlimited();
+
+    let (router, _admin, _user, tokens) = create_routing_setup(&e);
+    router.estimate_swap_path(
+        &Vec::from_array(&e, [tokens[0].address.clone()]),
+        &10_0000000,
+    );
+}
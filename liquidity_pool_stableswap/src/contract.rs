@@ -753,6 +753,7 @@ impl LiquidityPoolInterfaceTrait for LiquidityPool {
             &7u32,
             &"Pool Share Token".into_val(&e),
             &"POOL".into_val(&e),
+            &false,
         );
         put_token_share(&e, share_contract);
         let initial_reserves = Vec::from_array(&e, [0_u128; N_COINS]);
@@ -1091,11 +1092,11 @@ impl RewardsTrait for LiquidityPool {
         let rewards = get_rewards_manager(&e);
         let config = rewards.storage().get_pool_reward_config();
         let total_shares = get_total_shares(&e);
-        let pool_data = rewards.manager().update_rewards_data(total_shares);
+        let pool_data = rewards.manager().calculate_rewards_data(total_shares);
         let user_shares = get_user_balance_shares(&e, &user);
         let user_data = rewards
             .manager()
-            .update_user_reward(&pool_data, &user, user_shares);
+            .calculate_user_reward(&user, total_shares, user_shares);
         let mut result = Map::new(&e);
         result.set(symbol_short!("tps"), to_i128(config.tps).unwrap());
         result.set(symbol_short!("exp_at"), to_i128(config.expired_at));
@@ -1114,6 +1115,10 @@ impl RewardsTrait for LiquidityPool {
             symbol_short!("to_claim"),
             to_i128(user_data.to_claim).unwrap(),
         );
+        result.set(
+            symbol_short!("claimed"),
+            to_i128(user_data.claimed).unwrap(),
+        );
         result
     }
 
@@ -1 +1,7 @@
-pub(crate) const REWARD_PRECISION: u128 = 1_000;
+// Scale of the cumulative reward per share. Big enough to keep rounding dust
+//  below a unit of reward token for any realistic total shares
+pub(crate) const REWARD_PRECISION: u128 = 1_000_000_000_000_000_000;
+// scale of reward invariants recorded before the cumulative reward per share
+pub(crate) const LEGACY_REWARD_PRECISION: u128 = 1_000;
+// reward checkpoints of the latest blocks kept, older ones are overwritten
+pub(crate) const REWARD_CHECKPOINTS_KEPT: u64 = 1000;
@@ -10,24 +10,15 @@ pub use manager::Manager;
 pub use storage::Storage;
 pub use utils;
 
-#[derive(Clone)]
-pub struct RewardsConfig {
-    page_size: u64,
-}
-
 #[derive(Clone)]
 pub struct Rewards {
     env: Env,
-    config: RewardsConfig,
 }
 
 impl Rewards {
     #[inline(always)]
-    pub fn new(env: &Env, page_size: u64) -> Rewards {
-        Rewards {
-            env: env.clone(),
-            config: RewardsConfig { page_size },
-        }
+    pub fn new(env: &Env) -> Rewards {
+        Rewards { env: env.clone() }
     }
 
     pub fn storage(&self) -> Storage {
@@ -35,6 +26,6 @@ impl Rewards {
     }
 
     pub fn manager(&self) -> Manager {
-        Manager::new(&self.env, self.storage(), &self.config)
+        Manager::new(&self.env, self.storage())
     }
 }
@@ -1,32 +1,42 @@
-use crate::constants::REWARD_PRECISION;
+use crate::constants::{LEGACY_REWARD_PRECISION, REWARD_PRECISION};
 use crate::storage::{
     PoolRewardConfig, PoolRewardData, RewardsStorageTrait, Storage, UserRewardData,
 };
-use crate::RewardsConfig;
 use cast::u128 as to_u128;
-use soroban_sdk::{token::TokenClient as Client, Address, Env, Map};
+use soroban_sdk::{token::TokenClient as Client, Address, Env, Map, Vec, U256};
 
+// Rewards are distributed with a single cumulative reward per share checkpoint:
+//  every pool update adds tokens generated since the previous one divided by total shares,
+//  users store the checkpoint value they synced to, so update is O(1) regardless of history.
+// Users should be updated before every change of their shares, so the reward accrues against
+//  the balance held in every interval and new shares don't earn the past
+// Tokens generated while there are no shares are not distributed to anyone
+//  and not counted as accumulated, so they stay unspent.
+// Once enabled, every block records the reward per share reached, so the history can be audited.
+//  Only the latest blocks are kept. Blocks from before checkpoints were enabled have no records
+// Rewards may be scheduled as sequential phases with their own tps. The config holds
+//  the current phase and is switched to the next one on the first update after it ends
+// Pools created before the cumulative reward per share kept reward invariant pages instead.
+//  migration restarts pool reward per share from zero, users are converted on the first touch
 pub struct Manager {
     env: Env,
     storage: Storage,
-    config: RewardsConfig,
 }
 
 impl Manager {
-    pub fn new(e: &Env, storage: Storage, config: &RewardsConfig) -> Manager {
+    pub fn new(e: &Env, storage: Storage) -> Manager {
         Manager {
             env: e.clone(),
             storage,
-            config: config.clone(),
         }
     }
 
     pub fn initialize(&mut self) {
-        self.add_reward_inv(0, 0);
         self.storage.set_pool_reward_data(&PoolRewardData {
             block: 0,
             accumulated: 0,
             last_time: 0,
+            reward_per_share: 0,
         });
         self.storage.set_pool_reward_config(&PoolRewardConfig {
             tps: 0,
@@ -35,14 +45,36 @@ impl Manager {
     }
 
     pub fn update_rewards_data(&mut self, total_shares: u128) -> PoolRewardData {
-        let config = self.storage.get_pool_reward_config();
-        let data = self.storage.get_pool_reward_data();
+        let mut config = self.storage.get_pool_reward_config();
+        let mut data = self.storage.get_pool_reward_data();
         let now = self.env.ledger().timestamp();
 
+        // roll through scheduled phases ended by now, each phase generates with its own tps
+        let schedule = self.storage.get_pool_reward_schedule();
+        let mut phase = self.storage.get_pool_reward_phase();
+        let mut rolled = false;
+        while now >= config.expired_at && phase + 1 < schedule.len() {
+            if data.last_time < config.expired_at {
+                data = self.update_rewards_data_snapshot(
+                    config.expired_at,
+                    &config,
+                    &data,
+                    total_shares,
+                );
+            }
+            phase += 1;
+            config = schedule.get(phase).unwrap();
+            rolled = true;
+        }
+        if rolled {
+            self.storage.set_pool_reward_phase(phase);
+            self.storage.set_pool_reward_config(&config);
+        }
+
         // 1. config not expired - snapshot reward
         // 2. config expired
-        //  2.a data before config expiration - snapshot reward for now, increase block and generate inv
-        //  2.b data after config expiration - snapshot reward for config end, increase block, snapshot reward for now, don't increase block
+        //  2.a data before config expiration - snapshot reward for config end, increase block, snapshot reward for now
+        //  2.b data after config expiration - nothing generated, increase block
 
         if now < config.expired_at {
             self.update_rewards_data_snapshot(now, &config, &data, total_shares)
@@ -55,6 +87,7 @@ impl Manager {
                     block: data.block + 1,
                     accumulated: data.accumulated,
                     last_time: now,
+                    reward_per_share: data.reward_per_share,
                 },
             )
         } else {
@@ -62,14 +95,100 @@ impl Manager {
         }
     }
 
-    fn calculate_user_reward(
-        &mut self,
-        start_block: u64,
-        end_block: u64,
+    // Replace rewards config with sequential phases, the first one starts now.
+    //  rewards data should be up to date
+    pub fn set_schedule(&mut self, schedule: &Vec<PoolRewardConfig>) {
+        self.storage.set_pool_reward_schedule(schedule);
+        self.storage.set_pool_reward_phase(0);
+        self.storage
+            .set_pool_reward_config(&schedule.get(0).expect("Schedule is empty"));
+    }
+
+    // Schedule phase active at the current time and its config.
+    //  stored config is behind if the phase ended since the last pool update
+    pub fn get_current_phase(&self) -> (u32, PoolRewardConfig) {
+        let now = self.env.ledger().timestamp();
+        let schedule = self.storage.get_pool_reward_schedule();
+        let mut phase = self.storage.get_pool_reward_phase();
+        let mut config = self.storage.get_pool_reward_config();
+        while now >= config.expired_at && phase + 1 < schedule.len() {
+            phase += 1;
+            config = schedule.get(phase).unwrap();
+        }
+        (phase, config)
+    }
+
+    // Start or stop recording reward per share of every block. Enabling records the current block
+    pub fn set_reward_checkpoints_enabled(&mut self, enabled: bool) {
+        self.storage.set_reward_checkpoints_enabled(enabled);
+        if enabled {
+            let data = self.storage.get_pool_reward_data();
+            self.storage
+                .set_reward_checkpoint(data.block, data.reward_per_share);
+        }
+    }
+
+    // Recorded reward per share of up to limit blocks starting from start_block.
+    //  blocks without records are skipped
+    pub fn get_reward_checkpoints(&self, start_block: u64, limit: u32) -> Map<u64, u128> {
+        let last_block = self.storage.get_pool_reward_data().block;
+        let end_block = start_block
+            .saturating_add(limit as u64)
+            .min(last_block.saturating_add(1));
+        let mut result = Map::new(&self.env);
+        for block in start_block..end_block {
+            if let Some(reward_per_share) = self.storage.get_reward_checkpoint(block) {
+                result.set(block, reward_per_share);
+            }
+        }
+        result
+    }
+
+    // Tokens to be generated from now till the end of the last scheduled phase
+    pub fn get_promised_tokens(&self) -> u128 {
+        let now = self.env.ledger().timestamp();
+        let schedule = self.storage.get_pool_reward_schedule();
+        let (mut phase, mut config) = self.get_current_phase();
+        let mut from = now;
+        let mut promised = 0;
+        loop {
+            if config.expired_at > from {
+                promised += to_u128(config.expired_at - from) * config.tps;
+                from = config.expired_at;
+            }
+            phase += 1;
+            if phase >= schedule.len() {
+                break;
+            }
+            config = schedule.get(phase).unwrap();
+        }
+        promised
+    }
+
+    fn calculate_reward_for_shares(
+        &self,
+        pool_reward_per_share: u128,
+        user_reward_per_share: u128,
         user_share: u128,
     ) -> u128 {
-        let result = self.calculate_reward(start_block, end_block, true);
-        (result) * user_share / REWARD_PRECISION
+        self.mul_div(
+            pool_reward_per_share - user_reward_per_share,
+            user_share,
+            REWARD_PRECISION,
+        )
+    }
+
+    // a * b / c rounded down. Scaled reward amounts of 18 decimal tokens overflow u128
+    //  when multiplied, so the product is taken in U256 if it doesn't fit
+    fn mul_div(&self, a: u128, b: u128, c: u128) -> u128 {
+        match a.checked_mul(b) {
+            Some(product) => product / c,
+            None => U256::from_u128(&self.env, a)
+                .mul(&U256::from_u128(&self.env, b))
+                .div(&U256::from_u128(&self.env, c))
+                .to_u128()
+                .expect("reward amount overflow"),
+        }
     }
 
     pub fn update_user_reward(
@@ -78,7 +197,14 @@ impl Manager {
         user: &Address,
         user_balance_shares: u128,
     ) -> UserRewardData {
-        match self.storage.get_user_reward_data(user) {
+        let user_data = match self.convert_legacy_user_data(user, user_balance_shares) {
+            Some(user_data) => {
+                self.storage.set_user_reward_data(user, &user_data);
+                Some(user_data)
+            }
+            None => self.storage.get_user_reward_data(user),
+        };
+        match user_data {
             Some(user_data) => {
                 if user_data.pool_accumulated == pool_data.accumulated {
                     // nothing accumulated since last update
@@ -87,45 +213,148 @@ impl Manager {
 
                 if user_balance_shares == 0 {
                     // zero balance, no new reward
-                    return self.create_new_user_data(user, pool_data, user_data.to_claim);
+                    return self.create_new_user_data(
+                        user,
+                        pool_data,
+                        user_data.to_claim,
+                        user_data.claimed,
+                    );
                 }
 
-                let reward = self.calculate_user_reward(
-                    user_data.last_block + 1,
-                    pool_data.block,
+                let reward = self.calculate_reward_for_shares(
+                    pool_data.reward_per_share,
+                    user_data.reward_per_share,
                     user_balance_shares,
                 );
-                // let new_reward =
-                //     (pool_data.accumulated - user_data.pool_accumulated) * user_shares / total_shares;
-                self.create_new_user_data(user, pool_data, user_data.to_claim + reward)
+                self.create_new_user_data(
+                    user,
+                    pool_data,
+                    user_data.to_claim + reward,
+                    user_data.claimed,
+                )
             }
-            None => self.create_new_user_data(user, pool_data, 0),
+            None => self.create_new_user_data(user, pool_data, 0, 0),
         }
     }
 
     pub fn get_amount_to_claim(
-        &mut self,
+        &self,
         user: &Address,
         total_shares: u128,
         user_balance_shares: u128,
     ) -> u128 {
-        // update pool data & calculate reward
-        self.user_reward_data(user, total_shares, user_balance_shares)
+        // calculate reward without touching the storage
+        self.calculate_user_reward(user, total_shares, user_balance_shares)
             .to_claim
     }
 
+    // Read-only estimation of pool rewards data for the current time.
+    //  doesn't create new block and doesn't touch the storage
+    pub fn calculate_rewards_data(&self, total_shares: u128) -> PoolRewardData {
+        let data = self.storage.get_pool_reward_data();
+        let generated_tokens = self.get_pending_generated_tokens(&data);
+        let mut result = PoolRewardData {
+            block: data.block,
+            accumulated: data.accumulated,
+            last_time: self.env.ledger().timestamp(),
+            reward_per_share: data.reward_per_share,
+        };
+        if total_shares > 0 {
+            result.accumulated += generated_tokens;
+            result.reward_per_share +=
+                self.mul_div(REWARD_PRECISION, generated_tokens, total_shares);
+        }
+        result
+    }
+
+    // Read-only estimation of user rewards data for the current time.
+    //  tokens generated since last pool update are distributed using current total shares
+    pub fn calculate_user_reward(
+        &self,
+        user: &Address,
+        total_shares: u128,
+        user_balance_shares: u128,
+    ) -> UserRewardData {
+        let data = self.calculate_rewards_data(total_shares);
+        let pool_accumulated = data.accumulated;
+        let reward_per_share = data.reward_per_share;
+
+        let user_data = match self
+            .convert_legacy_user_data(user, user_balance_shares)
+            .or_else(|| self.storage.get_user_reward_data(user))
+        {
+            Some(user_data) => user_data,
+            None => {
+                return UserRewardData {
+                    last_block: data.block,
+                    pool_accumulated,
+                    to_claim: 0,
+                    reward_per_share,
+                    claimed: 0,
+                }
+            }
+        };
+        if user_data.pool_accumulated == pool_accumulated || user_balance_shares == 0 {
+            return UserRewardData {
+                last_block: data.block,
+                pool_accumulated,
+                to_claim: user_data.to_claim,
+                reward_per_share,
+                claimed: user_data.claimed,
+            };
+        }
+
+        UserRewardData {
+            last_block: data.block,
+            pool_accumulated,
+            to_claim: user_data.to_claim
+                + self.calculate_reward_for_shares(
+                    reward_per_share,
+                    user_data.reward_per_share,
+                    user_balance_shares,
+                ),
+            reward_per_share,
+            claimed: user_data.claimed,
+        }
+    }
+
     pub fn claim_reward(
         &mut self,
         user: &Address,
         total_shares: u128,
         user_balance_shares: u128,
     ) -> u128 {
-        // update pool data & calculate reward
-        let UserRewardData {
-            last_block,
-            pool_accumulated,
-            to_claim: reward_amount,
-        } = self.user_reward_data(user, total_shares, user_balance_shares);
+        self.claim_reward_to(user, user, total_shares, user_balance_shares)
+    }
+
+    // Claim user reward, transferring it to the recipient
+    pub fn claim_reward_to(
+        &mut self,
+        user: &Address,
+        recipient: &Address,
+        total_shares: u128,
+        user_balance_shares: u128,
+    ) -> u128 {
+        self.claim_reward_amount_to(
+            user,
+            recipient,
+            total_shares,
+            user_balance_shares,
+            u128::MAX,
+        )
+    }
+
+    // Claim up to max_amount of user reward, transferring it to the recipient
+    pub fn claim_reward_amount_to(
+        &mut self,
+        user: &Address,
+        recipient: &Address,
+        total_shares: u128,
+        user_balance_shares: u128,
+        max_amount: u128,
+    ) -> u128 {
+        let reward_amount =
+            self.take_reward_amount(user, total_shares, user_balance_shares, max_amount);
 
         // transfer reward
         let reward_token = self.storage.get_reward_token();
@@ -133,131 +362,102 @@ impl Manager {
         if rewards_storage == self.env.current_contract_address() {
             Client::new(&self.env, &reward_token).transfer(
                 &rewards_storage,
-                user,
+                recipient,
                 &(reward_amount as i128),
             );
         } else {
             Client::new(&self.env, &reward_token).transfer_from(
                 &self.env.current_contract_address(),
                 &rewards_storage,
-                user,
+                recipient,
                 &(reward_amount as i128),
             );
         };
+        reward_amount
+    }
+
+    // Mark user reward as claimed without transferring it. Caller is responsible for payout
+    pub fn take_reward(
+        &mut self,
+        user: &Address,
+        total_shares: u128,
+        user_balance_shares: u128,
+    ) -> u128 {
+        self.take_reward_amount(user, total_shares, user_balance_shares, u128::MAX)
+    }
+
+    // Same as take_reward, taking up to max_amount. The rest stays available to claim
+    pub fn take_reward_amount(
+        &mut self,
+        user: &Address,
+        total_shares: u128,
+        user_balance_shares: u128,
+        max_amount: u128,
+    ) -> u128 {
+        // update pool data & calculate reward
+        let UserRewardData {
+            last_block,
+            pool_accumulated,
+            to_claim,
+            reward_per_share,
+            claimed,
+        } = self.user_reward_data(user, total_shares, user_balance_shares);
+        let reward_amount = to_claim.min(max_amount);
 
-        // set available reward to zero
+        // decrease available reward by the claimed amount
         let new_data = UserRewardData {
             last_block,
             pool_accumulated,
-            to_claim: 0,
+            to_claim: to_claim - reward_amount,
+            reward_per_share,
+            claimed: claimed + reward_amount,
         };
         self.storage.set_user_reward_data(user, &new_data);
+        self.storage
+            .set_total_claimed(self.storage.get_total_claimed() + reward_amount);
         reward_amount
     }
 
-    // private functions
-
-    fn write_reward_inv_to_page(&mut self, pow: u32, start_block: u64, value: u128) {
-        let page_number = start_block / self.config.page_size.pow(pow + 1);
-        let mut page = match start_block % self.config.page_size.pow(pow + 1) {
-            0 => Map::new(&self.env),
-            _ => self.storage.get_reward_inv_data(pow, page_number),
+    // Convert pool rewards data from the legacy layout. Invariant pages are kept
+    //  for user conversion. Returns false if there's nothing to convert
+    pub fn migrate_legacy_rewards(&mut self, page_size: u64) -> bool {
+        let legacy = match self.storage.get_legacy_pool_reward_data() {
+            Some(legacy) => legacy,
+            None => return false,
         };
-        page.set(start_block, value);
-        self.storage.set_reward_inv_data(pow, page_number, page);
+        self.storage.set_pool_reward_data(&PoolRewardData {
+            block: legacy.block,
+            accumulated: legacy.accumulated,
+            last_time: legacy.last_time,
+            reward_per_share: 0,
+        });
+        self.storage.set_legacy_reward_info(legacy.block, page_size);
+        true
     }
 
-    fn calculate_reward(&mut self, start_block: u64, end_block: u64, use_max_pow: bool) -> u128 {
-        // calculate result from start_block to end_block [...]
-        // use_max_pow disabled during aggregation process
-        //  since we don't have such information and can be enabled after
-        let mut result = 0;
-        let mut block = start_block;
-
-        let mut max_pow = 0;
-        for pow in 1..255 {
-            if start_block + self.config.page_size.pow(pow) - 1 > end_block {
-                break;
-            }
-            max_pow = pow;
-        }
-
-        while block <= end_block {
-            if block % self.config.page_size == 0 {
-                // check possibilities to skip
-                let mut block_increased = false;
-                let mut max_block_pow = 0;
-                for i in (1..max_pow + 1).rev() {
-                    if block % self.config.page_size.pow(i) == 0 {
-                        max_block_pow = i;
-                        break;
-                    }
-                }
-                if !use_max_pow {
-                    // value not precalculated yet
-                    max_block_pow -= 1;
-                }
+    // private functions
 
-                for l_pow in (1..max_block_pow + 1).rev() {
-                    let next_block = block + self.config.page_size.pow(l_pow);
-                    if next_block > end_block {
-                        continue;
-                    }
-
-                    let page_number = block / self.config.page_size.pow(l_pow + 1);
-                    let page = self.storage.get_reward_inv_data(l_pow, page_number);
-                    result += page.get(block).expect("unknown block");
-                    block = next_block;
-                    block_increased = true;
-                    break;
-                }
-                if !block_increased {
-                    // couldn't find shortcut, looks like we're close to the tail. go one by one
-                    let page = self
-                        .storage
-                        .get_reward_inv_data(0, block / self.config.page_size);
-                    result += page.get(block).expect("unknown block");
-                    block += 1;
-                }
-            } else {
-                let page = self
-                    .storage
-                    .get_reward_inv_data(0, block / self.config.page_size);
-                result += page.get(block).expect("unknown block");
-                block += 1;
+    // Tokens generated since the last pool update, phases ended since then included
+    fn get_pending_generated_tokens(&self, data: &PoolRewardData) -> u128 {
+        let now = self.env.ledger().timestamp();
+        let schedule = self.storage.get_pool_reward_schedule();
+        let mut phase = self.storage.get_pool_reward_phase();
+        let mut config = self.storage.get_pool_reward_config();
+        let mut last_time = data.last_time;
+        let mut generated = 0;
+        loop {
+            let reward_timestamp = now.min(config.expired_at);
+            if reward_timestamp > last_time {
+                generated += to_u128(reward_timestamp - last_time) * to_u128(config.tps);
+                last_time = reward_timestamp;
             }
-        }
-        result
-    }
-
-    fn add_reward_inv(&mut self, block: u64, value: u128) {
-        // write zero level page first
-        self.write_reward_inv_to_page(0, block, value);
-
-        if (block + 1) % self.config.page_size == 0 {
-            // page end, at least one aggregation should be applicable
-            for pow in 1..255 {
-                let aggregation_size = self.config.page_size.pow(pow);
-                if (block + 1) % aggregation_size != 0 {
-                    // aggregation level not applicable
-                    break;
-                }
-                let agg_page_start = block - block % aggregation_size;
-                let aggregation = self.calculate_reward(agg_page_start, block, false);
-                self.write_reward_inv_to_page(pow, agg_page_start, aggregation);
+            if now < config.expired_at || phase + 1 >= schedule.len() {
+                break;
             }
+            phase += 1;
+            config = schedule.get(phase).unwrap();
         }
-    }
-
-    fn update_reward_inv(&mut self, accumulated: u128, total_shares: u128) {
-        let reward_per_share = if total_shares > 0 {
-            REWARD_PRECISION * accumulated / total_shares
-        } else {
-            0
-        };
-
-        let data = self.storage.get_pool_reward_data();
-        self.add_reward_inv(data.block, reward_per_share);
+        generated
     }
 
     fn update_rewards_data_snapshot(
@@ -274,20 +474,31 @@ impl Manager {
             total_shares,
             PoolRewardData {
                 block: data.block + 1,
-                accumulated: data.accumulated + generated_tokens,
+                accumulated: data.accumulated,
                 last_time: now,
+                reward_per_share: data.reward_per_share,
             },
         )
     }
 
+    // Store new pool rewards data distributing generated tokens across total shares.
+    //  nothing is accumulated if there are no shares to distribute across
     fn create_new_rewards_data(
         &mut self,
         generated_tokens: u128,
         total_shares: u128,
-        new_data: PoolRewardData,
+        mut new_data: PoolRewardData,
     ) -> PoolRewardData {
+        if total_shares > 0 {
+            new_data.accumulated += generated_tokens;
+            new_data.reward_per_share +=
+                self.mul_div(REWARD_PRECISION, generated_tokens, total_shares);
+        }
         self.storage.set_pool_reward_data(&new_data);
-        self.update_reward_inv(generated_tokens, total_shares);
+        if self.storage.get_reward_checkpoints_enabled() {
+            self.storage
+                .set_reward_checkpoint(new_data.block, new_data.reward_per_share);
+        }
         new_data
     }
 
@@ -303,11 +514,12 @@ impl Manager {
         let generated_tokens = to_u128(reward_timestamp - data.last_time) * to_u128(config.tps);
         let catchup_data = PoolRewardData {
             block: data.block + 1,
-            accumulated: data.accumulated + generated_tokens,
+            accumulated: data.accumulated,
             last_time: config.expired_at,
+            reward_per_share: data.reward_per_share,
         };
-        self.create_new_rewards_data(generated_tokens, total_shares, catchup_data.clone());
-        // todo: don't increase block when config not enabled thus keeping invariants list small
+        let catchup_data =
+            self.create_new_rewards_data(generated_tokens, total_shares, catchup_data);
         self.create_new_rewards_data(
             0,
             total_shares,
@@ -315,20 +527,84 @@ impl Manager {
                 block: catchup_data.block + 1,
                 accumulated: catchup_data.accumulated,
                 last_time: now,
+                reward_per_share: catchup_data.reward_per_share,
             },
         )
     }
 
+    // User data in the legacy layout converted to the current one. Reward of the blocks
+    //  missed till the pool conversion is added to claim, reward per share starts from zero
+    fn convert_legacy_user_data(
+        &self,
+        user: &Address,
+        user_balance_shares: u128,
+    ) -> Option<UserRewardData> {
+        let (legacy_block, page_size) = self.storage.get_legacy_reward_info()?;
+        let legacy = self.storage.get_legacy_user_reward_data(user)?;
+        let mut to_claim = legacy.to_claim;
+        if legacy.last_block < legacy_block && user_balance_shares > 0 {
+            let reward_inv =
+                self.get_legacy_reward_inv_sum(legacy.last_block + 1, legacy_block, page_size);
+            to_claim += self.mul_div(reward_inv, user_balance_shares, LEGACY_REWARD_PRECISION);
+        }
+        Some(UserRewardData {
+            pool_accumulated: legacy.pool_accumulated,
+            to_claim,
+            last_block: legacy_block,
+            reward_per_share: 0,
+            claimed: 0,
+        })
+    }
+
+    // Sum of legacy reward invariants of blocks from start_block to end_block inclusive.
+    //  aggregated page of level pow covers page_size^pow blocks from the one it's keyed by
+    fn get_legacy_reward_inv_sum(&self, start_block: u64, end_block: u64, page_size: u64) -> u128 {
+        let mut max_pow = 0;
+        for pow in 1..255 {
+            match page_size.checked_pow(pow) {
+                Some(size) if start_block + size - 1 <= end_block => max_pow = pow,
+                _ => break,
+            }
+        }
+
+        let mut result = 0;
+        let mut block = start_block;
+        let mut page_key = None;
+        let mut page = Map::new(&self.env);
+        while block <= end_block {
+            // widest aggregation starting at the block and ending before end_block
+            let mut pow = 0;
+            for l_pow in (1..max_pow + 1).rev() {
+                let size = page_size.pow(l_pow);
+                if block % size == 0 && block + size <= end_block {
+                    pow = l_pow;
+                    break;
+                }
+            }
+            let key = (pow, block / page_size.pow(pow + 1));
+            if page_key != Some(key) {
+                page = self.storage.get_legacy_reward_inv(key.0, key.1);
+                page_key = Some(key);
+            }
+            result += page.get(block).unwrap_or(0);
+            block += page_size.pow(pow);
+        }
+        result
+    }
+
     fn create_new_user_data(
         &self,
         user: &Address,
         pool_data: &PoolRewardData,
         to_claim: u128,
+        claimed: u128,
     ) -> UserRewardData {
         let new_data = UserRewardData {
             last_block: pool_data.block,
             pool_accumulated: pool_data.accumulated,
             to_claim,
+            reward_per_share: pool_data.reward_per_share,
+            claimed,
         };
         self.storage.set_user_reward_data(user, &new_data);
         new_data
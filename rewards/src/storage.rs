@@ -1,4 +1,5 @@
-use soroban_sdk::{contracttype, Address, Env, Map};
+use crate::constants::REWARD_CHECKPOINTS_KEPT;
+use soroban_sdk::{contracttype, Address, Env, FromVal, Map, Symbol, Val, Vec};
 use utils::bump::bump_persistent;
 
 // Rewards configuration for specific pool
@@ -13,8 +14,11 @@ pub struct PoolRewardConfig {
 #[contracttype]
 pub struct PoolRewardData {
     pub block: u64,
+    // tokens distributed across shares. excludes emission while there were no shares
     pub accumulated: u128,
     pub last_time: u64,
+    // cumulative reward per share, scaled by REWARD_PRECISION
+    pub reward_per_share: u128,
 }
 
 #[derive(Clone)]
@@ -23,6 +27,28 @@ pub struct UserRewardData {
     pub pool_accumulated: u128,
     pub to_claim: u128,
     pub last_block: u64,
+    // pool reward per share user synced to
+    pub reward_per_share: u128,
+    // total tokens claimed by user
+    pub claimed: u128,
+}
+
+// Pool and user rewards data stored before the cumulative reward per share was introduced.
+//  reward per share of every block was kept in pages of reward invariants instead
+#[derive(Clone)]
+#[contracttype]
+pub struct LegacyPoolRewardData {
+    pub block: u64,
+    pub accumulated: u128,
+    pub last_time: u64,
+}
+
+#[derive(Clone)]
+#[contracttype]
+pub struct LegacyUserRewardData {
+    pub pool_accumulated: u128,
+    pub to_claim: u128,
+    pub last_block: u64,
 }
 
 #[derive(Clone)]
@@ -31,22 +57,29 @@ enum DataKey {
     PoolRewardConfig,
     PoolRewardData,
     UserRewardData(Address),
-    RewardInvData(u32, u64),
     RewardStorage,
     RewardToken,
+    TotalClaimed,
+    PoolRewardSchedule,
+    PoolRewardPhase,
+    RewardCheckpointsEnabled,
+    RewardCheckpointSlot(u32),
+    RewardInvData(u32, u64),
+    LegacyRewardInfo,
+}
+
+// Legacy data has no reward per share field
+fn is_legacy_reward_data(e: &Env, value: &Val) -> bool {
+    !Map::<Symbol, Val>::from_val(e, value).contains_key(Symbol::new(e, "reward_per_share"))
 }
 
 pub struct Storage {
     env: Env,
-    inv_cache: Map<DataKey, Map<u64, u128>>,
 }
 
 impl Storage {
     pub fn new(e: &Env) -> Storage {
-        Storage {
-            env: e.clone(),
-            inv_cache: Map::new(e),
-        }
+        Storage { env: e.clone() }
     }
 }
 
@@ -54,16 +87,31 @@ pub trait RewardsStorageTrait {
     fn get_pool_reward_config(&self) -> PoolRewardConfig;
     fn set_pool_reward_config(&self, config: &PoolRewardConfig);
 
+    fn get_pool_reward_schedule(&self) -> Vec<PoolRewardConfig>;
+    fn set_pool_reward_schedule(&self, schedule: &Vec<PoolRewardConfig>);
+    fn get_pool_reward_phase(&self) -> u32;
+    fn set_pool_reward_phase(&self, phase: u32);
+
     fn get_pool_reward_data(&self) -> PoolRewardData;
     fn set_pool_reward_data(&self, data: &PoolRewardData);
 
+    fn get_reward_checkpoints_enabled(&self) -> bool;
+    fn set_reward_checkpoints_enabled(&self, enabled: bool);
+    fn get_reward_checkpoint(&self, block: u64) -> Option<u128>;
+    fn set_reward_checkpoint(&self, block: u64, reward_per_share: u128);
+
     fn get_user_reward_data(&self, user: &Address) -> Option<UserRewardData>;
     fn set_user_reward_data(&self, user: &Address, config: &UserRewardData);
     fn bump_user_reward_data(&self, user: &Address);
 
-    fn get_reward_inv_data(&mut self, pow: u32, page_number: u64) -> Map<u64, u128>;
-    fn set_reward_inv_data(&mut self, pow: u32, page_number: u64, value: Map<u64, u128>);
-    fn bump_reward_inv_data(&self, pow: u32, page_number: u64);
+    fn get_legacy_pool_reward_data(&self) -> Option<LegacyPoolRewardData>;
+    fn get_legacy_user_reward_data(&self, user: &Address) -> Option<LegacyUserRewardData>;
+    fn get_legacy_reward_inv(&self, pow: u32, page_number: u64) -> Map<u64, u128>;
+    fn get_legacy_reward_info(&self) -> Option<(u64, u64)>;
+    fn set_legacy_reward_info(&self, block: u64, page_size: u64);
+
+    fn get_total_claimed(&self) -> u128;
+    fn set_total_claimed(&self, value: u128);
 
     fn get_reward_storage(&self) -> Address;
     fn put_reward_storage(&self, contract: Address);
@@ -89,6 +137,38 @@ impl RewardsStorageTrait for Storage {
             .set(&DataKey::PoolRewardConfig, config);
     }
 
+    // Sequential reward phases, ordered by expiration. Empty unless rewards are scheduled
+    fn get_pool_reward_schedule(&self) -> Vec<PoolRewardConfig> {
+        self.env
+            .storage()
+            .instance()
+            .get(&DataKey::PoolRewardSchedule)
+            .unwrap_or(Vec::new(&self.env))
+    }
+
+    fn set_pool_reward_schedule(&self, schedule: &Vec<PoolRewardConfig>) {
+        self.env
+            .storage()
+            .instance()
+            .set(&DataKey::PoolRewardSchedule, schedule);
+    }
+
+    // Index of the schedule phase copied into the pool reward config
+    fn get_pool_reward_phase(&self) -> u32 {
+        self.env
+            .storage()
+            .instance()
+            .get(&DataKey::PoolRewardPhase)
+            .unwrap_or(0)
+    }
+
+    fn set_pool_reward_phase(&self, phase: u32) {
+        self.env
+            .storage()
+            .instance()
+            .set(&DataKey::PoolRewardPhase, &phase);
+    }
+
     fn get_pool_reward_data(&self) -> PoolRewardData {
         self.env
             .storage()
@@ -104,7 +184,61 @@ impl RewardsStorageTrait for Storage {
             .set(&DataKey::PoolRewardData, data);
     }
 
+    // Checkpoints cost a persistent write on every pool update, so they're off by default
+    fn get_reward_checkpoints_enabled(&self) -> bool {
+        self.env
+            .storage()
+            .instance()
+            .get(&DataKey::RewardCheckpointsEnabled)
+            .unwrap_or(false)
+    }
+
+    fn set_reward_checkpoints_enabled(&self, enabled: bool) {
+        self.env
+            .storage()
+            .instance()
+            .set(&DataKey::RewardCheckpointsEnabled, &enabled);
+    }
+
+    // Reward per share the pool reached at the block. Kept for history, not used in calculations.
+    //  blocks share REWARD_CHECKPOINTS_KEPT slots, so only the latest ones are available
+    fn get_reward_checkpoint(&self, block: u64) -> Option<u128> {
+        let slot = (block % REWARD_CHECKPOINTS_KEPT) as u32;
+        let stored: Option<(u64, u128)> = self
+            .env
+            .storage()
+            .persistent()
+            .get(&DataKey::RewardCheckpointSlot(slot));
+        match stored {
+            Some((stored_block, reward_per_share)) if stored_block == block => {
+                Some(reward_per_share)
+            }
+            _ => None,
+        }
+    }
+
+    fn set_reward_checkpoint(&self, block: u64, reward_per_share: u128) {
+        let key = DataKey::RewardCheckpointSlot((block % REWARD_CHECKPOINTS_KEPT) as u32);
+        self.env
+            .storage()
+            .persistent()
+            .set(&key, &(block, reward_per_share));
+        bump_persistent(&self.env, &key);
+    }
+
+    // Legacy user data isn't returned, it's converted by the manager on the first touch
     fn get_user_reward_data(&self, user: &Address) -> Option<UserRewardData> {
+        if self.get_legacy_reward_info().is_some() {
+            let value: Val = self
+                .env
+                .storage()
+                .persistent()
+                .get(&DataKey::UserRewardData(user.clone()))?;
+            if is_legacy_reward_data(&self.env, &value) {
+                return None;
+            }
+            return Some(UserRewardData::from_val(&self.env, &value));
+        }
         match self
             .env
             .storage()
@@ -127,33 +261,70 @@ impl RewardsStorageTrait for Storage {
         bump_persistent(&self.env, &DataKey::UserRewardData(user.clone()))
     }
 
-    fn get_reward_inv_data(&mut self, pow: u32, page_number: u64) -> Map<u64, u128> {
-        let key = DataKey::RewardInvData(pow, page_number);
-        let cached_value_result = self.inv_cache.get(key.clone());
-        match cached_value_result {
-            Some(value) => value,
-            None => {
-                let value: Map<u64, u128> = self
-                    .env
-                    .storage()
-                    .persistent()
-                    .get(&key)
-                    .expect("Please, initialize reward inv data");
-                self.inv_cache.set(key, value.clone());
-                value
-            }
+    // Pool data if it's still in the legacy layout
+    fn get_legacy_pool_reward_data(&self) -> Option<LegacyPoolRewardData> {
+        let value: Val = self
+            .env
+            .storage()
+            .instance()
+            .get(&DataKey::PoolRewardData)?;
+        match is_legacy_reward_data(&self.env, &value) {
+            true => Some(LegacyPoolRewardData::from_val(&self.env, &value)),
+            false => None,
+        }
+    }
+
+    // User data if it's still in the legacy layout
+    fn get_legacy_user_reward_data(&self, user: &Address) -> Option<LegacyUserRewardData> {
+        let value: Val = self
+            .env
+            .storage()
+            .persistent()
+            .get(&DataKey::UserRewardData(user.clone()))?;
+        match is_legacy_reward_data(&self.env, &value) {
+            true => Some(LegacyUserRewardData::from_val(&self.env, &value)),
+            false => None,
         }
     }
 
-    fn set_reward_inv_data(&mut self, pow: u32, page_number: u64, value: Map<u64, u128>) {
-        let key = DataKey::RewardInvData(pow, page_number);
-        self.inv_cache.set(key.clone(), value.clone());
-        self.env.storage().persistent().set(&key, &value);
-        self.bump_reward_inv_data(pow, page_number); // when set need bump
+    // Page of reward invariants aggregated by page_size^pow blocks, keyed by the first block
+    fn get_legacy_reward_inv(&self, pow: u32, page_number: u64) -> Map<u64, u128> {
+        self.env
+            .storage()
+            .persistent()
+            .get(&DataKey::RewardInvData(pow, page_number))
+            .unwrap_or(Map::new(&self.env))
+    }
+
+    // Block the pool was converted at and the page size of its reward invariants.
+    //  None if the pool never had the legacy layout
+    fn get_legacy_reward_info(&self) -> Option<(u64, u64)> {
+        self.env
+            .storage()
+            .instance()
+            .get(&DataKey::LegacyRewardInfo)
+    }
+
+    fn set_legacy_reward_info(&self, block: u64, page_size: u64) {
+        self.env
+            .storage()
+            .instance()
+            .set(&DataKey::LegacyRewardInfo, &(block, page_size));
+    }
+
+    fn get_total_claimed(&self) -> u128 {
+        self.env
+            .storage()
+            .instance()
+            .get(&DataKey::TotalClaimed)
+            .unwrap_or(0)
     }
 
-    fn bump_reward_inv_data(&self, pow: u32, page_number: u64) {
-        bump_persistent(&self.env, &DataKey::RewardInvData(pow, page_number))
+    fn set_total_claimed(&self, value: u128) {
+        self.env
+            .storage()
+            .instance()
+            .set(&DataKey::TotalClaimed, &value);
     }
 
     fn get_reward_storage(&self) -> Address {
@@ -1,4 +1,5 @@
-use soroban_sdk::{contracttype, Address, Env};
+use crate::contract::TokenError;
+use soroban_sdk::{contracttype, panic_with_error, Address, Env};
 
 #[derive(Clone)]
 #[contracttype]
@@ -40,31 +41,32 @@ pub fn write_allowance(
     amount: i128,
     expiration_ledger: u32,
 ) {
-    let allowance = AllowanceValue {
-        amount,
-        expiration_ledger,
-    };
-
     if amount > 0 && expiration_ledger < e.ledger().sequence() {
-        panic!("expiration_ledger is less than ledger seq when amount > 0")
+        panic_with_error!(e, TokenError::InvalidExpirationLedger);
     }
 
+    // zero allowance is the same as no allowance, so the entry is dropped
     let key = DataKey::Allowance(AllowanceDataKey { from, spender });
-    e.storage().temporary().set(&key.clone(), &allowance);
+    if amount == 0 {
+        e.storage().temporary().remove(&key);
+        return;
+    }
 
-    if amount > 0 {
-        let live_for = expiration_ledger
-            .checked_sub(e.ledger().sequence())
-            .unwrap();
+    let allowance = AllowanceValue {
+        amount,
+        expiration_ledger,
+    };
+    e.storage().temporary().set(&key, &allowance);
 
-        e.storage().temporary().extend_ttl(&key, live_for, live_for)
-    }
+    // entry lives exactly until the allowance expires
+    let live_for = expiration_ledger - e.ledger().sequence();
+    e.storage().temporary().extend_ttl(&key, live_for, live_for)
 }
 
 pub fn spend_allowance(e: &Env, from: Address, spender: Address, amount: i128) {
     let allowance = read_allowance(e, from.clone(), spender.clone());
     if allowance.amount < amount {
-        panic!("insufficient allowance");
+        panic_with_error!(e, TokenError::InsufficientAllowance);
     }
     write_allowance(
         e,
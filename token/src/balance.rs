@@ -1,10 +1,17 @@
-use soroban_sdk::{contracttype, Address, Env};
-use utils::bump::bump_persistent;
+use crate::contract::TokenError;
+use soroban_sdk::{contracttype, panic_with_error, Address, Env};
+use utils::bump::{bump_instance, bump_persistent};
 
 #[derive(Clone)]
 #[contracttype]
 enum DataKey {
     Balance(Address),
+    Locked(Address),
+    Deauthorized(Address),
+    Regulated,
+    TotalSupply,
+    MaxSupply,
+    Transferable,
 }
 
 fn write_balance(e: &Env, addr: Address, amount: i128) {
@@ -24,15 +31,136 @@ pub fn read_balance(e: &Env, addr: Address) -> i128 {
     }
 }
 
+// Part of the balance the account is able to transfer
+pub fn read_spendable_balance(e: &Env, addr: Address) -> i128 {
+    if read_regulated(e) && !read_authorized(e, addr.clone()) {
+        return 0;
+    }
+    (read_balance(e, addr.clone()) - read_locked(e, addr)).max(0)
+}
+
+// Part of the balance only the admin is able to move
+pub fn read_locked(e: &Env, addr: Address) -> i128 {
+    e.storage()
+        .persistent()
+        .get(&DataKey::Locked(addr))
+        .unwrap_or(0)
+}
+
+pub fn write_locked(e: &Env, addr: Address, amount: i128) {
+    let key = DataKey::Locked(addr);
+    if amount == 0 {
+        e.storage().persistent().remove(&key);
+    } else {
+        e.storage().persistent().set(&key, &amount);
+        bump_persistent(e, &key);
+    }
+}
+
 pub fn receive_balance(e: &Env, addr: Address, amount: i128) {
+    check_authorized(e, &addr);
     let balance = read_balance(e, addr.clone());
     write_balance(e, addr, balance + amount);
 }
 
 pub fn spend_balance(e: &Env, addr: Address, amount: i128) {
+    check_authorized(e, &addr);
+    clawback_balance(e, addr, amount);
+}
+
+// Take the balance regardless of the account authorization
+pub fn clawback_balance(e: &Env, addr: Address, amount: i128) {
     let balance = read_balance(e, addr.clone());
     if balance < amount {
-        panic!("insufficient balance");
+        panic_with_error!(e, TokenError::InsufficientBalance);
     }
     write_balance(e, addr, balance - amount);
 }
+
+pub fn read_total_supply(e: &Env) -> i128 {
+    bump_instance(e);
+    e.storage()
+        .instance()
+        .get(&DataKey::TotalSupply)
+        .unwrap_or(0)
+}
+
+fn write_total_supply(e: &Env, value: i128) {
+    bump_instance(e);
+    e.storage().instance().set(&DataKey::TotalSupply, &value)
+}
+
+// Supply cap, no cap unless set by admin
+pub fn read_max_supply(e: &Env) -> Option<i128> {
+    bump_instance(e);
+    e.storage().instance().get(&DataKey::MaxSupply)
+}
+
+pub fn write_max_supply(e: &Env, value: i128) {
+    bump_instance(e);
+    e.storage().instance().set(&DataKey::MaxSupply, &value)
+}
+
+pub fn increase_supply(e: &Env, amount: i128) {
+    let total_supply = read_total_supply(e) + amount;
+    if let Some(max_supply) = read_max_supply(e) {
+        if total_supply > max_supply {
+            panic_with_error!(e, TokenError::MaxSupplyExceeded);
+        }
+    }
+    write_total_supply(e, total_supply);
+}
+
+pub fn decrease_supply(e: &Env, amount: i128) {
+    write_total_supply(e, read_total_supply(e) - amount);
+}
+
+// Regulated token lets admin deauthorize accounts and clawback balances
+pub fn read_regulated(e: &Env) -> bool {
+    bump_instance(e);
+    e.storage()
+        .instance()
+        .get(&DataKey::Regulated)
+        .unwrap_or(false)
+}
+
+pub fn write_regulated(e: &Env, value: bool) {
+    bump_instance(e);
+    e.storage().instance().set(&DataKey::Regulated, &value)
+}
+
+// Non transferable token moves only to or by the admin
+pub fn read_transferable(e: &Env) -> bool {
+    bump_instance(e);
+    e.storage()
+        .instance()
+        .get(&DataKey::Transferable)
+        .unwrap_or(true)
+}
+
+pub fn write_transferable(e: &Env, value: bool) {
+    bump_instance(e);
+    e.storage().instance().set(&DataKey::Transferable, &value)
+}
+
+// Accounts are authorized unless admin deauthorized them
+pub fn read_authorized(e: &Env, addr: Address) -> bool {
+    !e.storage().persistent().has(&DataKey::Deauthorized(addr))
+}
+
+pub fn write_authorized(e: &Env, addr: Address, authorize: bool) {
+    let key = DataKey::Deauthorized(addr);
+    if authorize {
+        e.storage().persistent().remove(&key);
+    } else {
+        e.storage().persistent().set(&key, &true);
+        bump_persistent(e, &key);
+    }
+}
+
+// Deauthorized account can neither send nor receive tokens
+fn check_authorized(e: &Env, addr: &Address) {
+    if read_regulated(e) && !read_authorized(e, addr.clone()) {
+        panic_with_error!(e, TokenError::AccountDeauthorized);
+    }
+}
@@ -1,18 +1,83 @@
 //! This contract demonstrates a sample implementation of the Soroban token
 //! interface.
 use crate::allowance::{read_allowance, spend_allowance, write_allowance};
-use crate::balance::{read_balance, receive_balance, spend_balance};
-use crate::metadata::{read_decimal, read_name, read_symbol, write_metadata};
+use crate::balance::{
+    clawback_balance, decrease_supply, increase_supply, read_authorized, read_balance, read_locked,
+    read_max_supply, read_regulated, read_spendable_balance, read_total_supply, read_transferable,
+    receive_balance, spend_balance, write_authorized, write_locked, write_max_supply,
+    write_regulated, write_transferable,
+};
+use crate::metadata::{read_decimal, read_name, read_symbol, write_metadata, write_name_symbol};
 use access_control::access::{AccessControl, AccessControlTrait};
 use soroban_sdk::token::{self, Interface as _};
-use soroban_sdk::{contract, contractimpl, Address, Env, String};
+use soroban_sdk::{
+    contract, contracterror, contractimpl, panic_with_error, Address, Env, String, Symbol,
+};
 use soroban_token_sdk::metadata::TokenMetadata;
 use soroban_token_sdk::TokenUtils;
 use utils::bump::bump_instance;
 
-fn check_nonnegative_amount(amount: i128) {
+#[contracterror]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
+#[repr(u32)]
+pub enum TokenError {
+    NegativeAmount = 301,
+    InsufficientBalance = 302,
+    InsufficientAllowance = 303,
+    InvalidExpirationLedger = 304,
+    AccountDeauthorized = 305,
+    NotRegulated = 306,
+    InvalidMetadata = 307,
+    MaxSupplyExceeded = 308,
+    MaxSupplyAlreadySet = 309,
+    NotTransferable = 310,
+    BalanceLocked = 311,
+}
+
+fn check_nonnegative_amount(e: &Env, amount: i128) {
     if amount < 0 {
-        panic!("negative amount is not allowed: {}", amount)
+        panic_with_error!(e, TokenError::NegativeAmount);
+    }
+}
+
+const MAX_NAME_LENGTH: u32 = 32;
+const MAX_SYMBOL_LENGTH: u32 = 12;
+
+fn check_metadata(e: &Env, name: &String, symbol: &String) {
+    if name.len() == 0
+        || name.len() > MAX_NAME_LENGTH
+        || symbol.len() == 0
+        || symbol.len() > MAX_SYMBOL_LENGTH
+    {
+        panic_with_error!(e, TokenError::InvalidMetadata);
+    }
+}
+
+// Non transferable token can only be moved by the admin or to the admin
+fn check_transferable(e: &Env, spender: &Address, to: &Address) {
+    if read_transferable(e) {
+        return;
+    }
+    let admin = AccessControl::new(e).get_admin().unwrap();
+    if *spender != admin && *to != admin {
+        panic_with_error!(e, TokenError::NotTransferable);
+    }
+}
+
+// Locked part of the balance can only be moved by the admin
+fn check_unlocked(e: &Env, spender: &Address, from: &Address, amount: i128) {
+    let locked = read_locked(e, from.clone());
+    if locked == 0 || *spender == AccessControl::new(e).get_admin().unwrap() {
+        return;
+    }
+    if read_balance(e, from.clone()) - amount < locked {
+        panic_with_error!(e, TokenError::BalanceLocked);
+    }
+}
+
+fn check_regulated(e: &Env) {
+    if !read_regulated(e) {
+        panic_with_error!(e, TokenError::NotRegulated);
     }
 }
 
@@ -21,7 +86,15 @@ pub struct Token;
 
 #[contractimpl]
 impl Token {
-    pub fn initialize(e: Env, admin: Address, decimal: u32, name: String, symbol: String) {
+    // regulated token lets admin deauthorize accounts and clawback their balances
+    pub fn initialize(
+        e: Env,
+        admin: Address,
+        decimal: u32,
+        name: String,
+        symbol: String,
+        regulated: bool,
+    ) {
         let access_control = AccessControl::new(&e);
         if access_control.has_admin() {
             panic!("already initialized")
@@ -38,21 +111,50 @@ impl Token {
                 name,
                 symbol,
             },
-        )
+        );
+        write_regulated(&e, regulated);
     }
 
     pub fn mint(e: Env, to: Address, amount: i128) {
-        check_nonnegative_amount(amount);
+        check_nonnegative_amount(&e, amount);
         let access_control = AccessControl::new(&e);
         let admin = access_control.get_admin().unwrap();
         admin.require_auth();
 
         bump_instance(&e);
 
+        increase_supply(&e, amount);
         receive_balance(&e, to.clone(), amount);
         TokenUtils::new(&e).events().mint(admin, to, amount);
     }
 
+    // Cap the total supply. Can only be set once, never below the current supply
+    pub fn set_max_supply(e: Env, max_supply: i128) {
+        check_nonnegative_amount(&e, max_supply);
+        let admin = AccessControl::new(&e).get_admin().unwrap();
+        admin.require_auth();
+
+        bump_instance(&e);
+
+        if read_max_supply(&e).is_some() {
+            panic_with_error!(&e, TokenError::MaxSupplyAlreadySet);
+        }
+        if read_total_supply(&e) > max_supply {
+            panic_with_error!(&e, TokenError::MaxSupplyExceeded);
+        }
+        write_max_supply(&e, max_supply);
+        e.events()
+            .publish((Symbol::new(&e, "set_max_supply"), admin), max_supply);
+    }
+
+    pub fn total_supply(e: Env) -> i128 {
+        read_total_supply(&e)
+    }
+
+    pub fn max_supply(e: Env) -> Option<i128> {
+        read_max_supply(&e)
+    }
+
     pub fn set_admin(e: Env, new_admin: Address) {
         let access_control = AccessControl::new(&e);
         let admin = access_control.get_admin().unwrap();
@@ -60,9 +162,92 @@ impl Token {
 
         bump_instance(&e);
 
-        access_control.set_admin(&admin);
+        access_control.set_admin(&new_admin);
         TokenUtils::new(&e).events().set_admin(admin, new_admin);
     }
+
+    pub fn update_metadata(e: Env, admin: Address, name: String, symbol: String) {
+        admin.require_auth();
+        AccessControl::new(&e).check_admin(&admin);
+        check_metadata(&e, &name, &symbol);
+
+        bump_instance(&e);
+
+        write_name_symbol(&e, name.clone(), symbol.clone());
+        e.events()
+            .publish((Symbol::new(&e, "update_metadata"), admin), (name, symbol));
+    }
+
+    // Keep amount of the account balance from being moved by anyone but the admin, 0 releases it.
+    //  the lock may exceed the balance, then nothing is spendable
+    pub fn set_locked(e: Env, id: Address, amount: i128) {
+        check_nonnegative_amount(&e, amount);
+        let admin = AccessControl::new(&e).get_admin().unwrap();
+        admin.require_auth();
+
+        bump_instance(&e);
+
+        write_locked(&e, id.clone(), amount);
+        e.events()
+            .publish((Symbol::new(&e, "set_locked"), admin, id), amount);
+    }
+
+    pub fn locked(e: Env, id: Address) -> i128 {
+        bump_instance(&e);
+        read_locked(&e, id)
+    }
+
+    pub fn set_authorized(e: Env, id: Address, authorize: bool) {
+        let admin = AccessControl::new(&e).get_admin().unwrap();
+        admin.require_auth();
+        check_regulated(&e);
+
+        bump_instance(&e);
+
+        write_authorized(&e, id.clone(), authorize);
+        TokenUtils::new(&e)
+            .events()
+            .set_authorized(admin, id, authorize);
+    }
+
+    // Restrict transfers to the ones made by the admin or to the admin. Mint and burn are unaffected
+    pub fn set_transferable(e: Env, transferable: bool) {
+        let admin = AccessControl::new(&e).get_admin().unwrap();
+        admin.require_auth();
+
+        bump_instance(&e);
+
+        write_transferable(&e, transferable);
+        e.events()
+            .publish((Symbol::new(&e, "set_transferable"), admin), transferable);
+    }
+
+    pub fn transferable(e: Env) -> bool {
+        read_transferable(&e)
+    }
+
+    pub fn spendable_balance(e: Env, id: Address) -> i128 {
+        bump_instance(&e);
+        read_spendable_balance(&e, id)
+    }
+
+    pub fn authorized(e: Env, id: Address) -> bool {
+        bump_instance(&e);
+        read_authorized(&e, id)
+    }
+
+    pub fn clawback(e: Env, from: Address, amount: i128) {
+        check_nonnegative_amount(&e, amount);
+        let admin = AccessControl::new(&e).get_admin().unwrap();
+        admin.require_auth();
+        check_regulated(&e);
+
+        bump_instance(&e);
+
+        clawback_balance(&e, from.clone(), amount);
+        decrease_supply(&e, amount);
+        TokenUtils::new(&e).events().clawback(admin, from, amount);
+    }
 }
 
 #[contractimpl]
@@ -75,7 +260,7 @@ impl token::Interface for Token {
     fn approve(e: Env, from: Address, spender: Address, amount: i128, expiration_ledger: u32) {
         from.require_auth();
 
-        check_nonnegative_amount(amount);
+        check_nonnegative_amount(&e, amount);
 
         bump_instance(&e);
 
@@ -93,8 +278,10 @@ impl token::Interface for Token {
     fn transfer(e: Env, from: Address, to: Address, amount: i128) {
         from.require_auth();
 
-        check_nonnegative_amount(amount);
+        check_nonnegative_amount(&e, amount);
 
+        check_transferable(&e, &from, &to);
+        check_unlocked(&e, &from, &from, amount);
         bump_instance(&e);
 
         spend_balance(&e, from.clone(), amount);
@@ -105,8 +292,10 @@ impl token::Interface for Token {
     fn transfer_from(e: Env, spender: Address, from: Address, to: Address, amount: i128) {
         spender.require_auth();
 
-        check_nonnegative_amount(amount);
+        check_nonnegative_amount(&e, amount);
 
+        check_transferable(&e, &spender, &to);
+        check_unlocked(&e, &spender, &from, amount);
         bump_instance(&e);
 
         spend_allowance(&e, from.clone(), spender, amount);
@@ -118,23 +307,27 @@ impl token::Interface for Token {
     fn burn(e: Env, from: Address, amount: i128) {
         from.require_auth();
 
-        check_nonnegative_amount(amount);
+        check_nonnegative_amount(&e, amount);
+        check_unlocked(&e, &from, &from, amount);
 
         bump_instance(&e);
 
         spend_balance(&e, from.clone(), amount);
+        decrease_supply(&e, amount);
         TokenUtils::new(&e).events().burn(from, amount);
     }
 
     fn burn_from(e: Env, spender: Address, from: Address, amount: i128) {
         spender.require_auth();
 
-        check_nonnegative_amount(amount);
+        check_nonnegative_amount(&e, amount);
+        check_unlocked(&e, &spender, &from, amount);
 
         bump_instance(&e);
 
         spend_allowance(&e, from.clone(), spender, amount);
         spend_balance(&e, from.clone(), amount);
+        decrease_supply(&e, amount);
         TokenUtils::new(&e).events().burn(from, amount)
     }
 
@@ -20,3 +20,15 @@ pub fn write_metadata(e: &Env, metadata: TokenMetadata) {
     let util = TokenUtils::new(e);
     util.metadata().set_metadata(&metadata);
 }
+
+// Overwrite name and symbol, decimals are immutable
+pub fn write_name_symbol(e: &Env, name: String, symbol: String) {
+    write_metadata(
+        e,
+        TokenMetadata {
+            decimal: read_decimal(e),
+            name,
+            symbol,
+        },
+    );
+}
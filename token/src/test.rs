@@ -1,16 +1,26 @@
 #![cfg(test)]
 extern crate std;
 
+use crate::allowance::read_allowance;
 use crate::{contract::Token, TokenClient};
 use soroban_sdk::{
     symbol_short,
-    testutils::{Address as _, AuthorizedFunction, AuthorizedInvocation},
-    Address, Env, IntoVal, Symbol,
+    testutils::{
+        Address as _, AuthorizedFunction, AuthorizedInvocation, Events, Ledger, MockAuth,
+        MockAuthInvoke,
+    },
+    vec, Address, Env, IntoVal, String, Symbol, Val, Vec,
 };
 
 fn create_token<'a>(e: &Env, admin: &Address) -> TokenClient<'a> {
     let token = TokenClient::new(e, &e.register_contract(None, Token {}));
-    token.initialize(admin, &7, &"name".into_val(e), &"symbol".into_val(e));
+    token.initialize(
+        admin,
+        &7,
+        &"name".into_val(e),
+        &"symbol".into_val(e),
+        &false,
+    );
     token
 }
 
@@ -137,6 +147,62 @@ fn test() {
     assert_eq!(token.allowance(&user2, &user3), 0);
 }
 
+#[test]
+fn test_events() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin = Address::generate(&e);
+    let user1 = Address::generate(&e);
+    let user2 = Address::generate(&e);
+    let user3 = Address::generate(&e);
+    let token = create_token(&e, &admin);
+
+    // Stellar asset contract layout: action, addresses involved as topics and amount as data
+    let assert_last_event = |topics: Vec<Val>, data: Val| {
+        assert_eq!(
+            vec![&e, e.events().all().last().unwrap()],
+            vec![&e, (token.address.clone(), topics, data)]
+        );
+    };
+
+    token.mint(&user1, &1000);
+    assert_last_event(
+        (symbol_short!("mint"), admin.clone(), user1.clone()).into_val(&e),
+        1000_i128.into_val(&e),
+    );
+
+    token.transfer(&user1, &user2, &100);
+    assert_last_event(
+        (symbol_short!("transfer"), user1.clone(), user2.clone()).into_val(&e),
+        100_i128.into_val(&e),
+    );
+
+    token.approve(&user1, &user3, &500, &200);
+    assert_last_event(
+        (symbol_short!("approve"), user1.clone(), user3.clone()).into_val(&e),
+        (500_i128, 200_u32).into_val(&e),
+    );
+
+    token.transfer_from(&user3, &user1, &user2, &200);
+    assert_last_event(
+        (symbol_short!("transfer"), user1.clone(), user2.clone()).into_val(&e),
+        200_i128.into_val(&e),
+    );
+
+    token.burn(&user2, &50);
+    assert_last_event(
+        (symbol_short!("burn"), user2.clone()).into_val(&e),
+        50_i128.into_val(&e),
+    );
+
+    token.burn_from(&user3, &user1, &30);
+    assert_last_event(
+        (symbol_short!("burn"), user1.clone()).into_val(&e),
+        30_i128.into_val(&e),
+    );
+}
+
 #[test]
 fn test_burn() {
     let e = Env::default();
@@ -194,7 +260,48 @@ fn test_burn() {
 }
 
 #[test]
-#[should_panic(expected = "insufficient balance")]
+fn test_locked() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin = Address::generate(&e);
+    let user1 = Address::generate(&e);
+    let user2 = Address::generate(&e);
+    let token = create_token(&e, &admin);
+    let error = Ok(soroban_sdk::Error::from_contract_error(311));
+
+    token.mint(&user1, &1000);
+    token.set_locked(&user1, &600);
+    assert_eq!(token.locked(&user1), 600);
+    assert_eq!(token.spendable_balance(&user1), 400);
+
+    // unlocked part moves freely, locked one doesn't
+    token.transfer(&user1, &user2, &400);
+    assert_eq!(token.try_transfer(&user1, &user2, &1).unwrap_err(), error);
+    token.approve(&user1, &user2, &500, &200);
+    assert_eq!(
+        token
+            .try_transfer_from(&user2, &user1, &user2, &1)
+            .unwrap_err(),
+        error
+    );
+    assert_eq!(token.try_burn(&user1, &1).unwrap_err(), error);
+    assert_eq!(token.try_burn_from(&user2, &user1, &1).unwrap_err(), error);
+
+    // admin is still able to move locked tokens
+    token.approve(&user1, &admin, &500, &200);
+    token.transfer_from(&admin, &user1, &admin, &100);
+    assert_eq!(token.balance(&user1), 500);
+    assert_eq!(token.spendable_balance(&user1), 0);
+
+    token.set_locked(&user1, &0);
+    assert_eq!(token.locked(&user1), 0);
+    token.transfer(&user1, &user2, &500);
+    assert_eq!(token.balance(&user2), 900);
+}
+
+#[test]
+#[should_panic(expected = "Error(Contract, #302)")]
 fn transfer_insufficient_balance() {
     let e = Env::default();
     e.mock_all_auths();
@@ -211,7 +318,7 @@ fn transfer_insufficient_balance() {
 }
 
 #[test]
-#[should_panic(expected = "insufficient allowance")]
+#[should_panic(expected = "Error(Contract, #303)")]
 fn transfer_from_insufficient_allowance() {
     let e = Env::default();
     e.mock_all_auths();
@@ -231,6 +338,450 @@ fn transfer_from_insufficient_allowance() {
     token.transfer_from(&user3, &user1, &user2, &101);
 }
 
+#[test]
+fn test_burn_errors() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin = Address::generate(&e);
+    let user1 = Address::generate(&e);
+    let user2 = Address::generate(&e);
+    let token = create_token(&e, &admin);
+    let error = |code| Ok(soroban_sdk::Error::from_contract_error(code));
+
+    token.mint(&user1, &1000);
+    assert_eq!(token.try_burn(&user1, &-1).unwrap_err(), error(301));
+    assert_eq!(token.try_burn(&user1, &1001).unwrap_err(), error(302));
+
+    token.approve(&user1, &user2, &500, &200);
+    assert_eq!(
+        token.try_burn_from(&user2, &user1, &-1).unwrap_err(),
+        error(301)
+    );
+    token.burn_from(&user2, &user1, &300);
+    token.burn_from(&user2, &user1, &200);
+    // allowance is exhausted the same way as by transfer_from
+    assert_eq!(token.allowance(&user1, &user2), 0);
+    assert_eq!(
+        token.try_burn_from(&user2, &user1, &1).unwrap_err(),
+        error(303)
+    );
+
+    // allowance exceeding the balance doesn't allow burning more than the balance
+    token.approve(&user1, &user2, &1000, &200);
+    assert_eq!(
+        token.try_burn_from(&user2, &user1, &501).unwrap_err(),
+        error(302)
+    );
+    assert_eq!(token.balance(&user1), 500);
+    assert_eq!(token.allowance(&user1, &user2), 1000);
+}
+
+#[test]
+fn test_allowance_expiration() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin = Address::generate(&e);
+    let user1 = Address::generate(&e);
+    let user2 = Address::generate(&e);
+    let user3 = Address::generate(&e);
+    let token = create_token(&e, &admin);
+    let error = |code| Ok(soroban_sdk::Error::from_contract_error(code));
+    let read_entry = || {
+        e.as_contract(&token.address, || {
+            read_allowance(&e, user1.clone(), user3.clone())
+        })
+    };
+
+    token.mint(&user1, &1000);
+    e.ledger().with_mut(|li| li.sequence_number = 100);
+
+    // approval already expired is rejected unless it revokes the allowance
+    assert_eq!(
+        token.try_approve(&user1, &user3, &500, &99).unwrap_err(),
+        error(304)
+    );
+    token.approve(&user1, &user3, &500, &200);
+    assert_eq!(read_entry().expiration_ledger, 200);
+    token.approve(&user1, &user3, &0, &99);
+    assert_eq!(read_entry().expiration_ledger, 0);
+    assert_eq!(token.allowance(&user1, &user3), 0);
+
+    // entry is kept alive till the expiration ledger, far beyond the minimal TTL
+    token.approve(&user1, &user3, &500, &1100);
+    e.ledger().with_mut(|li| li.sequence_number = 1100);
+    assert_eq!(token.allowance(&user1, &user3), 500);
+    token.transfer_from(&user3, &user1, &user2, &100);
+    assert_eq!(token.allowance(&user1, &user3), 400);
+
+    e.ledger().with_mut(|li| li.sequence_number = 1101);
+    assert_eq!(token.allowance(&user1, &user3), 0);
+    assert_eq!(
+        token
+            .try_transfer_from(&user3, &user1, &user2, &100)
+            .unwrap_err(),
+        error(303)
+    );
+    assert_eq!(token.balance(&user2), 100);
+}
+
+#[test]
+fn test_authorization_and_clawback() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin = Address::generate(&e);
+    let user1 = Address::generate(&e);
+    let user2 = Address::generate(&e);
+    let token = TokenClient::new(&e, &e.register_contract(None, Token {}));
+    token.initialize(
+        &admin,
+        &7,
+        &"name".into_val(&e),
+        &"symbol".into_val(&e),
+        &true,
+    );
+    let error = |code| Ok(soroban_sdk::Error::from_contract_error(code));
+
+    token.mint(&user1, &1000);
+    token.mint(&user2, &1000);
+    token.approve(&user1, &user2, &500, &200);
+
+    token.set_authorized(&user1, &false);
+    assert_eq!(
+        vec![&e, e.events().all().last().unwrap()],
+        vec![
+            &e,
+            (
+                token.address.clone(),
+                (
+                    Symbol::new(&e, "set_authorized"),
+                    admin.clone(),
+                    user1.clone()
+                )
+                    .into_val(&e),
+                false.into_val(&e),
+            )
+        ]
+    );
+    assert!(!token.authorized(&user1));
+    // frozen account can neither send nor receive
+    assert_eq!(
+        token.try_transfer(&user1, &user2, &1).unwrap_err(),
+        error(305)
+    );
+    assert_eq!(
+        token.try_transfer(&user2, &user1, &1).unwrap_err(),
+        error(305)
+    );
+    assert_eq!(
+        token
+            .try_transfer_from(&user2, &user1, &user2, &1)
+            .unwrap_err(),
+        error(305)
+    );
+    assert_eq!(token.try_burn(&user1, &1).unwrap_err(), error(305));
+    assert_eq!(token.try_mint(&user1, &1).unwrap_err(), error(305));
+
+    // clawback works for both frozen and authorized accounts
+    token.clawback(&user1, &300);
+    assert_eq!(
+        vec![&e, e.events().all().last().unwrap()],
+        vec![
+            &e,
+            (
+                token.address.clone(),
+                (symbol_short!("clawback"), admin.clone(), user1.clone()).into_val(&e),
+                300_i128.into_val(&e),
+            )
+        ]
+    );
+    assert_eq!(token.balance(&user1), 700);
+    token.clawback(&user2, &100);
+    assert_eq!(token.balance(&user2), 900);
+    assert_eq!(token.try_clawback(&user2, &901).unwrap_err(), error(302));
+
+    token.set_authorized(&user1, &true);
+    token.transfer(&user1, &user2, &100);
+    assert_eq!(token.balance(&user1), 600);
+    assert_eq!(token.balance(&user2), 1000);
+}
+
+#[test]
+fn test_spendable_balance() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin = Address::generate(&e);
+    let user1 = Address::generate(&e);
+    let user2 = Address::generate(&e);
+    let token = TokenClient::new(&e, &e.register_contract(None, Token {}));
+    token.initialize(
+        &admin,
+        &7,
+        &"name".into_val(&e),
+        &"symbol".into_val(&e),
+        &false,
+    );
+    assert_eq!(token.spendable_balance(&user1), 0);
+    token.mint(&user1, &1000);
+    assert_eq!(token.spendable_balance(&user1), 1000);
+    assert!(token.authorized(&user1));
+
+    let regulated = TokenClient::new(&e, &e.register_contract(None, Token {}));
+    regulated.initialize(
+        &admin,
+        &7,
+        &"name".into_val(&e),
+        &"symbol".into_val(&e),
+        &true,
+    );
+    regulated.mint(&user1, &1000);
+    regulated.mint(&user2, &500);
+    regulated.set_authorized(&user1, &false);
+    assert!(!regulated.authorized(&user1));
+    assert_eq!(regulated.balance(&user1), 1000);
+    assert_eq!(regulated.spendable_balance(&user1), 0);
+    assert_eq!(regulated.spendable_balance(&user2), 500);
+
+    regulated.set_authorized(&user1, &true);
+    assert_eq!(regulated.spendable_balance(&user1), 1000);
+}
+
+#[test]
+fn test_clawback_not_admin() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin = Address::generate(&e);
+    let user1 = Address::generate(&e);
+    let token = TokenClient::new(&e, &e.register_contract(None, Token {}));
+    token.initialize(
+        &admin,
+        &7,
+        &"name".into_val(&e),
+        &"symbol".into_val(&e),
+        &true,
+    );
+    token.mint(&user1, &1000);
+
+    // only admin authorization is accepted
+    e.set_auths(&[]);
+    e.mock_auths(&[MockAuth {
+        address: &user1,
+        invoke: &MockAuthInvoke {
+            contract: &token.address,
+            fn_name: "clawback",
+            args: (&user1, 100_i128).into_val(&e),
+            sub_invokes: &[],
+        },
+    }]);
+    assert!(token.try_clawback(&user1, &100).is_err());
+    e.mock_auths(&[MockAuth {
+        address: &user1,
+        invoke: &MockAuthInvoke {
+            contract: &token.address,
+            fn_name: "set_authorized",
+            args: (&user1, false).into_val(&e),
+            sub_invokes: &[],
+        },
+    }]);
+    assert!(token.try_set_authorized(&user1, &false).is_err());
+    assert_eq!(token.balance(&user1), 1000);
+    assert!(token.authorized(&user1));
+}
+
+#[test]
+fn test_not_regulated() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin = Address::generate(&e);
+    let user1 = Address::generate(&e);
+    let token = create_token(&e, &admin);
+    let error = Ok(soroban_sdk::Error::from_contract_error(306));
+
+    token.mint(&user1, &1000);
+    assert_eq!(token.try_clawback(&user1, &100).unwrap_err(), error);
+    assert_eq!(token.try_set_authorized(&user1, &false).unwrap_err(), error);
+    assert!(token.authorized(&user1));
+}
+
+#[test]
+fn test_transferable() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin = Address::generate(&e);
+    let user1 = Address::generate(&e);
+    let user2 = Address::generate(&e);
+    let token = create_token(&e, &admin);
+    let error = Ok(soroban_sdk::Error::from_contract_error(310));
+
+    token.mint(&user1, &1000);
+    assert!(token.transferable());
+    token.set_transferable(&false);
+    assert!(!token.transferable());
+
+    // peer to peer transfers are blocked
+    assert_eq!(token.try_transfer(&user1, &user2, &100).unwrap_err(), error);
+    token.approve(&user1, &user2, &500, &200);
+    assert_eq!(
+        token
+            .try_transfer_from(&user2, &user1, &user2, &100)
+            .unwrap_err(),
+        error
+    );
+
+    // admin is still able to move tokens, mint and burn are unaffected
+    token.transfer(&user1, &admin, &100);
+    token.approve(&user1, &admin, &500, &200);
+    token.transfer_from(&admin, &user1, &user2, &100);
+    token.transfer(&admin, &user2, &100);
+    token.mint(&user1, &100);
+    token.burn(&user1, &100);
+    assert_eq!(token.balance(&user1), 800);
+    assert_eq!(token.balance(&user2), 200);
+    assert_eq!(token.balance(&admin), 0);
+
+    token.set_transferable(&true);
+    token.transfer(&user1, &user2, &100);
+    assert_eq!(token.balance(&user2), 300);
+}
+
+#[test]
+fn test_set_admin() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin1 = Address::generate(&e);
+    let admin2 = Address::generate(&e);
+    let user1 = Address::generate(&e);
+    let token = create_token(&e, &admin1);
+
+    token.set_admin(&admin2);
+    token.mint(&user1, &1000);
+    assert_eq!(
+        e.auths(),
+        std::vec![(
+            admin2.clone(),
+            AuthorizedInvocation {
+                function: AuthorizedFunction::Contract((
+                    token.address.clone(),
+                    symbol_short!("mint"),
+                    (&user1, 1000_i128).into_val(&e),
+                )),
+                sub_invocations: std::vec![]
+            }
+        )]
+    );
+    assert_eq!(token.balance(&user1), 1000);
+
+    // previous admin can't act anymore
+    e.set_auths(&[]);
+    e.mock_auths(&[MockAuth {
+        address: &admin1,
+        invoke: &MockAuthInvoke {
+            contract: &token.address,
+            fn_name: "mint",
+            args: (&user1, 1000_i128).into_val(&e),
+            sub_invokes: &[],
+        },
+    }]);
+    assert!(token.try_mint(&user1, &1000).is_err());
+    assert_eq!(token.balance(&user1), 1000);
+}
+
+#[test]
+fn test_update_metadata() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin = Address::generate(&e);
+    let user1 = Address::generate(&e);
+    let token = create_token(&e, &admin);
+    let error = |code| Ok(soroban_sdk::Error::from_contract_error(code));
+
+    token.update_metadata(&admin, &"new name".into_val(&e), &"NEW".into_val(&e));
+    assert_eq!(token.name(), String::from_str(&e, "new name"));
+    assert_eq!(token.symbol(), String::from_str(&e, "NEW"));
+    assert_eq!(token.decimals(), 7);
+    assert_eq!(
+        vec![&e, e.events().all().last().unwrap()],
+        vec![
+            &e,
+            (
+                token.address.clone(),
+                (Symbol::new(&e, "update_metadata"), admin.clone()).into_val(&e),
+                (
+                    String::from_str(&e, "new name"),
+                    String::from_str(&e, "NEW")
+                )
+                    .into_val(&e),
+            )
+        ]
+    );
+
+    assert_eq!(
+        token
+            .try_update_metadata(&admin, &"".into_val(&e), &"NEW".into_val(&e))
+            .unwrap_err(),
+        error(307)
+    );
+    assert_eq!(
+        token
+            .try_update_metadata(
+                &admin,
+                &"name".into_val(&e),
+                &"TOO_LONG_SYMBOL".into_val(&e)
+            )
+            .unwrap_err(),
+        error(307)
+    );
+    assert_eq!(
+        token
+            .try_update_metadata(&user1, &"name".into_val(&e), &"SYM".into_val(&e))
+            .unwrap_err(),
+        error(102)
+    );
+    assert_eq!(token.name(), String::from_str(&e, "new name"));
+}
+
+#[test]
+fn test_max_supply() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin = Address::generate(&e);
+    let user1 = Address::generate(&e);
+    let user2 = Address::generate(&e);
+    let token = create_token(&e, &admin);
+    let error = |code| Ok(soroban_sdk::Error::from_contract_error(code));
+
+    token.mint(&user1, &400);
+    assert_eq!(token.max_supply(), None);
+    assert_eq!(token.try_set_max_supply(&399).unwrap_err(), error(308));
+    token.set_max_supply(&1000);
+    assert_eq!(token.max_supply(), Some(1000));
+    assert_eq!(token.try_set_max_supply(&2000).unwrap_err(), error(309));
+
+    // minting up to exactly the cap
+    token.mint(&user2, &600);
+    assert_eq!(token.total_supply(), 1000);
+    assert_eq!(token.try_mint(&user2, &1).unwrap_err(), error(308));
+
+    // transfers don't change the supply, burnt tokens can be minted again
+    token.transfer(&user1, &user2, &100);
+    token.burn(&user2, &300);
+    assert_eq!(token.total_supply(), 700);
+    token.mint(&user1, &300);
+    assert_eq!(token.total_supply(), 1000);
+    assert_eq!(token.try_mint(&user1, &1).unwrap_err(), error(308));
+    assert_eq!(token.balance(&user1), 600);
+    assert_eq!(token.balance(&user2), 400);
+}
+
 #[test]
 #[should_panic(expected = "already initialized")]
 fn initialize_already_initialized() {
@@ -238,7 +789,13 @@ fn initialize_already_initialized() {
     let admin = Address::generate(&e);
     let token = create_token(&e, &admin);
 
-    token.initialize(&admin, &10, &"name".into_val(&e), &"symbol".into_val(&e));
+    token.initialize(
+        &admin,
+        &10,
+        &"name".into_val(&e),
+        &"symbol".into_val(&e),
+        &false,
+    );
 }
 
 #[test]
@@ -252,5 +809,6 @@ fn decimal_is_over_max() {
         &(u32::from(u8::MAX) + 1),
         &"name".into_val(&e),
         &"symbol".into_val(&e),
+        &false,
     );
 }
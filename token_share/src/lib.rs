@@ -11,6 +11,7 @@ use utils::bump::bump_instance;
 enum DataKey {
     TokenShare,
     TotalShares,
+    PoolShares,
 }
 
 pub mod token {
@@ -38,8 +39,52 @@ pub fn put_token_share(e: &Env, contract: Address) {
     e.storage().instance().set(&DataKey::TokenShare, &contract)
 }
 
+// Shares held by the pool itself, mirrored in storage to avoid calling the token contract.
+//  Shares transferred to the pool directly are only counted after sync_balance_shares
 pub fn get_balance_shares(e: &Env) -> u128 {
-    get_balance(e, get_token_share(e))
+    bump_instance(e);
+    e.storage()
+        .instance()
+        .get(&DataKey::PoolShares)
+        .unwrap_or(0)
+}
+
+pub fn put_balance_shares(e: &Env, value: u128) {
+    bump_instance(e);
+    e.storage().instance().set(&DataKey::PoolShares, &value)
+}
+
+// Reconcile the mirror with the actual pool balance on the token contract
+pub fn sync_balance_shares(e: &Env) -> u128 {
+    let balance = get_balance(e, get_token_share(e));
+    put_balance_shares(e, balance);
+    balance
+}
+
+// Pull shares from the user to the pool, allowance is required
+pub fn receive_shares(e: &Env, from: &Address, amount: u128) {
+    SorobanTokenClient::new(e, &get_token_share(e)).transfer_from(
+        &e.current_contract_address(),
+        from,
+        &e.current_contract_address(),
+        &(amount as i128),
+    );
+    put_balance_shares(e, get_balance_shares(e) + amount);
+}
+
+// Pull shares from the user to the pool with the user authorization, no allowance is needed
+pub fn take_shares(e: &Env, from: &Address, amount: u128) {
+    SorobanTokenClient::new(e, &get_token_share(e)).transfer(
+        from,
+        &e.current_contract_address(),
+        &(amount as i128),
+    );
+    put_balance_shares(e, get_balance_shares(e) + amount);
+}
+
+// Keep amount of user shares from being moved by anyone but the pool, 0 releases them
+pub fn lock_user_shares(e: &Env, user: &Address, amount: u128) {
+    Client::new(e, &get_token_share(e)).set_locked(user, &(amount as i128));
 }
 
 pub fn get_user_balance_shares(e: &Env, user: &Address) -> u128 {
@@ -62,6 +107,7 @@ pub fn put_total_shares(e: &Env, value: u128) {
 pub fn burn_shares(e: &Env, amount: i128) {
     let total_share = get_total_shares(e);
     put_total_shares(e, total_share - amount as u128);
+    put_balance_shares(e, get_balance_shares(e).saturating_sub(amount as u128));
 
     let share_contract = get_token_share(e);
     SorobanTokenClient::new(e, &share_contract).burn(&e.current_contract_address(), &amount);
@@ -1,6 +1,6 @@
 use crate::constant::{
     INSTANCE_BUMP_AMOUNT, INSTANCE_LIFETIME_THRESHOLD, PERSISTENT_BUMP_AMOUNT,
-    PERSISTENT_LIFETIME_THRESHOLD,
+    PERSISTENT_LIFETIME_THRESHOLD, TEMPORARY_BUMP_AMOUNT, TEMPORARY_LIFETIME_THRESHOLD,
 };
 use soroban_sdk::{Env, IntoVal, Val};
 
@@ -18,3 +18,12 @@ where
         .persistent()
         .extend_ttl(key, PERSISTENT_LIFETIME_THRESHOLD, PERSISTENT_BUMP_AMOUNT);
 }
+
+pub fn bump_temporary<K>(e: &Env, key: &K)
+where
+    K: IntoVal<Env, Val>,
+{
+    e.storage()
+        .temporary()
+        .extend_ttl(key, TEMPORARY_LIFETIME_THRESHOLD, TEMPORARY_BUMP_AMOUNT);
+}